@@ -0,0 +1,399 @@
+//! A process-wide budget for the bytes written to temporary files (see
+//! [`env::override_disk_budget`](crate::env::override_disk_budget)).
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::mem;
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+// Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
+use once_cell::sync::OnceCell as OnceLock;
+
+static BUDGET: OnceLock<DiskBudget> = OnceLock::new();
+// Counted in `u64`: temporary files can hold more bytes than fit into memory (or `usize`).
+static USED: Counter = Counter::new();
+
+type ExceededFn = dyn Fn(&BudgetExceeded) + Send + Sync;
+
+/// A limit on the number of bytes all temporary files of this process may hold at once, installed
+/// with [`env::override_disk_budget`](crate::env::override_disk_budget).
+///
+/// Bytes written through a [`NamedTempFile`](crate::NamedTempFile) (or a `&NamedTempFile`) or a
+/// [`SpooledTempFile`](crate::SpooledTempFile) that has been rolled over to disk (including the data
+/// written out when rolling over) count against the budget until the file is deleted, persisted,
+/// or kept. Only writes that grow the file past the largest size it has had are counted:
+/// overwriting data isn't counted again, and shrinking the file with `set_len` gives the cut off
+/// bytes back. When a write would exceed the budget, it either fails with a [`BudgetExceeded`]
+/// error (the default), or calls the callback installed with [`DiskBudget::on_exceeded`] and goes
+/// ahead.
+///
+/// Only writes through this crate's types can be accounted for: writes to the
+/// [`File`](std::fs::File) returned by [`tempfile()`](crate::tempfile()) or
+/// [`NamedTempFile::as_file`](crate::NamedTempFile::as_file), or to files inside a
+/// [`TempDir`](crate::TempDir), aren't counted. After the file has been accessed through `as_file`
+/// or `as_file_mut`, its position is no longer known, so writes are counted as growing the file
+/// until it's seeked through the `NamedTempFile` again.
+pub struct DiskBudget {
+    limit: u64,
+    on_exceeded: Option<Box<ExceededFn>>,
+}
+
+impl DiskBudget {
+    /// Create a budget of `limit` bytes, failing writes that would exceed it.
+    #[must_use]
+    pub fn new(limit: u64) -> DiskBudget {
+        DiskBudget {
+            limit,
+            on_exceeded: None,
+        }
+    }
+
+    /// Call `callback` instead of failing when a write would exceed the budget (e.g., to log a
+    /// warning or to trigger a cleanup). The write then goes ahead.
+    ///
+    /// The callback is called on the writing thread, for every write beyond the budget.
+    #[must_use]
+    pub fn on_exceeded<C>(mut self, callback: C) -> DiskBudget
+    where
+        C: Fn(&BudgetExceeded) + Send + Sync + 'static,
+    {
+        self.on_exceeded = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns the budget, in bytes.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl fmt::Debug for DiskBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskBudget")
+            .field("limit", &self.limit)
+            .field("on_exceeded", &self.on_exceeded.is_some())
+            .finish()
+    }
+}
+
+/// The error returned when a write to a temporary file would exceed the process-wide
+/// [`DiskBudget`] (or passed to its callback).
+///
+/// It's returned wrapped in an [`io::Error`] (of kind [`io::ErrorKind::Other`]), use
+/// [`io::Error::get_ref`] and `downcast_ref` to detect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    limit: u64,
+    used: u64,
+    requested: u64,
+}
+
+impl BudgetExceeded {
+    /// Returns the budget, in bytes.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Returns the number of bytes held by temporary files before the write.
+    #[must_use]
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Returns the number of bytes the write tried to add.
+    #[must_use]
+    pub fn requested(&self) -> u64 {
+        self.requested
+    }
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "writing {} bytes would exceed the temporary file budget of {} bytes ({} bytes in use)",
+            self.requested, self.limit, self.used
+        )
+    }
+}
+
+impl error::Error for BudgetExceeded {}
+
+impl From<BudgetExceeded> for io::Error {
+    fn from(err: BudgetExceeded) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+/// Installs the process-wide budget. See [`crate::env::override_disk_budget`].
+pub(crate) fn install(budget: DiskBudget) -> Result<(), DiskBudget> {
+    let mut budget = Some(budget);
+    BUDGET.get_or_init(|| budget.take().unwrap());
+    match budget {
+        None => Ok(()),
+        Some(budget) => Err(budget),
+    }
+}
+
+/// Returns the number of bytes currently charged against the budget.
+pub(crate) fn used() -> u64 {
+    USED.load()
+}
+
+/// A byte counter that doesn't wrap around at 4 GiB on 32-bit platforms.
+#[derive(Debug)]
+struct Counter(
+    #[cfg(target_has_atomic = "64")] AtomicU64,
+    #[cfg(not(target_has_atomic = "64"))] Mutex<u64>,
+);
+
+impl Counter {
+    const fn new() -> Counter {
+        #[cfg(target_has_atomic = "64")]
+        return Counter(AtomicU64::new(0));
+        #[cfg(not(target_has_atomic = "64"))]
+        return Counter(Mutex::new(0));
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    fn load(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    fn fetch_add(&self, n: u64) -> u64 {
+        self.0.fetch_add(n, Ordering::Relaxed)
+    }
+
+    #[cfg(target_has_atomic = "64")]
+    fn fetch_sub(&self, n: u64) -> u64 {
+        self.0.fetch_sub(n, Ordering::Relaxed)
+    }
+
+    #[cfg(not(target_has_atomic = "64"))]
+    fn load(&self) -> u64 {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[cfg(not(target_has_atomic = "64"))]
+    fn fetch_add(&self, n: u64) -> u64 {
+        let mut count = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let old = *count;
+        *count = old.wrapping_add(n);
+        old
+    }
+
+    #[cfg(not(target_has_atomic = "64"))]
+    fn fetch_sub(&self, n: u64) -> u64 {
+        let mut count = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let old = *count;
+        *count = old.wrapping_sub(n);
+        old
+    }
+}
+
+/// The bytes a single temporary file holds, charged against the budget until it's dropped.
+///
+/// Only growth past the file's high-water mark is charged, so overwriting data isn't charged
+/// again. To tell overwrites from growth, the position of the file is tracked as long as it's only
+/// moved through [`Charge::write`], [`Charge::read`] and [`Charge::seeked`]. Once it's unknown
+/// (see [`Charge::forget`]), writes are assumed to go to the end of the file.
+#[derive(Debug, Default)]
+pub(crate) struct Charge(Mutex<Extent>);
+
+#[derive(Debug, Default)]
+struct Extent {
+    /// The size of the file, as far as writes through this crate can tell.
+    mark: u64,
+    /// The bytes of `mark` charged against the budget (bytes written while there's no budget
+    /// aren't).
+    charged: u64,
+    /// The position of the file, `None` if unknown.
+    pos: Option<u64>,
+    /// Whether writes always go to the end of the file.
+    append: bool,
+}
+
+impl Extent {
+    /// Returns where a write goes, and by how much writing `len` bytes there grows the file.
+    fn target(&self, len: u64) -> (u64, u64) {
+        let start = match self.pos {
+            Some(pos) if !self.append => pos,
+            _ => self.mark,
+        };
+        (start, start.saturating_add(len).saturating_sub(self.mark))
+    }
+
+    /// Records that `written` bytes were written at `start`, refunding the part of the `reserved`
+    /// bytes that didn't grow the file.
+    fn wrote(&mut self, start: u64, written: u64, reserved: u64) {
+        let end = start.saturating_add(written);
+        let growth = end.saturating_sub(self.mark);
+        if reserved > 0 {
+            refund(reserved - growth.min(reserved));
+            self.charged += growth.min(reserved);
+        }
+        self.mark = self.mark.max(end);
+        if self.pos.is_some() || self.append {
+            self.pos = Some(end);
+        }
+    }
+}
+
+impl Charge {
+    /// Charges the growth of the file for a write of `len` bytes, calls `write`, and refunds
+    /// whatever it didn't write.
+    pub(crate) fn write<W>(&self, len: usize, write: W) -> io::Result<usize>
+    where
+        W: FnOnce() -> io::Result<usize>,
+    {
+        let mut extent = self.lock();
+        let (start, growth) = extent.target(len as u64);
+        let reserved = reserve(growth)?;
+        let result = write();
+        let written = result.as_ref().map_or(0, |&n| n);
+        extent.wrote(start, written as u64, reserved);
+        result
+    }
+
+    /// Charges the growth of the file for writing all of `buf` by calling `write` repeatedly (like
+    /// [`io::Write::write_all`]), and refunds whatever it didn't write if it fails part way.
+    pub(crate) fn write_all<W>(&self, buf: &[u8], mut write: W) -> io::Result<()>
+    where
+        W: FnMut(&[u8]) -> io::Result<usize>,
+    {
+        let mut extent = self.lock();
+        let (start, growth) = extent.target(buf.len() as u64);
+        // Charged up front, so that a write that doesn't fit fails before anything is written.
+        let reserved = reserve(growth)?;
+        let mut rest = buf;
+        let result = loop {
+            if rest.is_empty() {
+                break Ok(());
+            }
+            match write(rest) {
+                Ok(0) => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(n) => rest = &rest[n..],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        extent.wrote(start, (buf.len() - rest.len()) as u64, reserved);
+        result
+    }
+
+    /// Charges the growth of the file for resizing it to `len` bytes (e.g., with `set_len`, or by
+    /// copying `len` bytes into it), calls `resize`, and refunds the bytes it cut off.
+    pub(crate) fn resize<T, R>(&self, len: u64, resize: R) -> io::Result<T>
+    where
+        R: FnOnce() -> io::Result<T>,
+    {
+        let mut extent = self.lock();
+        let reserved = reserve(len.saturating_sub(extent.mark))?;
+        let result = resize();
+        if result.is_err() {
+            refund(reserved);
+            return result;
+        }
+        let charged = (extent.charged + reserved).min(len);
+        refund(extent.charged + reserved - charged);
+        extent.charged = charged;
+        extent.mark = len;
+        result
+    }
+
+    /// Calls `read`, and records that the file's position has been advanced by the number of bytes
+    /// `len` returns for its result (or to somewhere unknown, if it failed).
+    pub(crate) fn read<T, R, L>(&self, read: R, len: L) -> io::Result<T>
+    where
+        R: FnOnce() -> io::Result<T>,
+        L: FnOnce(&T) -> usize,
+    {
+        let mut extent = self.lock();
+        let result = read();
+        extent.pos = match (extent.pos, &result) {
+            (Some(pos), Ok(n)) => Some(pos.saturating_add(len(n) as u64)),
+            _ => None,
+        };
+        result
+    }
+
+    /// Records that the file has been seeked to `pos` (or to somewhere unknown, if `None`).
+    pub(crate) fn seeked(&self, pos: Option<u64>) {
+        self.lock().pos = pos;
+    }
+
+    /// Forgets the position of the file, e.g., because the file has been handed out and may be
+    /// seeked without going through this crate.
+    pub(crate) fn forget(&self) {
+        self.lock().pos = None;
+    }
+
+    /// Records that writes always go to the end of the file (see
+    /// [`Builder::append`](crate::Builder::append)).
+    pub(crate) fn append(&self) {
+        self.lock().append = true;
+    }
+
+    /// Refunds everything charged so far (e.g., because the file has been deleted or persisted),
+    /// starting over from an empty file.
+    pub(crate) fn clear(&self) {
+        let mut extent = self.lock();
+        refund(mem::take(&mut extent.charged));
+        extent.mark = 0;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Extent> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Charges `len` bytes, returning the number of bytes charged (`0` if there's no budget to charge
+/// them against).
+fn reserve(len: u64) -> io::Result<u64> {
+    let budget = match BUDGET.get() {
+        Some(budget) => budget,
+        None => return Ok(0),
+    };
+    if len == 0 {
+        return Ok(0);
+    }
+    let used = USED.fetch_add(len);
+    if used.saturating_add(len) > budget.limit {
+        let exceeded = BudgetExceeded {
+            limit: budget.limit,
+            used,
+            requested: len,
+        };
+        match &budget.on_exceeded {
+            Some(callback) => callback(&exceeded),
+            None => {
+                USED.fetch_sub(len);
+                return Err(exceeded.into());
+            }
+        }
+    }
+    Ok(len)
+}
+
+fn refund(len: u64) {
+    if len > 0 {
+        USED.fetch_sub(len);
+    }
+}
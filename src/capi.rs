@@ -0,0 +1,147 @@
+//! C bindings for creating temporary files and directories (requires the `capi` feature).
+//!
+//! These functions are drop-in replacements for POSIX `mkstemp(3)` and `mkdtemp(3)`, backed by this
+//! crate's random name generation and retry logic. They're intended for mixed C/Rust codebases that
+//! want to share a single temporary file implementation. Declare them in C as:
+//!
+//! ```c
+//! int tempfile_mkstemp(char *template);
+//! char *tempfile_mkdtemp(char *template);
+//! ```
+//!
+//! Just like their POSIX counterparts, both functions take a NUL-terminated `template` ending in
+//! `XXXXXX`, replace those six characters in-place with the randomly chosen ones, and set `errno`
+//! on failure.
+//!
+//! This module is only available on Unix-like platforms.
+
+use std::ffi::{CStr, OsStr};
+use std::io;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::IntoRawFd;
+use std::path::{Path, PathBuf};
+
+use rustix::io::Errno;
+
+use crate::Builder;
+
+const TEMPLATE_SUFFIX: &[u8] = b"XXXXXX";
+
+/// Create and open a unique temporary file from `template`, like `mkstemp(3)`.
+///
+/// The file is created with mode `0600` (before applying the umask) and opened for reading and
+/// writing. Returns the file descriptor on success. On failure, returns `-1` and sets `errno`
+/// (`EINVAL` if the template doesn't end with `XXXXXX`).
+///
+/// # Safety
+///
+/// `template` must be a valid pointer to a writable, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tempfile_mkstemp(template: *mut c_char) -> c_int {
+    let result = with_template(template, |builder, dir| {
+        let (file, path) = builder
+            .permissions(std::fs::Permissions::from_mode(0o600))
+            .tempfile_in(dir)?
+            .keep()?;
+        Ok((file, path))
+    });
+    match result {
+        Ok(file) => file.into_raw_fd(),
+        Err(e) => {
+            set_errno(&e);
+            -1
+        }
+    }
+}
+
+/// Create a unique temporary directory from `template`, like `mkdtemp(3)`.
+///
+/// The directory is created with mode `0700` (before applying the umask). Returns `template` on
+/// success. On failure, returns `NULL` and sets `errno` (`EINVAL` if the template doesn't end with
+/// `XXXXXX`).
+///
+/// # Safety
+///
+/// `template` must be a valid pointer to a writable, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tempfile_mkdtemp(template: *mut c_char) -> *mut c_char {
+    let result = with_template(template, |builder, dir| {
+        let path = builder
+            .permissions(std::fs::Permissions::from_mode(0o700))
+            .tempdir_in(dir)?
+            .keep();
+        Ok(((), path))
+    });
+    match result {
+        Ok(()) => template,
+        Err(e) => {
+            set_errno(&e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Parse `template`, create the temporary resource with `f`, and write the chosen random
+/// characters back into `template`.
+///
+/// If the created name doesn't have the shape of the template (the prefix followed by exactly six
+/// random characters), the resource is removed again and `EINVAL` is returned instead of writing
+/// arbitrary bytes back into `template`.
+unsafe fn with_template<T>(
+    template: *mut c_char,
+    f: impl FnOnce(&mut Builder<'_, '_>, &Path) -> io::Result<(T, PathBuf)>,
+) -> io::Result<T> {
+    let invalid = || io::Error::from(Errno::INVAL);
+    if template.is_null() {
+        return Err(invalid());
+    }
+    let bytes = CStr::from_ptr(template).to_bytes();
+    let stem = bytes.strip_suffix(TEMPLATE_SUFFIX).ok_or_else(invalid)?;
+
+    // Split the template into the directory and the file name prefix.
+    let (dir, prefix) = match stem.iter().rposition(|&b| b == b'/') {
+        Some(i) => (&stem[..=i], &stem[i + 1..]),
+        None => (&b"."[..], stem),
+    };
+
+    let (value, path) = f(
+        Builder::new()
+            .prefix(OsStr::from_bytes(prefix))
+            .suffix("")
+            .rand_bytes(TEMPLATE_SUFFIX.len()),
+        Path::new(OsStr::from_bytes(dir)),
+    )?;
+
+    // Only accept names of the form `{prefix}{random}` so that the random characters line up
+    // with the `XXXXXX` placeholder.
+    let random = match path.file_name().map(OsStrExt::as_bytes) {
+        Some(name)
+            if name.len() == prefix.len() + TEMPLATE_SUFFIX.len() && name.starts_with(prefix) =>
+        {
+            name[prefix.len()..].to_vec()
+        }
+        _ => {
+            drop(value);
+            let _ = match path.symlink_metadata() {
+                Ok(meta) if meta.is_dir() => std::fs::remove_dir(&path),
+                _ => std::fs::remove_file(&path),
+            };
+            return Err(invalid());
+        }
+    };
+    std::ptr::copy_nonoverlapping(
+        random.as_ptr() as *const c_char,
+        template.add(stem.len()),
+        random.len(),
+    );
+    Ok(value)
+}
+
+fn set_errno(err: &io::Error) {
+    let errno = err
+        .raw_os_error()
+        .unwrap_or_else(|| Errno::IO.raw_os_error());
+    errno::set_errno(errno::Errno(errno));
+}
@@ -0,0 +1,297 @@
+//! A content-addressed cache for temporary files (see [`CasDir`]).
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{dir, Builder, NamedTempFile};
+
+/// A content-addressed cache directory that temporary files can be committed into (requires the
+/// `cas` feature).
+///
+/// Each file inserted with [`CasDir::insert`] is stored under the SHA-256 hash of its contents
+/// (as `<root>/<first two hex digits>/<remaining hex digits>`), so identical artifacts are only
+/// stored once: inserting a file whose contents are already cached just deletes the temporary file
+/// and returns the existing copy. Cached files are made read-only, and can be hard-linked to where
+/// they're needed with [`CachedPath::link_to`] instead of being copied.
+///
+/// Files are moved into the cache with a rename when possible, so create them with
+/// [`CasDir::tempfile`] (or on the same filesystem) to avoid copying them.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use tempfile::CasDir;
+///
+/// # let root = tempfile::tempdir()?;
+/// let cache = CasDir::new(root.path().join("cache"))?;
+///
+/// let mut first = cache.tempfile()?;
+/// first.write_all(b"artifact")?;
+/// let first = cache.insert(first)?;
+///
+/// let mut second = cache.tempfile()?;
+/// second.write_all(b"artifact")?;
+/// let second = cache.insert(second)?;
+///
+/// assert!(second.was_cached());
+/// assert_eq!(first.path(), second.path());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct CasDir {
+    root: PathBuf,
+}
+
+impl CasDir {
+    /// Opens the cache directory at `root`, creating it (and its parents) if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be created, `Err` is returned.
+    pub fn new<P: AsRef<Path>>(root: P) -> io::Result<CasDir> {
+        let root = root.as_ref();
+        dir::create_all(root, None)?;
+        Ok(CasDir {
+            root: root.to_owned(),
+        })
+    }
+
+    /// Returns the path of the cache directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Creates a temporary file inside the cache directory, so it can be inserted without being
+    /// copied.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be created, `Err` is returned.
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        // The leading dot keeps these apart from the two-digit directories holding the objects.
+        Builder::new().prefix(".tmp").tempfile_in(&self.root)
+    }
+
+    /// Commits `file` into the cache under the hash of its contents, and returns the cached copy.
+    ///
+    /// If the contents are already cached, `file` is deleted and the existing copy is returned
+    /// (see [`CachedPath::was_cached`]). Otherwise, `file` is made read-only and moved into the
+    /// cache, or copied if it's on another filesystem.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or can't be moved or copied into the cache, `Err` is returned
+    /// (and the temporary file is deleted).
+    pub fn insert(&self, mut file: NamedTempFile) -> io::Result<CachedPath> {
+        let hash = hash_file(&mut file).with_err_path(|| file.path())?;
+        let path = self.object_path(&hash);
+        if path.exists() {
+            return Ok(CachedPath {
+                path,
+                hash,
+                was_cached: true,
+            });
+        }
+
+        let parent = path.parent().unwrap();
+        dir::create_all(parent, None)?;
+        let was_cached = match commit(file, &path) {
+            Ok(was_cached) => was_cached,
+            Err(err) if !crosses_devices(&err.error) => return Err(err.error),
+            Err(mut err) => {
+                // On another filesystem: copy it instead.
+                let mut copy = Builder::new().prefix(".tmp").tempfile_in(parent)?;
+                err.file
+                    .seek(SeekFrom::Start(0))
+                    .with_err_path(|| err.file.path())?;
+                io::copy(&mut err.file, &mut copy).with_err_path(|| copy.path())?;
+                commit(copy, &path).map_err(|err| err.error)?
+            }
+        };
+        Ok(CachedPath {
+            path,
+            hash,
+            was_cached,
+        })
+    }
+
+    /// Returns the cached file with the given SHA-256 hash (as lowercase hex digits), if any.
+    #[must_use]
+    pub fn get(&self, hash: &str) -> Option<CachedPath> {
+        if hash.len() != 64 || !hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return None;
+        }
+        let path = self.object_path(hash);
+        if !path.is_file() {
+            return None;
+        }
+        Some(CachedPath {
+            path,
+            hash: hash.to_owned(),
+            was_cached: true,
+        })
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        let (dir, name) = hash.split_at(2);
+        self.root.join(dir).join(name)
+    }
+}
+
+impl fmt::Debug for CasDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CasDir")
+            .field("root", &Redact(&self.root))
+            .finish()
+    }
+}
+
+impl AsRef<Path> for CasDir {
+    fn as_ref(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// A file stored in a [`CasDir`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct CachedPath {
+    path: PathBuf,
+    hash: String,
+    was_cached: bool,
+}
+
+impl fmt::Debug for CachedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedPath")
+            .field("path", &Redact(&self.path))
+            .field("hash", &self.hash)
+            .field("was_cached", &self.was_cached)
+            .finish()
+    }
+}
+
+impl CachedPath {
+    /// Returns the path of the cached file.
+    ///
+    /// The file is read-only and shared with every insertion of the same contents, so it must
+    /// not be modified.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the SHA-256 hash of the file's contents, as lowercase hex digits.
+    #[must_use]
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Returns `true` if the contents were already cached (i.e., the inserted file was a
+    /// duplicate, and has been deleted).
+    #[must_use]
+    pub fn was_cached(&self) -> bool {
+        self.was_cached
+    }
+
+    /// Creates a hard link to the cached file at `dest`, which must not exist.
+    ///
+    /// # Errors
+    ///
+    /// If `dest` already exists, or the link can't be created (e.g., because `dest` is on another
+    /// filesystem), `Err` is returned.
+    pub fn link_to<P: AsRef<Path>>(&self, dest: P) -> io::Result<()> {
+        let dest = dest.as_ref();
+        fs::hard_link(&self.path, dest).with_err_path(|| dest)
+    }
+}
+
+impl AsRef<Path> for CachedPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Moves `file` to `path` (read-only), returning `Ok(true)` if another file got there first.
+fn commit(file: NamedTempFile, path: &Path) -> Result<bool, crate::PersistError> {
+    let original = match file.as_file().metadata() {
+        Ok(meta) => meta.permissions(),
+        Err(error) => return Err(crate::PersistError { error, file }),
+    };
+    let mut readonly = original.clone();
+    readonly.set_readonly(true);
+    if let Err(error) = file.as_file().set_permissions(readonly) {
+        return Err(crate::PersistError { error, file });
+    }
+    match file.persist_noclobber(path) {
+        Ok(_) => Ok(false),
+        Err(err) => {
+            // Make the file writable again, so it can be deleted everywhere.
+            let _ = err.file.as_file().set_permissions(original);
+            if err.error.kind() == io::ErrorKind::AlreadyExists && path.exists() {
+                return Ok(true);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Returns true if `err` means that a file couldn't be moved because it's on another filesystem.
+fn crosses_devices(err: &io::Error) -> bool {
+    #[cfg(any(unix, target_os = "wasi"))]
+    {
+        err.raw_os_error() == Some(rustix::io::Errno::XDEV.raw_os_error())
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Foundation::ERROR_NOT_SAME_DEVICE;
+        err.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE as i32)
+    }
+    #[cfg(not(any(unix, target_os = "wasi", windows)))]
+    {
+        let _ = err;
+        false
+    }
+}
+
+fn hash_file(file: &mut NamedTempFile) -> io::Result<String> {
+    file.seek(SeekFrom::Start(0))?;
+    HashAlgo::Sha256.hash(file)
+}
+
+/// A hash algorithm for naming files by their contents (see
+/// [`NamedTempFile::persist_by_hash`](crate::NamedTempFile::persist_by_hash)), requires the `cas`
+/// feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HashAlgo {
+    /// SHA-256, written as 64 lowercase hex digits.
+    Sha256,
+    /// SHA-512, written as 128 lowercase hex digits.
+    Sha512,
+}
+
+impl HashAlgo {
+    /// Hashes everything read from `reader`, returning the digest as lowercase hex digits.
+    pub(crate) fn hash<R: Read + ?Sized>(self, reader: &mut R) -> io::Result<String> {
+        fn digest<D: Digest + io::Write, R: Read + ?Sized>(reader: &mut R) -> io::Result<String> {
+            let mut hasher = D::new();
+            io::copy(reader, &mut hasher)?;
+            Ok(hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect())
+        }
+        match self {
+            HashAlgo::Sha256 => digest::<Sha256, R>(reader),
+            HashAlgo::Sha512 => digest::<Sha512, R>(reader),
+        }
+    }
+}
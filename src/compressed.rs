@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, mem};
+
+use zstd::stream::read::Decoder;
+use zstd::stream::write::Encoder;
+
+use crate::file::tempfile;
+use crate::tempfile_in;
+use crate::util::Redact;
+
+enum State {
+    InMemory(Vec<u8>),
+    /// Compressing appended data into the backing file.
+    Writing(Encoder<'static, File>),
+    /// Decompressing the backing file, positioned at `min(pos, len)`.
+    Reading(Decoder<'static, BufReader<File>>),
+    /// A previous operation failed while switching between reading and writing.
+    Failed,
+}
+
+/// Like a [`SpooledTempFile`](crate::SpooledTempFile), but compresses the data with
+/// [zstd](https://facebook.github.io/zstd/) as it's spilled to the temporary file on disk, and
+/// decompresses it when it's read back (requires the `zstd` feature).
+///
+/// This trades CPU time for a much smaller scratch footprint when buffering compressible data
+/// (e.g., JSON or logs).
+///
+/// Unlike a regular file, a `CompressedSpooledTempFile` is an append-only buffer: writes always
+/// append to the end of the data, and [seeking](Seek) only moves the position data is _read_ from.
+/// Once the data has been spilled to disk, seeking backwards, or reading after writing, requires
+/// decompressing the data from the beginning, so this type is best suited for writing all the data
+/// before reading it back sequentially.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Seek, SeekFrom, Write};
+/// use tempfile::CompressedSpooledTempFile;
+///
+/// let mut file = CompressedSpooledTempFile::new(1024, 3);
+/// for i in 0..1000 {
+///     writeln!(file, "{{\"event\": {}}}", i)?;
+/// }
+/// assert!(file.is_rolled());
+///
+/// file.seek(SeekFrom::Start(0))?;
+/// let mut first = [0; 13];
+/// file.read_exact(&mut first)?;
+/// assert_eq!(&first, b"{\"event\": 0}\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct CompressedSpooledTempFile {
+    max_size: usize,
+    level: i32,
+    dir: Option<PathBuf>,
+    pos: u64,
+    len: u64,
+    state: State,
+}
+
+impl CompressedSpooledTempFile {
+    /// Construct a new [`CompressedSpooledTempFile`] keeping up to `max_size` bytes in memory, and
+    /// compressing the data with the given zstd compression `level` once it's spilled to disk
+    /// (`0` selects zstd's default level).
+    #[must_use]
+    pub fn new(max_size: usize, level: i32) -> CompressedSpooledTempFile {
+        CompressedSpooledTempFile {
+            max_size,
+            level,
+            dir: None,
+            pos: 0,
+            len: 0,
+            state: State::InMemory(Vec::new()),
+        }
+    }
+
+    /// Construct a new [`CompressedSpooledTempFile`], backed by a file in the specified directory.
+    #[must_use]
+    pub fn new_in<P: AsRef<Path>>(
+        max_size: usize,
+        level: i32,
+        dir: P,
+    ) -> CompressedSpooledTempFile {
+        CompressedSpooledTempFile {
+            dir: Some(dir.as_ref().to_owned()),
+            ..CompressedSpooledTempFile::new(max_size, level)
+        }
+    }
+
+    /// Returns true if the data has been spilled to disk.
+    #[must_use]
+    pub fn is_rolled(&self) -> bool {
+        !matches!(self.state, State::InMemory(_))
+    }
+
+    /// Returns the (uncompressed) length of the data.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if no data has been written.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Spills the data to a compressed file on disk, regardless of its current size. Does nothing
+    /// if it has already been spilled.
+    pub fn roll(&mut self) -> io::Result<()> {
+        if let State::InMemory(data) = &self.state {
+            let file = match &self.dir {
+                Some(dir) => tempfile_in(dir)?,
+                None => tempfile()?,
+            };
+            let mut encoder = Encoder::new(file, self.level)?;
+            encoder.write_all(data)?;
+            self.state = State::Writing(encoder);
+        }
+        Ok(())
+    }
+
+    fn failed() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "compressed temporary file is unusable after a previous error",
+        )
+    }
+
+    /// Switches to decompressing the data from the current position.
+    fn start_reading(&mut self, restart: bool) -> io::Result<()> {
+        let mut file = match mem::replace(&mut self.state, State::Failed) {
+            State::Writing(encoder) => encoder.finish()?,
+            State::Reading(decoder) if restart => decoder.finish().into_inner(),
+            State::Failed => return Err(Self::failed()),
+            state => {
+                self.state = state;
+                return Ok(());
+            }
+        };
+        file.seek(SeekFrom::Start(0))?;
+        let mut decoder = Decoder::new(file)?;
+        io::copy(&mut (&mut decoder).take(self.pos), &mut io::sink())?;
+        self.state = State::Reading(decoder);
+        Ok(())
+    }
+
+    /// Switches to compressing appended data.
+    fn start_writing(&mut self) -> io::Result<()> {
+        match mem::replace(&mut self.state, State::Failed) {
+            State::Reading(decoder) => {
+                let mut file = decoder.finish().into_inner();
+                file.seek(SeekFrom::End(0))?;
+                // Concatenated zstd frames are decompressed as a single stream.
+                self.state = State::Writing(Encoder::new(file, self.level)?);
+            }
+            State::Failed => return Err(Self::failed()),
+            state => self.state = state,
+        }
+        Ok(())
+    }
+}
+
+impl Read for CompressedSpooledTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let State::InMemory(data) = &self.state {
+            let start = self.pos.min(self.len) as usize;
+            let n = (&data[start..]).read(buf)?;
+            self.pos += n as u64;
+            return Ok(n);
+        }
+        self.start_reading(false)?;
+        match &mut self.state {
+            State::Reading(decoder) => {
+                let n = decoder.read(buf)?;
+                self.pos += n as u64;
+                Ok(n)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Write for CompressedSpooledTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if matches! {
+            &self.state, State::InMemory(_)
+            if self.len.saturating_add(buf.len() as u64) > self.max_size as u64
+        } {
+            self.roll()?;
+        }
+        self.start_writing()?;
+        let n = match &mut self.state {
+            State::InMemory(data) => data.write(buf)?,
+            State::Writing(encoder) => encoder.write(buf)?,
+            _ => unreachable!(),
+        };
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            State::Writing(encoder) => encoder.flush(),
+            State::Failed => Err(Self::failed()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Seek for CompressedSpooledTempFile {
+    /// Moves the position data is read from. Writes always append to the end of the data.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (self.len, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        if let State::Reading(decoder) = &mut self.state {
+            let current = self.pos.min(self.len);
+            let target = new_pos.min(self.len);
+            if target < current {
+                self.pos = new_pos;
+                self.start_reading(true)?;
+            } else {
+                io::copy(&mut decoder.take(target - current), &mut io::sink())?;
+            }
+        }
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
+impl fmt::Debug for CompressedSpooledTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedSpooledTempFile")
+            .field("max_size", &self.max_size)
+            .field("level", &self.level)
+            .field("dir", &Redact(&self.dir))
+            .field("pos", &self.pos)
+            .field("len", &self.len)
+            .field("rolled", &self.is_rolled())
+            .finish()
+    }
+}
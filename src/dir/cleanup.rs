@@ -0,0 +1,200 @@
+use std::fmt;
+use std::fs::{self, remove_dir_all};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::util::Redact;
+
+type Filter = dyn Fn(&Path) -> bool + Send + Sync;
+
+/// How a temporary directory is removed.
+#[derive(Default)]
+pub(crate) struct Cleanup {
+    pub(crate) remove_only_if_empty: bool,
+    pub(crate) preserve: Option<(Box<Filter>, PathBuf)>,
+    /// Whether a tmpfs has been mounted on the directory (see [`crate::Builder::tmpfs`]).
+    #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+    pub(crate) tmpfs: bool,
+    /// The watchdog removing the directory if the process is killed (see
+    /// [`crate::Builder::windows_cleanup_on_kill`]).
+    #[cfg(windows)]
+    pub(crate) watchdog: Option<super::watchdog::Watchdog>,
+}
+
+impl Cleanup {
+    /// Records whether cleanup has been disabled, so that the directory is also kept if the process
+    /// is killed.
+    pub(crate) fn set_disabled(&self, #[allow(unused)] disabled: bool) {
+        #[cfg(windows)]
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.set_keep(disabled);
+        }
+    }
+
+    /// Removes the temporary directory at `path`, first moving out the files to preserve.
+    pub(crate) fn run(&self, path: &Path) -> io::Result<()> {
+        #[cfg(feature = "failpoints")]
+        crate::failpoints::check(crate::failpoints::Failpoint::CleanupPermissionDenied)?;
+        if let Some((filter, destination)) = &self.preserve {
+            preserve(path, Path::new(""), &**filter, destination)?;
+        }
+        #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+        self.unmount_tmpfs(path)?;
+        if self.remove_only_if_empty {
+            fs::remove_dir(path)
+        } else {
+            remove_dir_all(path)
+        }
+    }
+}
+
+impl Cleanup {
+    /// Unmounts the tmpfs mounted on `path`, if any, unless that would discard files that should
+    /// be left behind.
+    #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+    fn unmount_tmpfs(&self, path: &Path) -> io::Result<()> {
+        if !self.tmpfs {
+            return Ok(());
+        }
+        if self.remove_only_if_empty && fs::read_dir(path)?.next().is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "temporary directory isn't empty",
+            ));
+        }
+        super::tmpfs::unmount(path)
+    }
+
+    /// Like [`Cleanup::run`], but removes the directory entry by entry, reporting progress.
+    pub(crate) fn run_with_progress(
+        &self,
+        path: &Path,
+        progress: &mut dyn FnMut(&RemovalProgress<'_>),
+    ) -> io::Result<()> {
+        #[cfg(feature = "failpoints")]
+        crate::failpoints::check(crate::failpoints::Failpoint::CleanupPermissionDenied)?;
+        if let Some((filter, destination)) = &self.preserve {
+            preserve(path, Path::new(""), &**filter, destination)?;
+        }
+        let mut counters = (0, 0);
+        if !self.remove_only_if_empty {
+            remove_contents(path, &mut counters, progress)?;
+        }
+        #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+        self.unmount_tmpfs(path)?;
+        remove_entry(path, true, 0, &mut counters, progress)
+    }
+}
+
+/// Progress of the recursive deletion of a temporary directory, see
+/// [`TempDir::close_with_progress`](crate::TempDir::close_with_progress).
+#[derive(Clone, Copy)]
+pub struct RemovalProgress<'a> {
+    entries_removed: u64,
+    bytes_freed: u64,
+    path: &'a Path,
+}
+
+impl fmt::Debug for RemovalProgress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemovalProgress")
+            .field("entries_removed", &self.entries_removed)
+            .field("bytes_freed", &self.bytes_freed)
+            .field("path", &Redact(self.path))
+            .finish()
+    }
+}
+
+impl RemovalProgress<'_> {
+    /// Returns the number of files and directories removed so far.
+    #[must_use]
+    pub fn entries_removed(&self) -> u64 {
+        self.entries_removed
+    }
+
+    /// Returns the total size of the files removed so far, in bytes.
+    #[must_use]
+    pub fn bytes_freed(&self) -> u64 {
+        self.bytes_freed
+    }
+
+    /// Returns the path of the file or directory that was just removed.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+}
+
+fn remove_contents(
+    dir: &Path,
+    counters: &mut (u64, u64),
+    progress: &mut dyn FnMut(&RemovalProgress<'_>),
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // Doesn't follow symbolic links.
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            remove_contents(&path, counters, progress)?;
+            remove_entry(&path, true, 0, counters, progress)?;
+        } else {
+            remove_entry(&path, false, meta.len(), counters, progress)?;
+        }
+    }
+    Ok(())
+}
+
+fn remove_entry(
+    path: &Path,
+    is_dir: bool,
+    len: u64,
+    counters: &mut (u64, u64),
+    progress: &mut dyn FnMut(&RemovalProgress<'_>),
+) -> io::Result<()> {
+    if is_dir {
+        fs::remove_dir(path)?;
+    } else {
+        // Symbolic links to directories must be removed as directories on Windows.
+        fs::remove_file(path).or_else(|e| fs::remove_dir(path).map_err(|_| e))?;
+    }
+    counters.0 += 1;
+    counters.1 += len;
+    progress(&RemovalProgress {
+        entries_removed: counters.0,
+        bytes_freed: counters.1,
+        path,
+    });
+    Ok(())
+}
+
+impl fmt::Debug for Cleanup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cleanup")
+            .field("remove_only_if_empty", &self.remove_only_if_empty)
+            .field("preserve_to", &self.preserve.as_ref().map(|(_, dest)| dest))
+            .finish()
+    }
+}
+
+/// Recursively moves the files below `root/relative` for which `filter` returns `true` to the
+/// same relative location below `destination`.
+fn preserve(root: &Path, relative: &Path, filter: &Filter, destination: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            preserve(root, &relative, filter, destination)?;
+        } else if filter(&relative) {
+            let target = destination.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Fall back on copying if the destination is on another filesystem.
+            if fs::rename(entry.path(), &target).is_err() {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+
+/// An iterator over the entries of a [`TempDir`](crate::TempDir), returned by
+/// [`TempDir::entries`](crate::TempDir::entries).
+///
+/// Yields paths relative to the temporary directory. By default, only the direct children of the
+/// temporary directory are listed, in no particular order; use [`Entries::recursive`] and
+/// [`Entries::sorted`] to change that before iterating.
+pub struct Entries<'a> {
+    root: &'a Path,
+    recurse: bool,
+    sort: bool,
+    started: bool,
+    // Stack of the partially listed directories: (relative path, is directory).
+    stack: Vec<vec::IntoIter<(PathBuf, bool)>>,
+}
+
+impl fmt::Debug for Entries<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entries")
+            .field("root", &Redact(self.root))
+            .field("recurse", &self.recurse)
+            .field("sort", &self.sort)
+            .field("started", &self.started)
+            .field("stack", &Redact(&self.stack))
+            .finish()
+    }
+}
+
+impl<'a> Entries<'a> {
+    pub(crate) fn new(root: &'a Path) -> Self {
+        Entries {
+            root,
+            recurse: false,
+            sort: false,
+            started: false,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Also list the contents of subdirectories, recursively (depth-first, with each directory
+    /// listed before its contents). Symbolic links are not followed.
+    #[must_use]
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recurse = recursive;
+        self
+    }
+
+    /// Sort the entries of each directory by name.
+    #[must_use]
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sort = sorted;
+        self
+    }
+
+    fn list(&mut self, relative: &Path) -> io::Result<()> {
+        let dir = self.root.join(relative);
+        let mut entries = fs::read_dir(&dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| {
+                        let entry = entry?;
+                        Ok((
+                            relative.join(entry.file_name()),
+                            entry.file_type()?.is_dir(),
+                        ))
+                    })
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .with_err_path(|| dir)?;
+        if self.sort {
+            entries.sort();
+        }
+        self.stack.push(entries.into_iter());
+        Ok(())
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<io::Result<PathBuf>> {
+        if !self.started {
+            self.started = true;
+            if let Err(e) = self.list(Path::new("")) {
+                return Some(Err(e));
+            }
+        }
+        loop {
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                }
+                Some((path, is_dir)) => {
+                    if self.recurse && is_dir {
+                        if let Err(e) = self.list(&path) {
+                            return Some(Err(e));
+                        }
+                    }
+                    return Some(Ok(path));
+                }
+            }
+        }
+    }
+}
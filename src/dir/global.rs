@@ -0,0 +1,99 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
+use once_cell::sync::OnceCell as OnceLock;
+
+use crate::util::Redact;
+use crate::Builder;
+
+/// A temporary directory intended to be declared as a `static`.
+///
+/// The directory is created on first use (by [`GlobalTempDir::path`]) and shared for the rest of
+/// the program's lifetime. Unlike a [`TempDir`](crate::TempDir) declared statically (e.g., with
+/// `lazy_static`), which is never dropped and therefore never deleted, a `GlobalTempDir` is
+/// recursively deleted by an exit hook.
+///
+/// # Resource Leaking
+///
+/// The exit hook runs when the process exits normally, e.g., by returning from `main` (and by
+/// calling [`std::process::exit`] on Unix-like platforms). It does not run if the process is killed
+/// by a signal, aborts, or crashes. On platforms without exit hooks (e.g., `wasm32-unknown-unknown`)
+/// the directory is never deleted.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use tempfile::GlobalTempDir;
+///
+/// static SCRATCH: GlobalTempDir = GlobalTempDir::with_prefix("my-app-");
+///
+/// let path = SCRATCH.path()?;
+/// fs::write(path.join("cache.bin"), b"cached")?;
+///
+/// // The same directory is returned every time.
+/// assert_eq!(SCRATCH.path()?, path);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct GlobalTempDir {
+    prefix: Option<&'static str>,
+    path: OnceLock<PathBuf>,
+}
+
+impl fmt::Debug for GlobalTempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlobalTempDir")
+            .field("prefix", &self.prefix)
+            .field("path", &Redact(&self.path))
+            .finish()
+    }
+}
+
+impl GlobalTempDir {
+    /// Declare a global temporary directory, to be created inside of
+    /// [`env::temp_dir()`](crate::env::temp_dir) on first use.
+    #[must_use]
+    pub const fn new() -> Self {
+        GlobalTempDir {
+            prefix: None,
+            path: OnceLock::new(),
+        }
+    }
+
+    /// Declare a global temporary directory with the specified prefix, to be created inside of
+    /// [`env::temp_dir()`](crate::env::temp_dir) on first use.
+    #[must_use]
+    pub const fn with_prefix(prefix: &'static str) -> Self {
+        GlobalTempDir {
+            prefix: Some(prefix),
+            path: OnceLock::new(),
+        }
+    }
+
+    /// Returns the path to the temporary directory, creating it if it doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned. The next call will try again.
+    pub fn path(&'static self) -> io::Result<&'static Path> {
+        self.path
+            .get_or_try_init(|| {
+                let mut builder = Builder::new();
+                if let Some(prefix) = self.prefix {
+                    builder.prefix(prefix);
+                }
+                let path = builder.tempdir()?.keep();
+                crate::exit::cleanup_dir_at_exit(path.clone(), Default::default());
+                Ok(path)
+            })
+            .map(PathBuf::as_path)
+    }
+}
+
+impl Default for GlobalTempDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
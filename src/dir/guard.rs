@@ -0,0 +1,95 @@
+use std::fmt;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use super::Cleanup;
+use crate::error::IoResultExt;
+use crate::manifest;
+use crate::token::Kind;
+use crate::util::Redact;
+
+/// The cleanup responsibility for a temporary directory, detached from the
+/// [`TempDir`](crate::TempDir) with [`TempDir::into_parts`](crate::TempDir::into_parts).
+///
+/// The directory is recursively deleted when the guard is dropped, so the guard can be moved to
+/// wherever the directory's lifetime should end (e.g., a shutdown task) while the rest of the
+/// application only holds on to the path.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::TempDir;
+///
+/// let (path, guard) = TempDir::new()?.into_parts();
+/// assert!(path.exists());
+///
+/// let shutdown = std::thread::spawn(move || {
+///     // ...wait for the shutdown signal, then:
+///     guard.close()
+/// });
+///
+/// shutdown.join().unwrap()?;
+/// assert!(!path.exists());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct CleanupGuard {
+    path: Box<Path>,
+    disable_cleanup: bool,
+    cleanup: Cleanup,
+}
+
+impl fmt::Debug for CleanupGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CleanupGuard")
+            .field("path", &Redact(&*self.path))
+            .field("disable_cleanup", &self.disable_cleanup)
+            .field("cleanup", &self.cleanup)
+            .finish()
+    }
+}
+
+impl CleanupGuard {
+    pub(crate) fn new(path: PathBuf, disable_cleanup: bool, cleanup: Cleanup) -> Self {
+        CleanupGuard {
+            path: path.into_boxed_path(),
+            disable_cleanup,
+            cleanup,
+        }
+    }
+
+    /// Accesses the path of the temporary directory this guard cleans up.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Disable cleanup of the temporary directory. If `disable_cleanup` is `true`, the temporary
+    /// directory will not be deleted when this guard is dropped.
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.disable_cleanup = disable_cleanup;
+        self.cleanup.set_disabled(disable_cleanup);
+    }
+
+    /// Closes and removes the temporary directory, returning a `Result`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be deleted, `Err` is returned.
+    pub fn close(mut self) -> io::Result<()> {
+        let path = mem::replace(&mut self.path, PathBuf::new().into_boxed_path());
+        self.disable_cleanup = true;
+        self.cleanup.run(&path).with_err_path(|| &*path)?;
+        manifest::release(Kind::Dir, &path);
+        Ok(())
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        if !self.disable_cleanup && self.cleanup.run(&self.path).is_err() {
+            return;
+        }
+        manifest::release(Kind::Dir, &self.path);
+    }
+}
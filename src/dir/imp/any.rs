@@ -1,6 +1,9 @@
 use crate::error::IoResultExt;
+use crate::telemetry::Live;
+use crate::token::Kind;
 use crate::TempDir;
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 fn not_supported<T>(msg: &str) -> io::Result<T> {
@@ -20,5 +23,48 @@ pub fn create(
         .map(|_| TempDir {
             path: path.into_boxed_path(),
             disable_cleanup,
+            cleanup: Default::default(),
+            live: Live::new(Kind::Dir),
         })
 }
+
+pub fn create_all(path: &Path, permissions: Option<&std::fs::Permissions>) -> io::Result<()> {
+    if permissions.map_or(false, |p| p.readonly()) {
+        return not_supported("changing permissions is not supported on this platform");
+    }
+    fs::create_dir_all(path)
+}
+
+#[cfg(windows)]
+pub fn open(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_BACKUP_SEMANTICS;
+    // Directories can only be opened with FILE_FLAG_BACKUP_SEMANTICS.
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(not(windows))]
+pub fn open(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+#[cfg(windows)]
+pub fn set_permissions(path: &Path, permissions: std::fs::Permissions) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_READ_ATTRIBUTES, FILE_WRITE_ATTRIBUTES,
+    };
+    fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?
+        .set_permissions(permissions)
+}
+
+#[cfg(not(windows))]
+pub fn set_permissions(_path: &Path, _permissions: std::fs::Permissions) -> io::Result<()> {
+    not_supported("changing permissions is not supported on this platform")
+}
@@ -1,7 +1,10 @@
 use crate::error::IoResultExt;
+use crate::telemetry::Live;
+use crate::token::Kind;
 use crate::TempDir;
+use std::fs::{File, OpenOptions};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn create(
     path: PathBuf,
@@ -22,5 +25,72 @@ pub fn create(
         .map(|_| TempDir {
             path: path.into_boxed_path(),
             disable_cleanup,
+            cleanup: Default::default(),
+            live: Live::new(Kind::Dir),
         })
 }
+
+pub fn create_all(path: &Path, permissions: Option<&std::fs::Permissions>) -> io::Result<()> {
+    let mut dir_options = std::fs::DirBuilder::new();
+    dir_options.recursive(true);
+    #[cfg(not(target_os = "wasi"))]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        if let Some(p) = permissions {
+            dir_options.mode(p.mode());
+        }
+    }
+    #[cfg(target_os = "wasi")]
+    let _ = permissions;
+    dir_options.create(path)
+}
+
+pub fn open(path: &Path) -> io::Result<File> {
+    use rustix::fs::OFlags;
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new()
+        .read(true)
+        .custom_flags((OFlags::DIRECTORY | OFlags::NOFOLLOW).bits() as i32)
+        .open(path)
+}
+
+pub fn set_permissions(path: &Path, permissions: std::fs::Permissions) -> io::Result<()> {
+    open(path)?.set_permissions(permissions)
+}
+
+pub fn create_private(path: &Path, uid: u32) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    // The directory always belongs to the user this process is running as. If it belonged to
+    // `uid`, that user could replace it with a symbolic link between the checks below and the
+    // creation of the temporary files in it, redirecting a privileged process's writes.
+    // Instead, another user is only allowed to reach (but not list or modify) its contents.
+    let euid = rustix::process::geteuid().as_raw();
+    let mode = if uid == euid { 0o700 } else { 0o711 };
+    let created = match std::fs::DirBuilder::new().mode(0o700).create(path) {
+        Ok(()) => true,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => false,
+        Err(e) => return Err(e),
+    };
+    // Anyone can create files in the shared temporary directory, so the directory may have been
+    // created (or replaced with a symbolic link) by someone else. Check what was actually opened.
+    let not_private = || {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("directory isn't private to user {}", uid),
+        )
+    };
+    let dir = open(path).map_err(|e| match rustix::io::Errno::from_io_error(&e) {
+        Some(rustix::io::Errno::LOOP | rustix::io::Errno::NOTDIR) => not_private(),
+        _ => e,
+    })?;
+    if created {
+        // Not subject to the `umask`, unlike the mode passed to `mkdir`.
+        dir.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    }
+    let meta = dir.metadata()?;
+    if meta.uid() != euid || meta.mode() & 0o777 & !mode != 0 {
+        return Err(not_private());
+    }
+    Ok(())
+}
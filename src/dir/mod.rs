@@ -9,17 +9,31 @@
 // except according to those terms.
 
 use std::ffi::OsStr;
-use std::fs::remove_dir_all;
+use std::fs::{self, File};
 use std::mem;
-use std::path::{self, Path, PathBuf};
+use std::path::{self, Component, Path, PathBuf};
 use std::{fmt, io};
 
 use crate::error::IoResultExt;
+use crate::manifest;
+use crate::telemetry::{self, Live};
+use crate::token::{Kind, OwnershipToken};
+use crate::util::Redact;
 use crate::Builder;
 
 #[cfg(doc)]
 use crate::env;
 
+pub(crate) use self::cleanup::Cleanup;
+pub use self::cleanup::RemovalProgress;
+pub use self::entries::Entries;
+pub use self::global::GlobalTempDir;
+pub use self::guard::CleanupGuard;
+pub use self::shared::SharedTempDir;
+pub use self::subdir::SubdirBuilder;
+#[cfg(feature = "notify")]
+pub use self::watch::TempDirWatcher;
+
 /// Create a new temporary directory. Also see [`tempdir_in`].
 ///
 /// The `tempdir` function creates a directory in the file system and returns a
@@ -183,6 +197,8 @@ pub fn tempdir_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
 pub struct TempDir {
     path: Box<Path>,
     disable_cleanup: bool,
+    cleanup: Cleanup,
+    live: Live,
 }
 
 impl TempDir {
@@ -382,7 +398,419 @@ impl TempDir {
         self.path.as_ref()
     }
 
-    /// Deprecated alias for [`TempDir::keep`].
+    /// Accesses the temporary directory's path as a UTF-8 string, e.g., to embed it in a JSON
+    /// document or a command line. Use [`Builder::ascii_only`] to make sure it's ASCII.
+    ///
+    /// # Errors
+    ///
+    /// If the path isn't valid UTF-8, an error of kind [`io::ErrorKind::InvalidData`] is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let arg = format!("--out-dir={}", tmp_dir.utf8_path()?);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn utf8_path(&self) -> io::Result<&str> {
+        crate::util::utf8_path(self.path())
+    }
+
+    /// Opens a handle to the temporary directory itself.
+    ///
+    /// The returned [`File`] refers to the directory and remains valid even if the directory is
+    /// later renamed (or replaced by a temporary file cleaner). It can be used to identify the
+    /// directory (e.g., via [`File::metadata`]) or, with platform-specific APIs (e.g.,
+    /// `openat(2)`), to operate on the directory's contents relative to the handle instead of by
+    /// path.
+    ///
+    /// # Platform Notes
+    ///
+    /// On Unix, the directory is opened read-only with `O_DIRECTORY | O_NOFOLLOW`. On Windows, the
+    /// directory is opened with `FILE_FLAG_BACKUP_SEMANTICS`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be opened, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let handle = tmp_dir.open_handle()?;
+    /// assert!(handle.metadata()?.is_dir());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn open_handle(&self) -> io::Result<File> {
+        imp::open(self.path()).with_err_path(|| self.path())
+    }
+
+    /// Change the permissions of the temporary directory.
+    ///
+    /// Unlike `fs::set_permissions(tmp_dir.path(), ..)`, the directory is opened first (without
+    /// following symbolic links on Unix) and the permissions are changed through the resulting
+    /// handle.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be opened or its permissions can't be changed, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::Permissions;
+    /// use std::os::unix::fs::PermissionsExt;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// tmp_dir.set_permissions(Permissions::from_mode(0o700))?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn set_permissions(&self, permissions: fs::Permissions) -> io::Result<()> {
+        imp::set_permissions(self.path(), permissions).with_err_path(|| self.path())
+    }
+
+    /// Watch the temporary directory (recursively) for changes, returning a stream of filesystem
+    /// events. This is useful for asserting what a child process writes into the temporary
+    /// directory as it happens.
+    ///
+    /// This method requires the `notify` feature and reports events using the [`notify`] crate.
+    /// Events are delivered on a best-effort basis using the platform's native file watching APIs
+    /// (inotify, FSEvents, ReadDirectoryChangesW, etc.).
+    ///
+    /// # Errors
+    ///
+    /// If the watcher can't be started, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs;
+    /// use std::time::Duration;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let events = tmp_dir.watch()?;
+    ///
+    /// fs::write(tmp_dir.path().join("output.log"), "done")?;
+    ///
+    /// let event = events.recv_timeout(Duration::from_secs(5))?.expect("no event received");
+    /// println!("{:?}", event);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "notify")]
+    pub fn watch(&self) -> io::Result<TempDirWatcher> {
+        TempDirWatcher::new(self.path())
+    }
+
+    /// Reads the entire contents of a file inside the temporary directory into a string.
+    ///
+    /// The `path` is interpreted relative to the temporary directory. This is primarily intended
+    /// for making assertions about the contents of a temporary directory in tests.
+    ///
+    /// # Errors
+    ///
+    /// If `path` is absolute or contains `..` components (i.e., it could refer to a file outside
+    /// of the temporary directory), an error with kind [`io::ErrorKind::InvalidInput`] is returned.
+    /// Otherwise, any error encountered while reading the file is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// fs::write(tmp_dir.path().join("note.txt"), "Brian was here. Briefly.")?;
+    ///
+    /// assert_eq!(tmp_dir.read_to_string("note.txt")?, "Brian was here. Briefly.");
+    /// assert!(tmp_dir.read_to_string("../note.txt").is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let path = self.join_relative(path.as_ref())?;
+        fs::read_to_string(&path).with_err_path(|| path)
+    }
+
+    /// Write `contents` to a new executable file named `name` inside the temporary directory, and
+    /// return its path, ready to be run (e.g., with [`Command::new`](std::process::Command::new)).
+    ///
+    /// This is useful for tools that ship embedded helper binaries or scripts. The file is
+    /// created with mode `0o700` on Unix. On Windows, an `.exe` suffix is appended to `name`
+    /// unless it already has one. The file is closed before this function returns (on Linux,
+    /// files still open for writing can't be executed).
+    ///
+    /// # Errors
+    ///
+    /// If `name` is absolute or contains `..` components, an error with kind
+    /// [`io::ErrorKind::InvalidInput`] is returned. If a file named `name` already exists, or the
+    /// file cannot be created or written to, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// static HELPER: &[u8] = b"#!/bin/sh\necho hello\n";
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let helper = tmp_dir.write_executable("helper", HELPER)?;
+    /// # #[cfg(unix)]
+    /// assert_eq!(std::process::Command::new(&helper).output()?.stdout, b"hello\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_executable<S, C>(&self, name: S, contents: C) -> io::Result<PathBuf>
+    where
+        S: AsRef<OsStr>,
+        C: AsRef<[u8]>,
+    {
+        let name = crate::file::executable_name(name.as_ref());
+        let path = self.join_relative(Path::new(&name))?;
+        crate::file::write_executable(&path, contents.as_ref()).with_err_path(|| &path)?;
+        Ok(path)
+    }
+
+    /// Returns `true` if a file or directory exists at `path`, relative to the temporary
+    /// directory.
+    ///
+    /// Like [`Path::exists`], this follows symbolic links and returns `false` if the entry can't
+    /// be accessed. Paths that are absolute or contain `..` components are never considered to
+    /// exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// fs::create_dir(tmp_dir.path().join("subdir"))?;
+    ///
+    /// assert!(tmp_dir.exists("subdir"));
+    /// assert!(!tmp_dir.exists("missing.txt"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.join_relative(path.as_ref())
+            .map_or(false, |path| fs::metadata(path).is_ok())
+    }
+
+    /// Returns the names of the entries directly inside the temporary directory, sorted.
+    ///
+    /// The returned paths are relative to the temporary directory, which makes them convenient to
+    /// compare against an expected listing in tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error encountered while reading the directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::path::PathBuf;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// fs::write(tmp_dir.path().join("b.txt"), "")?;
+    /// fs::write(tmp_dir.path().join("a.txt"), "")?;
+    ///
+    /// assert_eq!(
+    ///     tmp_dir.entries_sorted()?,
+    ///     [PathBuf::from("a.txt"), PathBuf::from("b.txt")],
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn entries_sorted(&self) -> io::Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(self.path())
+            .and_then(|dir| {
+                dir.map(|entry| entry.map(|e| PathBuf::from(e.file_name())))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .with_err_path(|| self.path())?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Returns an iterator over the entries of the temporary directory, as paths relative to the
+    /// temporary directory.
+    ///
+    /// The iterator can be configured to walk subdirectories recursively
+    /// ([`Entries::recursive`]) and to sort the entries ([`Entries::sorted`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::path::PathBuf;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// fs::create_dir(tmp_dir.path().join("sub"))?;
+    /// fs::write(tmp_dir.path().join("sub").join("b.txt"), "")?;
+    /// fs::write(tmp_dir.path().join("a.txt"), "")?;
+    ///
+    /// let entries = tmp_dir
+    ///     .entries()
+    ///     .recursive(true)
+    ///     .sorted(true)
+    ///     .collect::<std::io::Result<Vec<_>>>()?;
+    /// assert_eq!(
+    ///     entries,
+    ///     [
+    ///         PathBuf::from("a.txt"),
+    ///         PathBuf::from("sub"),
+    ///         PathBuf::from("sub").join("b.txt"),
+    ///     ],
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn entries(&self) -> Entries<'_> {
+        Entries::new(self.path())
+    }
+
+    /// Joins `path` onto the temporary directory's path, refusing paths that would escape it. This
+    /// is meant for composing paths from untrusted names (e.g., file names received by a server).
+    ///
+    /// `path` is normalized first: `.` components are dropped, and `..` components remove the
+    /// preceding component. The joined path is then refused if:
+    ///
+    /// - `path` is absolute (or has a root or prefix, like `\foo` or `C:foo` on Windows);
+    /// - a `..` component would leave the temporary directory;
+    /// - any existing component of the joined path (including the last one) is a symbolic link.
+    ///
+    /// An empty path (or one normalizing to nothing) returns the temporary directory's path.
+    ///
+    /// Symbolic links are only checked when this method is called, so this doesn't protect
+    /// against links created afterwards by someone with write access to the temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// If the path is refused, an error of kind [`io::ErrorKind::InvalidInput`] is returned. If an
+    /// existing component can't be inspected, `Err` is returned as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    ///
+    /// assert_eq!(
+    ///     tmp_dir.join_checked("uploads/./../report.txt")?,
+    ///     tmp_dir.path().join("report.txt"),
+    /// );
+    /// assert!(tmp_dir.join_checked("../etc/passwd").is_err());
+    /// assert!(tmp_dir.join_checked("/etc/passwd").is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn join_checked<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = path.as_ref();
+        let refuse = |msg: &str| {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, msg)).with_err_path(|| path)
+        };
+
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => normalized.push(name),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return refuse("path escapes the temporary directory");
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    return refuse("path escapes the temporary directory");
+                }
+            }
+        }
+
+        let mut joined = self.path.to_path_buf();
+        let mut exists = true;
+        for component in normalized.components() {
+            joined.push(component);
+            // Once a component is missing, nothing below it can exist either.
+            if !exists {
+                continue;
+            }
+            match fs::symlink_metadata(&joined) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    return refuse("path traverses a symbolic link");
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => exists = false,
+                Err(e) => return Err(e).with_err_path(|| joined),
+            }
+        }
+        Ok(joined)
+    }
+
+    /// Joins `path` onto the temporary directory's path, refusing any path that could refer to a
+    /// location outside of the temporary directory.
+    fn join_relative(&self, path: &Path) -> io::Result<PathBuf> {
+        if !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path escapes the temporary directory",
+            ))
+            .with_err_path(|| path);
+        }
+        Ok(self.path.join(path))
+    }
+
+    /// Leak the temporary directory for the rest of the process, returning its path, and delete it
+    /// when the process exits.
+    ///
+    /// Unlike [`TempDir::keep`], which leaves the directory behind for good, this is meant for
+    /// directories that must outlive any particular scope but not the process itself (e.g., a
+    /// scratch directory handed to a long-lived library). The directory is cleaned up by an exit
+    /// hook as it would have been on drop (honoring [`Builder::remove_only_if_empty`], for example),
+    /// unless cleanup has been disabled (see [`TempDir::disable_cleanup`]), in which case it's kept.
+    ///
+    /// Like with [`GlobalTempDir`], the exit hook only runs when the process exits normally, e.g.,
+    /// by returning from `main`. It does not run if the process is killed by a signal, aborts, or
+    /// crashes, nor on platforms without exit hooks (e.g., `wasm32-unknown-unknown`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use tempfile::TempDir;
+    ///
+    /// let scratch: &'static Path = TempDir::new()?.leak();
+    /// std::thread::spawn(move || std::fs::write(scratch.join("log.txt"), "..."))
+    ///     .join()
+    ///     .unwrap()?;
+    /// assert!(scratch.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn leak(mut self) -> &'static Path {
+        let disable_cleanup = self.disable_cleanup;
+        let cleanup = mem::take(&mut self.cleanup);
+        let path = self.disarm();
+        let leaked = Box::leak(path.clone().into_boxed_path());
+        if !disable_cleanup {
+            crate::exit::cleanup_dir_at_exit(path, cleanup);
+        }
+        leaked
+    }
+
+    /// Deprecated alias for [`TempDir::keep`]. To keep the directory only for the rest of the
+    /// process, use [`TempDir::leak`].
     #[must_use]
     #[deprecated = "use TempDir::keep()"]
     pub fn into_path(self) -> PathBuf {
@@ -418,10 +846,128 @@ impl TempDir {
     /// ```
     #[must_use]
     pub fn keep(mut self) -> PathBuf {
+        manifest::release(Kind::Dir, self.path());
+        self.disarm()
+    }
+
+    /// Disable cleanup and return the path, leaving the directory recorded in the manifest (for
+    /// when the responsibility of deleting it is passed on within this process).
+    fn disarm(&mut self) -> PathBuf {
         self.disable_cleanup(true);
         mem::replace(&mut self.path, PathBuf::new().into_boxed_path()).into()
     }
 
+    /// Returns a builder for temporary directories and files inside this temporary directory, each
+    /// with their own settings (prefix, permissions, cleanup, etc.).
+    ///
+    /// See [`SubdirBuilder`] for an example.
+    #[must_use]
+    pub fn subdir_builder(&self) -> SubdirBuilder<'_, '_, '_> {
+        SubdirBuilder::new(self)
+    }
+
+    /// Convert this `TempDir` into a [`SharedTempDir`] that can be cloned and shared. The directory
+    /// is deleted when the last clone is dropped or closed.
+    #[must_use]
+    pub fn share(self) -> SharedTempDir {
+        SharedTempDir::from(self)
+    }
+
+    /// Split the temporary directory into its path and a [`CleanupGuard`] that deletes it when
+    /// dropped.
+    ///
+    /// This makes it possible to move the responsibility of cleaning up the directory somewhere
+    /// else than where the path is used. If cleanup has been disabled on this `TempDir`, it is also
+    /// disabled on the returned guard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let (path, guard) = TempDir::new()?.into_parts();
+    /// assert_eq!(path, guard.path());
+    ///
+    /// drop(guard);
+    /// assert!(!path.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn into_parts(mut self) -> (PathBuf, CleanupGuard) {
+        let disable_cleanup = self.disable_cleanup;
+        let cleanup = mem::take(&mut self.cleanup);
+        let path = self.disarm();
+        let guard = CleanupGuard::new(path.clone(), disable_cleanup, cleanup);
+        (path, guard)
+    }
+
+    /// Give up ownership of the temporary directory, returning an [`OwnershipToken`] that can be
+    /// used to re-adopt it with [`TempDir::adopt`], possibly in another process.
+    ///
+    /// The temporary directory will _not_ be deleted when the token is dropped. Whoever adopts the
+    /// token becomes responsible for deleting it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// let token = tmp_dir.into_token();
+    ///
+    /// let tmp_dir = TempDir::adopt(token)?;
+    /// assert!(tmp_dir.path().exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn into_token(self) -> OwnershipToken {
+        OwnershipToken::new(Kind::Dir, self.keep())
+    }
+
+    /// Adopt a temporary directory from an [`OwnershipToken`] created by [`TempDir::into_token`],
+    /// taking over the responsibility of deleting it.
+    ///
+    /// # Errors
+    ///
+    /// If the token refers to a file, or if the directory no longer exists, `Err` is returned.
+    pub fn adopt(token: OwnershipToken) -> io::Result<TempDir> {
+        token.into_path(Kind::Dir).map(|path| TempDir {
+            path: path.into_boxed_path(),
+            disable_cleanup: false,
+            cleanup: Cleanup::default(),
+            live: Live::new(Kind::Dir),
+        })
+    }
+
+    /// Register this long-lived temporary directory under `name` in the current user's on-disk
+    /// registry, so it can be re-adopted (or deleted) with [`reclaim`](crate::reclaim) if this
+    /// process exits without cleaning it up (e.g., because it crashed).
+    ///
+    /// Registering a name again (from this process) replaces the previous registration. Remove the
+    /// registration with [`unregister`](crate::unregister) once the directory is no longer needed.
+    ///
+    /// # Errors
+    ///
+    /// If `name` contains anything but ASCII letters, digits, `.`, `_`, and `-` (or starts with a
+    /// `.`), an error of kind [`io::ErrorKind::InvalidInput`] is returned. If another running
+    /// process has registered `name`, an error of kind [`io::ErrorKind::AlreadyExists`] is
+    /// returned. If the registry can't be written, `Err` is returned as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// let cache = TempDir::new()?;
+    /// cache.register("myapp-render-cache")?;
+    /// // ...
+    /// tempfile::unregister("myapp-render-cache")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn register(&self, name: &str) -> io::Result<()> {
+        crate::registry::register(name, Kind::Dir, &self.path)
+    }
+
     /// Disable cleanup of the temporary directory. If `disable_cleanup` is `true`, the temporary
     /// directory will not be deleted when this `TempDir` is dropped. This method is equivalent to
     /// calling [`Builder::disable_cleanup`] when creating the `TempDir`.
@@ -429,7 +975,56 @@ impl TempDir {
     /// **NOTE:** this method is primarily useful for testing/debugging. If you want to simply turn
     /// a temporary directory into a non-temporary directory, prefer [`TempDir::keep`].
     pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
-        self.disable_cleanup = disable_cleanup
+        self.disable_cleanup = disable_cleanup;
+        self.cleanup.set_disabled(disable_cleanup);
+    }
+
+    /// Only delete the temporary directory if it's empty. If `remove_only_if_empty` is `true`, the
+    /// temporary directory is left behind (together with its contents) when it's not empty at the
+    /// time this `TempDir` is dropped or closed. This method is equivalent to calling
+    /// [`Builder::remove_only_if_empty`] when creating the `TempDir`.
+    pub fn remove_only_if_empty(&mut self, remove_only_if_empty: bool) {
+        self.cleanup.remove_only_if_empty = remove_only_if_empty
+    }
+
+    /// Preserve selected files when the temporary directory is deleted (on drop or by
+    /// [`TempDir::close`]) by first moving them to `destination`.
+    ///
+    /// `filter` is called with the path (relative to the temporary directory) of every
+    /// non-directory entry, recursively; matching entries are moved to the same relative path
+    /// below `destination`, creating directories as needed. Files are copied instead if they can't
+    /// be renamed (e.g., because `destination` is on another filesystem). Calling this method again
+    /// replaces the previous filter and destination.
+    ///
+    /// If a file can't be preserved, the temporary directory is left behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// let artifacts = TempDir::new()?;
+    ///
+    /// let mut scratch = TempDir::new()?;
+    /// scratch.preserve_on_drop(
+    ///     |path| path.extension().map_or(false, |ext| ext == "log"),
+    ///     artifacts.path(),
+    /// );
+    /// fs::write(scratch.path().join("build.log"), "build output")?;
+    /// fs::write(scratch.path().join("build.o"), "object file")?;
+    /// drop(scratch);
+    ///
+    /// assert!(artifacts.path().join("build.log").exists());
+    /// assert!(!artifacts.path().join("build.o").exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn preserve_on_drop<F, P>(&mut self, filter: F, destination: P)
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+        P: Into<PathBuf>,
+    {
+        self.cleanup.preserve = Some((Box::new(filter), destination.into()));
     }
 
     /// Closes and removes the temporary directory, returning a `Result`.
@@ -470,17 +1065,64 @@ impl TempDir {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn close(mut self) -> io::Result<()> {
-        let result = remove_dir_all(self.path()).with_err_path(|| self.path());
+        let result = self.cleanup.run(self.path()).with_err_path(|| self.path());
+        if result.is_ok() {
+            manifest::release(Kind::Dir, self.path());
+        }
 
         // Set self.path to empty Box to release the memory, since an empty
         // Box does not allocate any heap memory.
         self.path = PathBuf::new().into_boxed_path();
 
-        // Prevent the Drop impl from being called.
+        // Prevent the Drop impl from being called, but do drop the cleanup settings (which may own
+        // a watchdog process).
+        drop(mem::take(&mut self.cleanup));
+        self.live.release();
         mem::forget(self);
 
         result
     }
+
+    /// Closes and removes the temporary directory like [`TempDir::close`], calling `progress`
+    /// after each file or directory is removed.
+    ///
+    /// This is useful for reporting the progress of deleting large scratch directories (e.g., in
+    /// a progress bar or a log).
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be deleted, `Err` is returned. The directory may have been partially
+    /// deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// let tmp_dir = TempDir::new()?;
+    /// fs::write(tmp_dir.path().join("data.bin"), [0u8; 1024])?;
+    ///
+    /// let mut freed = 0;
+    /// tmp_dir.close_with_progress(|progress| {
+    ///     freed = progress.bytes_freed();
+    ///     println!("removed {}", progress.path().display());
+    /// })?;
+    /// assert_eq!(freed, 1024);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn close_with_progress<F>(mut self, mut progress: F) -> io::Result<()>
+    where
+        F: FnMut(&RemovalProgress<'_>),
+    {
+        let path = mem::replace(&mut self.path, PathBuf::new().into_boxed_path());
+        self.disable_cleanup = true;
+        self.cleanup
+            .run_with_progress(&path, &mut progress)
+            .with_err_path(|| &*path)?;
+        manifest::release(Kind::Dir, &path);
+        Ok(())
+    }
 }
 
 impl AsRef<Path> for TempDir {
@@ -492,7 +1134,7 @@ impl AsRef<Path> for TempDir {
 impl fmt::Debug for TempDir {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TempDir")
-            .field("path", &self.path())
+            .field("path", &Redact(self.path()))
             .finish()
     }
 }
@@ -500,11 +1142,60 @@ impl fmt::Debug for TempDir {
 impl Drop for TempDir {
     fn drop(&mut self) {
         if !self.disable_cleanup {
-            let _ = remove_dir_all(self.path());
+            if let Err(e) = self.cleanup.run(self.path()) {
+                telemetry::cleanup_failed(Kind::Dir, self.path(), &e);
+                return;
+            }
         }
+        manifest::release(Kind::Dir, self.path());
     }
 }
 
+/// Creates `path` and all of its missing parents (like `mkdir -p`).
+pub(crate) fn create_all(
+    path: &Path,
+    permissions: Option<&std::fs::Permissions>,
+) -> io::Result<()> {
+    imp::create_all(path, permissions).with_err_path(|| path)
+}
+
+/// Creates the directory at `path`, owned by the effective user and accessible only to it and
+/// the user `uid`, or checks that the existing directory is.
+#[cfg(unix)]
+pub(crate) fn create_private(path: &Path, uid: u32) -> io::Result<()> {
+    imp::create_private(path, uid).with_err_path(|| path)
+}
+
+/// Mounts a private tmpfs of at most `size` bytes on the freshly created temporary directory `dir`,
+/// unmounting it again when `dir` is cleaned up.
+#[cfg(all(feature = "tmpfs", target_os = "linux"))]
+pub(crate) fn mount_tmpfs(
+    dir: &mut TempDir,
+    size: u64,
+    permissions: Option<&std::fs::Permissions>,
+) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.map_or(0o700, |p| p.mode());
+    tmpfs::mount(dir.path(), size, mode).with_err_path(|| dir.path())?;
+    dir.cleanup.tmpfs = true;
+    Ok(())
+}
+
+/// Starts a watchdog that removes the freshly created temporary directory `dir` if the process is
+/// killed (Windows only).
+#[cfg(windows)]
+pub(crate) fn watch_for_kill(dir: &mut TempDir) -> io::Result<()> {
+    let watchdog = watchdog::Watchdog::spawn(dir.path()).with_err_path(|| dir.path())?;
+    watchdog.set_keep(dir.disable_cleanup);
+    dir.cleanup.watchdog = Some(watchdog);
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub(crate) fn watch_for_kill(_dir: &mut TempDir) -> io::Result<()> {
+    Ok(())
+}
+
 pub(crate) fn create(
     path: PathBuf,
     permissions: Option<&std::fs::Permissions>,
@@ -513,4 +1204,16 @@ pub(crate) fn create(
     imp::create(path, permissions, disable_cleanup)
 }
 
+mod cleanup;
+mod entries;
+mod global;
+mod guard;
 mod imp;
+mod shared;
+mod subdir;
+#[cfg(all(feature = "tmpfs", target_os = "linux"))]
+mod tmpfs;
+#[cfg(feature = "notify")]
+mod watch;
+#[cfg(windows)]
+mod watchdog;
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::TempDir;
+use crate::util::Redact;
+
+/// A reference-counted [`TempDir`], created with [`TempDir::share`], that can be cheaply cloned
+/// and shared (e.g., between async tasks).
+///
+/// The directory is deleted when the last clone is dropped or [closed](SharedTempDir::close).
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use tempfile::TempDir;
+///
+/// let scratch = TempDir::new()?.share();
+///
+/// let workers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let scratch = scratch.clone();
+///         std::thread::spawn(move || fs::write(scratch.path().join(i.to_string()), "done"))
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap()?;
+/// }
+///
+/// // All other clones are gone, so this deletes the directory.
+/// scratch.close()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedTempDir {
+    inner: Arc<TempDir>,
+}
+
+impl SharedTempDir {
+    /// Accesses the path to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// Returns the underlying [`TempDir`] if this is the only remaining reference to it.
+    ///
+    /// # Errors
+    ///
+    /// If other clones of this `SharedTempDir` exist, `self` is returned unchanged.
+    pub fn try_unwrap(self) -> Result<TempDir, SharedTempDir> {
+        Arc::try_unwrap(self.inner).map_err(|inner| SharedTempDir { inner })
+    }
+
+    /// Returns the number of references to this temporary directory.
+    #[must_use]
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+
+    /// Releases this reference to the temporary directory. If it's the last one, the directory is
+    /// removed like [`TempDir::close`] and any error is returned.
+    ///
+    /// # Errors
+    ///
+    /// If this is the last reference and the directory cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        match self.try_unwrap() {
+            Ok(dir) => dir.close(),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl From<TempDir> for SharedTempDir {
+    fn from(dir: TempDir) -> SharedTempDir {
+        SharedTempDir {
+            inner: Arc::new(dir),
+        }
+    }
+}
+
+impl AsRef<Path> for SharedTempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl fmt::Debug for SharedTempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedTempDir")
+            .field("path", &Redact(self.path()))
+            .finish()
+    }
+}
@@ -0,0 +1,114 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::Permissions;
+use std::io;
+use std::path::Path;
+
+use super::TempDir;
+use crate::util::Redact;
+use crate::{Builder, NamedTempFile};
+
+/// A [`Builder`] for temporary directories and files inside an existing [`TempDir`], returned by
+/// [`TempDir::subdir_builder`].
+///
+/// Each created directory or file is an independent guard with its own settings. Note that the
+/// parent `TempDir` still deletes everything inside it when it's cleaned up.
+///
+/// # Examples
+///
+/// Create a world-readable directory inside a private scratch directory.
+///
+/// ```
+/// # #[cfg(unix)]
+/// # {
+/// use std::fs::Permissions;
+/// use std::os::unix::fs::PermissionsExt;
+/// use tempfile::Builder;
+///
+/// let scratch = Builder::new().permissions(Permissions::from_mode(0o700)).tempdir()?;
+/// let public = scratch
+///     .subdir_builder()
+///     .prefix("public-")
+///     .permissions(Permissions::from_mode(0o755))
+///     .tempdir()?;
+/// assert!(public.path().starts_with(scratch.path()));
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SubdirBuilder<'d, 'a, 'b> {
+    parent: &'d Path,
+    builder: Builder<'a, 'b>,
+}
+
+impl fmt::Debug for SubdirBuilder<'_, '_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubdirBuilder")
+            .field("parent", &Redact(self.parent))
+            .field("builder", &self.builder)
+            .finish()
+    }
+}
+
+impl<'d, 'a, 'b> SubdirBuilder<'d, 'a, 'b> {
+    pub(crate) fn new(parent: &'d TempDir) -> Self {
+        SubdirBuilder {
+            parent: parent.path(),
+            builder: Builder::new(),
+        }
+    }
+
+    /// Set a custom filename prefix. See [`Builder::prefix`].
+    pub fn prefix<S: AsRef<OsStr> + ?Sized>(&mut self, prefix: &'a S) -> &mut Self {
+        self.builder.prefix(prefix);
+        self
+    }
+
+    /// Set a custom filename suffix. See [`Builder::suffix`].
+    pub fn suffix<S: AsRef<OsStr> + ?Sized>(&mut self, suffix: &'b S) -> &mut Self {
+        self.builder.suffix(suffix);
+        self
+    }
+
+    /// Set the number of random bytes. See [`Builder::rand_bytes`].
+    pub fn rand_bytes(&mut self, rand: usize) -> &mut Self {
+        self.builder.rand_bytes(rand);
+        self
+    }
+
+    /// Set the permissions to create the directory or file with. See [`Builder::permissions`].
+    pub fn permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.builder.permissions(permissions);
+        self
+    }
+
+    /// Disable cleanup of the directory or file. See [`Builder::disable_cleanup`].
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) -> &mut Self {
+        self.builder.disable_cleanup(disable_cleanup);
+        self
+    }
+
+    /// Only delete the directory if it's empty. See [`Builder::remove_only_if_empty`].
+    pub fn remove_only_if_empty(&mut self, remove_only_if_empty: bool) -> &mut Self {
+        self.builder.remove_only_if_empty(remove_only_if_empty);
+        self
+    }
+
+    /// Create a temporary directory inside the parent `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    pub fn tempdir(&self) -> io::Result<TempDir> {
+        self.builder.tempdir_in(self.parent)
+    }
+
+    /// Create a named temporary file inside the parent `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, `Err` is returned.
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.builder.tempfile_in(self.parent)
+    }
+}
@@ -0,0 +1,30 @@
+use std::ffi::CString;
+use std::io;
+use std::path::Path;
+
+use rustix::mount::{MountFlags, MountPropagationFlags, UnmountFlags};
+
+/// Mounts a private tmpfs of at most `size` bytes on the (empty) directory at `path`.
+pub(crate) fn mount(path: &Path, size: u64, mode: u32) -> io::Result<()> {
+    let data = CString::new(format!("size={},mode={:o}", size, mode & 0o7777))
+        .expect("mount options never contain NUL bytes");
+    rustix::mount::mount(
+        "tmpfs",
+        path,
+        "tmpfs",
+        MountFlags::NOSUID | MountFlags::NODEV,
+        data.as_c_str(),
+    )?;
+    // Don't propagate the mount (or anything mounted on top of it) to other mount namespaces.
+    if let Err(e) = rustix::mount::mount_change(path, MountPropagationFlags::PRIVATE) {
+        let _ = unmount(path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Lazily unmounts the tmpfs mounted on `path`, discarding its contents.
+pub(crate) fn unmount(path: &Path) -> io::Result<()> {
+    rustix::mount::unmount(path, UnmountFlags::DETACH)?;
+    Ok(())
+}
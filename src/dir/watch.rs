@@ -0,0 +1,84 @@
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::IoResultExt;
+
+/// A stream of filesystem events inside of a [`TempDir`](crate::TempDir), created by
+/// [`TempDir::watch`](crate::TempDir::watch).
+///
+/// Events are reported using the [`notify`] crate's [`Event`] type. Iterating over a
+/// `TempDirWatcher` blocks until the next event arrives; use [`TempDirWatcher::recv_timeout`] or
+/// [`TempDirWatcher::try_recv`] to avoid blocking indefinitely.
+///
+/// The underlying watcher is stopped when the `TempDirWatcher` is dropped.
+pub struct TempDirWatcher {
+    rx: Receiver<notify::Result<Event>>,
+    // Keep the watcher alive for as long as we're receiving events.
+    _watcher: RecommendedWatcher,
+}
+
+impl TempDirWatcher {
+    pub(crate) fn new(path: &Path) -> io::Result<TempDirWatcher> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(to_io_error)
+            .with_err_path(|| path)?;
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(to_io_error)
+            .with_err_path(|| path)?;
+        Ok(TempDirWatcher {
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait up to `timeout` for the next event.
+    ///
+    /// Returns `Ok(None)` if no event arrived within `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the watcher reported an error.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Event>> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(event) => event.map(Some).map_err(to_io_error),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+
+    /// Returns the next event if one is immediately available.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the watcher reported an error.
+    pub fn try_recv(&self) -> io::Result<Option<Event>> {
+        self.recv_timeout(Duration::from_secs(0))
+    }
+}
+
+impl Iterator for TempDirWatcher {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        self.rx.recv().ok().map(|event| event.map_err(to_io_error))
+    }
+}
+
+impl std::fmt::Debug for TempDirWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TempDirWatcher").finish_non_exhaustive()
+    }
+}
+
+fn to_io_error(err: notify::Error) -> io::Error {
+    match err.kind {
+        notify::ErrorKind::Io(err) => err,
+        notify::ErrorKind::PathNotFound => io::ErrorKind::NotFound.into(),
+        _ => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
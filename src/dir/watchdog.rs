@@ -0,0 +1,213 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use windows_sys::Win32::Foundation::{GetLastError, ERROR_ALREADY_EXISTS, HANDLE};
+use windows_sys::Win32::System::Threading::{
+    CreateEventW, ResetEvent, SetEvent, WaitForSingleObject, CREATE_BREAKAWAY_FROM_JOB,
+    CREATE_NO_WINDOW,
+};
+
+/// How long to wait for the watchdog to start (PowerShell can be slow to start on a busy system).
+const READY_TIMEOUT_MS: u32 = 30_000;
+
+/// Removes the contents of a directory like `remove_contents` in `cleanup.rs` does: symbolic links
+/// and junctions are removed, never followed. `Remove-Item -Recurse` follows junctions in Windows
+/// PowerShell, so it can't be used.
+const REMOVE_CONTENTS: &str = "function Remove-Contents($dir) {
+    foreach ($entry in (New-Object System.IO.DirectoryInfo -ArgumentList $dir).GetFileSystemInfos()) {
+        $isLink = ($entry.Attributes -band [System.IO.FileAttributes]::ReparsePoint) -ne 0
+        if ($entry -is [System.IO.DirectoryInfo]) {
+            if (-not $isLink) {
+                Remove-Contents $entry.FullName
+            }
+            [System.IO.Directory]::Delete($entry.FullName, $false)
+        } else {
+            if (-not $isLink) {
+                $entry.Attributes = [System.IO.FileAttributes]::Normal
+            }
+            [System.IO.File]::Delete($entry.FullName)
+        }
+    }
+}";
+
+/// A watchdog process that removes a temporary directory once this process has exited, even if it
+/// was killed (see [`crate::Builder::windows_cleanup_on_kill`]).
+///
+/// The watchdog is a hidden PowerShell process. It's started through an intermediate PowerShell
+/// process that exits right away, so that it isn't part of this process's tree and survives
+/// "End process tree". We communicate with it through three named events:
+///
+/// - `ready` is set by the watchdog once it has opened the other events and this process.
+/// - `keep` is set by us while cleanup is disabled. The watchdog checks it after we've exited.
+/// - `done` is set by us once the `TempDir` has been dropped, telling the watchdog to exit early.
+pub(crate) struct Watchdog {
+    keep: OwnedHandle,
+    done: OwnedHandle,
+}
+
+impl Watchdog {
+    /// Starts a watchdog for the directory at `path`, and waits until it's ready.
+    pub(crate) fn spawn(path: &Path) -> io::Result<Watchdog> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "Local\\tempfile-watchdog-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let keep = create_event(&format!("{}-keep", name))?;
+        let done = create_event(&format!("{}-done", name))?;
+        let ready = create_event(&format!("{}-ready", name))?;
+
+        let watchdog = format!(
+            "$ErrorActionPreference = 'Stop'
+$parent = Get-Process -Id {pid}
+$exited = New-Object System.Threading.ManualResetEvent -ArgumentList $false
+$exited.SafeWaitHandle = New-Object Microsoft.Win32.SafeHandles.SafeWaitHandle -ArgumentList $parent.Handle, $false
+$keep = [System.Threading.EventWaitHandle]::OpenExisting('{name}-keep')
+$done = [System.Threading.EventWaitHandle]::OpenExisting('{name}-done')
+[void][System.Threading.EventWaitHandle]::OpenExisting('{name}-ready').Set()
+if ([System.Threading.WaitHandle]::WaitAny(@($exited, $done)) -ne 0 -or $keep.WaitOne(0)) {{
+    exit
+}}
+{remove}
+try {{
+    Remove-Contents {path}
+    [System.IO.Directory]::Delete({path}, $false)
+}} catch {{}}",
+            pid = process::id(),
+            name = name,
+            remove = REMOVE_CONTENTS,
+            path = quote(path.as_os_str()),
+        );
+        let powershell = powershell();
+        let launcher = format!(
+            "Start-Process -FilePath {} -WindowStyle Hidden -ArgumentList '-NoProfile -NonInteractive -EncodedCommand {}'",
+            quote(powershell.as_os_str()),
+            encode_command(&watchdog),
+        );
+
+        let mut command = Command::new(&powershell);
+        command
+            .args(["-NoProfile", "-NonInteractive", "-EncodedCommand"])
+            .arg(encode_command(&launcher))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Break away from our job (if any), in case it kills its processes when it's closed. Jobs
+        // that don't allow this make process creation fail, in which case we simply stay in it.
+        let mut launcher = command
+            .creation_flags(CREATE_NO_WINDOW | CREATE_BREAKAWAY_FROM_JOB)
+            .spawn()
+            .or_else(|_| command.creation_flags(CREATE_NO_WINDOW).spawn())?;
+        let status = launcher.wait()?;
+        if !status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to start the cleanup watchdog ({})", status),
+            ));
+        }
+
+        match unsafe { WaitForSingleObject(raw(&ready), READY_TIMEOUT_MS) } {
+            // WAIT_OBJECT_0
+            0 => Ok(Watchdog { keep, done }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for the cleanup watchdog to start",
+            )),
+        }
+    }
+
+    /// Tells the watchdog whether to leave the directory alone after this process has exited.
+    pub(crate) fn set_keep(&self, keep: bool) {
+        unsafe {
+            if keep {
+                SetEvent(raw(&self.keep));
+            } else {
+                ResetEvent(raw(&self.keep));
+            }
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // The directory has been cleaned up (or kept), so the watchdog is no longer needed.
+        unsafe { SetEvent(raw(&self.done)) };
+    }
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog").finish_non_exhaustive()
+    }
+}
+
+fn raw(handle: &OwnedHandle) -> HANDLE {
+    handle.as_raw_handle() as HANDLE
+}
+
+/// Creates a new manual-reset event named `name`, failing if it already exists.
+fn create_event(name: &str) -> io::Result<OwnedHandle> {
+    let name_w: Vec<u16> = name.encode_utf16().chain(iter::once(0)).collect();
+    unsafe {
+        let handle = CreateEventW(ptr::null(), 1, 0, name_w.as_ptr());
+        if handle as usize == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let handle = OwnedHandle::from_raw_handle(handle as RawHandle);
+        if GetLastError() == ERROR_ALREADY_EXISTS {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("event {} already exists", name),
+            ));
+        }
+        Ok(handle)
+    }
+}
+
+/// Returns the path of Windows PowerShell, which is installed on every supported version of
+/// Windows. Don't rely on `PATH`, it may point at something else.
+fn powershell() -> PathBuf {
+    match std::env::var_os("SystemRoot") {
+        Some(root) => Path::new(&root).join(r"System32\WindowsPowerShell\v1.0\powershell.exe"),
+        None => PathBuf::from("powershell.exe"),
+    }
+}
+
+/// Quotes `s` as a PowerShell expression that evaluates to exactly `s`. It's spelled out as UTF-16
+/// code units so that paths that aren't valid Unicode survive unchanged.
+fn quote(s: &OsStr) -> String {
+    let units: Vec<String> = s.encode_wide().map(|u| u.to_string()).collect();
+    format!("(-join [char[]]@({}))", units.join(","))
+}
+
+/// Encodes `script` for PowerShell's `-EncodedCommand` (base64-encoded UTF-16LE), which avoids any
+/// command line quoting issues.
+fn encode_command(script: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let bytes: Vec<u8> = script.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,108 @@
+//! Seeds for the random name generator, from the best source of entropy available at runtime.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// The source of entropy used to re-seed the random name generator (see
+/// [`env::entropy_source`](crate::env::entropy_source)).
+///
+/// Sources are tried in the order listed here, and the first one that works is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EntropySource {
+    /// The operating system's random number generator, through the [`getrandom`] crate (requires
+    /// the `getrandom` feature).
+    ///
+    /// [`getrandom`]: https://docs.rs/getrandom
+    Getrandom,
+    /// The `/dev/urandom` device (Unix only), e.g., when the `getrandom` feature is disabled or
+    /// the `getrandom` system call is blocked by a sandbox.
+    DevUrandom,
+    /// A mix of the current time, the process and thread IDs, memory addresses (randomized by
+    /// ASLR), and the standard library's hash keys. This is hard, but not impossible, to predict.
+    Mixed,
+}
+
+impl EntropySource {
+    /// Returns `true` if the source is a cryptographically secure random number generator.
+    #[must_use]
+    pub fn is_secure(self) -> bool {
+        !matches!(self, EntropySource::Mixed)
+    }
+}
+
+/// The most recently used source, or `NONE`.
+static LAST_SOURCE: AtomicU8 = AtomicU8::new(NONE);
+const NONE: u8 = u8::MAX;
+
+/// Returns the source that was used by the last call to [`seed`], trying the sources (and
+/// discarding the seed) if it hasn't been called yet.
+pub(crate) fn source() -> EntropySource {
+    match LAST_SOURCE.load(Ordering::Relaxed) {
+        NONE => seed().1,
+        n => from_u8(n),
+    }
+}
+
+/// Returns a seed from the best available source of entropy, along with the source.
+pub(crate) fn seed() -> (u64, EntropySource) {
+    let (seed, source) = getrandom()
+        .map(|seed| (seed, EntropySource::Getrandom))
+        .or_else(|| dev_urandom().map(|seed| (seed, EntropySource::DevUrandom)))
+        .unwrap_or_else(|| (mixed(), EntropySource::Mixed));
+    LAST_SOURCE.store(source as u8, Ordering::Relaxed);
+    (seed, source)
+}
+
+fn from_u8(n: u8) -> EntropySource {
+    match n {
+        0 => EntropySource::Getrandom,
+        1 => EntropySource::DevUrandom,
+        _ => EntropySource::Mixed,
+    }
+}
+
+#[cfg(all(feature = "getrandom", any(windows, unix, target_os = "wasi")))]
+fn getrandom() -> Option<u64> {
+    getrandom::u64().ok()
+}
+
+#[cfg(not(all(feature = "getrandom", any(windows, unix, target_os = "wasi"))))]
+fn getrandom() -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn dev_urandom() -> Option<u64> {
+    use std::io::Read;
+    let mut buf = [0; 8];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut buf))
+        .ok()?;
+    Some(u64::from_ne_bytes(buf))
+}
+
+#[cfg(not(unix))]
+fn dev_urandom() -> Option<u64> {
+    None
+}
+
+fn mixed() -> u64 {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // `RandomState` is seeded from system randomness where the standard library can get it.
+    let mut hasher = RandomState::new().build_hasher();
+    SystemTime::now().hash(&mut hasher);
+    COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    std::thread::current().id().hash(&mut hasher);
+    #[cfg(any(unix, windows))]
+    std::process::id().hash(&mut hasher);
+    // Stack, heap, and code addresses differ between runs on systems with ASLR.
+    let local = 0u8;
+    (&local as *const u8 as usize).hash(&mut hasher);
+    (Box::new(0u8).as_ref() as *const u8 as usize).hash(&mut hasher);
+    (mixed as fn() -> u64 as usize).hash(&mut hasher);
+    hasher.finish()
+}
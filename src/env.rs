@@ -1,10 +1,23 @@
 use std::env;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(windows)]
+use std::{io, iter};
 
 // Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
 use once_cell::sync::OnceCell as OnceLock;
 
+pub use crate::budget::{BudgetExceeded, DiskBudget};
+pub use crate::entropy::EntropySource;
+use crate::Builder;
+
 static DEFAULT_TEMPDIR: OnceLock<PathBuf> = OnceLock::new();
+static ALLOWED_TEMP_ROOT: OnceLock<PathBuf> = OnceLock::new();
+static DEFAULT_BUILDER: OnceLock<Builder<'static, 'static>> = OnceLock::new();
+static FOLLOW_SYMLINKS: AtomicBool = AtomicBool::new(false);
+static REDACT_DEBUG: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "rand")]
+static DEFAULT_RNG: OnceLock<std::sync::Mutex<Box<dyn rand::RngCore + Send>>> = OnceLock::new();
 
 /// Override the default temporary directory (defaults to [`std::env::temp_dir`]). This function
 /// changes the _global_ default temporary directory for the entire program and should not be called
@@ -42,3 +55,405 @@ pub fn temp_dir() -> PathBuf {
         // Don't cache this in case the user uses std::env::set to change the temporary directory.
         .unwrap_or_else(env::temp_dir)
 }
+
+/// Override the directory that paths converted into a [`TempPath`](crate::TempPath) with
+/// [`TryFrom`] must reside in (defaults to [`temp_dir`]), e.g., to allow adopting files from an
+/// application-specific scratch directory. This function changes the allowed root for the entire
+/// program.
+///
+/// Only the first call to this function will succeed. All further calls will fail with `Err(path)`
+/// where `path` is previously set allowed root.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use tempfile::TempPath;
+///
+/// let scratch = tempfile::tempdir()?;
+/// tempfile::env::override_allowed_temp_root(scratch.path()).unwrap();
+///
+/// let path = scratch.path().join("download.part");
+/// std::fs::write(&path, b"partial")?;
+/// let path = TempPath::try_from(path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn override_allowed_temp_root(path: &Path) -> Result<(), PathBuf> {
+    let mut we_set = false;
+    let val = ALLOWED_TEMP_ROOT.get_or_init(|| {
+        we_set = true;
+        path.to_path_buf()
+    });
+    if we_set {
+        Ok(())
+    } else {
+        Err(val.to_owned())
+    }
+}
+
+/// Returns the directory paths converted into a [`TempPath`](crate::TempPath) must reside in (see
+/// [`override_allowed_temp_root`]).
+pub(crate) fn allowed_temp_root() -> PathBuf {
+    ALLOWED_TEMP_ROOT.get().cloned().unwrap_or_else(temp_dir)
+}
+
+/// Returns the temporary directory with the most free space (requires Windows), for applications
+/// that routinely write more temporary data than fits on the system drive (e.g., video editors).
+///
+/// The candidates are the default temporary directory (see [`temp_dir`]) and the root directory of
+/// every fixed (i.e., local, non-removable) drive. The default temporary directory wins ties, so
+/// it's returned whenever its volume has the most free space. Use [`best_temp_dir_among`] to
+/// choose among a configured list of directories instead.
+///
+/// # Errors
+///
+/// If no candidate has at least `min_free_bytes` available, `Err` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(windows)]
+/// # {
+/// use tempfile::{env, Builder};
+///
+/// // Make sure there's room for a 50 GiB scratch file.
+/// let dir = env::best_temp_dir(50 << 30)?;
+/// let scratch = Builder::new().suffix(".mov").tempfile_in(dir)?;
+/// # }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(windows)]
+pub fn best_temp_dir(min_free_bytes: u64) -> io::Result<PathBuf> {
+    best_temp_dir_among(
+        iter::once(temp_dir()).chain(imp::fixed_drives()),
+        min_free_bytes,
+    )
+}
+
+/// Returns the candidate directory with the most free space (requires Windows), as long as it
+/// has at least `min_free_bytes` available to the current user. Earlier candidates win ties, so
+/// the candidates should be listed in order of preference. Candidates whose free space can't be
+/// determined (e.g., because they don't exist) are skipped.
+///
+/// # Errors
+///
+/// If no candidate has at least `min_free_bytes` available, `Err` is returned.
+#[cfg(windows)]
+pub fn best_temp_dir_among<I>(candidates: I, min_free_bytes: u64) -> io::Result<PathBuf>
+where
+    I: IntoIterator,
+    I::Item: Into<PathBuf>,
+{
+    let mut best: Option<(PathBuf, u64)> = None;
+    for candidate in candidates {
+        let candidate = candidate.into();
+        let free = match imp::free_space(&candidate) {
+            Ok(free) => free,
+            Err(_) => continue,
+        };
+        if free >= min_free_bytes && best.as_ref().map_or(true, |&(_, most)| free > most) {
+            best = Some((candidate, free));
+        }
+    }
+    best.map(|(dir, _)| dir).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "no temporary directory has {} bytes of free space",
+                min_free_bytes
+            ),
+        )
+    })
+}
+
+/// Override the default [`Builder`] settings for the entire program. This makes it possible for an
+/// application to enforce conventions (e.g., a common prefix or restrictive permissions) for all
+/// temporary files and directories, including those created by libraries that don't expose a
+/// `Builder` of their own.
+///
+/// The following settings are taken from `builder` whenever they haven't been explicitly configured
+/// on the `Builder` used to create a temporary file or directory:
+///
+/// - [`Builder::prefix`]
+/// - [`Builder::suffix`]
+/// - [`Builder::rand_bytes`]
+/// - the permissions, as set with [`Builder::permissions`], [`Builder::permissions_exact`],
+///   [`Builder::world_readable`], or [`Builder::group_readable`]. These are taken together: if
+///   any of them is configured on the `Builder` used, none are taken from `builder`.
+///
+/// All other settings are ignored. Unnamed temporary files (see [`tempfile()`](crate::tempfile))
+/// aren't affected by this function.
+///
+/// Only the first call to this function will succeed. All further calls will fail with
+/// `Err(builder)` where `builder` is the previously set default builder.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{env, Builder};
+///
+/// let mut defaults = Builder::new();
+/// defaults.prefix("myapp-");
+/// env::override_default_builder(defaults).unwrap();
+///
+/// let dir = tempfile::tempdir()?;
+/// let name = dir.path().file_name().unwrap().to_str().unwrap();
+/// assert!(name.starts_with("myapp-"));
+///
+/// // Explicit settings still take precedence.
+/// let dir = Builder::new().prefix("other-").tempdir()?;
+/// let name = dir.path().file_name().unwrap().to_str().unwrap();
+/// assert!(name.starts_with("other-"));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[allow(clippy::result_large_err)]
+pub fn override_default_builder(
+    builder: Builder<'static, 'static>,
+) -> Result<(), Builder<'static, 'static>> {
+    let mut builder = Some(builder);
+    let val = DEFAULT_BUILDER.get_or_init(|| builder.take().unwrap());
+    if builder.is_none() {
+        Ok(())
+    } else {
+        Err(val.clone())
+    }
+}
+
+/// Returns the default builder installed by [`override_default_builder`], if any.
+pub(crate) fn default_builder() -> Option<&'static Builder<'static, 'static>> {
+    DEFAULT_BUILDER.get()
+}
+
+/// Record every temporary file and directory created by this process in the manifest at `path`,
+/// so that leftovers can be cleaned up (or re-adopted) with [`recover`](crate::recover) after a
+/// crash. This is typically a file in the application's private state or runtime directory, e.g.,
+/// `$XDG_RUNTIME_DIR/my-daemon.manifest`.
+///
+/// Every file and directory created through a [`Builder`] (including the convenience functions
+/// like [`tempdir()`](crate::tempdir())) is appended to the manifest right after it has been
+/// created, unless cleanup is disabled (see [`Builder::disable_cleanup`]). Once it has been
+/// deleted, persisted, or kept, that's recorded too, and the manifest is compacted from time to
+/// time so it doesn't grow without bound. Unnamed temporary files (see
+/// [`tempfile()`](crate::tempfile)) aren't recorded because the operating system deletes them.
+///
+/// # Security
+///
+/// [`recover`](crate::recover) hands out the recorded paths for deletion, so nobody else may be
+/// able to modify the manifest. On Unix, the directory containing the manifest must be owned by
+/// the current user and not be writable by anyone else (a shared temporary directory like `/tmp`
+/// is rejected), the manifest itself must be a regular file owned by the current user, and it's
+/// never opened through a symbolic link.
+///
+/// # Errors
+///
+/// If the manifest can't be opened, if it isn't in a private location, or if a manifest has already
+/// been installed, `Err` is returned.
+/// Only the first successful call to this function has an effect.
+pub fn override_manifest<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    crate::manifest::install(path.as_ref())
+}
+
+/// Limit the number of bytes all temporary files of this process may hold at once, to keep one
+/// process from filling up a shared temporary directory. This function changes the budget for the
+/// entire program.
+///
+/// See [`DiskBudget`] for what counts against the budget, and what happens when it's exceeded.
+/// Only bytes written after the budget has been installed are counted.
+///
+/// Only the first call to this function will succeed. All further calls will fail with
+/// `Err(budget)` where `budget` is the budget passed to this function.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use tempfile::env::{self, BudgetExceeded, DiskBudget};
+/// use tempfile::NamedTempFile;
+///
+/// env::override_disk_budget(DiskBudget::new(1024)).unwrap();
+///
+/// let mut file = NamedTempFile::new()?;
+/// let err = file.write_all(&[0; 2048]).unwrap_err();
+/// assert!(err.get_ref().and_then(|e| e.downcast_ref::<BudgetExceeded>()).is_some());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn override_disk_budget(budget: DiskBudget) -> Result<(), DiskBudget> {
+    crate::budget::install(budget)
+}
+
+/// Returns the number of bytes currently charged against the budget installed with
+/// [`override_disk_budget`] (zero if no budget has been installed).
+#[must_use]
+pub fn disk_usage() -> u64 {
+    crate::budget::used()
+}
+
+/// Allow (or, again, forbid) following symbolic links when this crate accesses its own temporary
+/// files by path (requires Unix). This function changes the behavior for the entire program.
+///
+/// By default, every path-based operation on a temporary file that was created by this crate
+/// refuses to follow a symbolic link at the final component (with `O_NOFOLLOW` or the equivalent
+/// `AT_SYMLINK_NOFOLLOW`), so that swapping the file for a symbolic link (e.g., in a shared
+/// temporary directory) can't redirect these operations to some other file. This covers
+/// [`NamedTempFile::reopen`](crate::NamedTempFile::reopen) and
+/// [`NamedTempFile::reopen_read_only`](crate::NamedTempFile::reopen_read_only), opening the ends of a
+/// `TempFifo`, and copying metadata onto a temporary file before it's persisted. Creating,
+/// renaming, and deleting temporary files never follows symbolic links in the first place.
+///
+/// Only opt out if the application deliberately replaces its temporary files with symbolic links.
+/// On other platforms this setting has no effect.
+///
+/// # Examples
+///
+/// ```
+/// // This application symlinks its temporary files into a cache and reopens them by path.
+/// tempfile::env::override_follow_symlinks(true);
+/// ```
+pub fn override_follow_symlinks(follow: bool) {
+    FOLLOW_SYMLINKS.store(follow, Ordering::Relaxed);
+}
+
+/// Returns whether symbolic links may be followed (see [`override_follow_symlinks`]).
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn follow_symlinks() -> bool {
+    FOLLOW_SYMLINKS.load(Ordering::Relaxed)
+}
+
+/// Redact paths and contents in the `Debug` output of this crate's types (or, again, stop doing
+/// so), for applications whose temporary file names embed sensitive identifiers that must not
+/// reach logs. This function changes the behavior for the entire program.
+///
+/// With redaction, the paths of temporary files and directories (and of the directories they're
+/// created in, persisted to, etc.) and the prefixes and suffixes of their names are printed as
+/// `<redacted>`, both in `Debug` output and in the records logged with the `log` feature. This
+/// also covers the paths attached to the [`io::Error`](std::io::Error)s returned by this crate
+/// when they're `Debug`-formatted, but not their `Display` output, nor paths returned by methods
+/// like [`NamedTempFile::path`](crate::NamedTempFile::path). The data held in memory by a
+/// [`SpooledTempFile`](crate::SpooledTempFile) is never printed, with or without redaction.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::NamedTempFile;
+///
+/// tempfile::env::override_redact_debug(true);
+///
+/// let file = NamedTempFile::new()?;
+/// assert_eq!(format!("{:?}", file), "NamedTempFile(<redacted>)");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn override_redact_debug(redact: bool) {
+    REDACT_DEBUG.store(redact, Ordering::Relaxed);
+}
+
+/// Returns whether `Debug` output is redacted (see [`override_redact_debug`]).
+pub(crate) fn redact_debug() -> bool {
+    REDACT_DEBUG.load(Ordering::Relaxed)
+}
+
+/// Returns the source of entropy used to re-seed the random file name generator after repeated
+/// collisions (see the "Denial of Service" section of the [crate documentation](crate)).
+///
+/// Sources are tried at runtime in order: the `getrandom` crate (when the `getrandom` feature is
+/// enabled), then `/dev/urandom` (on Unix), then a mix of the time, process state, and memory
+/// addresses. This returns the source used by the last re-seed, probing the sources if none has
+/// happened yet, so applications can warn if only the (weaker) last resort is available.
+///
+/// When the `rand` feature is enabled, names come from the generator installed with
+/// `override_rng` instead, and this source is unused.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::env::{self, EntropySource};
+///
+/// if !env::entropy_source().is_secure() {
+///     eprintln!("warning: temporary file names may be predictable");
+/// }
+/// ```
+#[must_use]
+pub fn entropy_source() -> EntropySource {
+    crate::entropy::source()
+}
+
+/// Override the random number generator used to generate temporary file names (requires the
+/// `rand` feature). This function changes the generator for the entire program, letting an
+/// application route all of its randomness through a single, audited, source.
+///
+/// When the `rand` feature is enabled but no generator has been installed, [`rand::thread_rng`] is
+/// used. Just like the built-in generator is re-seeded from system randomness when names keep
+/// colliding, names are drawn from [`rand::thread_rng`] after the first three collisions, so a
+/// predictable generator can't make temporary file creation fail where it otherwise wouldn't.
+///
+/// Only the first call to this function will succeed. All further calls will fail with `Err(rng)`
+/// where `rng` is the generator passed to this function.
+///
+/// # Examples
+///
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use tempfile::env;
+///
+/// env::override_rng(StdRng::from_entropy()).unwrap();
+/// let dir = tempfile::tempdir()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "rand")]
+pub fn override_rng<R>(rng: R) -> Result<(), R>
+where
+    R: rand::RngCore + Send + 'static,
+{
+    let mut rng = Some(rng);
+    DEFAULT_RNG.get_or_init(|| std::sync::Mutex::new(Box::new(rng.take().unwrap())));
+    match rng {
+        None => Ok(()),
+        Some(rng) => Err(rng),
+    }
+}
+
+/// Calls `f` with the generator installed by [`override_rng`], or with [`rand::thread_rng`].
+#[cfg(feature = "rand")]
+pub(crate) fn with_rng<R>(f: impl FnOnce(&mut dyn rand::RngCore) -> R) -> R {
+    match DEFAULT_RNG.get() {
+        Some(rng) => f(&mut **rng.lock().unwrap_or_else(|e| e.into_inner())),
+        None => f(&mut rand::thread_rng()),
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::path::{Path, PathBuf};
+    use std::{io, ptr};
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDrives,
+    };
+
+    use crate::file::imp::to_utf16;
+
+    // Defined in `Win32_System_WindowsProgramming`, which we don't otherwise need.
+    const DRIVE_FIXED: u32 = 3;
+
+    /// Returns the number of bytes available to the current user on the volume containing `dir`.
+    pub fn free_space(dir: &Path) -> io::Result<u64> {
+        let dir_w = to_utf16(dir);
+        let mut free = 0;
+        if unsafe {
+            GetDiskFreeSpaceExW(dir_w.as_ptr(), &mut free, ptr::null_mut(), ptr::null_mut())
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(free)
+    }
+
+    /// Returns the root directories of all fixed drives, in drive letter order.
+    pub fn fixed_drives() -> Vec<PathBuf> {
+        let mask = unsafe { GetLogicalDrives() };
+        (0..26u8)
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| PathBuf::from(format!("{}:\\", char::from(b'A' + i))))
+            .filter(|root| unsafe { GetDriveTypeW(to_utf16(root).as_ptr()) } == DRIVE_FIXED)
+            .collect()
+    }
+}
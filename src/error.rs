@@ -1,12 +1,22 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{error, fmt, io};
 
-#[derive(Debug)]
+use crate::util::Redact;
+
 struct PathError {
     path: PathBuf,
     err: io::Error,
 }
 
+impl fmt::Debug for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PathError")
+            .field("path", &Redact(&self.path))
+            .field("err", &self.err)
+            .finish()
+    }
+}
+
 impl fmt::Display for PathError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} at path {:?}", self.err, self.path)
@@ -43,3 +53,98 @@ impl<T> IoResultExt<T> for Result<T, io::Error> {
         })
     }
 }
+
+/// Why a directory can't hold temporary files, see [`UnsupportedDirError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnsupportedDirReason {
+    /// The path refers to a device in the Win32 device namespace (e.g., `\\.\pipe\`) instead of
+    /// a directory.
+    DevicePath,
+    /// The path is a UNC path without a share name (e.g., `\\server`).
+    IncompleteUncPath,
+    /// A component of the path is a reserved device name (e.g., `NUL` or `COM1`), which Windows
+    /// interprets as the device instead of a directory.
+    ReservedName,
+    /// The directory is on a filesystem that doesn't reliably fail to create files that already
+    /// exist (e.g., a WebDAV share), so temporary files could silently clobber existing ones.
+    UnreliableCreateNew,
+}
+
+/// The error returned when temporary files or directories are to be created in a directory that
+/// can't (safely) hold them. This is only detected on Windows.
+///
+/// This error is wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidInput`], and can be
+/// retrieved by downcasting.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(windows)]
+/// # {
+/// use tempfile::{UnsupportedDirError, UnsupportedDirReason};
+///
+/// let err = tempfile::tempfile_in(r"\\.\pipe\").unwrap_err();
+/// let err = err.get_ref().unwrap().downcast_ref::<UnsupportedDirError>().unwrap();
+/// assert_eq!(err.reason(), UnsupportedDirReason::DevicePath);
+/// # }
+/// ```
+pub struct UnsupportedDirError {
+    path: PathBuf,
+    reason: UnsupportedDirReason,
+}
+
+impl fmt::Debug for UnsupportedDirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnsupportedDirError")
+            .field("path", &Redact(&self.path))
+            .field("reason", &self.reason)
+            .finish()
+    }
+}
+
+impl UnsupportedDirError {
+    /// Returns the rejected directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns why the directory was rejected.
+    #[must_use]
+    pub fn reason(&self) -> UnsupportedDirReason {
+        self.reason
+    }
+}
+
+/// Returns an [`UnsupportedDirError`] for `path`, wrapped in an [`io::Error`].
+#[cfg(windows)]
+pub(crate) fn unsupported_dir(path: &Path, reason: UnsupportedDirReason) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        UnsupportedDirError {
+            path: path.to_owned(),
+            reason,
+        },
+    )
+}
+
+impl fmt::Display for UnsupportedDirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            UnsupportedDirReason::DevicePath => "is a device path",
+            UnsupportedDirReason::IncompleteUncPath => "is a UNC path without a share name",
+            UnsupportedDirReason::ReservedName => "contains a reserved device name",
+            UnsupportedDirReason::UnreliableCreateNew => {
+                "is on a filesystem that can't reliably create new files"
+            }
+        };
+        write!(
+            f,
+            "{:?} {} and can't hold temporary files",
+            self.path, reason
+        )
+    }
+}
+
+impl error::Error for UnsupportedDirError {}
@@ -0,0 +1,49 @@
+//! Best-effort cleanup of temporary resources when the process exits.
+//!
+//! Paths registered here are removed by a C `atexit` handler. This handler runs when the process
+//! exits normally (e.g., by returning from `main`) but, like destructors, not when the process is
+//! killed by a signal.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::dir::Cleanup;
+
+static DIRS: Mutex<Vec<(PathBuf, Cleanup)>> = Mutex::new(Vec::new());
+
+/// Remove the directory at `path` as configured by `cleanup` when the process exits.
+pub(crate) fn cleanup_dir_at_exit(path: PathBuf, cleanup: Cleanup) {
+    register_handler();
+    if let Ok(mut dirs) = DIRS.lock() {
+        dirs.push((path, cleanup));
+    }
+}
+
+#[cfg(any(unix, windows, target_os = "wasi"))]
+fn register_handler() {
+    extern "C" {
+        fn atexit(cb: extern "C" fn()) -> std::os::raw::c_int;
+    }
+
+    extern "C" fn handler() {
+        // Never panic (or block) here: we're running inside the C runtime's exit sequence.
+        let dirs = match DIRS.try_lock() {
+            Ok(mut dirs) => std::mem::take(&mut *dirs),
+            Err(_) => return,
+        };
+        for (dir, cleanup) in dirs {
+            let _ = cleanup.run(&dir);
+        }
+    }
+
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+    REGISTER.call_once(|| unsafe {
+        // If this fails, there's nothing we can do. The directories will simply be leaked.
+        let _ = atexit(handler);
+    });
+}
+
+#[cfg(not(any(unix, windows, target_os = "wasi")))]
+fn register_handler() {
+    // No exit hooks on this platform, registered directories will be leaked.
+}
@@ -0,0 +1,135 @@
+//! Force operations of this crate to fail on demand, to deterministically test how code handles
+//! temporary file errors.
+//!
+//! Arm a [`Failpoint`] with [`fail`] to make the next operations it covers fail with the
+//! corresponding error. Failpoints are armed per thread, so tests running in parallel don't
+//! interfere with each other, but only operations on the thread that armed them fail (e.g., a
+//! temporary file dropped on another thread is deleted normally).
+//!
+//! # Examples
+//!
+//! ```
+//! use tempfile::failpoints::{self, Failpoint};
+//! use tempfile::NamedTempFile;
+//!
+//! let dir = tempfile::tempdir()?;
+//! let file = NamedTempFile::new_in(&dir)?;
+//!
+//! failpoints::fail(Failpoint::PersistCrossDevice, 1);
+//! let err = file.persist(dir.path().join("target")).unwrap_err();
+//! # #[cfg(unix)]
+//! assert_eq!(err.error.raw_os_error(), Some(18)); // EXDEV
+//!
+//! // Only the first attempt fails.
+//! err.file.persist(dir.path().join("target"))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::cell::Cell;
+use std::io;
+
+/// An operation that can be forced to fail with [`fail`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Failpoint {
+    /// Creating a temporary file or directory finds its random name already taken, and retries
+    /// with another name (failing with an error of kind [`io::ErrorKind::AlreadyExists`] once out
+    /// of retries). Arm it with a large count to exhaust the retries.
+    Collision,
+    /// Persisting a temporary file fails as if the target were on another filesystem (`EXDEV` on
+    /// Unix, `ERROR_NOT_SAME_DEVICE` on Windows).
+    PersistCrossDevice,
+    /// Deleting a temporary file or directory (when dropped or closed) fails with a permission
+    /// error (`EACCES` on Unix, `ERROR_ACCESS_DENIED` on Windows), leaving it in place.
+    CleanupPermissionDenied,
+}
+
+const COUNT: usize = 3;
+
+impl Failpoint {
+    fn index(self) -> usize {
+        match self {
+            Failpoint::Collision => 0,
+            Failpoint::PersistCrossDevice => 1,
+            Failpoint::CleanupPermissionDenied => 2,
+        }
+    }
+
+    #[cfg(any(unix, target_os = "wasi"))]
+    fn error(self) -> io::Error {
+        use rustix::io::Errno;
+        let errno = match self {
+            Failpoint::Collision => Errno::EXIST,
+            Failpoint::PersistCrossDevice => Errno::XDEV,
+            Failpoint::CleanupPermissionDenied => Errno::ACCESS,
+        };
+        io::Error::from_raw_os_error(errno.raw_os_error())
+    }
+
+    #[cfg(windows)]
+    fn error(self) -> io::Error {
+        use windows_sys::Win32::Foundation::{
+            ERROR_ACCESS_DENIED, ERROR_FILE_EXISTS, ERROR_NOT_SAME_DEVICE,
+        };
+        let code = match self {
+            Failpoint::Collision => ERROR_FILE_EXISTS,
+            Failpoint::PersistCrossDevice => ERROR_NOT_SAME_DEVICE,
+            Failpoint::CleanupPermissionDenied => ERROR_ACCESS_DENIED,
+        };
+        io::Error::from_raw_os_error(code as i32)
+    }
+
+    #[cfg(not(any(unix, target_os = "wasi", windows)))]
+    fn error(self) -> io::Error {
+        match self {
+            Failpoint::Collision => io::Error::from(io::ErrorKind::AlreadyExists),
+            Failpoint::PersistCrossDevice => {
+                io::Error::new(io::ErrorKind::Other, "cross-device link")
+            }
+            Failpoint::CleanupPermissionDenied => io::Error::from(io::ErrorKind::PermissionDenied),
+        }
+    }
+}
+
+thread_local! {
+    static ARMED: [Cell<usize>; COUNT] = Default::default();
+}
+
+/// Makes the next `times` operations covered by `point` on the current thread fail, replacing any
+/// previous count. Pass `0` to disarm it.
+pub fn fail(point: Failpoint, times: usize) {
+    ARMED.with(|armed| armed[point.index()].set(times));
+}
+
+/// Returns how many more operations covered by `point` will fail on the current thread.
+#[must_use]
+pub fn remaining(point: Failpoint) -> usize {
+    ARMED.with(|armed| armed[point.index()].get())
+}
+
+/// Disarms all failpoints on the current thread.
+pub fn clear() {
+    ARMED.with(|armed| armed.iter().for_each(|count| count.set(0)));
+}
+
+/// Fails with the error of `point` if it's armed, counting down.
+pub(crate) fn check(point: Failpoint) -> io::Result<()> {
+    // The thread-local may already be gone if we're called from another thread-local's destructor.
+    let armed = ARMED
+        .try_with(|armed| {
+            let count = &armed[point.index()];
+            match count.get() {
+                0 => false,
+                n => {
+                    count.set(n - 1);
+                    true
+                }
+            }
+        })
+        .unwrap_or(false);
+    if armed {
+        Err(point.error())
+    } else {
+        Ok(())
+    }
+}
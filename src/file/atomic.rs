@@ -0,0 +1,186 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, IoSlice, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::{copy_existing_metadata, NamedTempFile};
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::Builder;
+
+/// How hard [`AtomicWriteFile::commit`] tries to make sure the new contents survive a crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Durability {
+    /// Don't synchronize anything: the new contents atomically replace the old ones, but may be
+    /// lost (leaving the old contents in place) if the system crashes shortly afterwards.
+    None,
+    /// Synchronize the new contents to disk before replacing the target.
+    File,
+    /// Also make the replacement itself durable, see [`NamedTempFile::persist_durable`].
+    #[default]
+    Full,
+}
+
+/// A file that atomically replaces `target` once it has been written and
+/// [committed](AtomicWriteFile::commit), so that readers only ever see either the old or the new
+/// contents of `target`.
+///
+/// This packages the most common use of [`NamedTempFile::persist`]: the new contents are written
+/// to a temporary file in the target's directory (so that it's on the same filesystem and can be
+/// atomically renamed over the target), which is then synchronized to disk (see [`Durability`])
+/// and persisted. If the target already exists, its permissions, ownership, and extended
+/// attributes are carried over (see [`NamedTempFile::persist_preserving_metadata`]).
+///
+/// If the `AtomicWriteFile` is dropped without being committed, the temporary file is deleted and
+/// the target is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use tempfile::AtomicWriteFile;
+///
+/// let dir = tempfile::tempdir()?;
+/// let config = dir.path().join("config.toml");
+///
+/// let mut file = AtomicWriteFile::open(&config)?;
+/// writeln!(file, "debug = true")?;
+/// file.commit()?;
+///
+/// assert_eq!(std::fs::read_to_string(&config)?, "debug = true\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct AtomicWriteFile {
+    file: NamedTempFile,
+    target: PathBuf,
+    durability: Durability,
+}
+
+impl AtomicWriteFile {
+    /// Start atomically rewriting `target`, by creating a temporary file next to it.
+    ///
+    /// # Errors
+    ///
+    /// If the temporary file cannot be created (e.g., because the target's directory doesn't
+    /// exist), `Err` is returned.
+    pub fn open<P: AsRef<Path>>(target: P) -> io::Result<AtomicWriteFile> {
+        let target = target.as_ref();
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        // Name the temporary file after the target (e.g., `.config.toml.AbC123`) so it's easy to
+        // tell where a leftover came from.
+        let mut prefix = OsString::from(".");
+        if let Some(name) = target.file_name() {
+            prefix.push(name);
+            prefix.push(".");
+        }
+        let file = Builder::new()
+            .prefix(&prefix)
+            .fit_name_length(true)
+            .tempfile_in(dir)?;
+        Ok(AtomicWriteFile {
+            file,
+            target: target.to_owned(),
+            durability: Durability::default(),
+        })
+    }
+
+    /// Set how hard [`AtomicWriteFile::commit`] tries to make the new contents durable.
+    ///
+    /// Default: [`Durability::Full`].
+    pub fn set_durability(&mut self, durability: Durability) -> &mut Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Returns the path of the file that will be replaced on commit.
+    #[must_use]
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Get a reference to the underlying (temporary) file.
+    #[must_use]
+    pub fn as_file(&self) -> &File {
+        self.file.as_file()
+    }
+
+    /// Get a mutable reference to the underlying (temporary) file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.file.as_file_mut()
+    }
+
+    /// Atomically replace the target with the data written so far, and return the (now
+    /// persisted) file.
+    ///
+    /// # Errors
+    ///
+    /// If the data cannot be synchronized to disk or the target cannot be replaced, `Err` is
+    /// returned and the temporary file is deleted.
+    pub fn commit(self) -> io::Result<File> {
+        let AtomicWriteFile {
+            mut file,
+            target,
+            durability,
+        } = self;
+        file.flush()?;
+        match durability {
+            Durability::Full => {
+                copy_existing_metadata(&target, file.path()).with_err_path(|| file.path())?;
+                file.persist_durable(&target)
+            }
+            Durability::File => {
+                file.as_file().sync_all().with_err_path(|| file.path())?;
+                file.persist_preserving_metadata(&target)
+            }
+            Durability::None => file.persist_preserving_metadata(&target),
+        }
+        .map_err(io::Error::from)
+    }
+
+    /// Give up on replacing the target, and delete the temporary file.
+    ///
+    /// Use this if you want to detect errors in deleting the temporary file, otherwise simply drop
+    /// the `AtomicWriteFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the temporary file cannot be deleted, `Err` is returned.
+    pub fn discard(self) -> io::Result<()> {
+        self.file.close()
+    }
+}
+
+impl fmt::Debug for AtomicWriteFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWriteFile")
+            .field("path", &Redact(self.file.path()))
+            .field("target", &Redact(&self.target))
+            .field("durability", &self.durability)
+            .finish()
+    }
+}
+
+impl Write for AtomicWriteFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.file.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for AtomicWriteFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -0,0 +1,128 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::Deref;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use super::{imp, TempPath};
+use crate::error::IoResultExt;
+use crate::util::Redact;
+
+/// A named pipe (FIFO) at a unique temporary path, created with
+/// [`Builder::fifo`](crate::Builder::fifo) (Unix only, except Apple platforms and Redox).
+///
+/// The FIFO is deleted when the `TempFifo` is dropped. Open its ends with
+/// [`TempFifo::open_reader`] and [`TempFifo::open_writer`], or hand the [path](TempFifo::path) to
+/// another process.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use tempfile::Builder;
+///
+/// let fifo = Builder::new().fifo()?;
+/// let path = fifo.path().to_owned();
+/// let writer = std::thread::spawn(move || {
+///     std::fs::write(path, b"through the pipe")
+/// });
+///
+/// let mut message = String::new();
+/// fifo.open_reader()?.read_to_string(&mut message)?;
+/// writer.join().unwrap()?;
+/// assert_eq!(message, "through the pipe");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TempFifo {
+    path: TempPath,
+}
+
+impl TempFifo {
+    pub(crate) fn new(path: TempPath) -> TempFifo {
+        TempFifo { path }
+    }
+
+    /// Returns the path of the FIFO.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open the reading end of the FIFO.
+    ///
+    /// This blocks until the FIFO is opened for writing (e.g., with [`TempFifo::open_writer`] on
+    /// another thread, or by another process).
+    ///
+    /// # Errors
+    ///
+    /// If the FIFO cannot be opened, `Err` is returned.
+    pub fn open_reader(&self) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(imp::nofollow())
+            .open(&self.path)
+            .with_err_path(|| self.path())
+    }
+
+    /// Open the writing end of the FIFO.
+    ///
+    /// This blocks until the FIFO is opened for reading (e.g., with [`TempFifo::open_reader`] on
+    /// another thread, or by another process).
+    ///
+    /// # Errors
+    ///
+    /// If the FIFO cannot be opened, `Err` is returned.
+    pub fn open_writer(&self) -> io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .custom_flags(imp::nofollow())
+            .open(&self.path)
+            .with_err_path(|| self.path())
+    }
+
+    /// Turn this FIFO into a [`TempPath`], which deletes it when dropped.
+    #[must_use]
+    pub fn into_temp_path(self) -> TempPath {
+        self.path
+    }
+}
+
+impl fmt::Debug for TempFifo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempFifo")
+            .field("path", &Redact(self.path()))
+            .finish()
+    }
+}
+
+impl Deref for TempFifo {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempFifo {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<OsStr> for TempFifo {
+    fn as_ref(&self) -> &OsStr {
+        self.path.as_os_str()
+    }
+}
+
+/// Creates a FIFO at `path`.
+pub(crate) fn create(path: &Path, permissions: Option<&std::fs::Permissions>) -> io::Result<()> {
+    use rustix::fs::{mkfifoat, Mode, CWD};
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.map_or(0o600, |p| p.mode());
+    mkfifoat(CWD, path, Mode::from_raw_mode(mode as _))
+        .map_err(io::Error::from)
+        .with_err_path(|| path)
+}
@@ -7,3 +7,35 @@
 mod platform;
 
 pub use self::platform::*;
+
+// With failpoints, these shadow the platform functions imported above to inject failures.
+#[cfg(feature = "failpoints")]
+mod failpoints {
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+
+    use super::platform;
+    use crate::failpoints::{check, Failpoint};
+
+    pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+        check(Failpoint::PersistCrossDevice)?;
+        platform::persist(old_path, new_path, overwrite)
+    }
+
+    pub fn persist_durable(file: &File, old_path: &Path, new_path: &Path) -> io::Result<()> {
+        check(Failpoint::PersistCrossDevice)?;
+        platform::persist_durable(file, old_path, new_path)
+    }
+
+    #[cfg(windows)]
+    pub fn persist_by_handle(file: &File, new_path: &Path, overwrite: bool) -> io::Result<()> {
+        check(Failpoint::PersistCrossDevice)?;
+        platform::persist_by_handle(file, new_path, overwrite)
+    }
+}
+
+#[cfg(all(feature = "failpoints", windows))]
+pub use self::failpoints::persist_by_handle;
+#[cfg(feature = "failpoints")]
+pub use self::failpoints::{persist, persist_durable};
@@ -25,10 +25,46 @@ pub fn reopen(_file: &File, _path: &Path) -> io::Result<File> {
     not_supported()
 }
 
+pub fn reopen_read_only(_file: &File, _path: &Path) -> io::Result<File> {
+    not_supported()
+}
+
+pub fn verify(_file: &File, _path: &Path) -> io::Result<()> {
+    not_supported()
+}
+
 pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Result<()> {
     not_supported()
 }
 
+pub fn persist_durable(_file: &File, _old_path: &Path, _new_path: &Path) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn set_inheritable(_file: &File) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn set_sparse(_file: &File) -> io::Result<()> {
+    Ok(())
+}
+
+pub fn deallocate(_file: &File, _offset: u64, _len: u64) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn same_filesystem(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
 pub fn keep(_path: &Path) -> io::Result<()> {
     not_supported()
 }
+
+pub fn clear_temporary_attribute(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+pub fn copy_metadata(_from: &Path, _to: &Path) -> io::Result<()> {
+    not_supported()
+}
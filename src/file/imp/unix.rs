@@ -1,4 +1,3 @@
-use std::ffi::OsStr;
 use std::fs::{self, File, OpenOptions};
 use std::io;
 
@@ -11,11 +10,23 @@ use {
     std::fs::hard_link,
 };
 
+/// Returns `O_NOFOLLOW`, unless following symbolic links has been allowed with
+/// [`override_follow_symlinks`](crate::env::override_follow_symlinks).
+#[cfg(not(target_os = "wasi"))]
+pub fn nofollow() -> i32 {
+    if crate::env::follow_symlinks() {
+        0
+    } else {
+        rustix::fs::OFlags::NOFOLLOW.bits() as i32
+    }
+}
+
 pub fn create_named(
     path: &Path,
     open_options: &mut OpenOptions,
     #[cfg_attr(target_os = "wasi", allow(unused))] permissions: Option<&std::fs::Permissions>,
 ) -> io::Result<File> {
+    // `O_CREAT | O_EXCL` never follows symbolic links, so there's no need for `O_NOFOLLOW`.
     open_options.read(true).write(true).create_new(true);
 
     #[cfg(not(target_os = "wasi"))]
@@ -70,32 +81,58 @@ pub fn create(dir: &Path) -> io::Result<File> {
 }
 
 fn create_unix(dir: &Path) -> io::Result<File> {
-    util::create_helper(
-        dir,
-        OsStr::new(".tmp"),
-        OsStr::new(""),
-        crate::NUM_RAND_CHARS,
-        |path| create_unlinked(&path),
-    )
+    util::create_helper(dir, &util::NameSpec::unnamed(), |path| {
+        create_unlinked(&path)
+    })
 }
 
 #[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
 pub fn reopen(file: &File, path: &Path) -> io::Result<File> {
+    reopen_with(file, path, OpenOptions::new().read(true).write(true))
+}
+
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+pub fn reopen_read_only(file: &File, path: &Path) -> io::Result<File> {
+    reopen_with(file, path, OpenOptions::new().read(true))
+}
+
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+fn reopen_with(file: &File, path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    #[cfg(not(target_os = "wasi"))]
+    use std::os::unix::fs::OpenOptionsExt;
+
+    #[cfg(not(target_os = "wasi"))]
+    options.custom_flags(nofollow());
+    let new_file = options.open(path)?;
+    check_same_file(&file.metadata()?, &new_file.metadata()?)?;
+    Ok(new_file)
+}
+
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+fn check_same_file(old_meta: &fs::Metadata, new_meta: &fs::Metadata) -> io::Result<()> {
     #[cfg(not(target_os = "wasi"))]
     use std::os::unix::fs::MetadataExt;
     #[cfg(target_os = "wasi")]
     use std::os::wasi::fs::MetadataExt;
 
-    let new_file = OpenOptions::new().read(true).write(true).open(path)?;
-    let old_meta = file.metadata()?;
-    let new_meta = new_file.metadata()?;
     if old_meta.dev() != new_meta.dev() || old_meta.ino() != new_meta.ino() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
             "original tempfile has been replaced",
         ));
     }
-    Ok(new_file)
+    Ok(())
+}
+
+#[cfg(any(not(target_os = "wasi"), feature = "nightly"))]
+pub fn verify(file: &File, path: &Path) -> io::Result<()> {
+    check_same_file(&file.metadata()?, &fs::symlink_metadata(path)?)
+}
+
+#[cfg(all(target_os = "wasi", not(feature = "nightly")))]
+pub fn verify(_file: &File, path: &Path) -> io::Result<()> {
+    // Without file identities, we can only tell whether the path still exists.
+    fs::symlink_metadata(path).map(|_| ())
 }
 
 #[cfg(all(target_os = "wasi", not(feature = "nightly")))]
@@ -106,6 +143,11 @@ pub fn reopen(_file: &File, _path: &Path) -> io::Result<File> {
     ));
 }
 
+#[cfg(all(target_os = "wasi", not(feature = "nightly")))]
+pub fn reopen_read_only(file: &File, path: &Path) -> io::Result<File> {
+    reopen(file, path)
+}
+
 #[cfg(not(target_os = "redox"))]
 pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
     if overwrite {
@@ -156,6 +198,188 @@ pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Resu
     Err(Errno::NOSYS.into())
 }
 
+pub fn persist_durable(file: &File, old_path: &Path, new_path: &Path) -> io::Result<()> {
+    file.sync_all()?;
+    persist(old_path, new_path, true)?;
+    // The rename is only durable once the directory containing the new name has been synchronized.
+    #[cfg(not(target_os = "wasi"))]
+    {
+        let dir = match new_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "wasi"))]
+pub fn set_inheritable(file: &File) -> io::Result<()> {
+    use rustix::io::{fcntl_getfd, fcntl_setfd, FdFlags};
+    let flags = fcntl_getfd(file)?;
+    fcntl_setfd(file, flags - FdFlags::CLOEXEC)?;
+    Ok(())
+}
+
+#[cfg(target_os = "wasi")]
+pub fn set_inheritable(_file: &File) -> io::Result<()> {
+    use rustix::io::Errno;
+    Err(Errno::NOSYS.into())
+}
+
+pub fn set_sparse(_file: &File) -> io::Result<()> {
+    // Regions that are never written (e.g., after extending the file with `set_len`) are holes
+    // by default on Unix filesystems that support them.
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn deallocate(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    use rustix::fs::{fallocate, FallocateFlags};
+    fallocate(
+        file,
+        FallocateFlags::PUNCH_HOLE | FallocateFlags::KEEP_SIZE,
+        offset,
+        len,
+    )?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn deallocate(_file: &File, _offset: u64, _len: u64) -> io::Result<()> {
+    use rustix::io::Errno;
+    Err(Errno::NOSYS.into())
+}
+
+#[cfg(not(target_os = "wasi"))]
+pub fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(a)?.dev() == fs::metadata(b)?.dev())
+}
+
+#[cfg(target_os = "wasi")]
+pub fn same_filesystem(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
 pub fn keep(_: &Path) -> io::Result<()> {
     Ok(())
 }
+
+pub fn clear_temporary_attribute(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+pub fn copy_metadata(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::metadata(from)?;
+
+    #[cfg(not(target_os = "wasi"))]
+    {
+        use rustix::fs::{fchown, Gid, Uid};
+        use rustix::io::Errno;
+        use std::os::unix::fs::MetadataExt;
+
+        // Change the temporary file through a handle, so that the changes can't be redirected to
+        // some other file by replacing it with a symbolic link.
+        let to = open_metadata_target(to)?;
+
+        // Only privileged processes can give files away, but we may still be able to change the
+        // group (if we're a member). Otherwise, leave the ownership alone.
+        let (uid, gid) = (Uid::from_raw(meta.uid()), Gid::from_raw(meta.gid()));
+        match fchown(&to, Some(uid), Some(gid)) {
+            Err(Errno::PERM) => match fchown(&to, None, Some(gid)) {
+                Ok(()) | Err(Errno::PERM) => {}
+                Err(e) => return Err(e.into()),
+            },
+            res => res?,
+        }
+        // Changing the owner may clear the setuid/setgid bits, so set the permissions last.
+        to.set_permissions(meta.permissions())?;
+
+        #[cfg(any(
+            target_os = "android",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "tvos",
+            target_os = "visionos",
+            target_os = "watchos",
+        ))]
+        copy_xattrs(from, &to)?;
+    }
+    #[cfg(target_os = "wasi")]
+    let _ = (meta, to);
+
+    Ok(())
+}
+
+/// Opens `path` (without following symbolic links, see [`nofollow`]) to change its metadata.
+#[cfg(not(target_os = "wasi"))]
+fn open_metadata_target(path: &Path) -> io::Result<File> {
+    use rustix::fs::OFlags;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // Don't block if the file has been replaced with a FIFO, and fall back on write access for
+    // files the owner can't read.
+    let flags = nofollow() | OFlags::NONBLOCK.bits() as i32;
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(flags)
+        .open(path)
+        .or_else(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied => OpenOptions::new()
+                .write(true)
+                .custom_flags(flags)
+                .open(path),
+            _ => Err(e),
+        })
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "visionos",
+    target_os = "watchos",
+))]
+fn copy_xattrs(from: &Path, to: &File) -> io::Result<()> {
+    use rustix::fs::{fsetxattr, getxattr, listxattr, XattrFlags};
+    use rustix::io::Errno;
+
+    let mut names = match listxattr(from, &mut [0u8; 0][..]) {
+        Ok(len) => vec![0; len],
+        Err(e) if is_unsupported(e) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let len = listxattr(from, &mut names[..])?;
+    names.truncate(len);
+
+    for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+        let mut value = vec![0; getxattr(from, name, &mut [0u8; 0][..])?];
+        let len = getxattr(from, name, &mut value[..])?;
+        match fsetxattr(to, name, &value[..len], XattrFlags::empty()) {
+            // Some namespaces (e.g., `trusted.`) are reserved for privileged processes, and the
+            // target filesystem may not support extended attributes at all.
+            Ok(()) | Err(Errno::PERM) => {}
+            Err(e) if is_unsupported(e) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+// `ENOTSUP` and `EOPNOTSUPP` are the same on some platforms (e.g., Linux), but not all.
+#[cfg(any(
+    target_os = "android",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "visionos",
+    target_os = "watchos",
+))]
+fn is_unsupported(e: rustix::io::Errno) -> bool {
+    e == rustix::io::Errno::NOTSUP || e == rustix::io::Errno::OPNOTSUPP
+}
@@ -1,22 +1,89 @@
-use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs::{File, OpenOptions};
-use std::os::windows::ffi::OsStrExt;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::os::windows::fs::OpenOptionsExt;
 use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
 use std::path::Path;
-use std::{io, iter};
+use std::{io, mem, ptr};
 
-use windows_sys::Win32::Foundation::{HANDLE, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::Foundation::{
+    SetHandleInformation, HANDLE, HANDLE_FLAG_INHERIT, INVALID_HANDLE_VALUE,
+};
 use windows_sys::Win32::Storage::FileSystem::{
-    MoveFileExW, ReOpenFile, SetFileAttributesW, FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_TEMPORARY,
-    FILE_FLAG_DELETE_ON_CLOSE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_DELETE,
-    FILE_SHARE_READ, FILE_SHARE_WRITE, MOVEFILE_REPLACE_EXISTING,
+    FileBasicInfo, FileRenameInfo, FlushFileBuffers, GetFileAttributesW,
+    GetFileInformationByHandle, GetFullPathNameW, GetVolumePathNameW, MoveFileExW, ReOpenFile,
+    SetFileAttributesW, SetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, DELETE,
+    FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_TEMPORARY, FILE_BASIC_INFO, FILE_FLAG_DELETE_ON_CLOSE,
+    FILE_FLAG_OPEN_REPARSE_POINT, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_READ_ATTRIBUTES,
+    FILE_RENAME_INFO, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_WRITE_ATTRIBUTES,
+    INVALID_FILE_ATTRIBUTES, MOVEFILE_REPLACE_EXISTING, MOVEFILE_WRITE_THROUGH, MOVE_FILE_FLAGS,
 };
 
+use windows_sys::Win32::System::IO::DeviceIoControl;
+
 use crate::util;
 
-fn to_utf16(s: &Path) -> Vec<u16> {
-    s.as_os_str().encode_wide().chain(iter::once(0)).collect()
+/// Paths at least this long are turned into extended-length paths before being passed to the
+/// Windows API. This matches what the standard library does: the limit is `MAX_PATH` minus room for
+/// an 8.3 file name, because that's the limit when creating directories.
+const LEGACY_MAX_PATH: usize = 248;
+
+const SEP: u16 = b'\\' as u16;
+const VERBATIM_PREFIX: [u16; 4] = [SEP, SEP, b'?' as u16, SEP];
+const DEVICE_PREFIX: [u16; 4] = [SEP, SEP, b'.' as u16, SEP];
+
+/// Converts `path` to a NUL-terminated wide string. Long paths are converted to extended-length
+/// (`\\?\`) paths so they aren't limited to `MAX_PATH` characters, even on systems that haven't
+/// opted into long path support.
+pub fn to_utf16(path: &Path) -> Vec<u16> {
+    let mut wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    if wide.len() >= LEGACY_MAX_PATH && !wide.starts_with(&VERBATIM_PREFIX) {
+        wide.push(0);
+        // Extended-length paths aren't normalized by Windows, so they must be absolute and can't
+        // contain `.`, `..`, or `/`. If the path can't be normalized, let the call fail instead.
+        if let Ok(full) = full_path_name(&wide) {
+            wide = if full.starts_with(&VERBATIM_PREFIX) || full.starts_with(&DEVICE_PREFIX) {
+                full
+            } else if full.starts_with(&[SEP, SEP]) {
+                // `\\server\share` becomes `\\?\UNC\server\share`.
+                VERBATIM_PREFIX
+                    .iter()
+                    .copied()
+                    .chain("UNC".encode_utf16())
+                    .chain(full[1..].iter().copied())
+                    .collect()
+            } else {
+                VERBATIM_PREFIX.iter().copied().chain(full).collect()
+            };
+        }
+        wide.pop();
+    }
+    wide.push(0);
+    wide
+}
+
+/// Returns the absolute, normalized form of the NUL-terminated `path`, without the trailing NUL.
+fn full_path_name(path: &[u16]) -> io::Result<Vec<u16>> {
+    let mut buf = Vec::new();
+    loop {
+        let len = unsafe {
+            GetFullPathNameW(
+                path.as_ptr(),
+                buf.len() as u32,
+                buf.as_mut_ptr(),
+                ptr::null_mut(),
+            )
+        } as usize;
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        } else if len < buf.len() {
+            // On success, the length doesn't include the trailing NUL.
+            buf.truncate(len);
+            return Ok(buf);
+        }
+        // The buffer was too small, and the length includes the trailing NUL.
+        buf.resize(len, 0);
+    }
 }
 
 fn not_supported<T>(msg: &str) -> io::Result<T> {
@@ -35,41 +102,80 @@ pub fn create_named(
         .create_new(true)
         .read(true)
         .write(true)
-        .custom_flags(FILE_ATTRIBUTE_TEMPORARY)
         .open(path)
 }
 
 pub fn create(dir: &Path) -> io::Result<File> {
-    util::create_helper(
-        dir,
-        OsStr::new(".tmp"),
-        OsStr::new(""),
-        crate::NUM_RAND_CHARS,
-        |path| {
-            let f = OpenOptions::new()
-                .create_new(true)
-                .read(true)
-                .write(true)
-                .share_mode(0)
-                .custom_flags(FILE_ATTRIBUTE_TEMPORARY | FILE_FLAG_DELETE_ON_CLOSE)
-                .open(path)?;
-            // NOTE: in theory, we could delete the file immediately (we open the file in "unix
-            // semantics" mode) but this seemed to corrupt something in Windows at scale (see #339).
-            // So we just rely on `FILE_FLAG_DELETE_ON_CLOSE`.
-            Ok(f)
-        },
-    )
+    util::create_helper(dir, &util::NameSpec::unnamed(), |path| {
+        let f = OpenOptions::new()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .share_mode(0)
+            .custom_flags(FILE_ATTRIBUTE_TEMPORARY | FILE_FLAG_DELETE_ON_CLOSE)
+            .open(path)?;
+        // NOTE: in theory, we could delete the file immediately (we open the file in "unix
+        // semantics" mode) but this seemed to corrupt something in Windows at scale (see #339).
+        // So we just rely on `FILE_FLAG_DELETE_ON_CLOSE`.
+        Ok(f)
+    })
 }
 
 pub fn reopen(file: &File, _path: &Path) -> io::Result<File> {
+    reopen_with(
+        file,
+        FILE_GENERIC_READ | FILE_GENERIC_WRITE,
+        FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+    )
+}
+
+pub fn verify(file: &File, path: &Path) -> io::Result<()> {
+    // Only ask for the attributes (which doesn't conflict with any sharing mode), and don't follow
+    // symbolic links.
+    let current = OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES)
+        .share_mode(FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE)
+        .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)?;
+    let (old, new) = (file_info(file)?, file_info(&current)?);
+    if old.dwVolumeSerialNumber != new.dwVolumeSerialNumber
+        || old.nFileIndexHigh != new.nFileIndexHigh
+        || old.nFileIndexLow != new.nFileIndexLow
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "original tempfile has been replaced",
+        ));
+    }
+    Ok(())
+}
+
+fn file_info(file: &File) -> io::Result<BY_HANDLE_FILE_INFORMATION> {
+    let handle = file.as_raw_handle() as HANDLE;
+    unsafe {
+        let mut info = mem::zeroed();
+        if GetFileInformationByHandle(handle, &mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(info)
+    }
+}
+
+pub fn reopen_read_only(file: &File, _path: &Path) -> io::Result<File> {
+    // The share mode has to allow the access of `file` (which is usually writable), or reopening
+    // fails with a sharing violation. Other processes can open the file with only
+    // `FILE_SHARE_READ` as long as the handles they're sharing it with don't have write access.
+    reopen_with(
+        file,
+        FILE_GENERIC_READ,
+        FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+    )
+}
+
+fn reopen_with(file: &File, access: u32, share_mode: u32) -> io::Result<File> {
     let handle = file.as_raw_handle();
     unsafe {
-        let handle = ReOpenFile(
-            handle as HANDLE,
-            FILE_GENERIC_READ | FILE_GENERIC_WRITE,
-            FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
-            0,
-        );
+        let handle = ReOpenFile(handle as HANDLE, access, share_mode, 0);
         if handle == INVALID_HANDLE_VALUE {
             Err(io::Error::last_os_error())
         } else {
@@ -78,6 +184,101 @@ pub fn reopen(file: &File, _path: &Path) -> io::Result<File> {
     }
 }
 
+pub fn set_inheritable(file: &File) -> io::Result<()> {
+    let handle = file.as_raw_handle() as HANDLE;
+    if unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT, HANDLE_FLAG_INHERIT) } == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub fn set_sparse(file: &File) -> io::Result<()> {
+    // Defined in `Win32_System_Ioctl`, which we don't otherwise need.
+    const FSCTL_SET_SPARSE: u32 = 0x0009_00c4;
+
+    let handle = file.as_raw_handle() as HANDLE;
+    let mut returned = 0;
+    let ok = unsafe {
+        DeviceIoControl(
+            handle,
+            FSCTL_SET_SPARSE,
+            ptr::null(),
+            0,
+            ptr::null_mut(),
+            0,
+            &mut returned,
+            ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub fn deallocate(file: &File, offset: u64, len: u64) -> io::Result<()> {
+    // Defined in `Win32_System_Ioctl`, which we don't otherwise need.
+    const FSCTL_SET_ZERO_DATA: u32 = 0x0009_80c8;
+    // `FILE_ZERO_DATA_INFORMATION`
+    #[repr(C)]
+    struct ZeroDataInformation {
+        file_offset: i64,
+        beyond_final_zero: i64,
+    }
+
+    // Zeroing only releases the underlying storage in sparse files.
+    set_sparse(file)?;
+
+    let info = ZeroDataInformation {
+        file_offset: offset as i64,
+        beyond_final_zero: (offset + len) as i64,
+    };
+    let handle = file.as_raw_handle() as HANDLE;
+    let mut returned = 0;
+    let ok = unsafe {
+        DeviceIoControl(
+            handle,
+            FSCTL_SET_ZERO_DATA,
+            &info as *const ZeroDataInformation as *const _,
+            mem::size_of::<ZeroDataInformation>() as u32,
+            ptr::null_mut(),
+            0,
+            &mut returned,
+            ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    Ok(volume_path(a)?.eq_ignore_ascii_case(&volume_path(b)?))
+}
+
+/// Returns the root of the volume containing `path` (e.g., `C:\` or a mounted folder).
+fn volume_path(path: &Path) -> io::Result<String> {
+    let path_w = to_utf16(path);
+    let mut buf = vec![0u16; path_w.len().max(LEGACY_MAX_PATH) + 1];
+    if unsafe { GetVolumePathNameW(path_w.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    let volume = String::from_utf16_lossy(&buf[..len]);
+    // Long paths are verbatim (see `to_utf16`), so the volume may be too.
+    Ok(if let Some(unc) = volume.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(local) = volume.strip_prefix(r"\\?\") {
+        local.to_owned()
+    } else {
+        volume
+    })
+}
+
 pub fn keep(path: &Path) -> io::Result<()> {
     unsafe {
         let path_w = to_utf16(path);
@@ -89,7 +290,119 @@ pub fn keep(path: &Path) -> io::Result<()> {
     }
 }
 
+pub fn clear_temporary_attribute(path: &Path) -> io::Result<()> {
+    unsafe {
+        let path_w = to_utf16(path);
+        let attributes = GetFileAttributesW(path_w.as_ptr());
+        if attributes == INVALID_FILE_ATTRIBUTES {
+            return Err(io::Error::last_os_error());
+        }
+        if attributes & FILE_ATTRIBUTE_TEMPORARY == 0 {
+            return Ok(());
+        }
+        // Keep any other attributes (e.g., hidden), `FILE_ATTRIBUTE_NORMAL` is only valid alone.
+        let attributes = match attributes & !FILE_ATTRIBUTE_TEMPORARY {
+            0 => FILE_ATTRIBUTE_NORMAL,
+            attributes => attributes,
+        };
+        if SetFileAttributesW(path_w.as_ptr(), attributes) == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub fn copy_metadata(_from: &Path, _to: &Path) -> io::Result<()> {
+    // Files created in the target's directory inherit the same access control entries, and the
+    // file attributes are reset when persisting (see below), so there's nothing to carry over.
+    Ok(())
+}
+
 pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    let mut flags = 0;
+
+    if overwrite {
+        flags |= MOVEFILE_REPLACE_EXISTING;
+    }
+
+    move_file(old_path, new_path, flags)
+}
+
+pub fn persist_durable(file: &File, old_path: &Path, new_path: &Path) -> io::Result<()> {
+    if unsafe { FlushFileBuffers(file.as_raw_handle() as HANDLE) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // With `MOVEFILE_WRITE_THROUGH`, `MoveFileExW` doesn't return until the rename has been
+    // flushed to disk.
+    move_file(
+        old_path,
+        new_path,
+        MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH,
+    )
+}
+
+pub fn persist_by_handle(file: &File, new_path: &Path, overwrite: bool) -> io::Result<()> {
+    // Renaming through a handle requires `DELETE` access, which our handles don't have.
+    let file = reopen_with(
+        file,
+        DELETE | FILE_WRITE_ATTRIBUTES,
+        FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+    )?;
+    let handle = file.as_raw_handle() as HANDLE;
+
+    // The new name has to be absolute (relative names are relative to `RootDirectory`).
+    let full = full_path_name(&to_utf16(new_path))?;
+    let name = to_utf16(Path::new(&OsString::from_wide(&full)));
+
+    // `FILE_RENAME_INFO` ends with a variable-length file name, so allocate it manually (suitably
+    // aligned). The size of the struct already includes one (padded) character for the NUL.
+    let size = mem::size_of::<FILE_RENAME_INFO>() + (name.len() - 1) * mem::size_of::<u16>();
+    let mut buf = vec![0u64; (size + 7) / 8];
+    let info = buf.as_mut_ptr() as *mut FILE_RENAME_INFO;
+    unsafe {
+        // `Flags` overlaps `ReplaceIfExists`, whose type differs between versions of windows-sys.
+        (*info).Anonymous.Flags = u32::from(overwrite);
+        (*info).FileNameLength = ((name.len() - 1) * mem::size_of::<u16>()) as u32;
+        ptr::copy_nonoverlapping(
+            name.as_ptr(),
+            ptr::addr_of_mut!((*info).FileName).cast::<u16>(),
+            name.len(),
+        );
+    }
+
+    // See `move_file` for why the attributes are changed first.
+    set_attributes(handle, FILE_ATTRIBUTE_NORMAL)?;
+    let renamed =
+        unsafe { SetFileInformationByHandle(handle, FileRenameInfo, info.cast(), size as u32) };
+    if renamed == 0 {
+        let e = io::Error::last_os_error();
+        let _ = set_attributes(handle, FILE_ATTRIBUTE_TEMPORARY);
+        Err(e)
+    } else {
+        Ok(())
+    }
+}
+
+fn set_attributes(handle: HANDLE, attributes: u32) -> io::Result<()> {
+    // Zero timestamps are left unchanged.
+    let info = FILE_BASIC_INFO {
+        CreationTime: 0,
+        LastAccessTime: 0,
+        LastWriteTime: 0,
+        ChangeTime: 0,
+        FileAttributes: attributes,
+    };
+    let size = mem::size_of::<FILE_BASIC_INFO>() as u32;
+    let info = ptr::addr_of!(info).cast();
+    if unsafe { SetFileInformationByHandle(handle, FileBasicInfo, info, size) } == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn move_file(old_path: &Path, new_path: &Path, flags: MOVE_FILE_FLAGS) -> io::Result<()> {
     unsafe {
         let old_path_w = to_utf16(old_path);
         let new_path_w = to_utf16(new_path);
@@ -100,12 +413,6 @@ pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<
             return Err(io::Error::last_os_error());
         }
 
-        let mut flags = 0;
-
-        if overwrite {
-            flags |= MOVEFILE_REPLACE_EXISTING;
-        }
-
         if MoveFileExW(old_path_w.as_ptr(), new_path_w.as_ptr(), flags) == 0 {
             let e = io::Error::last_os_error();
             // If this fails, the temporary file is now un-hidden and no longer marked temporary
@@ -1,5 +1,5 @@
 use std::error;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
@@ -12,12 +12,67 @@ use std::os::wasi::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
 #[cfg(windows)]
 use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, RawHandle};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
+use crate::budget::Charge;
 use crate::env;
 use crate::error::IoResultExt;
+use crate::manifest;
+use crate::telemetry::{self, Live};
+use crate::token::{Kind, OwnershipToken};
+use crate::util::{self, Redact};
 use crate::Builder;
 
-mod imp;
+mod atomic;
+#[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+mod fifo;
+pub(crate) mod imp;
+mod reserved;
+mod shared;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(
+            target_os = "android",
+            target_os = "espidf",
+            target_os = "horizon",
+            target_os = "vita"
+        ))
+    )
+))]
+mod shm;
+#[cfg(any(unix, windows))]
+mod symlink;
+#[cfg(feature = "notify")]
+mod watch;
+
+pub use self::atomic::{AtomicWriteFile, Durability};
+#[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+pub(crate) use self::fifo::create as create_fifo;
+#[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+pub use self::fifo::TempFifo;
+pub use self::reserved::ReservedPath;
+pub use self::shared::SharedTempPath;
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(
+            target_os = "android",
+            target_os = "espidf",
+            target_os = "horizon",
+            target_os = "vita"
+        ))
+    )
+))]
+pub use self::shm::ShmTempFile;
+#[cfg(any(unix, windows))]
+pub(crate) use self::symlink::create as create_symlink;
+#[cfg(any(unix, windows))]
+pub use self::symlink::TempSymlink;
+#[cfg(feature = "notify")]
+pub use self::watch::TempFileWatcher;
 
 /// Create a new temporary file. Also see [`tempfile_in`].
 ///
@@ -84,6 +139,34 @@ pub fn tempfile_in<P: AsRef<Path>>(dir: P) -> io::Result<File> {
     imp::create(dir.as_ref())
 }
 
+/// Create a new temporary file (see [`tempfile`]) containing everything read from `reader`, rewound
+/// to the start.
+///
+/// The data is streamed into the file, using kernel-assisted copies where the platform supports
+/// them (e.g., when `reader` is a [`File`] on Linux).
+///
+/// # Errors
+///
+/// If the file can not be created, or reading from `reader` or writing to the file fails, `Err` is
+/// returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+///
+/// let mut file = tempfile::tempfile_from_reader(&b"downloaded data"[..])?;
+/// let mut data = String::new();
+/// file.read_to_string(&mut data)?;
+/// assert_eq!(data, "downloaded data");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn tempfile_from_reader<R: Read>(reader: R) -> io::Result<File> {
+    let mut file = tempfile()?;
+    fill_from(&mut file, reader)?;
+    Ok(file)
+}
+
 /// Error returned when persisting a temporary file path fails.
 #[derive(Debug)]
 pub struct PathPersistError {
@@ -130,8 +213,13 @@ impl error::Error for PathPersistError {
 pub struct TempPath {
     path: Box<Path>,
     disable_cleanup: bool,
+    cleanup: Option<Box<CleanupFn>>,
+    charge: Charge,
+    live: Live,
 }
 
+type CleanupFn = dyn FnOnce(&Path) -> io::Result<()> + Send + Sync;
+
 impl TempPath {
     /// Close and remove the temporary file.
     ///
@@ -159,8 +247,13 @@ impl TempPath {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn close(mut self) -> io::Result<()> {
-        let result = fs::remove_file(&self.path).with_err_path(|| &*self.path);
+        let result = self.remove().with_err_path(|| &*self.path);
+        if result.is_ok() {
+            manifest::release(Kind::File, &self.path);
+        }
         self.path = PathBuf::new().into_boxed_path();
+        self.charge.clear();
+        self.live.release();
         mem::forget(self);
         result
     }
@@ -204,10 +297,15 @@ impl TempPath {
     pub fn persist<P: AsRef<Path>>(mut self, new_path: P) -> Result<(), PathPersistError> {
         match imp::persist(&self.path, new_path.as_ref(), true) {
             Ok(_) => {
+                telemetry::persisted(&self.path, new_path.as_ref());
+                manifest::release(Kind::File, &self.path);
                 // Don't drop `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
-                // seen.)
+                // seen.) Any custom cleanup callback is dropped without being called.
                 self.path = PathBuf::new().into_boxed_path();
+                self.cleanup = None;
+                self.charge.clear();
+                self.live.release();
                 mem::forget(self);
                 Ok(())
             }
@@ -259,10 +357,15 @@ impl TempPath {
     ) -> Result<(), PathPersistError> {
         match imp::persist(&self.path, new_path.as_ref(), false) {
             Ok(_) => {
+                telemetry::persisted(&self.path, new_path.as_ref());
+                manifest::release(Kind::File, &self.path);
                 // Don't drop `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
-                // seen.)
+                // seen.) Any custom cleanup callback is dropped without being called.
                 self.path = PathBuf::new().into_boxed_path();
+                self.cleanup = None;
+                self.charge.clear();
+                self.live.release();
                 mem::forget(self);
                 Ok(())
             }
@@ -273,6 +376,226 @@ impl TempPath {
         }
     }
 
+    /// Move the temporary file into the directory `dir`, keeping its (randomly generated) file
+    /// name, and return its new path.
+    ///
+    /// Existing files are never replaced. This method has the same caveats as
+    /// [`TempPath::persist_noclobber`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location or a file with the same name already exists
+    /// there, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let staging = tempfile::tempdir()?;
+    /// let artifacts = tempfile::tempdir()?;
+    ///
+    /// let path = NamedTempFile::new_in(&staging)?.into_temp_path();
+    /// let name = path.file_name().unwrap().to_owned();
+    /// let new_path = path.persist_in(&artifacts)?;
+    /// assert_eq!(new_path, artifacts.path().join(name));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_in<P: AsRef<Path>>(self, dir: P) -> Result<PathBuf, PathPersistError> {
+        let new_path = match self.path.file_name() {
+            Some(name) => dir.as_ref().join(name),
+            None => {
+                return Err(PathPersistError {
+                    error: io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "temporary path has no file name",
+                    ),
+                    path: self,
+                })
+            }
+        };
+        self.persist_noclobber(&new_path).map(|()| new_path)
+    }
+
+    /// Persist the temporary file at the target path, first saving any file already at the target
+    /// path as a backup (the target's file name with `backup_suffix` appended, replacing any
+    /// previous backup). If there's nothing at the target path, any previous backup is kept. A
+    /// symlink at the target path is backed up as-is, without following it.
+    ///
+    /// Where possible, the backup is created as a hard link so the target path keeps referring to
+    /// a complete file at all times, and the previous backup is only replaced once the new one
+    /// exists. Otherwise, the existing file is renamed (and renamed back if the temporary file
+    /// can't be persisted).
+    ///
+    /// Note: This method has the same caveats as [`TempPath::persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the backup cannot be created or the file cannot be moved to the new location, `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let config = dir.path().join("config.toml");
+    /// fs::write(&config, "old = true")?;
+    ///
+    /// let new_config = NamedTempFile::new_in(&dir)?;
+    /// fs::write(&new_config, "new = true")?;
+    /// new_config.into_temp_path().persist_with_backup(&config, ".bak")?;
+    ///
+    /// assert_eq!(fs::read_to_string(&config)?, "new = true");
+    /// assert_eq!(fs::read_to_string(dir.path().join("config.toml.bak"))?, "old = true");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_with_backup<P, S>(
+        self,
+        new_path: P,
+        backup_suffix: S,
+    ) -> Result<(), PathPersistError>
+    where
+        P: AsRef<Path>,
+        S: AsRef<OsStr>,
+    {
+        let new_path = new_path.as_ref();
+        let moved = match backup(new_path, backup_suffix.as_ref()) {
+            Ok(moved) => moved,
+            Err(error) => return Err(PathPersistError { error, path: self }),
+        };
+        self.persist(new_path).map_err(|err| {
+            // Don't leave the target missing: put the original file back.
+            if let Some(backup) = moved {
+                let _ = fs::rename(backup, new_path);
+            }
+            err
+        })
+    }
+
+    /// Persist the temporary file at the target path like [`TempPath::persist`] but, if a file
+    /// already exists there, first carry over its metadata to the temporary file, so that atomically
+    /// rewriting a file doesn't reset its permissions to the restrictive ones temporary files are
+    /// created with.
+    ///
+    /// On Unix, the target's permissions, ownership, and (on Linux and Apple platforms) extended
+    /// attributes are copied. Ownership and extended attributes that require privileges the
+    /// current process doesn't have are skipped. On Windows, the file simply keeps the access
+    /// control entries it inherited from the target's directory.
+    ///
+    /// Note: This method has the same caveats as [`TempPath::persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be copied or the file cannot be moved to the new location, `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let config = dir.path().join("config.toml");
+    /// fs::write(&config, "old = true")?;
+    ///
+    /// let new_config = NamedTempFile::new_in(&dir)?;
+    /// fs::write(&new_config, "new = true")?;
+    /// new_config
+    ///     .into_temp_path()
+    ///     .persist_preserving_metadata(&config)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_preserving_metadata<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<(), PathPersistError> {
+        let new_path = new_path.as_ref();
+        match copy_existing_metadata(new_path, &self.path) {
+            Ok(()) => self.persist(new_path),
+            Err(error) => Err(PathPersistError { error, path: self }),
+        }
+    }
+
+    /// Persist the temporary file at the target path or, if a file already exists there, at the
+    /// first unused "versioned" path (`name-1.ext`, `name-2.ext`, up to `name-9999.ext`). Existing
+    /// files are never replaced.
+    ///
+    /// Returns the path the file was persisted at. Use [`TempPath::persist_versioned_with`] to
+    /// customize the versioned names.
+    ///
+    /// Note: This method has the same caveats as [`TempPath::persist_noclobber`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location for any reason other than a file already
+    /// existing there, or if all 9999 versions are taken, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let report = dir.path().join("report.txt");
+    ///
+    /// let first = NamedTempFile::new_in(&dir)?.into_temp_path().persist_versioned(&report)?;
+    /// let second = NamedTempFile::new_in(&dir)?.into_temp_path().persist_versioned(&report)?;
+    /// assert_eq!(first, report);
+    /// assert_eq!(second, dir.path().join("report-1.txt"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_versioned<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<PathBuf, PathPersistError> {
+        self.persist_versioned_with(new_path, versioned_path)
+    }
+
+    /// Like [`TempPath::persist_versioned`], but `versioned` is called with the target path and
+    /// the version number (from 1 to 9999) to compute each alternative path to try.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location for any reason other than a file already
+    /// existing there, or if all 9999 versions are taken, `Err` is returned.
+    pub fn persist_versioned_with<P, V>(
+        mut self,
+        new_path: P,
+        mut versioned: V,
+    ) -> Result<PathBuf, PathPersistError>
+    where
+        P: AsRef<Path>,
+        V: FnMut(&Path, u32) -> PathBuf,
+    {
+        let new_path = new_path.as_ref();
+        let mut candidate = new_path.to_path_buf();
+        let mut version = 0;
+        loop {
+            match self.persist_noclobber(&candidate) {
+                Ok(()) => return Ok(candidate),
+                Err(e) if e.error.kind() == io::ErrorKind::AlreadyExists => {
+                    self = e.path;
+                    if version == MAX_VERSIONS {
+                        return Err(PathPersistError {
+                            error: io::Error::new(
+                                io::ErrorKind::AlreadyExists,
+                                "too many versions exist",
+                            ),
+                            path: self,
+                        });
+                    }
+                    version += 1;
+                    candidate = versioned(new_path, version);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Keep the temporary file from being deleted. This function will turn the
     /// temporary file into a non-temporary file without moving it.
     ///
@@ -299,6 +622,7 @@ impl TempPath {
     pub fn keep(mut self) -> Result<PathBuf, PathPersistError> {
         match imp::keep(&self.path) {
             Ok(_) => {
+                manifest::release(Kind::File, &self.path);
                 self.disable_cleanup(true);
                 Ok(mem::replace(
                     &mut self.path,
@@ -332,31 +656,138 @@ impl TempPath {
     /// components that provide files to be consumed or expect a path with no
     /// existing file to be given.
     pub fn from_path(path: impl Into<PathBuf>) -> Self {
-        Self {
-            path: path.into().into_boxed_path(),
-            disable_cleanup: false,
-        }
+        Self::new(path.into(), false)
+    }
+
+    /// Create a new TempPath from an existing path, cleaned up by calling `cleanup` instead of
+    /// deleting the file.
+    ///
+    /// This makes it possible to manage resources that need a custom teardown (e.g., unmounting a
+    /// filesystem or releasing a loop device) with a `TempPath`. The callback is invoked with the
+    /// path on [`close`](TempPath::close) (which returns its result) or when the `TempPath` is
+    /// dropped (ignoring errors). It is never invoked if the path is persisted, kept, or cleanup
+    /// has been disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::TempPath;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let mount_point = dir.path().join("mnt");
+    /// fs::create_dir(&mount_point)?;
+    ///
+    /// let path = TempPath::with_cleanup(&mount_point, |path| {
+    ///     // e.g., unmount `path` first.
+    ///     fs::remove_dir(path)
+    /// });
+    /// path.close()?;
+    /// assert!(!mount_point.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_cleanup<F>(path: impl Into<PathBuf>, cleanup: F) -> Self
+    where
+        F: FnOnce(&Path) -> io::Result<()> + Send + Sync + 'static,
+    {
+        let mut temp_path = Self::new(path.into(), false);
+        temp_path.cleanup = Some(Box::new(cleanup));
+        temp_path
+    }
+
+    /// Give up ownership of the temporary file, returning an [`OwnershipToken`] that can be used
+    /// to re-adopt it with [`TempPath::adopt`], possibly in another process.
+    ///
+    /// The temporary file will _not_ be deleted when the token is dropped. Whoever adopts the
+    /// token becomes responsible for deleting it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{NamedTempFile, TempPath};
+    ///
+    /// let path = NamedTempFile::new()?.into_temp_path();
+    /// let token = path.into_token();
+    /// let path = TempPath::adopt(token)?;
+    /// assert!(path.exists());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn into_token(mut self) -> OwnershipToken {
+        manifest::release(Kind::File, &self.path);
+        self.disable_cleanup(true);
+        let path = mem::replace(&mut self.path, PathBuf::new().into_boxed_path());
+        OwnershipToken::new(Kind::File, path.into_path_buf())
+    }
+
+    /// Register this long-lived temporary file under `name` in the current user's on-disk
+    /// registry, so it can be re-adopted (or deleted) with [`reclaim`](crate::reclaim) if this
+    /// process exits without cleaning it up.
+    ///
+    /// See [`TempDir::register`](crate::TempDir::register) for details.
+    ///
+    /// # Errors
+    ///
+    /// See [`TempDir::register`](crate::TempDir::register).
+    pub fn register(&self, name: &str) -> io::Result<()> {
+        crate::registry::register(name, Kind::File, &self.path)
+    }
+
+    /// Convert this `TempPath` into a [`SharedTempPath`] that can be cloned and shared. The
+    /// temporary file is deleted when the last clone is dropped.
+    #[must_use]
+    pub fn into_shared(self) -> SharedTempPath {
+        SharedTempPath::from(self)
+    }
+
+    /// Adopt a temporary file from an [`OwnershipToken`] created by [`TempPath::into_token`],
+    /// taking over the responsibility of deleting it.
+    ///
+    /// # Errors
+    ///
+    /// If the token refers to a directory, or if the file no longer exists, `Err` is returned.
+    pub fn adopt(token: OwnershipToken) -> io::Result<TempPath> {
+        token
+            .into_path(Kind::File)
+            .map(|path| TempPath::new(path, false))
     }
 
     pub(crate) fn new(path: PathBuf, disable_cleanup: bool) -> Self {
         Self {
             path: path.into_boxed_path(),
             disable_cleanup,
+            cleanup: None,
+            charge: Charge::default(),
+            live: Live::new(Kind::File),
+        }
+    }
+
+    /// Delete the temporary file, or run the custom cleanup callback if there is one.
+    fn remove(&mut self) -> io::Result<()> {
+        #[cfg(feature = "failpoints")]
+        crate::failpoints::check(crate::failpoints::Failpoint::CleanupPermissionDenied)?;
+        match self.cleanup.take() {
+            Some(cleanup) => cleanup(&self.path),
+            None => fs::remove_file(&self.path),
         }
     }
 }
 
 impl fmt::Debug for TempPath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.path.fmt(f)
+        Redact(&*self.path).fmt(f)
     }
 }
 
 impl Drop for TempPath {
     fn drop(&mut self) {
         if !self.disable_cleanup {
-            let _ = fs::remove_file(&self.path);
+            if let Err(e) = self.remove() {
+                telemetry::cleanup_failed(Kind::File, &self.path, &e);
+                return;
+            }
         }
+        manifest::release(Kind::File, &self.path);
     }
 }
 
@@ -380,6 +811,67 @@ impl AsRef<OsStr> for TempPath {
     }
 }
 
+/// Adopt an existing path as a `TempPath`, deleting it when dropped, but only if it resides within
+/// the allowed temporary root: [`env::temp_dir()`] unless overridden with
+/// [`env::override_allowed_temp_root`]. Unlike [`TempPath::from_path`], this guards against
+/// accidentally deleting arbitrary user-supplied paths.
+///
+/// Symbolic links in the parent directories of the path are resolved before checking it, and the
+/// `TempPath` refers to the resolved path. The path itself may not exist yet, but its parent
+/// directory must.
+///
+/// # Errors
+///
+/// If the path (or its parent directory) can't be resolved, `Err` is returned. If it isn't
+/// strictly inside the allowed root, an error of kind [`io::ErrorKind::InvalidInput`] is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use tempfile::TempPath;
+///
+/// let file = tempfile::NamedTempFile::new()?;
+/// let (_, path) = file.keep()?;
+/// let path = TempPath::try_from(path)?;
+/// path.close()?;
+///
+/// assert!(TempPath::try_from(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))).is_err());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+impl TryFrom<PathBuf> for TempPath {
+    type Error = io::Error;
+
+    fn try_from(path: PathBuf) -> io::Result<TempPath> {
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => return Err(not_contained(&path)),
+        };
+        let parent = match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => return Err(not_contained(&path)),
+        };
+        let parent = fs::canonicalize(parent).with_err_path(|| parent)?;
+        let root = env::allowed_temp_root();
+        let root = fs::canonicalize(&root).with_err_path(|| &root)?;
+        if !parent.starts_with(&root) {
+            return Err(not_contained(&path));
+        }
+        Ok(TempPath::new(parent.join(name), false))
+    }
+}
+
+fn not_contained(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{:?} is not inside the allowed temporary root",
+            Redact(path)
+        ),
+    )
+}
+
 /// A named temporary file.
 ///
 /// The default constructor, [`NamedTempFile::new()`], creates files in
@@ -628,6 +1120,85 @@ impl NamedTempFile<File> {
     ) -> io::Result<NamedTempFile> {
         Builder::new().prefix(&prefix).tempfile_in(dir)
     }
+
+    /// Create a new named temporary file with the given builder's settings, containing everything
+    /// read from `reader`, rewound to the start.
+    ///
+    /// The data is streamed into the file, using kernel-assisted copies where the platform
+    /// supports them (e.g., when `reader` is a [`File`] on Linux).
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, or reading from `reader` or writing to the file fails, `Err`
+    /// is returned (and the file is deleted).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use tempfile::{Builder, NamedTempFile};
+    ///
+    /// let upload = TcpStream::connect("127.0.0.1:8080")?;
+    /// let file = NamedTempFile::from_reader(Builder::new().suffix(".bin"), upload)?;
+    /// println!("received {} bytes", file.len()?);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn from_reader<R: Read>(builder: &Builder<'_, '_>, reader: R) -> io::Result<NamedTempFile> {
+        let mut file = builder.tempfile()?;
+        fill_from(&mut file, reader)?;
+        Ok(file)
+    }
+
+    /// Create a new named temporary file with the given builder's settings, containing exactly
+    /// `contents`, rewound to the start.
+    ///
+    /// The contents aren't synchronized to disk, see [`NamedTempFile::with_contents_synced`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or written to, `Err` is returned (and the file is deleted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{Builder, NamedTempFile};
+    ///
+    /// let config = NamedTempFile::with_contents(Builder::new().suffix(".toml"), "debug = true\n")?;
+    /// assert_eq!(std::fs::read_to_string(config.path())?, "debug = true\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_contents<C: AsRef<[u8]>>(
+        builder: &Builder<'_, '_>,
+        contents: C,
+    ) -> io::Result<NamedTempFile> {
+        NamedTempFile::from_reader(builder, contents.as_ref())
+    }
+
+    /// Like [`NamedTempFile::with_contents`], but also synchronizes the contents (and metadata) to
+    /// disk before returning, e.g., before handing the file to another process that may outlive a
+    /// crash of this one.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, written to, or synchronized, `Err` is returned (and the file
+    /// is deleted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{Builder, NamedTempFile};
+    ///
+    /// let job = NamedTempFile::with_contents_synced(&Builder::new(), b"payload")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_contents_synced<C: AsRef<[u8]>>(
+        builder: &Builder<'_, '_>,
+        contents: C,
+    ) -> io::Result<NamedTempFile> {
+        let file = NamedTempFile::with_contents(builder, contents)?;
+        file.file.sync_all().with_err_path(|| file.path())?;
+        Ok(file)
+    }
 }
 
 impl<F> NamedTempFile<F> {
@@ -654,6 +1225,27 @@ impl<F> NamedTempFile<F> {
         &self.path
     }
 
+    /// Get the temporary file's path as a UTF-8 string, e.g., to embed it in a JSON document or
+    /// a command line. Use [`Builder::ascii_only`] to make sure it's ASCII.
+    ///
+    /// # Errors
+    ///
+    /// If the path isn't valid UTF-8, an error of kind [`io::ErrorKind::InvalidData`] is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new()?;
+    /// let config = format!("{{\"scratch\": {:?}}}", file.utf8_path()?);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn utf8_path(&self) -> io::Result<&str> {
+        crate::util::utf8_path(&self.path)
+    }
+
     /// Close and remove the temporary file.
     ///
     /// Use this if you want to detect errors in deleting the file.
@@ -731,6 +1323,43 @@ impl<F> NamedTempFile<F> {
         }
     }
 
+    /// Persist the temporary file at the target path, carrying over the permissions, ownership,
+    /// and extended attributes of any file already there. See
+    /// [`TempPath::persist_preserving_metadata`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be copied or the file cannot be moved to the new location, `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new_in("/etc")?;
+    /// writeln!(file, "nameserver 127.0.0.1")?;
+    /// file.persist_preserving_metadata("/etc/resolv.conf")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_preserving_metadata<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<F, PersistError<F>> {
+        let NamedTempFile { path, file } = self;
+        match path.persist_preserving_metadata(new_path) {
+            Ok(_) => Ok(file),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile { path, file },
+                    error,
+                })
+            }
+        }
+    }
+
     /// Persist the temporary file at the target path if and only if no file exists there.
     ///
     /// If a file exists at the target path, fail. If this method fails, it will
@@ -785,17 +1414,171 @@ impl<F> NamedTempFile<F> {
         }
     }
 
-    /// Keep the temporary file from being deleted. This function will turn the
-    /// temporary file into a non-temporary file without moving it.
+    /// Move the temporary file into the directory `dir`, keeping its (randomly generated) file
+    /// name. Returns the file and its new path.
+    ///
+    /// See [`TempPath::persist_in`] for details.
     ///
     /// # Errors
     ///
-    /// On some platforms (e.g., Windows), we need to mark the file as
-    /// non-temporary. This operation could fail.
+    /// If the file cannot be moved to the new location or a file with the same name already exists
+    /// there, `Err` is returned.
+    pub fn persist_in<P: AsRef<Path>>(self, dir: P) -> Result<(F, PathBuf), PersistError<F>> {
+        let NamedTempFile { path, file } = self;
+        match path.persist_in(dir) {
+            Ok(path) => Ok((file, path)),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile { path, file },
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Hash the contents of the temporary file with `algo`, and move it into the directory `dir`
+    /// as `<hash><suffix>` (e.g., for a cache or artifact store), where the suffix is the
+    /// extension of the temporary file's name, if any (e.g., `.tar.gz` becomes `.gz`). Returns the
+    /// file and its new path (requires the `cas` feature).
     ///
-    /// # Examples
+    /// The whole file is read to compute the hash, after which its position is restored. A file
+    /// already at the new path (presumably with the same contents) is replaced.
     ///
-    /// ```no_run
+    /// # Errors
+    ///
+    /// If the file cannot be read or moved to the new location, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::{Builder, HashAlgo};
+    ///
+    /// let store = tempfile::tempdir()?;
+    /// let mut file = Builder::new().suffix(".txt").tempfile()?;
+    /// file.write_all(b"artifact")?;
+    ///
+    /// let (_, path) = file.persist_by_hash(&store, HashAlgo::Sha256)?;
+    /// assert_eq!(
+    ///     path.file_name().unwrap(),
+    ///     "c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c.txt",
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "cas")]
+    pub fn persist_by_hash<P: AsRef<Path>>(
+        mut self,
+        dir: P,
+        algo: crate::HashAlgo,
+    ) -> Result<(F, PathBuf), PersistError<F>>
+    where
+        F: Read + Seek,
+    {
+        let hash = match hash_contents(&mut self.file, algo).with_err_path(|| self.path()) {
+            Ok(hash) => hash,
+            Err(error) => return Err(PersistError { error, file: self }),
+        };
+        let mut name = OsString::from(hash);
+        if let Some(extension) = self.path().extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        let new_path = dir.as_ref().join(name);
+        let file = self.persist(&new_path)?;
+        Ok((file, new_path))
+    }
+
+    /// Persist the temporary file at the target path, first saving any file already at the target
+    /// path as a backup (the target's file name with `backup_suffix` appended, replacing any
+    /// previous backup).
+    ///
+    /// See [`TempPath::persist_with_backup`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the backup cannot be created or the file cannot be moved to the new location, `Err` is
+    /// returned.
+    pub fn persist_with_backup<P, S>(
+        self,
+        new_path: P,
+        backup_suffix: S,
+    ) -> Result<F, PersistError<F>>
+    where
+        P: AsRef<Path>,
+        S: AsRef<OsStr>,
+    {
+        let NamedTempFile { path, file } = self;
+        match path.persist_with_backup(new_path, backup_suffix) {
+            Ok(()) => Ok(file),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile { path, file },
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Persist the temporary file at the target path or, if a file already exists there, at the
+    /// first unused "versioned" path (`name-1.ext`, `name-2.ext`, up to `name-9999.ext`). Existing
+    /// files are never replaced.
+    ///
+    /// Returns the file and the path it was persisted at. See [`TempPath::persist_versioned`] for
+    /// details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location for any reason other than a file already
+    /// existing there, or if all 9999 versions are taken, `Err` is returned.
+    pub fn persist_versioned<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<(F, PathBuf), PersistError<F>> {
+        self.persist_versioned_with(new_path, versioned_path)
+    }
+
+    /// Like [`NamedTempFile::persist_versioned`], but `versioned` is called with the target path
+    /// and the version number (from 1 to 9999) to compute each alternative path to try.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location for any reason other than a file already
+    /// existing there, or if all 9999 versions are taken, `Err` is returned.
+    pub fn persist_versioned_with<P, V>(
+        self,
+        new_path: P,
+        versioned: V,
+    ) -> Result<(F, PathBuf), PersistError<F>>
+    where
+        P: AsRef<Path>,
+        V: FnMut(&Path, u32) -> PathBuf,
+    {
+        let NamedTempFile { path, file } = self;
+        match path.persist_versioned_with(new_path, versioned) {
+            Ok(path) => Ok((file, path)),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile { path, file },
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Keep the temporary file from being deleted. This function will turn the
+    /// temporary file into a non-temporary file without moving it.
+    ///
+    /// # Errors
+    ///
+    /// On some platforms (e.g., Windows), we need to mark the file as
+    /// non-temporary. This operation could fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
     /// use std::io::Write;
     /// use tempfile::NamedTempFile;
     ///
@@ -829,13 +1612,59 @@ impl<F> NamedTempFile<F> {
         self.path.disable_cleanup(disable_cleanup)
     }
 
+    /// Register this long-lived temporary file under `name` in the current user's on-disk
+    /// registry, so it can be re-adopted (or deleted) with [`reclaim`](crate::reclaim) if this
+    /// process exits without cleaning it up.
+    ///
+    /// See [`TempDir::register`](crate::TempDir::register) for details.
+    ///
+    /// # Errors
+    ///
+    /// See [`TempDir::register`](crate::TempDir::register).
+    pub fn register(&self, name: &str) -> io::Result<()> {
+        self.path.register(name)
+    }
+
+    /// Clear the "temporary file" (`FILE_ATTRIBUTE_TEMPORARY`) attribute that temporary files are
+    /// created with on Windows, without otherwise changing the file. This is useful, e.g., after
+    /// [disabling cleanup](NamedTempFile::disable_cleanup), as the attribute tells Windows that
+    /// the file's data doesn't need to be written to disk. On other platforms, this does nothing.
+    ///
+    /// See also [`Builder::windows_temporary_attribute`].
+    ///
+    /// # Errors
+    ///
+    /// If the file's attributes cannot be read or changed, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?;
+    /// file.disable_cleanup(true);
+    /// file.clear_temporary_attribute()?;
+    /// # file.disable_cleanup(false);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn clear_temporary_attribute(&self) -> io::Result<()> {
+        imp::clear_temporary_attribute(self.path()).with_err_path(|| self.path())
+    }
+
+    /// Records that the file has been opened in append mode, for the disk budget.
+    pub(crate) fn set_append(&self) {
+        self.path.charge.append();
+    }
+
     /// Get a reference to the underlying file.
     pub fn as_file(&self) -> &F {
+        self.path.charge.forget();
         &self.file
     }
 
     /// Get a mutable reference to the underlying file.
     pub fn as_file_mut(&mut self) -> &mut F {
+        self.path.charge.forget();
         &mut self.file
     }
 
@@ -869,8 +1698,49 @@ impl<F> NamedTempFile<F> {
     /// This can be used with [`NamedTempFile::into_parts`] to reconstruct the
     /// `NamedTempFile`.
     pub fn from_parts(file: F, path: TempPath) -> Self {
+        path.charge.forget();
         Self { file, path }
     }
+
+    /// Transforms the underlying file (or file-like object), keeping the temporary path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{BufWriter, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?.map(BufWriter::new);
+    /// writeln!(file, "buffered")?;
+    /// let file = file.try_map(|f| f.into_inner().map_err(|e| e.into_error()))?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn map<G, M>(self, f: M) -> NamedTempFile<G>
+    where
+        M: FnOnce(F) -> G,
+    {
+        self.path.charge.forget();
+        NamedTempFile {
+            file: f(self.file),
+            path: self.path,
+        }
+    }
+
+    /// Fallibly transforms the underlying file (or file-like object), keeping the temporary path.
+    ///
+    /// # Errors
+    ///
+    /// If `f` fails, its error is returned and the temporary file is deleted.
+    pub fn try_map<G, E, M>(self, f: M) -> Result<NamedTempFile<G>, E>
+    where
+        M: FnOnce(F) -> Result<G, E>,
+    {
+        self.path.charge.forget();
+        Ok(NamedTempFile {
+            file: f(self.file)?,
+            path: self.path,
+        })
+    }
 }
 
 impl NamedTempFile<File> {
@@ -902,137 +1772,573 @@ impl NamedTempFile<File> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn reopen(&self) -> io::Result<File> {
-        imp::reopen(self.as_file(), NamedTempFile::path(self))
+        imp::reopen(&self.file, NamedTempFile::path(self))
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Persist the temporary file at the target path, renaming it through the open file handle
+    /// instead of by its path.
+    ///
+    /// On Windows, this renames the file with `SetFileInformationByHandle(FileRenameInfo)`, which
+    /// (unlike [`NamedTempFile::persist`]) works while the file is memory-mapped, and is
+    /// guaranteed to persist this very file even if its path has been tampered with. On other
+    /// platforms, this is equivalent to [`NamedTempFile::persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new_in("./")?;
+    /// file.as_file().set_len(4096)?;
+    /// // ... map the file into memory and fill it ...
+    /// let file = file.persist_by_handle("./data.bin")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_by_handle<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
+        #[cfg(windows)]
+        let result = imp::persist_by_handle(&self.file, new_path.as_ref(), true);
+        #[cfg(not(windows))]
+        let result = imp::persist(NamedTempFile::path(&self), new_path.as_ref(), true);
+        match result {
+            Ok(()) => {
+                telemetry::persisted(NamedTempFile::path(&self), new_path.as_ref());
+                let NamedTempFile { mut path, file } = self;
+                path.disable_cleanup(true);
+                Ok(file)
+            }
+            Err(error) => Err(PersistError { file: self, error }),
+        }
+    }
+
+    /// Securely reopen the temporary file for reading only. See [`NamedTempFile::reopen`].
+    ///
+    /// On Windows, other processes that open the file by path usually only share reading (e.g.,
+    /// .NET's `File.OpenRead`), which fails as long as this process holds a writable handle to the
+    /// file. Replacing the `NamedTempFile`'s handle with a read-only one (see the example below)
+    /// lets them read the file, while it can still be deleted once they're done.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be reopened, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use std::process::Command;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "input for the tool")?;
+    ///
+    /// // Give up write access before handing the path to another process.
+    /// let reader = file.reopen_read_only()?;
+    /// let file = file.map(|_| reader);
+    /// Command::new("some-tool").arg(file.path()).status()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reopen_read_only(&self) -> io::Result<File> {
+        imp::reopen_read_only(&self.file, NamedTempFile::path(self))
             .with_err_path(|| NamedTempFile::path(self))
     }
+
+    /// Copy the current contents of the temporary file into a new, independently named temporary
+    /// file created by `builder`, e.g., to give each consumer in a fan-out pipeline its own scratch
+    /// copy to modify.
+    ///
+    /// The contents are copied through a [reopened](NamedTempFile::reopen) handle, so
+    /// this file's position is left alone, and with the kernel's copy facilities where available
+    /// (e.g., `copy_file_range` on Linux). The copy is positioned at its start. Like writes through
+    /// [`NamedTempFile::as_file`], the copied bytes aren't counted against a
+    /// [`DiskBudget`](crate::env::DiskBudget).
+    ///
+    /// # Errors
+    ///
+    /// If this file can't be reopened, or the new file can't be created or written to, `Err` is
+    /// returned (and the new file is deleted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use tempfile::{Builder, NamedTempFile};
+    ///
+    /// let mut original = NamedTempFile::new()?;
+    /// original.write_all(b"shared input")?;
+    ///
+    /// let mut copy = original.duplicate(Builder::new().prefix("consumer-"))?;
+    /// copy.write_all(b"SHARED")?;
+    ///
+    /// let mut contents = String::new();
+    /// original.reopen()?.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "shared input");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn duplicate(&self, builder: &Builder<'_, '_>) -> io::Result<NamedTempFile> {
+        let mut source = self.reopen()?;
+        let mut copy = builder.tempfile()?;
+        io::copy(&mut source, &mut copy)?;
+        copy.seek(SeekFrom::Start(0))?;
+        Ok(copy)
+    }
+
+    /// Check that the temporary file still exists at its path, i.e., that it hasn't been deleted or
+    /// replaced by a temporary file cleaner (or anyone else) since it was created.
+    ///
+    /// Long-lived temporary files can use this to notice that data written to them can no longer be
+    /// found at their path (e.g., by other processes, or by [`NamedTempFile::reopen`]). This
+    /// compares the identity (device and inode on Unix, volume and file index on Windows) of the
+    /// open file with the file at the path, without following symbolic links. To be notified when
+    /// this happens instead, use `NamedTempFile::watch` (requires the `notify` feature).
+    ///
+    /// On WASI (without the `nightly` feature), files can't be identified, so this only checks that
+    /// something still exists at the path.
+    ///
+    /// # Errors
+    ///
+    /// If the temporary file has been deleted or replaced, an error of kind
+    /// [`io::ErrorKind::NotFound`] is returned. If the file at the path can't be examined, `Err` is
+    /// returned as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let file = NamedTempFile::new_in(&dir)?;
+    /// file.verify()?;
+    ///
+    /// // Someone moves the file away.
+    /// std::fs::rename(file.path(), dir.path().join("elsewhere"))?;
+    /// assert_eq!(file.verify().unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn verify(&self) -> io::Result<()> {
+        imp::verify(&self.file, NamedTempFile::path(self))
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Watch the temporary file, to be notified as soon as it has been deleted or replaced (see
+    /// [`NamedTempFile::verify`]), e.g., by a temporary file cleaner.
+    ///
+    /// This method requires the `notify` feature and watches the directory containing the
+    /// temporary file using the [`notify`] crate (inotify, FSEvents, ReadDirectoryChangesW, etc.).
+    /// See [`TempFileWatcher`].
+    ///
+    /// # Errors
+    ///
+    /// If the temporary file has already been deleted or replaced, an error of kind
+    /// [`io::ErrorKind::NotFound`] is returned. If the watcher can't be started, `Err` is returned
+    /// as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let file = NamedTempFile::new()?;
+    /// let watcher = file.watch()?;
+    ///
+    /// // ...
+    ///
+    /// if let Some(error) = watcher.try_recv() {
+    ///     eprintln!("lost the scratch file: {}", error);
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "notify")]
+    pub fn watch(&self) -> io::Result<TempFileWatcher> {
+        let watcher = TempFileWatcher::new(&self.file, NamedTempFile::path(self))?;
+        // Only check the file once it's being watched, so no change can be missed.
+        self.verify()?;
+        Ok(watcher)
+    }
+
+    /// Release the storage backing `len` bytes of the temporary file, starting at `offset`, without
+    /// changing its size. The deallocated region reads back as zeros.
+    ///
+    /// This lets a consumer streaming through a large scratch file give the disk space of the parts
+    /// it has already processed back to the filesystem:
+    ///
+    /// - On Linux and Android, this punches a hole with `fallocate(FALLOC_FL_PUNCH_HOLE)`.
+    /// - On Windows, this marks the file as sparse and zeroes the region with
+    ///   `FSCTL_SET_ZERO_DATA`.
+    ///
+    /// Filesystems may only release whole blocks, so parts of the region may stay allocated (but
+    /// still read back as zeros).
+    ///
+    /// # Errors
+    ///
+    /// If `offset + len` is larger than `i64::MAX`, an error of kind [`io::ErrorKind::InvalidInput`]
+    /// is returned. If the platform or filesystem doesn't support deallocating parts of a file (e.g.,
+    /// on other Unix platforms), `Err` is returned as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::{Read, Seek, SeekFrom};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// const CHUNK: u64 = 64 * 1024 * 1024;
+    /// # fn fill(_: &mut NamedTempFile) -> std::io::Result<()> { Ok(()) }
+    /// # fn process(_: &[u8]) {}
+    ///
+    /// let mut file = NamedTempFile::new()?;
+    /// fill(&mut file)?;
+    /// file.seek(SeekFrom::Start(0))?;
+    ///
+    /// let mut buf = vec![0; CHUNK as usize];
+    /// let mut offset = 0;
+    /// loop {
+    ///     let n = file.read(&mut buf)?;
+    ///     if n == 0 {
+    ///         break;
+    ///     }
+    ///     process(&buf[..n]);
+    ///     // This part is no longer needed.
+    ///     file.deallocate(offset, n as u64)?;
+    ///     offset += n as u64;
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn deallocate(&self, offset: u64, len: u64) -> io::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= i64::MAX as u64 => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "region to deallocate is too large",
+                ))
+                .with_err_path(|| NamedTempFile::path(self))
+            }
+        }
+        imp::deallocate(&self.file, offset, len).with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Persist the temporary file at the target path, and make sure that both its contents and the
+    /// rename have reached the disk before returning.
+    ///
+    /// Like [`NamedTempFile::persist`], this atomically replaces any file at the target path, but a
+    /// crash can't undo the replacement once this method has returned:
+    ///
+    /// - On Unix, the file is synchronized with `fsync` before it's renamed, and the directory
+    ///   containing the target is synchronized afterwards.
+    /// - On Windows, the file is flushed with `FlushFileBuffers` before it's renamed with
+    ///   `MoveFileExW(MOVEFILE_WRITE_THROUGH)`, which only returns once the rename is on disk.
+    ///
+    /// # Security
+    ///
+    /// This method persists the temporary file using its path and may not be
+    /// secure in all cases. Please read the security section on the top
+    /// level documentation of this type for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be synchronized or moved to the new location, `Err` is returned. On
+    /// Unix, if the directory cannot be synchronized, the file has already been moved to the new
+    /// location, but `Err` is still returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new_in("./")?;
+    /// writeln!(file, "committed = true")?;
+    /// file.persist_durable("./state.toml")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn persist_durable<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
+        let new_path = new_path.as_ref();
+        match imp::persist_durable(&self.file, NamedTempFile::path(&self), new_path) {
+            Ok(()) => {
+                telemetry::persisted(NamedTempFile::path(&self), new_path);
+                let NamedTempFile { mut path, file } = self;
+                path.disable_cleanup(true);
+                Ok(file)
+            }
+            Err(error) => Err(PersistError { file: self, error }),
+        }
+    }
+
+    /// Change the permissions of the temporary file.
+    ///
+    /// Unlike `fs::set_permissions(my_temp_file.path(), ..)`, this changes the permissions through
+    /// the open file handle and is therefore guaranteed to affect the temporary file, even in the
+    /// presence of pathological temporary file cleaners.
+    ///
+    /// # Errors
+    ///
+    /// If the permissions can't be changed, `Err` is returned.
+    pub fn set_permissions(&self, permissions: fs::Permissions) -> io::Result<()> {
+        self.file
+            .set_permissions(permissions)
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Queries metadata about the temporary file.
+    ///
+    /// Unlike `my_temp_file.path().metadata()`, this queries the open file handle and is therefore
+    /// guaranteed to describe the temporary file, even in the presence of pathological temporary
+    /// file cleaners.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be queried, `Err` is returned.
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        self.file
+            .metadata()
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Returns the size of the temporary file in bytes (queried through the open file handle).
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be queried, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let mut file = NamedTempFile::new()?;
+    /// file.write_all(b"hello")?;
+    /// assert_eq!(file.len()?, 5);
+    ///
+    /// file.set_len(2)?;
+    /// assert_eq!(file.len()?, 2);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> io::Result<u64> {
+        self.metadata().map(|m| m.len())
+    }
+
+    /// Truncates or extends the temporary file (through the open file handle).
+    ///
+    /// See [`File::set_len`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be resized, `Err` is returned.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.path.charge.resize(size, || {
+            self.file
+                .set_len(size)
+                .with_err_path(|| NamedTempFile::path(self))
+        })
+    }
+
+    /// Returns the last modification time of the temporary file (queried through the open file
+    /// handle).
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be queried or the platform doesn't record modification times, `Err`
+    /// is returned.
+    pub fn modified(&self) -> io::Result<std::time::SystemTime> {
+        self.metadata()?
+            .modified()
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Converts the temporary file into a [`Stdio`] handle (e.g., to redirect a child process's
+    /// output into it) and the [`TempPath`] guarding its path.
+    ///
+    /// The path stays around until the returned `TempPath` is dropped, so the child's output can be
+    /// read by path afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::process::Command;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// let (stdout, path) = NamedTempFile::new()?.into_stdio();
+    /// Command::new("ls").stdout(stdout).status()?;
+    ///
+    /// let listing = std::fs::read_to_string(&path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn into_stdio(self) -> (Stdio, TempPath) {
+        let (file, path) = self.into_parts();
+        (Stdio::from(file), path)
+    }
 }
 
 impl<F: Read> Read for NamedTempFile<F> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.as_file_mut().read(buf).with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge
+            .read(|| file.read(buf).with_err_path(|| &**path), |&n| n)
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
-        self.as_file_mut()
-            .read_vectored(bufs)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge.read(
+            || file.read_vectored(bufs).with_err_path(|| &**path),
+            |&n| n,
+        )
     }
 
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
-        self.as_file_mut()
-            .read_to_end(buf)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge
+            .read(|| file.read_to_end(buf).with_err_path(|| &**path), |&n| n)
     }
 
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
-        self.as_file_mut()
-            .read_to_string(buf)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge.read(
+            || file.read_to_string(buf).with_err_path(|| &**path),
+            |&n| n,
+        )
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.as_file_mut()
-            .read_exact(buf)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        let len = buf.len();
+        path.charge
+            .read(|| file.read_exact(buf).with_err_path(|| &**path), |_| len)
     }
 }
 
 impl Read for &NamedTempFile<File> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.as_file().read(buf).with_err_path(|| self.path())
+        self.path.charge.read(
+            || (&self.file).read(buf).with_err_path(|| self.path()),
+            |&n| n,
+        )
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
-        self.as_file()
-            .read_vectored(bufs)
-            .with_err_path(|| self.path())
+        self.path.charge.read(
+            || {
+                (&self.file)
+                    .read_vectored(bufs)
+                    .with_err_path(|| self.path())
+            },
+            |&n| n,
+        )
     }
 
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
-        self.as_file()
-            .read_to_end(buf)
-            .with_err_path(|| self.path())
+        self.path.charge.read(
+            || (&self.file).read_to_end(buf).with_err_path(|| self.path()),
+            |&n| n,
+        )
     }
 
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
-        self.as_file()
-            .read_to_string(buf)
-            .with_err_path(|| self.path())
+        self.path.charge.read(
+            || {
+                (&self.file)
+                    .read_to_string(buf)
+                    .with_err_path(|| self.path())
+            },
+            |&n| n,
+        )
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.as_file().read_exact(buf).with_err_path(|| self.path())
+        let len = buf.len();
+        self.path.charge.read(
+            || (&self.file).read_exact(buf).with_err_path(|| self.path()),
+            |_| len,
+        )
     }
 }
 
 impl<F: Write> Write for NamedTempFile<F> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.as_file_mut().write(buf).with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge
+            .write(buf.len(), || file.write(buf).with_err_path(|| &**path))
     }
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
-        self.as_file_mut().flush().with_err_path(|| self.path())
+        self.file.flush().with_err_path(|| self.path())
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        self.as_file_mut()
-            .write_vectored(bufs)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge.write(vectored_len(bufs), || {
+            file.write_vectored(bufs).with_err_path(|| &**path)
+        })
     }
 
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.as_file_mut()
-            .write_all(buf)
-            .with_err_path(|| self.path())
-    }
-
-    fn write_fmt(&mut self, fmt: fmt::Arguments<'_>) -> io::Result<()> {
-        self.as_file_mut()
-            .write_fmt(fmt)
-            .with_err_path(|| self.path())
+        let NamedTempFile { path, file } = self;
+        path.charge
+            .write_all(buf, |buf| file.write(buf).with_err_path(|| &**path))
     }
 }
 
 impl Write for &NamedTempFile<File> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.as_file().write(buf).with_err_path(|| self.path())
+        self.path.charge.write(buf.len(), || {
+            (&self.file).write(buf).with_err_path(|| self.path())
+        })
     }
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
-        self.as_file().flush().with_err_path(|| self.path())
+        (&self.file).flush().with_err_path(|| self.path())
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        self.as_file()
-            .write_vectored(bufs)
-            .with_err_path(|| self.path())
+        self.path.charge.write(vectored_len(bufs), || {
+            (&self.file)
+                .write_vectored(bufs)
+                .with_err_path(|| self.path())
+        })
     }
 
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.as_file().write_all(buf).with_err_path(|| self.path())
+        self.path.charge.write_all(buf, |buf| {
+            (&self.file).write(buf).with_err_path(|| self.path())
+        })
     }
+}
 
-    fn write_fmt(&mut self, fmt: fmt::Arguments<'_>) -> io::Result<()> {
-        self.as_file().write_fmt(fmt).with_err_path(|| self.path())
-    }
+fn vectored_len(bufs: &[io::IoSlice<'_>]) -> usize {
+    bufs.iter()
+        .map(|buf| buf.len())
+        .fold(0, usize::saturating_add)
 }
 
 impl<F: Seek> Seek for NamedTempFile<F> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.as_file_mut().seek(pos).with_err_path(|| self.path())
+        let result = self.file.seek(pos).with_err_path(|| self.path());
+        self.path.charge.seeked(result.as_ref().ok().copied());
+        result
     }
 }
 
 impl Seek for &NamedTempFile<File> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.as_file().seek(pos).with_err_path(|| self.path())
+        let result = (&self.file).seek(pos).with_err_path(|| self.path());
+        self.path.charge.seeked(result.as_ref().ok().copied());
+        result
     }
 }
 
 #[cfg(any(unix, target_os = "wasi"))]
 impl<F: AsFd> AsFd for NamedTempFile<F> {
     fn as_fd(&self) -> BorrowedFd<'_> {
-        self.as_file().as_fd()
+        self.file.as_fd()
     }
 }
 
@@ -1040,7 +2346,7 @@ impl<F: AsFd> AsFd for NamedTempFile<F> {
 impl<F: AsRawFd> AsRawFd for NamedTempFile<F> {
     #[inline]
     fn as_raw_fd(&self) -> RawFd {
-        self.as_file().as_raw_fd()
+        self.file.as_raw_fd()
     }
 }
 
@@ -1048,7 +2354,7 @@ impl<F: AsRawFd> AsRawFd for NamedTempFile<F> {
 impl<F: AsHandle> AsHandle for NamedTempFile<F> {
     #[inline]
     fn as_handle(&self) -> BorrowedHandle<'_> {
-        self.as_file().as_handle()
+        self.file.as_handle()
     }
 }
 
@@ -1056,10 +2362,181 @@ impl<F: AsHandle> AsHandle for NamedTempFile<F> {
 impl<F: AsRawHandle> AsRawHandle for NamedTempFile<F> {
     #[inline]
     fn as_raw_handle(&self) -> RawHandle {
-        self.as_file().as_raw_handle()
+        self.file.as_raw_handle()
+    }
+}
+
+/// Run `command` to completion, capturing its standard output in a new named temporary file.
+///
+/// The output is written directly to the temporary file (it's never buffered in memory) and the
+/// returned file is rewound to the start, ready to be read. The command's standard input and
+/// standard error are inherited unless configured otherwise.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file can't be created, the command fails to start, or the
+/// command exits unsuccessfully.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Read;
+/// use std::process::Command;
+///
+/// let mut output = tempfile::capture_output(Command::new("ls").arg("-l"))?;
+///
+/// let mut listing = String::new();
+/// output.read_to_string(&mut listing)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn capture_output(command: &mut Command) -> io::Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    let stdout = file.file.try_clone().with_err_path(|| file.path())?;
+    let status = command.stdout(stdout).status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("command exited unsuccessfully: {}", status),
+        ));
+    }
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Write `contents` to a new executable temporary file in [`env::temp_dir()`], and return its
+/// path, ready to be run (e.g., with [`Command::new`]).
+///
+/// The file is created with mode `0o700` on Unix and, on Windows, with an `.exe` suffix. It's
+/// closed before this function returns (on Linux, files still open for writing can't be executed).
+/// It's deleted when the returned [`TempPath`] is dropped. See
+/// [`TempDir::write_executable`](crate::TempDir::write_executable) to write an executable with a
+/// specific name.
+///
+/// # Errors
+///
+/// If the file cannot be created or written to, `Err` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process::Command;
+///
+/// static HELPER: &[u8] = b"#!/bin/sh\necho hello\n";
+///
+/// let helper = tempfile::executable_tempfile(HELPER)?;
+/// let status = Command::new(&helper).status()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn executable_tempfile<C: AsRef<[u8]>>(contents: C) -> io::Result<TempPath> {
+    Builder::new()
+        .suffix(std::env::consts::EXE_SUFFIX)
+        .make(|path| write_executable(path, contents.as_ref()))
+        .map(NamedTempFile::into_temp_path)
+}
+
+/// Creates a new executable file at `path` containing `contents`, and closes it.
+pub(crate) fn write_executable(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o700);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)
+}
+
+/// Appends the platform's executable suffix (e.g., `.exe` on Windows) to `name`, unless it
+/// already has it.
+pub(crate) fn executable_name(name: &OsStr) -> OsString {
+    let mut name = name.to_owned();
+    let has_extension = Path::new(&name).extension().map_or(false, |ext| {
+        ext.eq_ignore_ascii_case(std::env::consts::EXE_EXTENSION)
+    });
+    if !has_extension {
+        name.push(std::env::consts::EXE_SUFFIX);
+    }
+    name
+}
+
+/// Copies the metadata of the file at `from` (if any) to `to`.
+pub(crate) fn copy_existing_metadata(from: &Path, to: &Path) -> io::Result<()> {
+    match imp::copy_metadata(from, to) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Copies everything from `reader` into `file`, and rewinds it.
+fn fill_from<W: Write + Seek, R: Read>(file: &mut W, mut reader: R) -> io::Result<()> {
+    io::copy(&mut reader, file)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(())
+}
+
+/// Hashes everything in `file` with `algo`, restoring its position afterwards.
+#[cfg(feature = "cas")]
+fn hash_contents<F: Read + Seek>(file: &mut F, algo: crate::HashAlgo) -> io::Result<String> {
+    let pos = file.stream_position()?;
+    file.seek(SeekFrom::Start(0))?;
+    let hash = algo.hash(file)?;
+    file.seek(SeekFrom::Start(pos))?;
+    Ok(hash)
+}
+
+/// Saves the file at `path` (if any) as `path` + `suffix`.
+///
+/// Returns the path of the backup if the file had to be moved there (instead of being linked), so
+/// that it can be moved back if the new file can't be persisted.
+fn backup(path: &Path, suffix: &OsStr) -> io::Result<Option<PathBuf>> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(suffix);
+    let backup = PathBuf::from(backup);
+
+    // Don't follow symlinks: a symlink at the target is backed up (and replaced) like any other
+    // file. If there's nothing to back up, keep the previous backup.
+    match fs::symlink_metadata(path) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_err_path(|| path),
+    }
+    // Link the file under a temporary name first and then rename it over the previous backup, so
+    // that the previous backup is only replaced once the new one exists.
+    let dir = backup.parent().unwrap_or_else(|| Path::new(""));
+    let linked = util::create_helper(dir, &util::NameSpec::unnamed(), |tmp| {
+        fs::hard_link(path, &tmp).map(|()| tmp)
+    });
+    match linked {
+        Ok(tmp) => match fs::rename(&tmp, &backup) {
+            Ok(()) => Ok(None),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e).with_err_path(|| backup)
+            }
+        },
+        // Hard links aren't supported here, move the file out of the way instead.
+        Err(_) => match fs::rename(path, &backup) {
+            Ok(()) => Ok(Some(backup)),
+            Err(e) => Err(e).with_err_path(|| backup),
+        },
     }
 }
 
+/// The highest version tried by [`TempPath::persist_versioned`].
+const MAX_VERSIONS: u32 = 9999;
+
+/// Inserts `-{version}` before the extension of `path`'s file name.
+fn versioned_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("-{}", version));
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    path.with_file_name(name)
+}
+
 pub(crate) fn create_named(
     path: PathBuf,
     open_options: &mut OpenOptions,
@@ -1069,10 +2546,7 @@ pub(crate) fn create_named(
     imp::create_named(&path, open_options, permissions)
         .with_err_path(|| path.clone())
         .map(|file| NamedTempFile {
-            path: TempPath {
-                path: path.into_boxed_path(),
-                disable_cleanup: keep,
-            },
+            path: TempPath::new(path, keep),
             file,
         })
 }
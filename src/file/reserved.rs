@@ -0,0 +1,102 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use super::{PathPersistError, TempPath};
+use crate::util::Redact;
+
+/// A unique temporary path, claimed by an empty placeholder file, whose real content will be
+/// produced by someone else (e.g., an external program that insists on creating its output file
+/// itself).
+///
+/// Created with [`Builder::reserve_path`](crate::Builder::reserve_path). Reserving the name with a
+/// placeholder means that no other process can be handed the same path in the meantime. The
+/// producer is expected to overwrite or replace (e.g., rename over) the placeholder.
+///
+/// When dropped, whatever is at the path (the placeholder or the produced file) is deleted, unless
+/// the reservation is [kept](ReservedPath::keep).
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process::Command;
+/// use tempfile::Builder;
+///
+/// let output = Builder::new().suffix(".tar.gz").reserve_path()?;
+/// let status = Command::new("tar")
+///     .arg("-czf")
+///     .arg(&*output)
+///     .arg("src")
+///     .status()?;
+/// assert!(status.success());
+/// // ... upload the archive; it's deleted when `output` is dropped.
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ReservedPath {
+    path: TempPath,
+}
+
+impl ReservedPath {
+    pub(crate) fn new(path: TempPath) -> ReservedPath {
+        ReservedPath { path }
+    }
+
+    /// Returns the reserved path.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the placeholder hasn't been replaced by any content yet, i.e., the file
+    /// at the path is still empty.
+    #[must_use]
+    pub fn is_placeholder(&self) -> bool {
+        self.path.metadata().map_or(false, |meta| meta.len() == 0)
+    }
+
+    /// Keep the file at the reserved path from being deleted, and return the path.
+    ///
+    /// # Errors
+    ///
+    /// On some platforms (e.g., Windows), the file needs to be marked as non-temporary. This
+    /// operation could fail.
+    pub fn keep(self) -> Result<PathBuf, PathPersistError> {
+        self.path.keep()
+    }
+
+    /// Turn this reservation into a regular [`TempPath`], e.g., to
+    /// [persist](TempPath::persist) the produced file.
+    #[must_use]
+    pub fn into_temp_path(self) -> TempPath {
+        self.path
+    }
+}
+
+impl fmt::Debug for ReservedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReservedPath")
+            .field("path", &Redact(self.path()))
+            .finish()
+    }
+}
+
+impl Deref for ReservedPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ReservedPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<OsStr> for ReservedPath {
+    fn as_ref(&self) -> &OsStr {
+        self.path.as_os_str()
+    }
+}
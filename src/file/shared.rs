@@ -0,0 +1,91 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::TempPath;
+
+/// A reference-counted [`TempPath`] that can be cheaply cloned and shared between components.
+///
+/// The temporary file is deleted when the last clone is dropped. The underlying `TempPath` (and
+/// with it, the persist APIs) can be recovered with [`SharedTempPath::try_unwrap`] once the other
+/// clones are gone.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::NamedTempFile;
+///
+/// let shared = NamedTempFile::new()?.into_temp_path().into_shared();
+/// let for_worker = shared.clone();
+///
+/// std::thread::spawn(move || {
+///     assert!(for_worker.exists());
+/// })
+/// .join()
+/// .unwrap();
+///
+/// // The worker's clone has been dropped, so we can persist the file.
+/// let dir = tempfile::tempdir()?;
+/// shared
+///     .try_unwrap()
+///     .unwrap()
+///     .persist(dir.path().join("report.txt"))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedTempPath {
+    inner: Arc<TempPath>,
+}
+
+impl SharedTempPath {
+    /// Returns the underlying [`TempPath`] if this is the only remaining reference to it.
+    ///
+    /// # Errors
+    ///
+    /// If other clones of this `SharedTempPath` exist, `self` is returned unchanged.
+    pub fn try_unwrap(self) -> Result<TempPath, SharedTempPath> {
+        Arc::try_unwrap(self.inner).map_err(|inner| SharedTempPath { inner })
+    }
+
+    /// Returns the number of references to this temporary path.
+    #[must_use]
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+}
+
+impl From<TempPath> for SharedTempPath {
+    fn from(path: TempPath) -> SharedTempPath {
+        SharedTempPath {
+            inner: Arc::new(path),
+        }
+    }
+}
+
+impl fmt::Debug for SharedTempPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl Deref for SharedTempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.inner
+    }
+}
+
+impl AsRef<Path> for SharedTempPath {
+    fn as_ref(&self) -> &Path {
+        &self.inner
+    }
+}
+
+impl AsRef<OsStr> for SharedTempPath {
+    fn as_ref(&self) -> &OsStr {
+        self.inner.as_os_str()
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::io;
+
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, OwnedHandle, RawHandle};
+
+/// A temporary shared memory object, for inter-process scratch buffers that must never be written
+/// to a disk-backed temporary directory (Unix, except Android, and Windows).
+///
+/// - On Unix, this is a POSIX shared memory object created with `shm_open`, which is unlinked
+///   again right away: like an unnamed temporary file, it can only be reached through its file
+///   descriptor (e.g., inherited by a child process, or sent over a Unix domain socket), and it's
+///   freed once the last descriptor has been closed.
+/// - On Windows, this is an unnamed section backed by the paging file (created with
+///   `CreateFileMappingW`), which is freed once the last handle has been closed. Duplicate the
+///   handle into another process to share it.
+///
+/// Map the object into memory (e.g., with `mmap` or `MapViewOfFile`) to use it. On Linux, it can
+/// also be read and written like a regular file (see [`ShmTempFile::as_file`]), but other
+/// platforms only support mapping it.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::ShmTempFile;
+///
+/// let shm = ShmTempFile::new(64 * 1024)?;
+/// assert_eq!(shm.len(), 64 * 1024);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ShmTempFile {
+    #[cfg(unix)]
+    file: File,
+    #[cfg(windows)]
+    section: OwnedHandle,
+    len: u64,
+}
+
+impl ShmTempFile {
+    /// Create a new shared memory object of `len` bytes, initially filled with zeros.
+    ///
+    /// # Errors
+    ///
+    /// If `len` is zero, an error of kind [`io::ErrorKind::InvalidInput`] is returned. If the
+    /// object can't be created or resized (e.g., because the system is out of memory), `Err` is
+    /// returned as well.
+    pub fn new(len: u64) -> io::Result<ShmTempFile> {
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shared memory objects can't be empty",
+            ));
+        }
+        Ok(ShmTempFile {
+            #[cfg(unix)]
+            file: imp::create(len)?,
+            #[cfg(windows)]
+            section: imp::create(len)?,
+            len,
+        })
+    }
+
+    /// Returns the size of the shared memory object in bytes.
+    #[must_use]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the shared memory object as a [`File`] (requires Unix).
+    ///
+    /// It's safe to resize the object through the returned `File`, but [`ShmTempFile::len`] will
+    /// keep returning the original size.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Convert the shared memory object into a [`File`] (requires Unix).
+    #[cfg(unix)]
+    #[must_use]
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Convert the shared memory object into a handle of the section (requires Windows).
+    #[cfg(windows)]
+    #[must_use]
+    pub fn into_handle(self) -> OwnedHandle {
+        self.section
+    }
+}
+
+impl fmt::Debug for ShmTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmTempFile")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for ShmTempFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for ShmTempFile {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl AsHandle for ShmTempFile {
+    #[inline]
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        self.section.as_handle()
+    }
+}
+
+#[cfg(windows)]
+impl AsRawHandle for ShmTempFile {
+    #[inline]
+    fn as_raw_handle(&self) -> RawHandle {
+        self.section.as_raw_handle()
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::OsStr;
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+
+    use rustix::fs::Mode;
+    use rustix::shm;
+
+    use crate::util::{self, NameSpec};
+
+    pub fn create(len: u64) -> io::Result<File> {
+        // Shared memory object names look like absolute paths, but live in their own namespace.
+        // Keep them short: some platforms (e.g., macOS) only allow 31 bytes.
+        let spec = NameSpec {
+            prefix: OsStr::new("tempfile-shm-"),
+            ..NameSpec::unnamed()
+        };
+        let fd = util::create_helper(Path::new("/"), &spec, |name| {
+            let fd = shm::open(
+                &name,
+                shm::OFlags::CREATE | shm::OFlags::EXCL | shm::OFlags::RDWR,
+                Mode::RUSR | Mode::WUSR,
+            )?;
+            // The object lives on for as long as it's open, but no one else can open it anymore.
+            shm::unlink(&name)?;
+            Ok(fd)
+        })?;
+        let file = File::from(fd);
+        file.set_len(len)?;
+        Ok(file)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::os::windows::io::{FromRawHandle, OwnedHandle, RawHandle};
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+    use windows_sys::Win32::System::Memory::{CreateFileMappingW, PAGE_READWRITE};
+
+    pub fn create(len: u64) -> io::Result<OwnedHandle> {
+        // Passing `INVALID_HANDLE_VALUE` instead of a file backs the section with the paging file.
+        let section = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                ptr::null(),
+                PAGE_READWRITE,
+                (len >> 32) as u32,
+                len as u32,
+                ptr::null(),
+            )
+        };
+        if section as usize == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { OwnedHandle::from_raw_handle(section as RawHandle) })
+    }
+}
@@ -0,0 +1,166 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use super::TempPath;
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{env, Builder};
+
+/// A symbolic link at a unique temporary path, which is removed (without touching its target)
+/// when dropped.
+///
+/// This is useful for testing how code handles symbolic links, or for exposing a long path under
+/// a short alias. Create one with [`TempSymlink::new`] or, to customize its name and location,
+/// [`Builder::symlink`].
+///
+/// On Windows, creating symbolic links requires either administrator privileges or developer
+/// mode. A directory symbolic link is created if the target is an existing directory, a file
+/// symbolic link otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::TempSymlink;
+///
+/// let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("data.txt"), "contents")?;
+///
+/// let link = TempSymlink::new(dir.path().join("data.txt"))?;
+/// assert_eq!(std::fs::read_to_string(&link)?, "contents");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TempSymlink {
+    path: TempPath,
+    target: PathBuf,
+}
+
+impl TempSymlink {
+    /// Create a symbolic link to `target` at a new temporary path in [`env::temp_dir()`].
+    ///
+    /// The target doesn't need to exist. Relative targets are resolved relative to the temporary
+    /// directory, so an absolute target is usually what you want.
+    ///
+    /// # Errors
+    ///
+    /// If the symbolic link cannot be created, `Err` is returned.
+    pub fn new<P: AsRef<Path>>(target: P) -> io::Result<TempSymlink> {
+        Builder::new().symlink_in(target, env::temp_dir())
+    }
+
+    /// Create a symbolic link to `target` at a new temporary path in `dir`. See
+    /// [`TempSymlink::new`].
+    ///
+    /// # Errors
+    ///
+    /// If the symbolic link cannot be created, `Err` is returned.
+    pub fn new_in<P: AsRef<Path>, D: AsRef<Path>>(target: P, dir: D) -> io::Result<TempSymlink> {
+        Builder::new().symlink_in(target, dir)
+    }
+
+    /// Returns the path of the symbolic link itself.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path the symbolic link points to, as it was passed in.
+    #[must_use]
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Remove the symbolic link.
+    ///
+    /// Use this if you want to detect errors in removing the link.
+    ///
+    /// # Errors
+    ///
+    /// If the symbolic link cannot be removed, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.path.close()
+    }
+
+    /// Turn this symbolic link into a [`TempPath`], which removes it when dropped.
+    #[must_use]
+    pub fn into_temp_path(self) -> TempPath {
+        self.path
+    }
+}
+
+impl fmt::Debug for TempSymlink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempSymlink")
+            .field("path", &Redact(self.path()))
+            .field("target", &Redact(&self.target))
+            .finish()
+    }
+}
+
+impl Deref for TempSymlink {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempSymlink {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<OsStr> for TempSymlink {
+    fn as_ref(&self) -> &OsStr {
+        self.path.as_os_str()
+    }
+}
+
+/// Creates a symbolic link to `target` at `link`.
+pub(crate) fn create(
+    target: &Path,
+    link: PathBuf,
+    disable_cleanup: bool,
+) -> io::Result<TempSymlink> {
+    let mut path = imp::symlink(target, link)?;
+    path.disable_cleanup(disable_cleanup);
+    Ok(TempSymlink {
+        path,
+        target: target.to_owned(),
+    })
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+
+    pub fn symlink(target: &Path, link: PathBuf) -> io::Result<TempPath> {
+        std::os::unix::fs::symlink(target, &link).with_err_path(|| &link)?;
+        Ok(TempPath::new(link, false))
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::*;
+    use std::fs;
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+
+    pub fn symlink(target: &Path, link: PathBuf) -> io::Result<TempPath> {
+        // Relative targets are relative to the link's directory.
+        let resolved = link
+            .parent()
+            .map_or_else(|| target.to_owned(), |dir| dir.join(target));
+        if resolved.is_dir() {
+            symlink_dir(target, &link).with_err_path(|| &link)?;
+            // Directory symbolic links are removed like directories.
+            Ok(TempPath::with_cleanup(link, |path| fs::remove_dir(path)))
+        } else {
+            symlink_file(target, &link).with_err_path(|| &link)?;
+            Ok(TempPath::new(link, false))
+        }
+    }
+}
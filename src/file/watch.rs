@@ -0,0 +1,89 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::imp;
+use crate::error::IoResultExt;
+
+/// Notifies when a [`NamedTempFile`](crate::NamedTempFile) has been deleted or replaced (e.g., by
+/// a temporary file cleaner), created by [`NamedTempFile::watch`](crate::NamedTempFile::watch).
+///
+/// The directory containing the temporary file is watched with the [`notify`] crate, and the file
+/// is checked (like [`NamedTempFile::verify`](crate::NamedTempFile::verify)) whenever something
+/// in it changes. The file is checked through a duplicate of its handle, so the watcher keeps
+/// working after the `NamedTempFile` has been dropped or persisted (which also counts as removing
+/// it from its temporary path).
+///
+/// The underlying watcher is stopped when the `TempFileWatcher` is dropped.
+pub struct TempFileWatcher {
+    rx: Receiver<io::Error>,
+    // Keep the watcher alive for as long as we're receiving notifications.
+    _watcher: RecommendedWatcher,
+}
+
+impl TempFileWatcher {
+    pub(crate) fn new(file: &File, path: &Path) -> io::Result<TempFileWatcher> {
+        let file = file.try_clone().with_err_path(|| path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let path: PathBuf = path.to_owned();
+        let (tx, rx) = mpsc::channel();
+        let mut lost = false;
+        let mut watcher = notify::recommended_watcher(move |_: notify::Result<Event>| {
+            // We don't care what changed (or whether the watcher reported an error), just check
+            // the file again. Only report the first failure.
+            if lost {
+                return;
+            }
+            if let Err(error) = imp::verify(&file, &path).with_err_path(|| &path) {
+                lost = true;
+                let _ = tx.send(error);
+            }
+        })
+        .map_err(to_io_error)
+        .with_err_path(|| dir)?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(to_io_error)
+            .with_err_path(|| dir)?;
+        Ok(TempFileWatcher {
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait up to `timeout` for the temporary file to be deleted or replaced.
+    ///
+    /// Returns the error describing what happened to the file (see
+    /// [`NamedTempFile::verify`](crate::NamedTempFile::verify)), or `None` if the file is still in
+    /// place after `timeout`. Once the error has been returned, this always returns `None`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<io::Error> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(error) => Some(error),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Returns the error describing what happened to the temporary file if it has been deleted or
+    /// replaced, without blocking.
+    pub fn try_recv(&self) -> Option<io::Error> {
+        self.recv_timeout(Duration::from_secs(0))
+    }
+}
+
+impl std::fmt::Debug for TempFileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TempFileWatcher").finish_non_exhaustive()
+    }
+}
+
+fn to_io_error(err: notify::Error) -> io::Error {
+    match err.kind {
+        notify::ErrorKind::Io(err) => err,
+        notify::ErrorKind::PathNotFound => io::ErrorKind::NotFound.into(),
+        _ => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
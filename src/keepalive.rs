@@ -0,0 +1,193 @@
+//! Keep long-lived temporary files and directories from being deleted by temporary file cleaners.
+//!
+//! Many systems periodically delete temporary files that haven't been accessed or modified in a
+//! while (see the "Security" section of [`NamedTempFile`]). A [`Keepalive`] runs a background
+//! thread that periodically updates the access and modification times of every registered
+//! temporary file and directory so they never look stale.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use filetime::FileTime;
+
+use crate::error::IoResultExt;
+use crate::{NamedTempFile, TempDir};
+
+enum Target {
+    // A duplicate of the temporary file's handle, so we always touch the right file.
+    File(File),
+    Dir(PathBuf),
+}
+
+impl Target {
+    fn touch(&self) -> io::Result<()> {
+        let now = FileTime::now();
+        match self {
+            Target::File(file) => filetime::set_file_handle_times(file, Some(now), Some(now)),
+            Target::Dir(path) => filetime::set_file_times(path, now, now),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    targets: BTreeMap<u64, Target>,
+    next_id: u64,
+    stopped: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn register(self: &Arc<Self>, target: Target) -> KeepaliveGuard {
+        let mut state = self.state();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.targets.insert(id, target);
+        KeepaliveGuard {
+            shared: self.clone(),
+            id,
+        }
+    }
+
+    fn touch_all(&self) {
+        for target in self.state().targets.values() {
+            // Errors are ignored: the resource may have been deleted or persisted elsewhere.
+            let _ = target.touch();
+        }
+    }
+}
+
+/// A background thread that periodically "touches" registered temporary files and directories
+/// (requires the `keepalive` feature).
+///
+/// Registering a resource returns a [`KeepaliveGuard`]; the resource is kept alive until the guard
+/// is dropped. Dropping the `Keepalive` stops the background thread.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tempfile::keepalive::Keepalive;
+/// use tempfile::{NamedTempFile, TempDir};
+///
+/// let keepalive = Keepalive::new(Duration::from_secs(60 * 60))?;
+///
+/// let file = NamedTempFile::new()?;
+/// let _file_guard = keepalive.register_file(&file)?;
+///
+/// let dir = TempDir::new()?;
+/// let _dir_guard = keepalive.register_dir(&dir);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Keepalive {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Keepalive {
+    /// Start a keepalive thread that touches all registered resources every `interval`.
+    ///
+    /// # Errors
+    ///
+    /// If the background thread cannot be spawned, `Err` is returned.
+    pub fn new(interval: Duration) -> io::Result<Keepalive> {
+        let shared = Arc::new(Shared::default());
+        let thread_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("tempfile-keepalive".into())
+            .spawn(move || loop {
+                let state = thread_shared.state();
+                let (state, _) = thread_shared
+                    .wakeup
+                    .wait_timeout_while(state, interval, |state| !state.stopped)
+                    .unwrap_or_else(|e| e.into_inner());
+                if state.stopped {
+                    return;
+                }
+                drop(state);
+                thread_shared.touch_all();
+            })?;
+        Ok(Keepalive {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Keep a named temporary file alive until the returned guard is dropped.
+    ///
+    /// The file is touched through a duplicate of its handle (never by path).
+    ///
+    /// # Errors
+    ///
+    /// If the file handle cannot be duplicated, `Err` is returned.
+    pub fn register_file(&self, file: &NamedTempFile<File>) -> io::Result<KeepaliveGuard> {
+        let handle = file.as_file().try_clone().with_err_path(|| file.path())?;
+        Ok(self.shared.register(Target::File(handle)))
+    }
+
+    /// Keep a temporary directory alive until the returned guard is dropped.
+    ///
+    /// Only the directory itself is touched, not its contents.
+    pub fn register_dir(&self, dir: &TempDir) -> KeepaliveGuard {
+        self.shared.register(Target::Dir(dir.path().to_owned()))
+    }
+
+    /// Touch all registered resources immediately, without waiting for the next interval.
+    pub fn touch_now(&self) {
+        self.shared.touch_all()
+    }
+}
+
+impl fmt::Debug for Keepalive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keepalive")
+            .field("registered", &self.shared.state().targets.len())
+            .finish()
+    }
+}
+
+impl Drop for Keepalive {
+    fn drop(&mut self) {
+        self.shared.state().stopped = true;
+        self.shared.wakeup.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Keeps a resource registered with a [`Keepalive`] until dropped.
+#[must_use = "the resource is unregistered when the guard is dropped"]
+pub struct KeepaliveGuard {
+    shared: Arc<Shared>,
+    id: u64,
+}
+
+impl fmt::Debug for KeepaliveGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepaliveGuard")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl Drop for KeepaliveGuard {
+    fn drop(&mut self) {
+        self.shared.state().targets.remove(&self.id);
+    }
+}
@@ -22,7 +22,8 @@
 //! the OS to cleanup the underlying file, while `TempDir` and `NamedTempFile` rely on rust
 //! destructors to do so. Destructors may fail to run if the process exits through an unhandled
 //! signal interrupt (like `SIGINT`), or if the instance is declared statically (like with
-//! [`lazy_static`]), among other possible reasons.
+//! [`lazy_static`]), among other possible reasons. Use [`GlobalTempDir`] if you need a temporary
+//! directory that lives as long as the program.
 //!
 //! ## Unexpected File Deletion
 //!
@@ -78,8 +79,14 @@
 //!    of files before random collisions are expected (at which point you probably have larger
 //!    problems).
 //! 2. Re-seeding the random filename generator from system randomness after 3 failed attempts to
-//!    create temporary a file (when the `getrandom` feature is enabled as it is by default on all
-//!    major platforms).
+//!    create temporary a file. The seed comes from the `getrandom` crate when the `getrandom`
+//!    feature is enabled (as it is by default on all major platforms), falling back on
+//!    `/dev/urandom` and finally on a mix of the time, process state, and memory addresses if
+//!    those aren't available at runtime (see [`env::entropy_source`]).
+//!
+//! For additional protection, [`Builder::adaptive_rand_bytes`] can be enabled to lengthen the
+//! random part of the name every few failed attempts, so an attacker can't exhaust the retries by
+//! occupying the names at the configured length.
 //!
 //! ## Early drop pitfall
 //!
@@ -194,6 +201,7 @@
     feature(wasip2)
 )]
 #![cfg_attr(all(feature = "nightly", target_os = "wasi"), feature(wasi_ext))]
+#![cfg_attr(feature = "nightly", feature(can_vector))]
 
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md");
@@ -201,46 +209,230 @@ doc_comment::doctest!("../README.md");
 const NUM_RETRIES: u32 = 65536;
 const NUM_RAND_CHARS: usize = 6;
 
+use std::borrow::Cow;
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs::OpenOptions;
 use std::io;
 use std::path::Path;
 
+use crate::error::IoResultExt;
+use crate::token::Kind;
+use crate::util::Redact;
+
+mod budget;
+#[cfg(feature = "cas")]
+mod cas;
+#[cfg(feature = "zstd")]
+mod compressed;
 mod dir;
+mod entropy;
 mod error;
+mod exit;
 mod file;
+mod macros;
+mod manifest;
+mod purge;
+mod registry;
+mod sanitize;
+mod scratch;
 mod spooled;
+mod telemetry;
+mod token;
+#[cfg(feature = "camino")]
+mod utf8;
 mod util;
+mod workspace;
 
+#[cfg(all(feature = "capi", unix))]
+pub mod capi;
 pub mod env;
+#[cfg(feature = "failpoints")]
+pub mod failpoints;
+#[cfg(feature = "keepalive")]
+pub mod keepalive;
+pub mod vfs;
 
-pub use crate::dir::{tempdir, tempdir_in, TempDir};
+#[cfg(feature = "cas")]
+pub use crate::cas::{CachedPath, CasDir, HashAlgo};
+#[cfg(feature = "zstd")]
+pub use crate::compressed::CompressedSpooledTempFile;
+#[cfg(feature = "notify")]
+pub use crate::dir::TempDirWatcher;
+pub use crate::dir::{
+    tempdir, tempdir_in, CleanupGuard, Entries, GlobalTempDir, RemovalProgress, SharedTempDir,
+    SubdirBuilder, TempDir,
+};
+pub use crate::error::{UnsupportedDirError, UnsupportedDirReason};
+#[cfg(any(
+    windows,
+    all(
+        unix,
+        not(any(
+            target_os = "android",
+            target_os = "espidf",
+            target_os = "horizon",
+            target_os = "vita"
+        ))
+    )
+))]
+pub use crate::file::ShmTempFile;
+#[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+pub use crate::file::TempFifo;
+#[cfg(feature = "notify")]
+pub use crate::file::TempFileWatcher;
+#[cfg(any(unix, windows))]
+pub use crate::file::TempSymlink;
 pub use crate::file::{
-    tempfile, tempfile_in, NamedTempFile, PathPersistError, PersistError, TempPath,
+    capture_output, executable_tempfile, tempfile, tempfile_from_reader, tempfile_in,
+    AtomicWriteFile, Durability, NamedTempFile, PathPersistError, PersistError, ReservedPath,
+    SharedTempPath, TempPath,
+};
+#[doc(hidden)]
+pub use crate::macros::caller_prefix as __caller_prefix;
+pub use crate::manifest::recover;
+pub use crate::purge::purge_older_than;
+pub use crate::registry::{reclaim, unregister, Reclaimed};
+pub use crate::sanitize::{sanitize_affix, Sanitize};
+pub use crate::scratch::ScratchCache;
+#[cfg(feature = "metrics")]
+pub use crate::spooled::{spool_metrics, SpoolMetrics};
+pub use crate::spooled::{
+    spooled_tempfile, spooled_tempfile_in, SizeLimitExceeded, SpooledData, SpooledSnapshot,
+    SpooledTempFile,
 };
-pub use crate::spooled::{spooled_tempfile, spooled_tempfile_in, SpooledData, SpooledTempFile};
+pub use crate::token::OwnershipToken;
+#[cfg(feature = "camino")]
+pub use crate::utf8::{Utf8NamedTempFile, Utf8TempDir, Utf8TempPath};
+pub use crate::util::TimestampFormat;
+pub use crate::workspace::Workspace;
 
 /// Create a new temporary file or directory with custom options.
-#[derive(Debug, Clone, Eq, PartialEq)]
+///
+/// The name and permission settings that aren't explicitly configured fall back to the process-wide
+/// defaults installed with [`env::override_default_builder`] (which lists exactly what's taken from
+/// them), if any, and then to the built-in defaults.
+#[derive(Clone, Eq, PartialEq)]
 pub struct Builder<'a, 'b> {
-    random_len: usize,
-    prefix: &'a OsStr,
-    suffix: &'b OsStr,
+    random_len: Option<usize>,
+    prefix: Option<&'a OsStr>,
+    suffix: Option<&'b OsStr>,
     append: bool,
     permissions: Option<std::fs::Permissions>,
+    exact_permissions: bool,
+    readable_by: Option<Audience>,
     disable_cleanup: bool,
+    remove_only_if_empty: bool,
+    create_parents: bool,
+    parent_permissions: Option<std::fs::Permissions>,
+    default_dir: DefaultDir,
+    #[cfg(unix)]
+    private_uid: Option<u32>,
+    #[cfg(feature = "uuid")]
+    uuid_names: bool,
+    with_pid: bool,
+    timestamp: Option<TimestampFormat>,
+    with_sequence: bool,
+    adaptive_rand_bytes: bool,
+    hidden: bool,
+    no_temporary_attribute: bool,
+    executable: bool,
+    inheritable: bool,
+    #[cfg(unix)]
+    custom_flags: i32,
+    sparse: bool,
+    cleanup_on_kill: bool,
+    fit_name_length: bool,
+    ascii_only: bool,
+    #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+    tmpfs_size: Option<u64>,
+}
+
+/// Where a [`Builder`] creates temporary files and directories when not given a directory.
+#[derive(Clone, Eq, PartialEq)]
+enum DefaultDir {
+    /// [`env::temp_dir()`], resolved on every use.
+    TempDir,
+    /// A directory on the same filesystem as this path, resolved on every use.
+    SameFilesystemAs(Box<Path>),
+    /// A directory resolved ahead of time by [`Builder::clone_for_thread`].
+    Resolved(Box<Path>),
+}
+
+impl fmt::Debug for DefaultDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultDir::TempDir => f.write_str("TempDir"),
+            DefaultDir::SameFilesystemAs(path) => f
+                .debug_tuple("SameFilesystemAs")
+                .field(&Redact(path))
+                .finish(),
+            DefaultDir::Resolved(dir) => f.debug_tuple("Resolved").field(&Redact(dir)).finish(),
+        }
+    }
+}
+
+impl DefaultDir {
+    fn resolve(&self) -> io::Result<Cow<'_, Path>> {
+        match self {
+            DefaultDir::TempDir => Ok(Cow::Owned(env::temp_dir())),
+            DefaultDir::SameFilesystemAs(path) => util::same_filesystem_dir(path).map(Cow::Owned),
+            DefaultDir::Resolved(dir) => Ok(Cow::Borrowed(dir)),
+        }
+    }
+}
+
+/// Who besides the owner may read temporary files and directories, see
+/// [`Builder::world_readable`] and [`Builder::group_readable`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Audience {
+    Group,
+    World,
+}
+
+impl fmt::Debug for Builder<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Prefixes and suffixes are part of the names of temporary files, so they're redacted too.
+        let mut f = f.debug_struct("Builder");
+        f.field("random_len", &self.random_len)
+            .field("prefix", &Redact(&self.prefix))
+            .field("suffix", &Redact(&self.suffix))
+            .field("append", &self.append)
+            .field("permissions", &self.permissions)
+            .field("exact_permissions", &self.exact_permissions)
+            .field("readable_by", &self.readable_by)
+            .field("disable_cleanup", &self.disable_cleanup)
+            .field("remove_only_if_empty", &self.remove_only_if_empty)
+            .field("create_parents", &self.create_parents)
+            .field("parent_permissions", &self.parent_permissions)
+            .field("default_dir", &self.default_dir);
+        #[cfg(unix)]
+        f.field("private_uid", &self.private_uid);
+        #[cfg(feature = "uuid")]
+        f.field("uuid_names", &self.uuid_names);
+        f.field("with_pid", &self.with_pid)
+            .field("timestamp", &self.timestamp)
+            .field("with_sequence", &self.with_sequence)
+            .field("adaptive_rand_bytes", &self.adaptive_rand_bytes)
+            .field("hidden", &self.hidden)
+            .field("no_temporary_attribute", &self.no_temporary_attribute)
+            .field("executable", &self.executable)
+            .field("inheritable", &self.inheritable);
+        #[cfg(unix)]
+        f.field("custom_flags", &self.custom_flags);
+        f.field("sparse", &self.sparse)
+            .field("cleanup_on_kill", &self.cleanup_on_kill)
+            .field("fit_name_length", &self.fit_name_length)
+            .field("ascii_only", &self.ascii_only);
+        #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+        f.field("tmpfs_size", &self.tmpfs_size);
+        f.finish()
+    }
 }
 
 impl Default for Builder<'_, '_> {
     fn default() -> Self {
-        Builder {
-            random_len: crate::NUM_RAND_CHARS,
-            prefix: OsStr::new(".tmp"),
-            suffix: OsStr::new(""),
-            append: false,
-            permissions: None,
-            disable_cleanup: false,
-        }
+        Builder::new()
     }
 }
 
@@ -311,13 +503,96 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     #[must_use]
-    pub fn new() -> Self {
-        Self::default()
+    pub const fn new() -> Self {
+        Builder {
+            random_len: None,
+            prefix: None,
+            suffix: None,
+            append: false,
+            permissions: None,
+            exact_permissions: false,
+            readable_by: None,
+            disable_cleanup: false,
+            remove_only_if_empty: false,
+            create_parents: false,
+            parent_permissions: None,
+            default_dir: DefaultDir::TempDir,
+            #[cfg(unix)]
+            private_uid: None,
+            #[cfg(feature = "uuid")]
+            uuid_names: false,
+            with_pid: false,
+            timestamp: None,
+            with_sequence: false,
+            adaptive_rand_bytes: false,
+            hidden: false,
+            no_temporary_attribute: false,
+            executable: false,
+            inheritable: false,
+            #[cfg(unix)]
+            custom_flags: 0,
+            sparse: false,
+            cleanup_on_kill: false,
+            fit_name_length: false,
+            ascii_only: false,
+            #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+            tmpfs_size: None,
+        }
+    }
+
+    /// Returns a copy of this builder with its default directory ([`env::temp_dir()`], or the
+    /// directory chosen by [`Builder::same_filesystem_as`]) resolved once, up front.
+    ///
+    /// Methods like [`Builder::tempfile`] and [`Builder::tempdir`] otherwise resolve the default
+    /// directory on every call, which reads the environment (and, with
+    /// [`Builder::same_filesystem_as`], inspects the filesystem) and allocates. Give each thread
+    /// that creates many temporary files in a loop its own copy instead. Changes to the default
+    /// directory (e.g., to the `TMPDIR` environment variable) after this call don't affect the
+    /// copy, unless [`Builder::same_filesystem_as`] is called on it (which resolves the directory
+    /// on every call again).
+    ///
+    /// # Errors
+    ///
+    /// If the directory given to [`Builder::same_filesystem_as`] can't be resolved, `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use tempfile::Builder;
+    ///
+    /// static BUILDER: Builder<'static, 'static> = Builder::new();
+    ///
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let builder = BUILDER.clone_for_thread()?;
+    ///         Ok(thread::spawn(move || {
+    ///             for _ in 0..10 {
+    ///                 let file = builder.tempfile()?;
+    ///                 // ...
+    ///             }
+    ///             Ok::<(), std::io::Error>(())
+    ///         }))
+    ///     })
+    ///     .collect::<std::io::Result<_>>()?;
+    /// for worker in workers {
+    ///     worker.join().unwrap()?;
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn clone_for_thread(&self) -> io::Result<Builder<'a, 'b>> {
+        let dir = self.default_dir()?.into_owned();
+        Ok(Builder {
+            default_dir: DefaultDir::Resolved(dir.into()),
+            ..self.clone()
+        })
     }
 
     /// Set a custom filename prefix.
     ///
-    /// Path separators are legal but not advisable.
+    /// Path separators are legal but not advisable. To derive a prefix from untrusted input, use
+    /// [`sanitize_affix`].
     /// Default: `.tmp`.
     ///
     /// # Examples
@@ -331,7 +606,7 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn prefix<S: AsRef<OsStr> + ?Sized>(&mut self, prefix: &'a S) -> &mut Self {
-        self.prefix = prefix.as_ref();
+        self.prefix = Some(prefix.as_ref());
         self
     }
 
@@ -351,7 +626,7 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn suffix<S: AsRef<OsStr> + ?Sized>(&mut self, suffix: &'b S) -> &mut Self {
-        self.suffix = suffix.as_ref();
+        self.suffix = Some(suffix.as_ref());
         self
     }
 
@@ -370,11 +645,17 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     pub fn rand_bytes(&mut self, rand: usize) -> &mut Self {
-        self.random_len = rand;
+        self.random_len = Some(rand);
         self
     }
 
-    /// Set the file to be opened in append mode.
+    /// Shorten the prefix, if necessary, so that generated names fit within the file name length
+    /// limit of common filesystems (255 bytes), instead of failing to create the file.
+    ///
+    /// This is useful when the prefix is derived from long user-provided names. Only the prefix is
+    /// shortened (never in the middle of a character); the random part of the name and the suffix
+    /// are always kept intact. Non-unicode prefixes may be converted lossily on platforms other
+    /// than Unix.
     ///
     /// Default: `false`.
     ///
@@ -383,277 +664,1115 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// ```
     /// use tempfile::Builder;
     ///
+    /// let long_name = "a".repeat(1000);
     /// let named_tempfile = Builder::new()
-    ///     .append(true)
+    ///     .prefix(&long_name)
+    ///     .suffix(".txt")
+    ///     .fit_name_length(true)
     ///     .tempfile()?;
+    /// let name = named_tempfile.path().file_name().unwrap();
+    /// assert!(name.len() <= 255);
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn append(&mut self, append: bool) -> &mut Self {
-        self.append = append;
+    pub fn fit_name_length(&mut self, fit: bool) -> &mut Self {
+        self.fit_name_length = fit;
         self
     }
 
-    /// The permissions to create the tempfile or [tempdir](Self::tempdir) with.
+    /// Lengthen the random part of the name when name collisions keep occurring.
     ///
-    /// # Security
+    /// By default, every attempt to create a temporary file or directory uses the configured
+    /// [number of random bytes](Self::rand_bytes). With this setting enabled, the number of random
+    /// characters is doubled (up to 64) after every 3 consecutive collisions, so a directory
+    /// crowded with names at the configured length (e.g., by an attacker predicting them, see
+    /// [Denial of Service](crate#denial-of-service)) doesn't use up all the retries. This has no
+    /// effect on names without random characters, or on UUID names.
     ///
-    /// By default, the permissions of tempfiles on Unix are set for it to be
-    /// readable and writable by the owner only, yielding the greatest amount
-    /// of security.
-    /// As this method allows to widen the permissions, security would be
-    /// reduced in such cases.
+    /// Default: `false`.
     ///
-    /// # Platform Notes
-    /// ## Unix
+    /// # Examples
     ///
-    /// The actual permission bits set on the tempfile or tempdir will be affected by the `umask`
-    /// applied by the underlying syscall. The actual permission bits are calculated via
-    /// `permissions & !umask`.
+    /// ```
+    /// use tempfile::Builder;
     ///
-    /// Permissions default to `0o600` for tempfiles and `0o777` for tempdirs. Note, this doesn't
-    /// include effects of the current `umask`. For example, combined with the standard umask
-    /// `0o022`, the defaults yield `0o600` for tempfiles and `0o755` for tempdirs.
+    /// let named_tempfile = Builder::new()
+    ///     .rand_bytes(2)
+    ///     .adaptive_rand_bytes(true)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn adaptive_rand_bytes(&mut self, adaptive: bool) -> &mut Self {
+        self.adaptive_rand_bytes = adaptive;
+        self
+    }
+
+    /// Use a random (version 4) UUID instead of random alphanumeric characters in the name of the
+    /// temporary file or directory (requires the `uuid` feature).
     ///
-    /// ## Windows and others
+    /// The prefix and suffix are still applied and [`Builder::rand_bytes`] is ignored.
     ///
-    /// This setting is unsupported and trying to set a file or directory read-only
-    /// will return an error.
+    /// Default: `false`.
     ///
     /// # Examples
     ///
-    /// Create a named temporary file that is world-readable.
-    ///
     /// ```
-    /// # #[cfg(unix)]
-    /// # {
     /// use tempfile::Builder;
-    /// use std::os::unix::fs::PermissionsExt;
     ///
-    /// let all_read_write = std::fs::Permissions::from_mode(0o666);
-    /// let tempfile = Builder::new().permissions(all_read_write).tempfile()?;
-    /// let actual_permissions = tempfile.path().metadata()?.permissions();
-    /// assert_ne!(
-    ///     actual_permissions.mode() & !0o170000,
-    ///     0o600,
-    ///     "we get broader permissions than the default despite umask"
-    /// );
-    /// # }
+    /// let named_tempfile = Builder::new()
+    ///     .prefix("")
+    ///     .uuid_names(true)
+    ///     .tempfile()?;
+    ///
+    /// let name = named_tempfile.path().file_name().unwrap().to_str().unwrap();
+    /// assert_eq!(name.len(), 36);
     /// # Ok::<(), std::io::Error>(())
     /// ```
+    #[cfg(feature = "uuid")]
+    pub fn uuid_names(&mut self, uuid_names: bool) -> &mut Self {
+        self.uuid_names = uuid_names;
+        self
+    }
+
+    /// Insert the current process id (followed by a `-`) between the prefix and the random part of
+    /// the name, e.g., `.tmp4242-AbC123`.
     ///
-    /// Create a named temporary directory that is restricted to the owner.
+    /// This makes it easy to attribute leftover temporary files to the process that created them
+    /// (e.g., on shared CI machines), and to clean up after processes that are no longer running.
+    /// On platforms without process ids (e.g., WASI), this setting has no effect.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
     ///
     /// ```
-    /// # #[cfg(unix)]
-    /// # {
     /// use tempfile::Builder;
-    /// use std::os::unix::fs::PermissionsExt;
     ///
-    /// let owner_rwx = std::fs::Permissions::from_mode(0o700);
-    /// let tempdir = Builder::new().permissions(owner_rwx).tempdir()?;
-    /// let actual_permissions = tempdir.path().metadata()?.permissions();
-    /// assert_eq!(
-    ///     actual_permissions.mode() & !0o170000,
-    ///     0o700,
-    ///     "we get the narrow permissions we asked for"
-    /// );
-    /// # }
+    /// let tmp = Builder::new().prefix("build-").with_pid(true).tempdir()?;
+    /// let name = tmp.path().file_name().unwrap().to_str().unwrap();
+    /// # #[cfg(any(unix, windows))]
+    /// assert!(name.starts_with(&format!("build-{}-", std::process::id())));
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn permissions(&mut self, permissions: std::fs::Permissions) -> &mut Self {
-        self.permissions = Some(permissions);
+    pub fn with_pid(&mut self, with_pid: bool) -> &mut Self {
+        self.with_pid = with_pid;
         self
     }
 
-    /// Disable cleanup of the file/folder to even when the [`NamedTempFile`]/[`TempDir`] goes out
-    /// of scope. Prefer [`NamedTempFile::keep`] and `[`TempDir::keep`] where possible,
-    /// `disable_cleanup` is provided for testing & debugging.
-    ///
-    /// By default, the file/folder is automatically cleaned up in the destructor of
-    /// [`NamedTempFile`]/[`TempDir`]. When `disable_cleanup` is set to `true`, this behavior is
-    /// suppressed. If you wish to disable cleanup after creating a temporary file/directory, call
-    /// [`NamedTempFile::disable_cleanup`] or [`TempDir::disable_cleanup`].
+    /// Insert the creation time (followed by a `-`) between the prefix and the rest of the name,
+    /// e.g., `.tmp20231114T221320Z-AbC123`.
     ///
-    /// # Warnings
+    /// This lets operators see how old temporary files are (and sort them by age) without having to
+    /// look at their metadata. The timestamp is placed before the [process id](Self::with_pid), if
+    /// any. On platforms without a clock (e.g., `wasm32-unknown-unknown`), this setting has no
+    /// effect.
     ///
-    /// On some platforms (for now, only Windows), temporary files are marked with a special
-    /// "temporary file" (`FILE_ATTRIBUTE_TEMPORARY`) attribute. Disabling cleanup _will not_ unset
-    /// this attribute while calling [`NamedTempFile::keep`] will.
+    /// Default: no timestamp.
     ///
     /// # Examples
     ///
     /// ```
-    /// use tempfile::Builder;
+    /// use tempfile::{Builder, TimestampFormat};
     ///
-    /// let named_tempfile = Builder::new()
-    ///     .disable_cleanup(true)
+    /// let tmp = Builder::new()
+    ///     .prefix("job-")
+    ///     .with_timestamp(TimestampFormat::Iso8601)
     ///     .tempfile()?;
+    /// let name = tmp.path().file_name().unwrap().to_str().unwrap();
+    /// assert_eq!(name.as_bytes()["job-20231114".len()], b'T');
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    pub fn disable_cleanup(&mut self, disable_cleanup: bool) -> &mut Self {
-        self.disable_cleanup = disable_cleanup;
+    pub fn with_timestamp(&mut self, format: TimestampFormat) -> &mut Self {
+        self.timestamp = Some(format);
         self
     }
 
-    /// Deprecated alias for [`Builder::disable_cleanup`].
-    #[deprecated = "Use Builder::disable_cleanup"]
-    pub fn keep(&mut self, keep: bool) -> &mut Self {
-        self.disable_cleanup(keep)
-    }
-
-    /// Create the named temporary file.
+    /// Insert a per-process sequence number (zero-padded to at least four digits and followed by a
+    /// `-`) into the name, e.g., `job-0001-AbC123`, `job-0002-XyZ789`.
     ///
-    /// # Security
+    /// Temporary files created by one run will then sort in creation order and group naturally,
+    /// which random names alone can't provide. The sequence is shared by all builders in the
+    /// process and starts at 1; numbers consumed by failed attempts (e.g., name collisions) are
+    /// skipped. It's placed after the [timestamp](Self::with_timestamp) and
+    /// [process id](Self::with_pid), if any.
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// Default: `false`.
     ///
-    /// # Resource leaking
+    /// # Examples
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    /// builder.prefix("job-").with_sequence(true);
+    /// let first = builder.tempfile()?;
+    /// let second = builder.tempfile()?;
+    /// assert!(first.path() < second.path());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_sequence(&mut self, with_sequence: bool) -> &mut Self {
+        self.with_sequence = with_sequence;
+        self
+    }
+
+    /// Only create temporary files and directories whose full path is ASCII, for paths that must
+    /// be embedded in protocols or formats that only accept UTF-8 (or ASCII), like JSON
+    /// configuration files or the command lines of picky tools.
+    ///
+    /// The generated parts of names (random characters, timestamps, etc.) are always ASCII, so
+    /// this checks the directory, prefix, and suffix, and fails instead of creating anything if
+    /// they aren't. The path can then be retrieved with
+    /// [`NamedTempFile::utf8_path`]/[`TempDir::utf8_path`] without an error.
+    ///
+    /// Default: `false`.
     ///
     /// # Errors
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// With this option, creating a temporary file or directory whose path wouldn't be ASCII fails
+    /// with an error of kind [`io::ErrorKind::InvalidInput`].
     ///
     /// # Examples
     ///
     /// ```
     /// use tempfile::Builder;
     ///
-    /// let tempfile = Builder::new().tempfile()?;
+    /// let file = Builder::new().ascii_only(true).tempfile()?;
+    /// assert!(file.utf8_path()?.is_ascii());
+    ///
+    /// assert!(Builder::new().prefix("caf\u{e9}-").ascii_only(true).tempfile().is_err());
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
-        self.tempfile_in(env::temp_dir())
+    pub fn ascii_only(&mut self, ascii_only: bool) -> &mut Self {
+        self.ascii_only = ascii_only;
+        self
     }
 
-    /// Create the named temporary file in the specified directory.
+    /// Set the file to be opened in append mode.
     ///
-    /// # Security
+    /// Default: `false`.
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// # Examples
     ///
-    /// # Resource leaking
+    /// ```
+    /// use tempfile::Builder;
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// let named_tempfile = Builder::new()
+    ///     .append(true)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Create temporary files with the hidden attribute (`FILE_ATTRIBUTE_HIDDEN`) set on Windows.
     ///
-    /// # Errors
+    /// This keeps scratch files created next to the user's documents (e.g., with
+    /// [`tempfile_in`](Self::tempfile_in)) from cluttering Explorer views. The attribute is cleared
+    /// when the file is [persisted](NamedTempFile::persist) or [kept](NamedTempFile::keep). On other
+    /// platforms this setting has no effect: use a [prefix](Self::prefix) starting with a `.`
+    /// (like the default one) to hide files there.
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// This setting doesn't apply to temporary directories or to files created with
+    /// [`make`](Self::make).
+    ///
+    /// Default: `false`.
     ///
     /// # Examples
     ///
     /// ```
     /// use tempfile::Builder;
     ///
-    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// let named_tempfile = Builder::new()
+    ///     .hidden(true)
+    ///     .tempfile_in("./")?;
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
-        util::create_helper(
-            dir.as_ref(),
-            self.prefix,
-            self.suffix,
-            self.random_len,
-            |path| {
-                file::create_named(
-                    path,
-                    OpenOptions::new().append(self.append),
-                    self.permissions.as_ref(),
-                    self.disable_cleanup,
-                )
-            },
-        )
+    pub fn hidden(&mut self, hidden: bool) -> &mut Self {
+        self.hidden = hidden;
+        self
     }
 
-    /// Attempts to make a temporary directory inside of [`env::temp_dir()`] whose
-    /// name will have the prefix, `prefix`. The directory and
-    /// everything inside it will be automatically deleted once the
-    /// returned `TempDir` is destroyed.
-    ///
-    /// # Resource leaking
+    /// Whether to mark temporary files with the "temporary file" (`FILE_ATTRIBUTE_TEMPORARY`)
+    /// attribute on Windows, which tells Windows to avoid writing the file's data to disk if
+    /// possible.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
-    ///
-    /// # Errors
+    /// Disable this for temporary files that are large, or that are meant to outlive the process
+    /// (e.g., with [`disable_cleanup`](Self::disable_cleanup)). On other platforms this setting has
+    /// no effect.
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// Default: `true`.
     ///
     /// # Examples
     ///
     /// ```
     /// use tempfile::Builder;
     ///
-    /// let tmp_dir = Builder::new().tempdir()?;
+    /// let named_tempfile = Builder::new()
+    ///     .windows_temporary_attribute(false)
+    ///     .tempfile()?;
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir(&self) -> io::Result<TempDir> {
-        self.tempdir_in(env::temp_dir())
+    pub fn windows_temporary_attribute(&mut self, enabled: bool) -> &mut Self {
+        self.no_temporary_attribute = !enabled;
+        self
     }
 
-    /// Attempts to make a temporary directory inside of `dir`.
-    /// The directory and everything inside it will be automatically
-    /// deleted once the returned `TempDir` is destroyed.
+    /// Create temporary files whose handle is inherited by child processes, so it can be passed to
+    /// a child by value (e.g., as a command line argument) instead of by path.
     ///
-    /// # Resource leaking
+    /// On Windows, the handle is marked as inheritable (`HANDLE_FLAG_INHERIT`), and on Unix, the
+    /// close-on-exec flag (`FD_CLOEXEC`) is cleared. This is not supported on other platforms.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    /// Note: *every* child process spawned while the file is open inherits the handle, not just the
+    /// one it's meant for.
     ///
-    /// # Errors
+    /// This setting doesn't apply to temporary directories or to files created with
+    /// [`make`](Self::make).
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// Default: `false`.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::io::AsRawFd;
+    /// use std::process::Command;
     /// use tempfile::Builder;
     ///
-    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// let output = Builder::new().inheritable(true).tempfile()?;
+    /// Command::new("my-tool")
+    ///     .arg(format!("--output-fd={}", output.as_raw_fd()))
+    ///     .status()?;
+    /// # }
     /// # Ok::<(), std::io::Error>(())
     /// ```
-    ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
-        util::create_helper(
-            dir.as_ref(),
-            self.prefix,
-            self.suffix,
-            self.random_len,
-            |path| dir::create(path, self.permissions.as_ref(), self.disable_cleanup),
-        )
+    pub fn inheritable(&mut self, inheritable: bool) -> &mut Self {
+        self.inheritable = inheritable;
+        self
     }
 
-    /// Attempts to create a temporary file (or file-like object) using the
-    /// provided closure. The closure is passed a temporary file path and
-    /// returns an [`std::io::Result`]. The path provided to the closure will be
-    /// inside of [`env::temp_dir()`]. Use [`Builder::make_in`] to provide
-    /// a custom temporary directory. If the closure returns one of the
-    /// following errors, then another randomized file path is tried:
-    ///  - [`std::io::ErrorKind::AlreadyExists`]
-    ///  - [`std::io::ErrorKind::AddrInUse`]
+    /// Pass additional flags to `open` when creating temporary files (Unix only), like
+    /// `mkostemp` does. For example, `O_NOATIME`, `O_NONBLOCK`, or `O_SYNC`.
     ///
-    /// This can be helpful for taking full control over the file creation, but
-    /// leaving the temporary file path construction up to the library. This
-    /// also enables creating a temporary UNIX domain socket, since it is not
-    /// possible to bind to a socket that already exists.
+    /// The flags are passed on as-is, in addition to the flags the temporary file is always
+    /// created with (`O_RDWR | O_CREAT | O_EXCL | O_CLOEXEC`). The access mode bits (`O_ACCMODE`)
+    /// are ignored, see
+    /// [`OpenOptionsExt::custom_flags`](std::os::unix::fs::OpenOptionsExt::custom_flags).
     ///
-    /// Note that [`Builder::append`] is ignored when using [`Builder::make`].
+    /// This setting doesn't apply to temporary directories or to files created with
+    /// [`make`](Self::make).
     ///
-    /// # Security
+    /// Default: no additional flags.
     ///
-    /// This has the same [security implications][security] as
-    /// [`NamedTempFile`], but with additional caveats. Specifically, it is up
-    /// to the closure to ensure that the file does not exist and that such a
-    /// check is *atomic*. Otherwise, a [time-of-check to time-of-use
-    /// bug][TOCTOU] could be introduced.
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(target_os = "linux")]
+    /// # {
+    /// use tempfile::Builder;
+    ///
+    /// const O_NOATIME: i32 = 0o1000000;
+    /// let scratch = Builder::new().custom_flags(O_NOATIME).tempfile()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn custom_flags(&mut self, flags: i32) -> &mut Self {
+        self.custom_flags = flags;
+        self
+    }
+
+    /// Create sparse temporary files, so that huge but mostly empty scratch files (e.g., VM images
+    /// or partially downloaded torrents) only take up disk space for the regions actually written.
+    ///
+    /// Extend the file with [`File::set_len`](std::fs::File::set_len) (or by seeking past its end
+    /// before writing), rather than by writing zeros: regions that are never written are "holes"
+    /// that read as zeros but aren't backed by disk space.
+    ///
+    /// On Windows, files need to be marked as sparse explicitly (with `FSCTL_SET_SPARSE`), which
+    /// fails if the filesystem doesn't support sparse files (e.g., FAT). On Unix, files are sparse
+    /// by default on filesystems that support holes, so this setting has no effect.
+    ///
+    /// This setting doesn't apply to temporary directories or to files created with
+    /// [`make`](Self::make).
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let image = Builder::new().suffix(".img").sparse(true).tempfile()?;
+    /// image.as_file().set_len(1 << 30)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn sparse(&mut self, sparse: bool) -> &mut Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Create temporary files that can be executed, e.g., by JIT compilers or plugin loaders that
+    /// write code to a temporary file before running it.
+    ///
+    /// On Unix, files are created with mode `0o700` (unless [permissions](Self::permissions) are
+    /// set explicitly). On Windows, an `.exe` suffix is appended to the [suffix](Self::suffix),
+    /// unless it already ends with one.
+    ///
+    /// Note: on Linux, a file that's still open for writing can't be executed, so convert the
+    /// [`NamedTempFile`] into a [`TempPath`] (closing the file) before running it.
+    ///
+    /// This setting doesn't apply to temporary directories or to files created with
+    /// [`make`](Self::make).
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// let mut script = Builder::new().executable(true).tempfile()?;
+    /// script.write_all(b"#!/bin/sh\necho hello\n")?;
+    /// let script = script.into_temp_path();
+    /// # #[cfg(unix)]
+    /// assert_eq!(std::process::Command::new(&script).output()?.stdout, b"hello\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn executable(&mut self, executable: bool) -> &mut Self {
+        self.executable = executable;
+        self
+    }
+
+    /// The permissions to create the tempfile or [tempdir](Self::tempdir) with.
+    ///
+    /// # Security
+    ///
+    /// By default, the permissions of tempfiles on Unix are set for it to be
+    /// readable and writable by the owner only, yielding the greatest amount
+    /// of security.
+    /// As this method allows to widen the permissions, security would be
+    /// reduced in such cases.
+    ///
+    /// # Platform Notes
+    /// ## Unix
+    ///
+    /// The actual permission bits set on the tempfile or tempdir will be affected by the `umask`
+    /// applied by the underlying syscall. The actual permission bits are calculated via
+    /// `permissions & !umask`.
+    ///
+    /// Permissions default to `0o600` for tempfiles and `0o777` for tempdirs. Note, this doesn't
+    /// include effects of the current `umask`. For example, combined with the standard umask
+    /// `0o022`, the defaults yield `0o600` for tempfiles and `0o755` for tempdirs.
+    ///
+    /// ## Windows and others
+    ///
+    /// This setting is unsupported and trying to set a file or directory read-only
+    /// will return an error.
+    ///
+    /// # Examples
+    ///
+    /// Create a named temporary file that is world-readable.
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use tempfile::Builder;
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// let all_read_write = std::fs::Permissions::from_mode(0o666);
+    /// let tempfile = Builder::new().permissions(all_read_write).tempfile()?;
+    /// let actual_permissions = tempfile.path().metadata()?.permissions();
+    /// assert_ne!(
+    ///     actual_permissions.mode() & !0o170000,
+    ///     0o600,
+    ///     "we get broader permissions than the default despite umask"
+    /// );
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// Create a named temporary directory that is restricted to the owner.
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use tempfile::Builder;
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// let owner_rwx = std::fs::Permissions::from_mode(0o700);
+    /// let tempdir = Builder::new().permissions(owner_rwx).tempdir()?;
+    /// let actual_permissions = tempdir.path().metadata()?.permissions();
+    /// assert_eq!(
+    ///     actual_permissions.mode() & !0o170000,
+    ///     0o700,
+    ///     "we get the narrow permissions we asked for"
+    /// );
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn permissions(&mut self, permissions: std::fs::Permissions) -> &mut Self {
+        self.permissions = Some(permissions);
+        self.readable_by = None;
+        self
+    }
+
+    /// Like [`permissions`](Self::permissions), but makes sure the tempfile or
+    /// [tempdir](Self::tempdir) ends up with exactly these permissions, even if the process'
+    /// `umask` would narrow them.
+    ///
+    /// The file or directory is still created with the permissions narrowed by the `umask` (so it's
+    /// never more accessible than intended), and then changed to the requested permissions through
+    /// its handle (with `fchmod` on Unix), before it's handed out.
+    ///
+    /// # Security
+    ///
+    /// See [`permissions`](Self::permissions).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::fs::PermissionsExt;
+    /// use tempfile::Builder;
+    ///
+    /// let shared = std::fs::Permissions::from_mode(0o666);
+    /// let tempfile = Builder::new().permissions_exact(shared).tempfile()?;
+    /// let mode = tempfile.as_file().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o666);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn permissions_exact(&mut self, permissions: std::fs::Permissions) -> &mut Self {
+        self.permissions = Some(permissions);
+        self.exact_permissions = true;
+        self.readable_by = None;
+        self
+    }
+
+    /// Make the tempfile or [tempdir](Self::tempdir) readable (but not writable) by all users, e.g.,
+    /// to hand it to a process running as another user.
+    ///
+    /// On Unix, files are created with mode `0o644` (`0o755` if [executable](Self::executable)),
+    /// and directories with mode `0o755`. These modes are applied exactly, like with
+    /// [`permissions_exact`](Self::permissions_exact), so a restrictive `umask` doesn't get in the
+    /// way. On other platforms, this setting has no effect for now: files and directories inherit
+    /// the access control list of the directory they're created in.
+    ///
+    /// This replaces any [permissions](Self::permissions) set before, and is replaced by
+    /// permissions set afterwards.
+    ///
+    /// # Security
+    ///
+    /// See [`permissions`](Self::permissions). The contents of a world-readable file can be read by
+    /// any user on the system.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let shared = Builder::new().world_readable().tempfile()?;
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::fs::PermissionsExt;
+    /// let mode = shared.as_file().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o644);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn world_readable(&mut self) -> &mut Self {
+        self.permissions = None;
+        self.exact_permissions = false;
+        self.readable_by = Some(Audience::World);
+        self
+    }
+
+    /// Make the tempfile or [tempdir](Self::tempdir) readable (but not writable) by the members of
+    /// the owner's group.
+    ///
+    /// On Unix, files are created with mode `0o640` (`0o750` if [executable](Self::executable)),
+    /// and directories with mode `0o750`. See [`world_readable`](Self::world_readable) for the
+    /// details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let shared = Builder::new().group_readable().tempdir()?;
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::fs::PermissionsExt;
+    /// let mode = shared.path().metadata()?.permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o750);
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn group_readable(&mut self) -> &mut Self {
+        self.permissions = None;
+        self.exact_permissions = false;
+        self.readable_by = Some(Audience::Group);
+        self
+    }
+
+    /// Create the directory passed to [`Builder::tempfile_in`], [`Builder::tempdir_in`], or
+    /// [`Builder::make_in`], including any missing parent directories, if it doesn't exist (like
+    /// `mkdir -p`).
+    ///
+    /// Directories created this way are _not_ temporary: they're left behind when the temporary
+    /// file or directory is deleted. Use [`Builder::parent_permissions`] to control their
+    /// permissions.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let base = tempfile::tempdir()?;
+    /// let scratch = base.path().join("target/scratch/deep");
+    /// let tmp_dir = Builder::new().create_parents(true).tempdir_in(&scratch)?;
+    /// assert!(tmp_dir.path().starts_with(&scratch));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create_parents(&mut self, create_parents: bool) -> &mut Self {
+        self.create_parents = create_parents;
+        self
+    }
+
+    /// The permissions to create missing parent directories with when
+    /// [`Builder::create_parents`] is enabled.
+    ///
+    /// # Platform Notes
+    ///
+    /// See [`Builder::permissions`]. Permissions default to `0o777` (minus the `umask`) on Unix.
+    pub fn parent_permissions(&mut self, permissions: std::fs::Permissions) -> &mut Self {
+        self.parent_permissions = Some(permissions);
+        self
+    }
+
+    /// Create temporary files and directories in a private subdirectory for the user `uid` (named
+    /// `tempfile-<uid>`) of the directory they would otherwise be created in (requires Unix).
+    ///
+    /// This is meant for privileged processes (e.g., daemons running as root, or setuid programs)
+    /// creating temporary files on behalf of another user in a shared directory like `/tmp` or
+    /// `/var/tmp`, where anyone could otherwise interfere with them (e.g., by guessing names or
+    /// planting symbolic links). The subdirectory belongs to the user the process is running as,
+    /// never to `uid`: otherwise, that user could swap it for a symbolic link while the process is
+    /// creating files in it. It's created with permissions `0o700` or, if the process is running
+    /// as a different user, `0o711`, so that `uid` can reach (but not list or modify) the files in
+    /// it. If it already exists, it must be a directory (not a symbolic link) owned by the user the
+    /// process is running as, and no more accessible than that.
+    ///
+    /// The subdirectory is _not_ temporary: it's left behind (for reuse) when the temporary files
+    /// and directories in it are deleted. The temporary files themselves also belong to the user
+    /// the process is running as; change their ownership if the other user needs to access them.
+    ///
+    /// # Errors
+    ///
+    /// If the subdirectory exists but isn't private, creating a temporary file fails with an error
+    /// of kind [`io::ErrorKind::PermissionDenied`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::Builder;
+    ///
+    /// # let uid = 1000;
+    /// // Running as root, on behalf of the user `uid`.
+    /// let file = Builder::new().private_subdir_for(uid).tempfile_in("/var/tmp")?;
+    /// assert!(file.path().starts_with(format!("/var/tmp/tempfile-{}", uid)));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn private_subdir_for(&mut self, uid: u32) -> &mut Self {
+        self.private_uid = Some(uid);
+        self
+    }
+
+    /// Create temporary files and directories on the same filesystem as `path` (the path they'll be
+    /// persisted at, or a directory on the same filesystem), so that [`NamedTempFile::persist`] is a
+    /// true atomic rename instead of failing because the target is on another filesystem (`EXDEV`).
+    ///
+    /// This only affects the methods that would otherwise create temporary files in
+    /// [`env::temp_dir()`] (e.g., [`Builder::tempfile`], [`Builder::tempdir`], and the file a
+    /// [`Builder::spooled`] file rolls over to); a directory
+    /// passed explicitly (e.g., to [`Builder::tempfile_in`]) always takes precedence. The directory
+    /// is chosen as follows:
+    ///
+    /// 1. If [`env::temp_dir()`] is on the same filesystem as `path`, it's used as usual.
+    /// 2. Otherwise, the temporary file is created in the directory containing `path` (or in `path`
+    ///    itself, if it's a directory).
+    ///
+    /// On platforms other than Unix and Windows, filesystems can't be compared, so the second rule
+    /// always applies.
+    ///
+    /// # Errors
+    ///
+    /// Creating a temporary file fails if the directory containing `path` doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// # let dir = tempfile::tempdir()?;
+    /// # let target = dir.path().join("report.pdf");
+    /// let mut file = Builder::new().same_filesystem_as(&target).tempfile()?;
+    /// file.write_all(b"%PDF-1.7")?;
+    /// file.persist(&target)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn same_filesystem_as<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.default_dir = DefaultDir::SameFilesystemAs(path.as_ref().into());
+        self
+    }
+
+    /// Disable cleanup of the file/folder to even when the [`NamedTempFile`]/[`TempDir`] goes out
+    /// of scope. Prefer [`NamedTempFile::keep`] and `[`TempDir::keep`] where possible,
+    /// `disable_cleanup` is provided for testing & debugging.
+    ///
+    /// By default, the file/folder is automatically cleaned up in the destructor of
+    /// [`NamedTempFile`]/[`TempDir`]. When `disable_cleanup` is set to `true`, this behavior is
+    /// suppressed. If you wish to disable cleanup after creating a temporary file/directory, call
+    /// [`NamedTempFile::disable_cleanup`] or [`TempDir::disable_cleanup`].
+    ///
+    /// # Warnings
+    ///
+    /// On some platforms (for now, only Windows), temporary files are marked with a special
+    /// "temporary file" (`FILE_ATTRIBUTE_TEMPORARY`) attribute. Disabling cleanup _will not_ unset
+    /// this attribute while calling [`NamedTempFile::keep`] will. To avoid setting it in the first
+    /// place, see [`Builder::windows_temporary_attribute`]; to clear it later, see
+    /// [`NamedTempFile::clear_temporary_attribute`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .disable_cleanup(true)
+    ///     .tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) -> &mut Self {
+        self.disable_cleanup = disable_cleanup;
+        self
+    }
+
+    /// Only delete the [tempdir](Self::tempdir) on drop if it's empty at that time, leaving it (and
+    /// its contents) behind otherwise. This preserves outputs that, e.g., a child process
+    /// intentionally left behind while still cleaning up after successful runs.
+    ///
+    /// This setting has no effect on temporary files. See also
+    /// [`TempDir::remove_only_if_empty`].
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new().remove_only_if_empty(true).tempdir()?;
+    /// let path = tmp_dir.path().to_owned();
+    /// fs::write(path.join("crash.log"), "something went wrong")?;
+    ///
+    /// drop(tmp_dir);
+    /// assert!(path.join("crash.log").exists());
+    /// # fs::remove_dir_all(path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn remove_only_if_empty(&mut self, remove_only_if_empty: bool) -> &mut Self {
+        self.remove_only_if_empty = remove_only_if_empty;
+        self
+    }
+
+    /// Remove the [tempdir](Self::tempdir) even if the process is killed (e.g., with "End process
+    /// tree" in Task Manager) on Windows, where there are no signals to catch.
+    ///
+    /// This starts a small, hidden watchdog process (using Windows PowerShell) for each temporary
+    /// directory. The watchdog removes the directory once this process has exited, unless the
+    /// `TempDir` has been dropped, [kept](TempDir::keep), or had its
+    /// [cleanup disabled](TempDir::disable_cleanup) by then. Creating the directory waits until the
+    /// watchdog is running, which can take a moment, so only use this for long-lived directories.
+    ///
+    /// On other platforms this setting has no effect.
+    ///
+    /// This setting has no effect on temporary files.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::Builder;
+    ///
+    /// let cache = Builder::new()
+    ///     .prefix("render-cache-")
+    ///     .windows_cleanup_on_kill(true)
+    ///     .tempdir()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn windows_cleanup_on_kill(&mut self, enabled: bool) -> &mut Self {
+        self.cleanup_on_kill = enabled;
+        self
+    }
+
+    /// Mount a private, size-limited tmpfs on the [tempdir](Self::tempdir), so that its contents
+    /// live in memory and can never take up more than `size` bytes (requires the `tmpfs` feature,
+    /// Linux only).
+    ///
+    /// The tmpfs is mounted with `nosuid` and `nodev`, its root gets the configured
+    /// [permissions](Self::permissions) (`0o700` by default), and mount events aren't propagated
+    /// to other mount namespaces. It's unmounted (discarding its contents) when the `TempDir` is
+    /// cleaned up. Keeping the `TempDir` (e.g., with [`TempDir::keep`]) leaves the tmpfs mounted.
+    ///
+    /// **NOTE:** Mounting filesystems is a privileged operation (requires `CAP_SYS_ADMIN` in the
+    /// current mount namespace), creating the directory fails with
+    /// [`io::ErrorKind::PermissionDenied`] otherwise.
+    ///
+    /// This setting has no effect on temporary files.
+    ///
+    /// Default: no tmpfs is mounted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tempfile::Builder;
+    ///
+    /// // 64 MiB of guaranteed-RAM scratch space.
+    /// let scratch = Builder::new().tmpfs(64 << 20).tempdir()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+    pub fn tmpfs(&mut self, size: u64) -> &mut Self {
+        self.tmpfs_size = Some(size);
+        self
+    }
+
+    /// Deprecated alias for [`Builder::disable_cleanup`].
+    #[deprecated = "Use Builder::disable_cleanup"]
+    pub fn keep(&mut self, keep: bool) -> &mut Self {
+        self.disable_cleanup(keep)
+    }
+
+    /// Create the named temporary file.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tempfile = Builder::new().tempfile()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.tempfile_in(self.default_dir()?)
+    }
+
+    /// Create the named temporary file in the specified directory.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
+        let base = self.prepare_dir(dir.as_ref())?;
+        let suffix = self.file_suffix();
+        let spec = util::NameSpec {
+            suffix: &suffix,
+            ..self.name_spec()
+        };
+        let permissions = self.file_permissions();
+        util::create_helper(&base, &spec, |path| {
+            let file = file::create_named(
+                path,
+                &mut self.open_options(),
+                permissions.as_ref(),
+                self.disable_cleanup,
+            )?;
+            self.finish_named(file)
+        })
+    }
+
+    /// Reserve a unique temporary path for a file that will be produced by someone else, e.g., an
+    /// external program that refuses to write to an existing open file handle.
+    ///
+    /// The name is claimed atomically by creating an empty placeholder file (with this builder's
+    /// settings), which is closed before this function returns. See [`ReservedPath`].
+    ///
+    /// # Errors
+    ///
+    /// If the placeholder cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let reserved = Builder::new().suffix(".pdf").reserve_path()?;
+    /// assert!(reserved.is_placeholder());
+    /// std::fs::write(&reserved, b"%PDF-1.7")?;
+    /// assert!(!reserved.is_placeholder());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reserve_path(&self) -> io::Result<ReservedPath> {
+        self.reserve_path_in(self.default_dir()?)
+    }
+
+    /// Reserve a unique temporary path in the specified directory. See [`Builder::reserve_path`].
+    ///
+    /// # Errors
+    ///
+    /// If the placeholder cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let reserved = Builder::new().reserve_path_in("./")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn reserve_path_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<ReservedPath> {
+        self.tempfile_in(dir)
+            .map(|file| ReservedPath::new(file.into_temp_path()))
+    }
+
+    /// Create a set of named temporary files that share a randomly generated stem, one per
+    /// extension, e.g., `.tmpAbC123.idx`, `.tmpAbC123.dat`, and `.tmpAbC123.log`.
+    ///
+    /// This is useful for formats that require several co-named files. The extensions take the
+    /// place of the [suffix](Self::suffix); all other settings apply to every file in the set. If
+    /// any of the names is already taken, the files created so far are deleted and the whole set
+    /// is retried with a new stem.
+    ///
+    /// The files are returned in the same order as `extensions`.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files cannot be created, `Err` is returned and none of the files are left
+    /// behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let files = Builder::new().tempfile_set(&[".idx", ".dat", ".log"])?;
+    /// assert_eq!(files.len(), 3);
+    /// assert_eq!(files[0].path().with_extension(""), files[1].path().with_extension(""));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile_set<S: AsRef<OsStr>>(
+        &self,
+        extensions: &[S],
+    ) -> io::Result<Vec<NamedTempFile>> {
+        self.tempfile_set_in(self.default_dir()?, extensions)
+    }
+
+    /// Create a set of named temporary files that share a randomly generated stem in the
+    /// specified directory. See [`Builder::tempfile_set`].
+    ///
+    /// # Errors
+    ///
+    /// If any of the files cannot be created, `Err` is returned and none of the files are left
+    /// behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let files = Builder::new().tempfile_set_in("./", &[".idx", ".dat"])?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn tempfile_set_in<P: AsRef<Path>, S: AsRef<OsStr>>(
+        &self,
+        dir: P,
+        extensions: &[S],
+    ) -> io::Result<Vec<NamedTempFile>> {
+        let base = self.prepare_dir(dir.as_ref())?;
+        let spec = util::NameSpec {
+            suffix: OsStr::new(""),
+            ..self.name_spec()
+        };
+        let permissions = self.file_permissions();
+        util::create_helper(&base, &spec, |stem| {
+            let mut files: Vec<NamedTempFile> = Vec::with_capacity(extensions.len());
+            for extension in extensions {
+                let mut path = stem.clone().into_os_string();
+                path.push(extension);
+                let created = file::create_named(
+                    path.into(),
+                    &mut self.open_options(),
+                    permissions.as_ref(),
+                    self.disable_cleanup,
+                )
+                .and_then(|file| self.finish_named(file));
+                match created {
+                    Ok(file) => files.push(file),
+                    Err(e) => {
+                        // Don't leave a partial set behind, even if cleanup is disabled.
+                        for mut file in files {
+                            file.disable_cleanup(false);
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            Ok(files)
+        })
+    }
+
+    /// Attempts to make a temporary directory inside of [`env::temp_dir()`] whose
+    /// name will have the prefix, `prefix`. The directory and
+    /// everything inside it will be automatically deleted once the
+    /// returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new().tempdir()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir(&self) -> io::Result<TempDir> {
+        self.tempdir_in(self.default_dir()?)
+    }
+
+    /// Attempts to make a temporary directory inside of `dir`.
+    /// The directory and everything inside it will be automatically
+    /// deleted once the returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
+        let base = self.prepare_dir(dir.as_ref())?;
+        let permissions = self.dir_permissions();
+        util::create_helper(&base, &self.name_spec(), |path| {
+            let mut dir = dir::create(path, permissions.as_ref(), self.disable_cleanup)?;
+            if let Some(permissions) = self.exact_permissions(Kind::Dir) {
+                dir.set_permissions(permissions)?;
+            }
+            dir.remove_only_if_empty(self.remove_only_if_empty);
+            #[cfg(all(feature = "tmpfs", target_os = "linux"))]
+            if let Some(size) = self.tmpfs_size {
+                dir::mount_tmpfs(&mut dir, size, permissions.as_ref())?;
+            }
+            if self.cleanup_on_kill {
+                dir::watch_for_kill(&mut dir)?;
+            }
+            self.record(Kind::Dir, dir.path())?;
+            telemetry::created(Kind::Dir, dir.path());
+            Ok(dir)
+        })
+    }
+
+    /// Attempts to create a temporary file (or file-like object) using the
+    /// provided closure. The closure is passed a temporary file path and
+    /// returns an [`std::io::Result`]. The path provided to the closure will be
+    /// inside of [`env::temp_dir()`]. Use [`Builder::make_in`] to provide
+    /// a custom temporary directory. If the closure returns one of the
+    /// following errors, then another randomized file path is tried:
+    ///  - [`std::io::ErrorKind::AlreadyExists`]
+    ///  - [`std::io::ErrorKind::AddrInUse`]
+    ///
+    /// This can be helpful for taking full control over the file creation, but
+    /// leaving the temporary file path construction up to the library. This
+    /// also enables creating a temporary UNIX domain socket, since it is not
+    /// possible to bind to a socket that already exists.
+    ///
+    /// Note that [`Builder::append`] is ignored when using [`Builder::make`].
+    ///
+    /// # Security
+    ///
+    /// This has the same [security implications][security] as
+    /// [`NamedTempFile`], but with additional caveats. Specifically, it is up
+    /// to the closure to ensure that the file does not exist and that such a
+    /// check is *atomic*. Otherwise, a [time-of-check to time-of-use
+    /// bug][TOCTOU] could be introduced.
     ///
     /// For example, the following is **not** secure:
     ///
@@ -720,7 +1839,7 @@ impl<'a, 'b> Builder<'a, 'b> {
     where
         F: FnMut(&Path) -> io::Result<R>,
     {
-        self.make_in(env::temp_dir(), f)
+        self.make_in(self.default_dir()?, f)
     }
 
     /// This is the same as [`Builder::make`], except `dir` is used as the base
@@ -744,17 +1863,430 @@ impl<'a, 'b> Builder<'a, 'b> {
         F: FnMut(&Path) -> io::Result<R>,
         P: AsRef<Path>,
     {
-        util::create_helper(
-            dir.as_ref(),
-            self.prefix,
-            self.suffix,
-            self.random_len,
-            move |path| {
-                Ok(NamedTempFile::from_parts(
-                    f(&path)?,
-                    TempPath::new(path, self.disable_cleanup),
-                ))
-            },
-        )
+        let base = self.prepare_dir(dir.as_ref())?;
+        util::create_helper(&base, &self.name_spec(), move |path| {
+            let file =
+                NamedTempFile::from_parts(f(&path)?, TempPath::new(path, self.disable_cleanup));
+            self.record(Kind::File, file.path())?;
+            telemetry::created(Kind::File, file.path());
+            Ok(file)
+        })
+    }
+
+    /// Create a symbolic link to `target` at a new temporary path in [`env::temp_dir()`], which is
+    /// removed when the returned [`TempSymlink`] is dropped. See [`TempSymlink`] for details.
+    ///
+    /// Only the [prefix](Self::prefix), [suffix](Self::suffix), [random bytes](Self::rand_bytes),
+    /// and cleanup settings apply.
+    ///
+    /// # Errors
+    ///
+    /// If the symbolic link cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let link = Builder::new().prefix("alias-").symlink(dir.path())?;
+    /// assert!(link.is_dir());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(any(unix, windows))]
+    pub fn symlink<P: AsRef<Path>>(&self, target: P) -> io::Result<TempSymlink> {
+        self.symlink_in(target, self.default_dir()?)
+    }
+
+    /// Create a symbolic link to `target` at a new temporary path in `dir`. See
+    /// [`Builder::symlink`].
+    ///
+    /// # Errors
+    ///
+    /// If the symbolic link cannot be created, `Err` is returned.
+    #[cfg(any(unix, windows))]
+    pub fn symlink_in<P: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        target: P,
+        dir: D,
+    ) -> io::Result<TempSymlink> {
+        let base = self.prepare_dir(dir.as_ref())?;
+        util::create_helper(&base, &self.name_spec(), |path| {
+            let link = file::create_symlink(target.as_ref(), path, self.disable_cleanup)?;
+            self.record(Kind::File, link.path())?;
+            telemetry::created(Kind::File, link.path());
+            Ok(link)
+        })
+    }
+
+    /// Create a named pipe (FIFO) at a new temporary path in [`env::temp_dir()`], which is deleted
+    /// when the returned [`TempFifo`] is dropped.
+    ///
+    /// The FIFO is created with mode `0o600`, or the configured [permissions](Self::permissions).
+    /// The [prefix](Self::prefix), [suffix](Self::suffix), [random bytes](Self::rand_bytes), and
+    /// cleanup settings apply as well.
+    ///
+    /// This function is only available on Unix platforms other than Apple platforms and Redox.
+    ///
+    /// # Errors
+    ///
+    /// If the FIFO cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::Builder;
+    ///
+    /// let fifo = Builder::new().suffix(".pipe").fifo()?;
+    /// println!("write to {}", fifo.path().display());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+    pub fn fifo(&self) -> io::Result<TempFifo> {
+        self.fifo_in(self.default_dir()?)
+    }
+
+    /// Create a named pipe (FIFO) at a new temporary path in `dir`. See [`Builder::fifo`].
+    ///
+    /// # Errors
+    ///
+    /// If the FIFO cannot be created, `Err` is returned.
+    #[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+    pub fn fifo_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempFifo> {
+        let base = self.prepare_dir(dir.as_ref())?;
+        util::create_helper(&base, &self.name_spec(), |path| {
+            file::create_fifo(&path, self.resolved_permissions())?;
+            let path = TempPath::new(path, self.disable_cleanup);
+            self.record(Kind::File, &path)?;
+            telemetry::created(Kind::File, &path);
+            Ok(TempFifo::new(path))
+        })
+    }
+
+    /// Create a Unix domain socket listening at a new temporary path in [`env::temp_dir()`].
+    ///
+    /// The socket file is deleted when the returned [`NamedTempFile`] is dropped. Only the
+    /// [prefix](Self::prefix), [suffix](Self::suffix), [random bytes](Self::rand_bytes), and
+    /// cleanup settings apply.
+    ///
+    /// # Errors
+    ///
+    /// If the path would exceed the platform's socket path limit (`sun_path`, usually 108 or 104
+    /// bytes), an error with kind [`io::ErrorKind::InvalidInput`] is returned; use
+    /// [`Builder::unix_listener_in`] with a shorter directory. Otherwise, if the socket cannot be
+    /// bound, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::net::UnixStream;
+    /// use tempfile::Builder;
+    ///
+    /// let listener = Builder::new().suffix(".sock").unix_listener()?;
+    /// let _client = UnixStream::connect(listener.path())?;
+    /// let (_server, _) = listener.as_file().accept()?;
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn unix_listener(&self) -> io::Result<NamedTempFile<std::os::unix::net::UnixListener>> {
+        self.unix_listener_in(self.default_dir()?)
+    }
+
+    /// Create a Unix domain socket listening at a new temporary path in `dir`. See
+    /// [`Builder::unix_listener`].
+    ///
+    /// # Errors
+    ///
+    /// If the path would exceed the platform's socket path limit, an error with kind
+    /// [`io::ErrorKind::InvalidInput`] is returned. Otherwise, if the socket cannot be bound, `Err`
+    /// is returned.
+    #[cfg(unix)]
+    pub fn unix_listener_in<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> io::Result<NamedTempFile<std::os::unix::net::UnixListener>> {
+        self.make_in(dir, |path| {
+            util::check_socket_path(path)?;
+            std::os::unix::net::UnixListener::bind(path)
+        })
+    }
+
+    /// Create a Unix datagram socket bound to a new temporary path in [`env::temp_dir()`].
+    ///
+    /// The socket file is deleted when the returned [`NamedTempFile`] is dropped. Only the
+    /// [prefix](Self::prefix), [suffix](Self::suffix), [random bytes](Self::rand_bytes), and
+    /// cleanup settings apply.
+    ///
+    /// # Errors
+    ///
+    /// If the path would exceed the platform's socket path limit (`sun_path`, usually 108 or 104
+    /// bytes), an error with kind [`io::ErrorKind::InvalidInput`] is returned; use
+    /// [`Builder::unix_datagram_in`] with a shorter directory. Otherwise, if the socket cannot be
+    /// bound, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::net::UnixDatagram;
+    /// use tempfile::Builder;
+    ///
+    /// let socket = Builder::new().unix_datagram()?;
+    /// UnixDatagram::unbound()?.send_to(b"ping", socket.path())?;
+    /// let mut buf = [0; 4];
+    /// socket.as_file().recv(&mut buf)?;
+    /// assert_eq!(&buf, b"ping");
+    /// # }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn unix_datagram(&self) -> io::Result<NamedTempFile<std::os::unix::net::UnixDatagram>> {
+        self.unix_datagram_in(self.default_dir()?)
+    }
+
+    /// Create a Unix datagram socket bound to a new temporary path in `dir`. See
+    /// [`Builder::unix_datagram`].
+    ///
+    /// # Errors
+    ///
+    /// If the path would exceed the platform's socket path limit, an error with kind
+    /// [`io::ErrorKind::InvalidInput`] is returned. Otherwise, if the socket cannot be bound, `Err`
+    /// is returned.
+    #[cfg(unix)]
+    pub fn unix_datagram_in<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> io::Result<NamedTempFile<std::os::unix::net::UnixDatagram>> {
+        self.make_in(dir, |path| {
+            util::check_socket_path(path)?;
+            std::os::unix::net::UnixDatagram::bind(path)
+        })
+    }
+
+    /// Create a [`SpooledTempFile`] that keeps its data in memory until it reaches `max_size`
+    /// bytes, at which point it's written to a temporary file in [`env::temp_dir()`] (or the
+    /// directory chosen by [`Builder::same_filesystem_as`]) created with this builder's settings.
+    ///
+    /// The file on disk honors the [prefix](Self::prefix), [suffix](Self::suffix), [random
+    /// bytes](Self::rand_bytes), and [permissions](Self::permissions). Like the file created by
+    /// [`tempfile()`], it's deleted from the filesystem as soon as it has been created. All other
+    /// settings are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// let mut upload = Builder::new().prefix("upload-").spooled(1024 * 1024);
+    /// upload.write_all(b"request body")?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn spooled(&self, max_size: usize) -> SpooledTempFile {
+        SpooledTempFile::with_builder(max_size, None, self)
+    }
+
+    /// Create a [`SpooledTempFile`] that's written to a temporary file in `dir`, created with this
+    /// builder's settings, once it reaches `max_size` bytes. See [`Builder::spooled`].
+    ///
+    /// **NOTE:** The specified path isn't checked until the data is rolled over to disk.
+    #[must_use]
+    pub fn spooled_in<P: AsRef<Path>>(&self, max_size: usize, dir: P) -> SpooledTempFile {
+        SpooledTempFile::with_builder(max_size, Some(dir.as_ref()), self)
+    }
+
+    /// Returns the directory to create temporary files in when none has been specified.
+    fn default_dir(&self) -> io::Result<Cow<'_, Path>> {
+        self.default_dir.resolve()
+    }
+
+    /// Creates `dir` and its parents first, if requested, and returns the directory to create
+    /// temporary files in.
+    fn prepare_dir<'p>(&self, dir: &'p Path) -> io::Result<Cow<'p, Path>> {
+        if self.create_parents {
+            dir::create_all(dir, self.parent_permissions.as_ref())?;
+        }
+        #[cfg(unix)]
+        if let Some(uid) = self.private_uid {
+            let private = dir.join(format!("tempfile-{}", uid));
+            dir::create_private(&private, uid)?;
+            return Ok(Cow::Owned(private));
+        }
+        Ok(Cow::Borrowed(dir))
+    }
+
+    pub(crate) fn name_spec(&self) -> util::NameSpec<'_> {
+        util::NameSpec {
+            prefix: self.resolved_prefix(),
+            suffix: self.resolved_suffix(),
+            random_len: self.resolved_random_len(),
+            #[cfg(feature = "uuid")]
+            uuid: self.uuid_names,
+            pid: self.with_pid,
+            timestamp: self.timestamp,
+            sequence: self.with_sequence,
+            adaptive: self.adaptive_rand_bytes,
+            fit_name_length: self.fit_name_length,
+            ascii_only: self.ascii_only,
+        }
+    }
+
+    fn resolved_prefix(&self) -> &OsStr {
+        self.prefix
+            .or_else(|| env::default_builder().and_then(|b| b.prefix))
+            .unwrap_or_else(|| OsStr::new(".tmp"))
+    }
+
+    fn resolved_suffix(&self) -> &OsStr {
+        self.suffix
+            .or_else(|| env::default_builder().and_then(|b| b.suffix))
+            .unwrap_or_else(|| OsStr::new(""))
+    }
+
+    fn resolved_random_len(&self) -> usize {
+        self.random_len
+            .or_else(|| env::default_builder().and_then(|b| b.random_len))
+            .unwrap_or(crate::NUM_RAND_CHARS)
+    }
+
+    /// The options temporary files are opened with, before the platform-specific ones are added.
+    fn open_options(&self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options.append(self.append);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(self.custom_flags);
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            use windows_sys::Win32::Storage::FileSystem::{
+                FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_TEMPORARY,
+            };
+            let mut attributes = 0;
+            if self.hidden {
+                attributes |= FILE_ATTRIBUTE_HIDDEN;
+            }
+            if !self.no_temporary_attribute {
+                attributes |= FILE_ATTRIBUTE_TEMPORARY;
+            }
+            options.attributes(attributes);
+        }
+        options
+    }
+
+    /// Applies the settings that can only be applied once a named temporary file exists.
+    fn finish_named(&self, file: NamedTempFile) -> io::Result<NamedTempFile> {
+        if self.append {
+            file.set_append();
+        }
+        if let Some(permissions) = self.exact_permissions(Kind::File) {
+            file.set_permissions(permissions)?;
+        }
+        if self.inheritable {
+            file::imp::set_inheritable(file.as_file()).with_err_path(|| file.path())?;
+        }
+        if self.sparse {
+            file::imp::set_sparse(file.as_file()).with_err_path(|| file.path())?;
+        }
+        self.record(Kind::File, file.path())?;
+        telemetry::created(Kind::File, file.path());
+        Ok(file)
+    }
+
+    /// The suffix of temporary files, including the executable suffix if necessary.
+    fn file_suffix(&self) -> Cow<'_, OsStr> {
+        let suffix = self.resolved_suffix();
+        let exe_suffix = std::env::consts::EXE_SUFFIX;
+        if self.executable
+            && !suffix
+                .to_string_lossy()
+                .to_ascii_lowercase()
+                .ends_with(exe_suffix)
+        {
+            let mut suffix = suffix.to_owned();
+            suffix.push(exe_suffix);
+            Cow::Owned(suffix)
+        } else {
+            Cow::Borrowed(suffix)
+        }
+    }
+
+    /// The permissions temporary files are created with.
+    fn file_permissions(&self) -> Option<std::fs::Permissions> {
+        if let Some(permissions) = self.readable_permissions(Kind::File) {
+            return Some(permissions);
+        }
+        #[cfg(unix)]
+        if self.executable && self.resolved_permissions().is_none() {
+            use std::os::unix::fs::PermissionsExt;
+            return Some(std::fs::Permissions::from_mode(0o700));
+        }
+        self.resolved_permissions().cloned()
+    }
+
+    /// Records a newly created temporary file or directory in the manifest, unless it's kept
+    /// anyway.
+    fn record(&self, kind: Kind, path: &Path) -> io::Result<()> {
+        if self.disable_cleanup {
+            return Ok(());
+        }
+        manifest::record(kind, path)
+    }
+
+    /// The permissions temporary directories are created with.
+    fn dir_permissions(&self) -> Option<std::fs::Permissions> {
+        self.readable_permissions(Kind::Dir)
+            .or_else(|| self.resolved_permissions().cloned())
+    }
+
+    /// The permissions to apply after creation, if they must not be narrowed by the umask.
+    fn exact_permissions(&self, kind: Kind) -> Option<std::fs::Permissions> {
+        let source = self.permission_source();
+        self.readable_permissions(kind)
+            .or_else(|| source.permissions.clone().filter(|_| source.exact_permissions))
+    }
+
+    /// The permissions requested with [`Builder::world_readable`] or [`Builder::group_readable`].
+    #[cfg(unix)]
+    fn readable_permissions(&self, kind: Kind) -> Option<std::fs::Permissions> {
+        use std::os::unix::fs::PermissionsExt;
+        let readable_by = self.permission_source().readable_by?;
+        let executable = kind == Kind::Dir || self.executable;
+        let mode = match (readable_by, executable) {
+            (Audience::Group, false) => 0o640,
+            (Audience::Group, true) => 0o750,
+            (Audience::World, false) => 0o644,
+            (Audience::World, true) => 0o755,
+        };
+        Some(std::fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(not(unix))]
+    fn readable_permissions(&self, _kind: Kind) -> Option<std::fs::Permissions> {
+        None
+    }
+
+    fn resolved_permissions(&self) -> Option<&std::fs::Permissions> {
+        self.permission_source().permissions.as_ref()
+    }
+
+    /// The builder whose permission settings (the permissions, whether they're exact, and who the
+    /// file is readable by) apply: this one if it configures any of them, and the default builder
+    /// (if any) otherwise. The settings are never mixed.
+    fn permission_source(&self) -> &Self {
+        if self.permissions.is_some() || self.readable_by.is_some() {
+            return self;
+        }
+        match env::default_builder() {
+            Some(default) => default,
+            None => self,
+        }
     }
 }
@@ -0,0 +1,101 @@
+/// Create a new temporary directory whose name starts with the module path and line number of
+/// the call (e.g., `my_crate-tests-42-` for line 42 of `my_crate::tests`), so that leftovers in a
+/// shared temporary directory (e.g., on a CI machine) point back to the code that created them.
+///
+/// `tempdir!()` creates the directory in [`env::temp_dir()`](crate::env::temp_dir), and
+/// `tempdir!(dir)` creates it in `dir`, like [`Builder::tempdir`](crate::Builder::tempdir) and
+/// [`Builder::tempdir_in`](crate::Builder::tempdir_in). Long module paths are shortened to fit
+/// within file name length limits (see [`Builder::fit_name_length`](crate::Builder::fit_name_length)).
+///
+/// # Errors
+///
+/// If the directory can't be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// let tmp_dir = tempfile::tempdir!()?;
+///
+/// let nested = tempfile::tempdir!(tmp_dir.path())?;
+/// assert!(nested.path().starts_with(tmp_dir.path()));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[macro_export]
+macro_rules! tempdir {
+    () => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix(
+                ::std::module_path!(),
+                ::std::line!(),
+            ))
+            .fit_name_length(true)
+            .tempdir()
+    };
+    ($dir:expr $(,)?) => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix(
+                ::std::module_path!(),
+                ::std::line!(),
+            ))
+            .fit_name_length(true)
+            .tempdir_in($dir)
+    };
+}
+
+/// Create a new named temporary file whose name starts with the module path and line number of
+/// the call, like [`tempdir!`].
+///
+/// `named_tempfile!()` creates the file in [`env::temp_dir()`](crate::env::temp_dir), and
+/// `named_tempfile!(dir)` creates it in `dir`, like
+/// [`Builder::tempfile`](crate::Builder::tempfile) and
+/// [`Builder::tempfile_in`](crate::Builder::tempfile_in).
+///
+/// # Errors
+///
+/// If the file can't be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut file = tempfile::named_tempfile!()?;
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[macro_export]
+macro_rules! named_tempfile {
+    () => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix(
+                ::std::module_path!(),
+                ::std::line!(),
+            ))
+            .fit_name_length(true)
+            .tempfile()
+    };
+    ($dir:expr $(,)?) => {
+        $crate::Builder::new()
+            .prefix(&$crate::__caller_prefix(
+                ::std::module_path!(),
+                ::std::line!(),
+            ))
+            .fit_name_length(true)
+            .tempfile_in($dir)
+    };
+}
+
+/// The prefix used by [`tempdir!`] and [`named_tempfile!`]. Module paths only consist of
+/// identifiers, so the result is a valid file name on all platforms once the `::` separators are
+/// replaced. Identifiers may contain non-ASCII characters though, which are replaced with `_` so
+/// that the prefix also works with [`Builder::ascii_only`](crate::Builder::ascii_only).
+#[doc(hidden)]
+#[must_use]
+pub fn caller_prefix(module_path: &str, line: u32) -> String {
+    let module: String = module_path
+        .replace("::", "-")
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '_' })
+        .collect();
+    format!("{}-{}-", module, line)
+}
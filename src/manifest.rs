@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Once rust 1.70 is wide-spread (Debian stable), we can use OnceLock from stdlib.
+use once_cell::sync::OnceCell as OnceLock;
+
+use crate::error::IoResultExt;
+use crate::token::{Kind, OwnershipToken};
+
+static MANIFEST: OnceLock<Manifest> = OnceLock::new();
+
+/// Marks an entry that has been deleted, persisted, or kept since it was recorded.
+const RELEASED: u8 = b'-';
+
+/// Compact the manifest once it holds this many more lines than live entries.
+const COMPACT_SLACK: usize = 64;
+
+struct Manifest {
+    path: PathBuf,
+    dir: ManifestDir,
+    name: OsString,
+    state: Mutex<State>,
+}
+
+struct State {
+    file: File,
+    /// The live entries, with their creation order.
+    live: HashMap<(Kind, PathBuf), u64>,
+    next: u64,
+    /// The number of lines in the manifest file.
+    lines: usize,
+}
+
+/// Installs the process-wide manifest. See [`crate::env::override_manifest`].
+pub(crate) fn install(path: &Path) -> io::Result<()> {
+    let mut we_set = false;
+    MANIFEST.get_or_try_init(|| {
+        let (dir, name) = ManifestDir::open_parent(path).with_err_path(|| path)?;
+        let file = dir.open(&name, true).with_err_path(|| path)?;
+        we_set = true;
+        Ok::<_, io::Error>(Manifest {
+            path: path.to_owned(),
+            dir,
+            name,
+            state: Mutex::new(State {
+                file,
+                live: HashMap::new(),
+                next: 0,
+                lines: 0,
+            }),
+        })
+    })?;
+    if we_set {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a manifest has already been installed",
+        ))
+        .with_err_path(|| path)
+    }
+}
+
+/// Records a newly created temporary file or directory in the manifest, if one is installed.
+pub(crate) fn record(kind: Kind, path: &Path) -> io::Result<()> {
+    let manifest = match MANIFEST.get() {
+        Some(manifest) => manifest,
+        None => return Ok(()),
+    };
+    let line = entry_line(kind, path, false).with_err_path(|| &manifest.path)?;
+    let mut state = manifest.state.lock().unwrap_or_else(|e| e.into_inner());
+    // Write each entry in one go so that a crash can, at worst, truncate the last one.
+    state
+        .file
+        .write_all(&line)
+        .with_err_path(|| &manifest.path)?;
+    let order = state.next;
+    state.next += 1;
+    state.lines += 1;
+    state.live.insert((kind, path.to_owned()), order);
+    Ok(())
+}
+
+/// Records that a temporary file or directory has been deleted, persisted, or kept, so it's no
+/// longer returned by [`recover`]. Errors are ignored: at worst, the entry is recovered after a
+/// crash and found to be missing (or not owned by the current user).
+pub(crate) fn release(kind: Kind, path: &Path) {
+    let manifest = match MANIFEST.get() {
+        Some(manifest) => manifest,
+        None => return,
+    };
+    let mut state = manifest.state.lock().unwrap_or_else(|e| e.into_inner());
+    if state.live.remove(&(kind, path.to_owned())).is_none() {
+        return;
+    }
+    if state.lines > state.live.len() * 2 + COMPACT_SLACK && manifest.compact(&mut state).is_ok() {
+        return;
+    }
+    if let Ok(line) = entry_line(kind, path, true) {
+        if state.file.write_all(&line).is_ok() {
+            state.lines += 1;
+        }
+    }
+}
+
+impl Manifest {
+    /// Rewrites the manifest with just the live entries, atomically replacing the old one.
+    fn compact(&self, state: &mut State) -> io::Result<()> {
+        let mut live: Vec<_> = state.live.iter().collect();
+        live.sort_by_key(|&(_, order)| order);
+        let mut contents = Vec::new();
+        for ((kind, path), _) in live {
+            contents.extend(entry_line(*kind, path, false)?);
+        }
+
+        let mut tmp_name = self.name.clone();
+        tmp_name.push(".compact");
+        let mut file = self.dir.create_new(&tmp_name)?;
+        file.write_all(&contents)?;
+        self.dir.rename(&tmp_name, &self.name)?;
+        state.file = file;
+        state.lines = state.live.len();
+        Ok(())
+    }
+}
+
+/// Encodes a manifest line recording that the temporary file or directory at `path` has been
+/// created or, if `released` is `true`, released.
+fn entry_line(kind: Kind, path: &Path, released: bool) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    if released {
+        line.push(RELEASED);
+    }
+    line.extend(encode_line(
+        &OwnershipToken::new(kind, path.to_owned()).encode(),
+    )?);
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Recover the temporary files and directories left behind by a process that didn't get to clean
+/// up after itself (e.g., because it crashed), as recorded in a manifest installed with
+/// [`env::override_manifest`](crate::env::override_manifest).
+///
+/// Returns an [`OwnershipToken`] for each recorded file or directory that still exists, in the
+/// order they were created. Each leftover can either be re-adopted with
+/// [`TempPath::adopt`](crate::TempPath::adopt)/[`TempDir::adopt`](crate::TempDir::adopt), or
+/// deleted with [`OwnershipToken::remove`]. Entries that have been cleaned up, persisted, or kept
+/// are skipped, as are (on Unix) entries that aren't owned by the current user. The manifest
+/// itself is deleted.
+///
+/// This function should be called on startup, _before_ the manifest is re-installed with
+/// [`env::override_manifest`](crate::env::override_manifest). Only one process should use a
+/// given manifest at a time.
+///
+/// # Errors
+///
+/// If the manifest can't be read or deleted, or if it isn't in a private location (see
+/// [`env::override_manifest`](crate::env::override_manifest)), `Err` is returned. A missing
+/// manifest isn't an error (there's nothing to recover).
+///
+/// # Examples
+///
+/// ```no_run
+/// use tempfile::{env, TempDir};
+///
+/// // The manifest must live in a directory only the current user can write to.
+/// let manifest = "/var/lib/my-daemon/tempfiles.manifest";
+/// for leftover in tempfile::recover(manifest)? {
+///     if leftover.is_dir() {
+///         let _cache = TempDir::adopt(leftover)?;
+///         // ...
+///     } else {
+///         leftover.remove()?;
+///     }
+/// }
+/// env::override_manifest(manifest)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn recover<P: AsRef<Path>>(manifest: P) -> io::Result<Vec<OwnershipToken>> {
+    let manifest = manifest.as_ref();
+    let (dir, name) = ManifestDir::open_parent(manifest).with_err_path(|| manifest)?;
+    let mut contents = Vec::new();
+    match dir.open(&name, false) {
+        Ok(mut file) => file.read_to_end(&mut contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+    .with_err_path(|| manifest)?;
+
+    let mut leftovers: Vec<OwnershipToken> = Vec::new();
+    // Ignore anything we can't decode, e.g., an entry truncated by a crash mid-write.
+    for line in contents.split(|&b| b == b'\n') {
+        let (released, line) = match line.split_first() {
+            Some((&RELEASED, rest)) => (true, rest),
+            _ => (false, line),
+        };
+        let token =
+            match decode_line(line).and_then(|encoded| OwnershipToken::decode(&encoded).ok()) {
+                Some(token) => token,
+                None => continue,
+            };
+        let existing = leftovers.iter().position(|t| *t == token);
+        match (released, existing) {
+            (true, Some(i)) => {
+                leftovers.remove(i);
+            }
+            (false, None) => leftovers.push(token),
+            _ => {}
+        }
+    }
+    leftovers.retain(|token| {
+        token.path().symlink_metadata().map_or(false, |meta| {
+            meta.is_dir() == token.is_dir() && is_owned_by_current_user(&meta)
+        })
+    });
+
+    dir.remove(&name).with_err_path(|| manifest)?;
+    Ok(leftovers)
+}
+
+#[cfg(unix)]
+fn is_owned_by_current_user(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.uid() == rustix::process::geteuid().as_raw()
+}
+
+#[cfg(not(unix))]
+fn is_owned_by_current_user(_meta: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// The directory holding a manifest.
+///
+/// On Unix, the directory is kept open and checked to be owned by (and only writable by) the
+/// current user, and the manifest is accessed relative to it without following symbolic links.
+/// Otherwise, anyone who can write to the directory could redirect the manifest, or inject entries
+/// that [`recover`] hands out for deletion.
+struct ManifestDir {
+    #[cfg(unix)]
+    dir: File,
+    #[cfg(not(unix))]
+    dir: PathBuf,
+}
+
+fn split_path(path: &Path) -> io::Result<(&Path, &OsStr)> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "manifest path has no file name",
+        )
+    })?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok((parent, name))
+}
+
+fn not_private(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("manifest {} isn't private to the current user", what),
+    )
+}
+
+#[cfg(unix)]
+impl ManifestDir {
+    fn open_parent(path: &Path) -> io::Result<(ManifestDir, OsString)> {
+        use rustix::fs::OFlags;
+        use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+
+        let (parent, name) = split_path(path)?;
+        let dir = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(OFlags::DIRECTORY.bits() as i32)
+            .open(parent)?;
+        let meta = dir.metadata()?;
+        if !is_owned_by_current_user(&meta) || meta.mode() & 0o022 != 0 {
+            return Err(not_private("directory"));
+        }
+        Ok((ManifestDir { dir }, name.to_owned()))
+    }
+
+    /// Opens the manifest for appending (creating it if `create` is set) or for reading.
+    fn open(&self, name: &OsStr, create: bool) -> io::Result<File> {
+        use rustix::fs::{Mode, OFlags};
+        use std::os::unix::fs::MetadataExt;
+
+        let flags = if create {
+            OFlags::WRONLY | OFlags::APPEND | OFlags::CREATE
+        } else {
+            OFlags::RDONLY
+        };
+        let file = File::from(rustix::fs::openat(
+            &self.dir,
+            name,
+            flags | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+            Mode::from_raw_mode(0o600),
+        )?);
+        let meta = file.metadata()?;
+        if !meta.is_file() || !is_owned_by_current_user(&meta) || meta.mode() & 0o022 != 0 {
+            return Err(not_private("file"));
+        }
+        Ok(file)
+    }
+
+    fn create_new(&self, name: &OsStr) -> io::Result<File> {
+        use rustix::fs::{Mode, OFlags};
+
+        self.remove(name).or_else(|e| match e.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        })?;
+        Ok(File::from(rustix::fs::openat(
+            &self.dir,
+            name,
+            OFlags::WRONLY
+                | OFlags::APPEND
+                | OFlags::CREATE
+                | OFlags::EXCL
+                | OFlags::NOFOLLOW
+                | OFlags::CLOEXEC,
+            Mode::from_raw_mode(0o600),
+        )?))
+    }
+
+    fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        Ok(rustix::fs::renameat(&self.dir, from, &self.dir, to)?)
+    }
+
+    fn remove(&self, name: &OsStr) -> io::Result<()> {
+        Ok(rustix::fs::unlinkat(
+            &self.dir,
+            name,
+            rustix::fs::AtFlags::empty(),
+        )?)
+    }
+}
+
+#[cfg(not(unix))]
+impl ManifestDir {
+    fn open_parent(path: &Path) -> io::Result<(ManifestDir, OsString)> {
+        let (parent, name) = split_path(path)?;
+        Ok((
+            ManifestDir {
+                dir: parent.to_owned(),
+            },
+            name.to_owned(),
+        ))
+    }
+
+    /// Opens the manifest for appending (creating it if `create` is set) or for reading.
+    fn open(&self, name: &OsStr, create: bool) -> io::Result<File> {
+        let mut options = std::fs::OpenOptions::new();
+        if create {
+            options.create(true).append(true);
+        } else {
+            options.read(true);
+        }
+        // Open symbolic links themselves, so they can be rejected below.
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            options.custom_flags(
+                windows_sys::Win32::Storage::FileSystem::FILE_FLAG_OPEN_REPARSE_POINT,
+            );
+        }
+        let file = options.open(self.dir.join(name))?;
+        if !file.metadata()?.is_file() {
+            return Err(not_private("file"));
+        }
+        Ok(file)
+    }
+
+    fn create_new(&self, name: &OsStr) -> io::Result<File> {
+        self.remove(name).or_else(|e| match e.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        })?;
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(self.dir.join(name))
+    }
+
+    fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        std::fs::rename(self.dir.join(from), self.dir.join(to))
+    }
+
+    fn remove(&self, name: &OsStr) -> io::Result<()> {
+        std::fs::remove_file(self.dir.join(name))
+    }
+}
+
+// Paths on unix may contain newlines, so escape them (and the escape character itself).
+#[cfg(unix)]
+pub(crate) fn encode_line(encoded: &std::ffi::OsStr) -> io::Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    let mut line = Vec::with_capacity(encoded.len());
+    for &b in encoded.as_bytes() {
+        match b {
+            b'\\' => line.extend_from_slice(b"\\\\"),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            b => line.push(b),
+        }
+    }
+    Ok(line)
+}
+
+#[cfg(unix)]
+pub(crate) fn decode_line(line: &[u8]) -> Option<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+    let mut decoded = Vec::with_capacity(line.len());
+    let mut bytes = line.iter();
+    while let Some(&b) = bytes.next() {
+        decoded.push(match b {
+            b'\\' => match bytes.next()? {
+                b'\\' => b'\\',
+                b'n' => b'\n',
+                _ => return None,
+            },
+            b => b,
+        });
+    }
+    Some(OsString::from_vec(decoded))
+}
+
+// Paths on the remaining platforms can't contain newlines.
+#[cfg(not(unix))]
+pub(crate) fn encode_line(encoded: &std::ffi::OsStr) -> io::Result<Vec<u8>> {
+    encoded
+        .to_str()
+        .map(|s| s.as_bytes().to_vec())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "temporary path can't be recorded in the manifest",
+            )
+        })
+}
+
+#[cfg(not(unix))]
+pub(crate) fn decode_line(line: &[u8]) -> Option<OsString> {
+    std::str::from_utf8(line).ok().map(OsString::from)
+}
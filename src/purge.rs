@@ -0,0 +1,100 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::error::IoResultExt;
+
+/// Deletes abandoned temporary files and directories created by this crate directly inside `dir`
+/// that haven't been modified for at least `age`, and returns how many were deleted.
+///
+/// This lets long-running services periodically reclaim space from scratch data left behind by
+/// crashed processes. To avoid deleting anything else, an entry is only deleted if:
+///
+/// - its name follows this crate's default naming scheme (the `.tmp` prefix, followed by at least
+///   [the default number](crate::Builder::rand_bytes) of random alphanumeric characters, and no
+///   suffix), so files created with a custom prefix or suffix are never deleted;
+/// - on Unix, it's owned by the current (effective) user;
+/// - its own modification time (symbolic links aren't followed) is at least `age` in the past.
+///
+/// Directories are deleted with all their contents. Note that an entry still in use by another
+/// process may be deleted if it hasn't been modified for `age`, so pick an age well above the
+/// lifetime of your temporary files.
+///
+/// # Errors
+///
+/// If `dir` can't be read or a matching entry can't be deleted, `Err` is returned. Entries deleted
+/// up to that point stay deleted.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// // Reclaim anything left behind in the temporary directory over a day ago.
+/// tempfile::purge_older_than(tempfile::env::temp_dir(), Duration::from_secs(24 * 60 * 60))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn purge_older_than<P: AsRef<Path>>(dir: P, age: Duration) -> io::Result<usize> {
+    let dir = dir.as_ref();
+    let now = SystemTime::now();
+    let mut purged = 0;
+    for entry in fs::read_dir(dir).with_err_path(|| dir)? {
+        let entry = entry.with_err_path(|| dir)?;
+        if !is_default_name(&entry.file_name()) {
+            continue;
+        }
+        let path = entry.path();
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            // Deleted in the meantime (e.g., by its owner).
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_err_path(|| path),
+        };
+        if !is_owned(&meta) {
+            continue;
+        }
+        // Entries modified "in the future" (e.g., because the clock changed) are kept.
+        let stale = match now.duration_since(meta.modified().with_err_path(|| &path)?) {
+            Ok(elapsed) => elapsed >= age,
+            Err(_) => false,
+        };
+        if !stale {
+            continue;
+        }
+        match if meta.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        } {
+            Ok(()) => purged += 1,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_err_path(|| path),
+        }
+    }
+    Ok(purged)
+}
+
+/// Returns `true` if `name` is `.tmp` followed by (at least the default number of) alphanumeric
+/// characters.
+fn is_default_name(name: &OsStr) -> bool {
+    match name.to_str().and_then(|name| name.strip_prefix(".tmp")) {
+        Some(rest) => {
+            rest.len() >= crate::NUM_RAND_CHARS && rest.bytes().all(|b| b.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
+
+#[cfg(unix)]
+fn is_owned(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.uid() == rustix::process::geteuid().as_raw()
+}
+
+#[cfg(not(unix))]
+fn is_owned(_meta: &fs::Metadata) -> bool {
+    // The temporary directory is per-user on Windows.
+    true
+}
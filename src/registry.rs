@@ -0,0 +1,287 @@
+//! A per-user, on-disk registry of long-lived temporary files and directories, so they can be
+//! re-adopted (or deleted) after an unclean restart (see [`reclaim`]).
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::IoResultExt;
+use crate::token::{Kind, OwnershipToken};
+use crate::{dir, env, manifest, Builder};
+
+/// A temporary file or directory left behind by a process that exited without cleaning it up,
+/// returned by [`reclaim`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reclaimed {
+    name: String,
+    pid: Option<u32>,
+    token: OwnershipToken,
+}
+
+impl Reclaimed {
+    /// Returns the name the file or directory was registered under.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the id of the (no longer running) process that registered the file or directory,
+    /// if the platform has process ids.
+    #[must_use]
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Returns the path of the file or directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.token.path()
+    }
+
+    /// Returns `true` if a directory was registered.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.token.is_dir()
+    }
+
+    /// Returns the token to re-adopt the file or directory with
+    /// [`TempPath::adopt`](crate::TempPath::adopt)/[`TempDir::adopt`](crate::TempDir::adopt), or to
+    /// delete it with [`OwnershipToken::remove`].
+    #[must_use]
+    pub fn into_token(self) -> OwnershipToken {
+        self.token
+    }
+}
+
+/// Registers the temporary file or directory at `path` under `name` (see
+/// [`TempDir::register`](crate::TempDir::register)).
+pub(crate) fn register(name: &str, kind: Kind, path: &Path) -> io::Result<()> {
+    check_name(name)?;
+    let path = if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    let root = root()?;
+    let entry = root.join(name);
+    if let Some((Some(pid), _)) = read_entry(&entry)? {
+        if Some(pid) != crate::util::process_id() && is_running(pid) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "name is registered by another running process",
+            ))
+            .with_err_path(|| entry);
+        }
+    }
+
+    let mut contents = match crate::util::process_id() {
+        Some(pid) => pid.to_string().into_bytes(),
+        None => Vec::new(),
+    };
+    contents.push(b'\n');
+    contents.extend(manifest::encode_line(
+        &OwnershipToken::new(kind, path).encode(),
+    )?);
+    contents.push(b'\n');
+    // Replace the entry atomically, so `reclaim` never sees a partial one.
+    let mut file = Builder::new().prefix(".tmp").tempfile_in(&root)?;
+    file.write_all(&contents).with_err_path(|| file.path())?;
+    file.persist(&entry)?;
+    Ok(())
+}
+
+/// Removes the registration `name` made by this process with
+/// [`TempDir::register`](crate::TempDir::register) or
+/// [`TempPath::register`](crate::TempPath::register), returning `true` if there was one.
+///
+/// Call this once a registered temporary file or directory has been cleaned up (registrations of
+/// files and directories that no longer exist are also dropped by [`reclaim`]). Registrations made
+/// by other processes are left in place.
+///
+/// # Errors
+///
+/// If the registry can't be read, or the registration can't be removed, `Err` is returned.
+pub fn unregister(name: &str) -> io::Result<bool> {
+    check_name(name)?;
+    let entry = root()?.join(name);
+    match read_entry(&entry)? {
+        Some((pid, _)) if pid == crate::util::process_id() => {
+            fs::remove_file(&entry).with_err_path(|| &entry)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Reclaim the temporary files and directories registered (with
+/// [`TempDir::register`](crate::TempDir::register) or
+/// [`TempPath::register`](crate::TempPath::register)) by processes that are no longer running,
+/// e.g., because they crashed, or the system was restarted.
+///
+/// Each returned [`Reclaimed`] entry is removed from the registry, and can either be re-adopted
+/// (to continue using it) or deleted through its [token](Reclaimed::into_token). Registrations of
+/// files and directories that no longer exist are dropped silently, and registrations of running
+/// processes (including this one) are left alone.
+///
+/// The registry lives in the temporary directory and is shared by all applications of the current
+/// user, so prefix names with the application's name and ignore (or delete) entries you don't
+/// recognize. Process ids can be reused, so an entry of a crashed process may only be reclaimed
+/// once the process that reused its id has exited too.
+///
+/// # Errors
+///
+/// If the registry can't be read or an entry can't be removed, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::TempDir;
+///
+/// for leftover in tempfile::reclaim()? {
+///     if leftover.name() == "myapp-render-cache" && leftover.is_dir() {
+///         // Pick up where the previous run left off.
+///         let _cache = TempDir::adopt(leftover.into_token())?;
+///         // ...
+///     } else if leftover.name().starts_with("myapp-") {
+///         leftover.into_token().remove()?;
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn reclaim() -> io::Result<Vec<Reclaimed>> {
+    let root = root()?;
+    let mut reclaimed = Vec::new();
+    for entry in fs::read_dir(&root).with_err_path(|| &root)? {
+        let entry = entry.with_err_path(|| &root)?;
+        let name = match entry.file_name().into_string() {
+            Ok(name) if check_name(&name).is_ok() => name,
+            _ => continue,
+        };
+        let path = entry.path();
+        let (pid, token) = match read_entry(&path)? {
+            Some(contents) => contents,
+            None => continue,
+        };
+        if pid.map_or(false, is_running) {
+            continue;
+        }
+        // Whoever removes the entry gets to reclaim it.
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_err_path(|| path),
+        }
+        let token = match token {
+            Some(token) => token,
+            None => continue,
+        };
+        let exists = token
+            .path()
+            .symlink_metadata()
+            .map_or(false, |meta| meta.is_dir() == token.is_dir());
+        if exists {
+            reclaimed.push(Reclaimed { name, pid, token });
+        }
+    }
+    reclaimed.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(reclaimed)
+}
+
+/// Reads the registry entry at `path`, returning `None` if it doesn't exist. The token is `None`
+/// if the entry is corrupt.
+#[allow(clippy::type_complexity)]
+fn read_entry(path: &Path) -> io::Result<Option<(Option<u32>, Option<OwnershipToken>)>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_err_path(|| path),
+    };
+    let mut lines = contents.split(|&b| b == b'\n');
+    let pid = lines
+        .next()
+        .and_then(|pid| std::str::from_utf8(pid).ok())
+        .and_then(|pid| pid.parse().ok());
+    let token = lines
+        .next()
+        .and_then(manifest::decode_line)
+        .and_then(|encoded| OwnershipToken::decode(&encoded).ok());
+    Ok(Some((pid, token)))
+}
+
+/// Registry names are used as file names, so only allow a safe subset.
+fn check_name(name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid registry name {:?}: only ASCII letters, digits, `.`, `_`, and `-` are \
+                 allowed, and it can't start with `.`",
+                name
+            ),
+        ))
+    }
+}
+
+/// Returns the directory holding the current user's registry, creating it if necessary.
+#[cfg(unix)]
+fn root() -> io::Result<PathBuf> {
+    // Like workspaces, keep the registry out of reach of other users of the temporary directory.
+    let uid = rustix::process::geteuid().as_raw();
+    let root = env::temp_dir().join(format!("tempfile-registry-{}", uid));
+    dir::create_private(&root, uid)?;
+    Ok(root)
+}
+
+/// Returns the directory holding the current user's registry, creating it if necessary.
+#[cfg(not(unix))]
+fn root() -> io::Result<PathBuf> {
+    let root = env::temp_dir().join("tempfile-registry");
+    dir::create_all(&root, None)?;
+    Ok(root)
+}
+
+/// Returns `true` if the process `pid` is (probably) still running.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    use rustix::io::Errno;
+    use rustix::process::{test_kill_process, Pid};
+
+    let pid = match i32::try_from(pid).ok().and_then(Pid::from_raw) {
+        Some(pid) => pid,
+        None => return false,
+    };
+    // Signal 0 only checks whether the process exists. We may not be allowed to signal it, but it
+    // exists in that case too.
+    !matches!(test_kill_process(pid), Err(Errno::SRCH))
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle as usize == 0 {
+        // The process exists, we're just not allowed to look at it.
+        return io::Error::last_os_error().raw_os_error() == Some(ERROR_ACCESS_DENIED as i32);
+    }
+    let mut code = 0;
+    let ok = unsafe { GetExitCodeProcess(handle, &mut code) } != 0;
+    unsafe { CloseHandle(handle) };
+    ok && code == STILL_ACTIVE as u32
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: u32) -> bool {
+    // There are no process ids to record on these platforms anyway.
+    false
+}
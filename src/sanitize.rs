@@ -0,0 +1,95 @@
+use std::ffi::{OsStr, OsString};
+
+/// How [`sanitize_affix`] deals with characters that aren't allowed in file names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Sanitize {
+    /// Remove the offending characters.
+    Strip,
+    /// Replace each offending byte with `%XX` (its value in uppercase hexadecimal). `%` itself is
+    /// encoded too, so distinct inputs map to distinct outputs.
+    PercentEncode,
+}
+
+/// Make an untrusted string (e.g., the name of an uploaded file) safe to use as a
+/// [prefix](crate::Builder::prefix) or [suffix](crate::Builder::suffix) of a temporary file name.
+///
+/// Path separators and NUL bytes are always removed or encoded, so the resulting name can't escape
+/// the temporary directory. On Windows, the reserved characters `<>:"|?*` and control characters
+/// are handled the same way, and non-unicode input is converted lossily.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{sanitize_affix, Builder, Sanitize};
+///
+/// let upload_name = "../../etc/passwd";
+/// let prefix = sanitize_affix(upload_name, Sanitize::PercentEncode);
+/// assert_eq!(prefix, "..%2F..%2Fetc%2Fpasswd");
+///
+/// let tmp = Builder::new().prefix(&prefix).tempfile()?;
+/// assert_eq!(tmp.path().parent().unwrap(), tempfile::env::temp_dir());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[must_use]
+pub fn sanitize_affix<S: AsRef<OsStr>>(affix: S, mode: Sanitize) -> OsString {
+    imp::sanitize(affix.as_ref(), mode)
+}
+
+fn is_forbidden(b: u8) -> bool {
+    if cfg!(windows) {
+        b < 0x20
+            || matches!(
+                b,
+                b'/' | b'\\' | b'<' | b'>' | b':' | b'"' | b'|' | b'?' | b'*'
+            )
+    } else {
+        matches!(b, b'\0' | b'/')
+    }
+}
+
+fn push_sanitized(out: &mut Vec<u8>, b: u8, mode: Sanitize) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    match mode {
+        Sanitize::PercentEncode if is_forbidden(b) || b == b'%' => {
+            out.extend_from_slice(&[b'%', HEX[usize::from(b >> 4)], HEX[usize::from(b & 0xf)]])
+        }
+        Sanitize::Strip if is_forbidden(b) => {}
+        _ => out.push(b),
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::{OsStr, OsString};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    use super::{push_sanitized, Sanitize};
+
+    pub fn sanitize(affix: &OsStr, mode: Sanitize) -> OsString {
+        let mut out = Vec::with_capacity(affix.len());
+        for &b in affix.as_bytes() {
+            push_sanitized(&mut out, b, mode);
+        }
+        OsString::from_vec(out)
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::ffi::{OsStr, OsString};
+
+    use super::{push_sanitized, Sanitize};
+
+    pub fn sanitize(affix: &OsStr, mode: Sanitize) -> OsString {
+        let affix = affix.to_string_lossy();
+        let mut out = Vec::with_capacity(affix.len());
+        // Only ASCII bytes are ever removed or encoded, so the output remains valid UTF-8.
+        for &b in affix.as_bytes() {
+            push_sanitized(&mut out, b, mode);
+        }
+        String::from_utf8(out)
+            .expect("sanitizing only touches ASCII")
+            .into()
+    }
+}
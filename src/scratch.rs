@@ -0,0 +1,269 @@
+//! A size-capped scratch directory with least-recently-used eviction (see [`ScratchCache`]).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{Builder, NamedTempFile, TempDir};
+
+/// A temporary directory holding at most a fixed number of bytes of cached files, evicting the
+/// least recently used ones when it grows beyond that: the on-disk analog of the memory threshold
+/// of a [`SpooledTempFile`](crate::SpooledTempFile).
+///
+/// Files are inserted under a key (their file name in the directory) with
+/// [`ScratchCache::insert`], and looked up with [`ScratchCache::get`], which counts as a use. The
+/// cache only tracks the files inserted through it, and their size when they were inserted, so
+/// don't modify them afterwards.
+///
+/// Like a [`TempDir`], the directory (and everything in it) is deleted when the `ScratchCache` is
+/// dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use tempfile::ScratchCache;
+///
+/// let mut cache = ScratchCache::new(8)?;
+///
+/// let mut file = cache.tempfile()?;
+/// file.write_all(b"12345")?;
+/// cache.insert("a", file)?;
+///
+/// let mut file = cache.tempfile()?;
+/// file.write_all(b"67890")?;
+/// cache.insert("b", file)?;
+///
+/// // Both files don't fit, so the least recently used one was evicted.
+/// assert!(cache.get("a").is_none());
+/// assert!(cache.get("b").is_some());
+/// assert_eq!(cache.size(), 5);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ScratchCache {
+    dir: TempDir,
+    max_size: u64,
+    size: u64,
+    entries: HashMap<String, Entry>,
+    // Incremented on every use, to order entries by recency even if the clock doesn't move.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    size: u64,
+    last_used: u64,
+    accessed: SystemTime,
+}
+
+impl ScratchCache {
+    /// Creates a cache holding at most `max_size` bytes, in a new temporary directory in
+    /// [`env::temp_dir`](crate::env::temp_dir).
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be created, `Err` is returned.
+    pub fn new(max_size: u64) -> io::Result<ScratchCache> {
+        Ok(ScratchCache::with_dir(TempDir::new()?, max_size))
+    }
+
+    /// Creates a cache holding at most `max_size` bytes, in a new temporary directory in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be created, `Err` is returned.
+    pub fn new_in<P: AsRef<Path>>(dir: P, max_size: u64) -> io::Result<ScratchCache> {
+        Ok(ScratchCache::with_dir(TempDir::new_in(dir)?, max_size))
+    }
+
+    /// Creates a cache holding at most `max_size` bytes in the (empty) temporary directory `dir`,
+    /// which will be deleted when the cache is dropped.
+    #[must_use]
+    pub fn with_dir(dir: TempDir, max_size: u64) -> ScratchCache {
+        ScratchCache {
+            dir,
+            max_size,
+            size: 0,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the path of the cache directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Returns the maximum number of bytes held by the cache.
+    #[must_use]
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Returns the total size of the cached files, in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of cached files.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Creates a temporary file inside the cache directory, so it can be inserted without being
+    /// copied. It doesn't count towards the size of the cache until it's inserted.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be created, `Err` is returned.
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        Builder::new().prefix(".tmp").tempfile_in(self.dir.path())
+    }
+
+    /// Moves `file` into the cache under `key` (replacing any file already cached under that key),
+    /// and returns its new path. Least recently used files are then evicted until the cache fits
+    /// in its maximum size again.
+    ///
+    /// `file` is moved with a rename, so it must be on the same filesystem as the cache (e.g.,
+    /// created with [`ScratchCache::tempfile`]).
+    ///
+    /// # Errors
+    ///
+    /// If `key` isn't a file name (or starts with a `.`), or the file is larger than the whole
+    /// cache, an error of kind [`io::ErrorKind::InvalidInput`] is returned and the file is deleted.
+    /// If the file can't be moved into the cache, or an evicted file can't be deleted, `Err` is
+    /// returned as well.
+    pub fn insert(&mut self, key: &str, file: NamedTempFile) -> io::Result<PathBuf> {
+        if !is_valid_key(key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "scratch cache keys must be file names that don't start with `.`",
+            ))
+            .with_err_path(|| key);
+        }
+        let size = file
+            .as_file()
+            .metadata()
+            .with_err_path(|| file.path())?
+            .len();
+        if size > self.max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "file of {} bytes doesn't fit in a scratch cache of {} bytes",
+                    size, self.max_size
+                ),
+            ))
+            .with_err_path(|| file.path());
+        }
+
+        let path = self.dir.path().join(key);
+        file.persist(&path)?;
+        if let Some(old) = self.entries.remove(key) {
+            self.size -= old.size;
+        }
+        self.size += size;
+        self.clock += 1;
+        self.entries.insert(
+            key.to_owned(),
+            Entry {
+                size,
+                last_used: self.clock,
+                accessed: SystemTime::now(),
+            },
+        );
+        self.evict()?;
+        Ok(path)
+    }
+
+    /// Returns the path of the file cached under `key`, if any, and marks it as used.
+    pub fn get(&mut self, key: &str) -> Option<PathBuf> {
+        let entry = self.entries.get_mut(key)?;
+        self.clock += 1;
+        entry.last_used = self.clock;
+        entry.accessed = SystemTime::now();
+        Some(self.dir.path().join(key))
+    }
+
+    /// Returns when the file cached under `key` was last inserted or returned by
+    /// [`ScratchCache::get`], if it's cached.
+    #[must_use]
+    pub fn last_accessed(&self, key: &str) -> Option<SystemTime> {
+        self.entries.get(key).map(|entry| entry.accessed)
+    }
+
+    /// Deletes the file cached under `key`, returning `true` if there was one.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be deleted, `Err` is returned (and the file is forgotten anyway).
+    pub fn remove(&mut self, key: &str) -> io::Result<bool> {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.size -= entry.size;
+                self.delete(key)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Evicts least recently used files until the cache fits in its maximum size.
+    fn evict(&mut self) -> io::Result<()> {
+        while self.size > self.max_size {
+            let key = match self.entries.iter().min_by_key(|(_, entry)| entry.last_used) {
+                Some((key, _)) => key.clone(),
+                None => break,
+            };
+            self.remove(&key)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        let path = self.dir.path().join(key);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e).with_err_path(|| path),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ScratchCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScratchCache")
+            .field("path", &Redact(self.dir.path()))
+            .field("max_size", &self.max_size)
+            .field("size", &self.size)
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+impl AsRef<Path> for ScratchCache {
+    fn as_ref(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut components = Path::new(key).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(name)), None) if name == key && !key.starts_with('.')
+    )
+}
@@ -1,28 +1,209 @@
-use crate::file::tempfile;
-use crate::tempfile_in;
-use std::fs::File;
+use crate::budget::Charge;
+use crate::file::{self, tempfile};
+use crate::util::Redact;
+use crate::{tempfile_in, util, Builder, NamedTempFile};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions, Permissions};
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::{error, fmt, mem, panic, thread};
 
 /// A wrapper for the two states of a [`SpooledTempFile`]. Either:
 ///
 /// 1. An in-memory [`Cursor`] representing the state of the file.
 /// 2. A temporary [`File`].
-#[derive(Debug)]
 pub enum SpooledData {
     InMemory(Cursor<Vec<u8>>),
     OnDisk(File),
 }
 
+impl fmt::Debug for SpooledData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // On some platforms, the `Debug` output of a `File` includes its path.
+        match self {
+            // The data itself is never printed, even without redaction.
+            SpooledData::InMemory(cursor) => f
+                .debug_struct("InMemory")
+                .field("len", &cursor.get_ref().len())
+                .field("position", &cursor.position())
+                .finish(),
+            SpooledData::OnDisk(file) => f.debug_tuple("OnDisk").field(&Redact(file)).finish(),
+        }
+    }
+}
+
 /// An object that behaves like a regular temporary file, but keeps data in
 /// memory until it reaches a configured size, at which point the data is
 /// written to a temporary file on disk, and further operations use the file
 /// on disk.
-#[derive(Debug)]
 pub struct SpooledTempFile {
     max_size: usize,
+    max_total: Option<u64>,
     dir: Option<PathBuf>,
+    settings: Option<RollSettings>,
+    background: bool,
+    pending: Option<PendingSpill>,
     inner: SpooledData,
+    charge: Charge,
+    #[cfg(feature = "metrics")]
+    accounted: metrics::Accounted,
+}
+
+impl fmt::Debug for SpooledTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("SpooledTempFile");
+        f.field("max_size", &self.max_size)
+            .field("max_total", &self.max_total)
+            .field("dir", &Redact(&self.dir))
+            .field("settings", &self.settings)
+            .field("background", &self.background)
+            .field("pending", &self.pending)
+            .field("inner", &self.inner)
+            .field("charge", &self.charge);
+        #[cfg(feature = "metrics")]
+        f.field("accounted", &self.accounted);
+        f.finish()
+    }
+}
+
+type SpillResult = Result<File, (io::Error, Cursor<Vec<u8>>)>;
+
+/// A rollover in progress on a background thread, see [`SpooledTempFile::spill_in_background`].
+struct PendingSpill {
+    handle: thread::JoinHandle<SpillResult>,
+    /// The position the data is written at once the rollover completes.
+    pos: u64,
+    /// The length of the data being written out.
+    len: u64,
+    /// Data written while the rollover is in progress.
+    overflow: Vec<u8>,
+}
+
+impl fmt::Debug for PendingSpill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingSpill")
+            .field("handle", &self.handle)
+            .field("pos", &self.pos)
+            .field("len", &self.len)
+            .field("overflow_len", &self.overflow.len())
+            .finish()
+    }
+}
+
+/// The error returned when a write would grow a [`SpooledTempFile`] beyond the limit passed to
+/// [`SpooledTempFile::with_limits`].
+///
+/// It's returned wrapped in an [`io::Error`] (of kind [`io::ErrorKind::Other`]), use
+/// [`io::Error::get_ref`] and `downcast_ref` to detect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimitExceeded {
+    limit: u64,
+}
+
+impl SizeLimitExceeded {
+    /// Returns the maximum size of the file, in bytes.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl fmt::Display for SizeLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "spooled temporary file would exceed its size limit of {} bytes",
+            self.limit
+        )
+    }
+}
+
+impl error::Error for SizeLimitExceeded {}
+
+impl From<SizeLimitExceeded> for io::Error {
+    fn from(err: SizeLimitExceeded) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+/// The [`Builder`] settings used to create the file on disk, see [`Builder::spooled`].
+#[derive(Clone)]
+struct RollSettings {
+    prefix: OsString,
+    suffix: OsString,
+    random_len: usize,
+    #[cfg(feature = "uuid")]
+    uuid: bool,
+    pid: bool,
+    timestamp: Option<util::TimestampFormat>,
+    sequence: bool,
+    adaptive: bool,
+    fit_name_length: bool,
+    permissions: Option<Permissions>,
+    default_dir: crate::DefaultDir,
+}
+
+impl fmt::Debug for RollSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RollSettings")
+            .field("prefix", &Redact(&self.prefix))
+            .field("suffix", &Redact(&self.suffix))
+            .field("random_len", &self.random_len)
+            .field("permissions", &self.permissions)
+            .field("default_dir", &self.default_dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RollSettings {
+    fn new(builder: &Builder<'_, '_>) -> Self {
+        let spec = builder.name_spec();
+        RollSettings {
+            prefix: spec.prefix.to_owned(),
+            suffix: spec.suffix.to_owned(),
+            random_len: spec.random_len,
+            #[cfg(feature = "uuid")]
+            uuid: spec.uuid,
+            pid: spec.pid,
+            timestamp: spec.timestamp,
+            sequence: spec.sequence,
+            adaptive: spec.adaptive,
+            fit_name_length: spec.fit_name_length,
+            permissions: builder.resolved_permissions().cloned(),
+            default_dir: builder.default_dir.clone(),
+        }
+    }
+
+    /// Creates a named temporary file in `dir` and immediately unlinks it.
+    fn create(&self, dir: &Path) -> io::Result<File> {
+        let spec = util::NameSpec {
+            prefix: &self.prefix,
+            suffix: &self.suffix,
+            random_len: self.random_len,
+            #[cfg(feature = "uuid")]
+            uuid: self.uuid,
+            pid: self.pid,
+            timestamp: self.timestamp,
+            sequence: self.sequence,
+            adaptive: self.adaptive,
+            fit_name_length: self.fit_name_length,
+            // The file is unlinked right away, so its path is never exposed.
+            ascii_only: false,
+        };
+        util::create_helper(dir, &spec, |path| {
+            let mut options = OpenOptions::new();
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::OpenOptionsExt;
+                options
+                    .attributes(windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_TEMPORARY);
+            }
+            file::create_named(path, &mut options, self.permissions.as_ref(), false)
+        })
+        .map(NamedTempFile::into_file)
+    }
 }
 
 /// Create a new [`SpooledTempFile`]. Also see [`spooled_tempfile_in`].
@@ -81,10 +262,16 @@ pub fn spooled_tempfile_in<P: AsRef<Path>>(max_size: usize, dir: P) -> SpooledTe
 }
 
 /// Write a cursor into a temporary file, returning the temporary file.
-fn cursor_to_tempfile(cursor: &Cursor<Vec<u8>>, p: &Option<PathBuf>) -> io::Result<File> {
-    let mut file = match p {
-        Some(p) => tempfile_in(p)?,
-        None => tempfile()?,
+fn cursor_to_tempfile(
+    cursor: &Cursor<Vec<u8>>,
+    p: &Option<PathBuf>,
+    settings: &Option<RollSettings>,
+) -> io::Result<File> {
+    let mut file = match (p, settings) {
+        (Some(p), Some(settings)) => settings.create(p)?,
+        (None, Some(settings)) => settings.create(&settings.default_dir.resolve()?)?,
+        (Some(p), None) => tempfile_in(p)?,
+        (None, None) => tempfile()?,
     };
     file.write_all(cursor.get_ref())?;
     file.seek(SeekFrom::Start(cursor.position()))?;
@@ -97,8 +284,15 @@ impl SpooledTempFile {
     pub fn new(max_size: usize) -> SpooledTempFile {
         SpooledTempFile {
             max_size,
+            max_total: None,
             dir: None,
+            settings: None,
+            background: false,
+            pending: None,
             inner: SpooledData::InMemory(Cursor::new(Vec::new())),
+            charge: Charge::default(),
+            #[cfg(feature = "metrics")]
+            accounted: Default::default(),
         }
     }
 
@@ -107,8 +301,84 @@ impl SpooledTempFile {
     pub fn new_in<P: AsRef<Path>>(max_size: usize, dir: P) -> SpooledTempFile {
         SpooledTempFile {
             max_size,
+            max_total: None,
             dir: Some(dir.as_ref().to_owned()),
+            settings: None,
+            background: false,
+            pending: None,
+            inner: SpooledData::InMemory(Cursor::new(Vec::new())),
+            charge: Charge::default(),
+            #[cfg(feature = "metrics")]
+            accounted: Default::default(),
+        }
+    }
+
+    /// Construct a new [`SpooledTempFile`] that rolls over to disk once it exceeds `mem_threshold`
+    /// bytes, and never grows beyond `max_total` bytes.
+    ///
+    /// Writes (and calls to [`SpooledTempFile::set_len`]) that would grow the file beyond
+    /// `max_total` fail without writing anything, with an [`io::Error`] wrapping
+    /// [`SizeLimitExceeded`]. This makes it easy to, e.g., enforce a maximum request size when
+    /// buffering uploads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::{SizeLimitExceeded, SpooledTempFile};
+    ///
+    /// let mut upload = SpooledTempFile::with_limits(1024, 4096);
+    /// upload.write_all(&[0; 4096])?;
+    ///
+    /// let err = upload.write_all(b"one byte too many").unwrap_err();
+    /// let limit = err.get_ref().and_then(|e| e.downcast_ref::<SizeLimitExceeded>());
+    /// assert_eq!(limit.map(|l| l.limit()), Some(4096));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn with_limits(mem_threshold: usize, max_total: u64) -> SpooledTempFile {
+        SpooledTempFile {
+            max_total: Some(max_total),
+            ..SpooledTempFile::new(mem_threshold)
+        }
+    }
+
+    /// Fails if the file would grow beyond its size limit when writing `len` bytes at the current
+    /// position.
+    fn check_limit(&mut self, len: u64) -> io::Result<()> {
+        let limit = match self.max_total {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let position = match (&self.pending, &mut self.inner) {
+            (Some(pending), _) => pending.pos + pending.overflow.len() as u64,
+            (None, SpooledData::InMemory(cursor)) => cursor.position(),
+            (None, SpooledData::OnDisk(file)) => file.stream_position()?,
+        };
+        if position.saturating_add(len) > limit {
+            return Err(SizeLimitExceeded { limit }.into());
+        }
+        Ok(())
+    }
+
+    /// Construct a new [`SpooledTempFile`] whose file on disk is created with the settings of
+    /// `builder`, see [`Builder::spooled`].
+    pub(crate) fn with_builder(
+        max_size: usize,
+        dir: Option<&Path>,
+        builder: &Builder<'_, '_>,
+    ) -> SpooledTempFile {
+        SpooledTempFile {
+            max_size,
+            max_total: None,
+            dir: dir.map(Path::to_owned),
+            settings: Some(RollSettings::new(builder)),
+            background: false,
+            pending: None,
             inner: SpooledData::InMemory(Cursor::new(Vec::new())),
+            charge: Charge::default(),
+            #[cfg(feature = "metrics")]
+            accounted: Default::default(),
         }
     }
 
@@ -116,104 +386,644 @@ impl SpooledTempFile {
     #[must_use]
     pub fn is_rolled(&self) -> bool {
         match self.inner {
-            SpooledData::InMemory(_) => false,
+            SpooledData::InMemory(_) => self.pending.is_some(),
             SpooledData::OnDisk(_) => true,
         }
     }
 
+    /// Roll over to disk on a background thread when the data grows beyond the in-memory size
+    /// limit, so that the write crossing the limit doesn't block on writing out the buffered data.
+    ///
+    /// Writes made while the rollover is in progress are buffered in memory and written out once
+    /// it completes. All other operations (reading, seeking, etc.) wait for the rollover to
+    /// complete first. If the rollover fails, the data is kept in memory and the error is
+    /// returned by the next operation.
+    ///
+    /// If a background thread can't be spawned, the file is rolled over synchronously.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::SpooledTempFile;
+    ///
+    /// let mut file = SpooledTempFile::new(4 * 1024 * 1024);
+    /// file.spill_in_background(true);
+    ///
+    /// // Returns without waiting for the 4 MiB in memory to be written to disk.
+    /// file.write_all(&[0; 4 * 1024 * 1024 + 1])?;
+    /// assert!(file.is_rolled());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn spill_in_background(&mut self, background: bool) -> &mut Self {
+        self.background = background;
+        self
+    }
+
+    /// Rolls over to disk, on a background thread if requested.
+    fn spill(&mut self) -> io::Result<()> {
+        if !self.background {
+            return self.roll();
+        }
+        let cursor = match &mut self.inner {
+            SpooledData::InMemory(cursor) => mem::take(cursor),
+            SpooledData::OnDisk(_) => return Ok(()),
+        };
+        let pos = cursor.position();
+        let len = cursor.get_ref().len() as u64;
+        let (dir, settings) = (self.dir.clone(), self.settings.clone());
+        // Hand the data over through a slot so that we get it back if we can't spawn a thread.
+        let slot = Arc::new(Mutex::new(Some(cursor)));
+        let thread_slot = slot.clone();
+        // Charged up front, and refunded if the rollover fails.
+        let spawned = self.charge.resize(len, || {
+            thread::Builder::new()
+                .name("tempfile-spill".into())
+                .spawn(move || {
+                    let cursor = thread_slot
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .take()
+                        .unwrap_or_default();
+                    cursor_to_tempfile(&cursor, &dir, &settings).map_err(|e| (e, cursor))
+                })
+        });
+        match spawned {
+            Ok(handle) => {
+                crate::telemetry::spilled(len);
+                #[cfg(feature = "metrics")]
+                metrics::record_spill();
+                self.pending = Some(PendingSpill {
+                    handle,
+                    pos,
+                    len,
+                    overflow: Vec::new(),
+                });
+                Ok(())
+            }
+            Err(_) => {
+                let cursor = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+                self.inner = SpooledData::InMemory(cursor.unwrap_or_default());
+                self.roll()
+            }
+        }
+    }
+
+    /// Buffers `bufs` if a background rollover is still in progress, returning the number of bytes
+    /// written. Otherwise, waits for the rollover to complete and returns `None`.
+    fn write_pending(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<Option<usize>> {
+        match &mut self.pending {
+            Some(pending) if !pending.handle.is_finished() => {
+                let len = pending.overflow.len();
+                for buf in bufs {
+                    pending.overflow.extend_from_slice(buf);
+                }
+                Ok(Some(pending.overflow.len() - len))
+            }
+            Some(_) => self.settle().map(|_| None),
+            None => Ok(None),
+        }
+    }
+
+    /// Waits for a background rollover to complete, if any.
+    fn settle(&mut self) -> io::Result<()> {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let result = match pending.handle.join() {
+            Ok(Ok(file)) => {
+                self.inner = SpooledData::OnDisk(file);
+                self.charge.seeked(Some(pending.pos));
+                match &mut self.inner {
+                    SpooledData::OnDisk(file) => self
+                        .charge
+                        .write_all(&pending.overflow, |buf| file.write(buf)),
+                    SpooledData::InMemory(_) => unreachable!(),
+                }
+            }
+            Ok(Err((e, mut cursor))) => {
+                self.charge.clear();
+                cursor.write_all(&pending.overflow)?;
+                self.inner = SpooledData::InMemory(cursor);
+                Err(e)
+            }
+            Err(panic) => panic::resume_unwind(panic),
+        };
+        #[cfg(feature = "metrics")]
+        self.account();
+        result
+    }
+
+    /// Returns the number of bytes of data currently held in memory.
+    ///
+    /// This is `0` once the file has been rolled over to disk (apart from data buffered while
+    /// [rolling over in the background](SpooledTempFile::spill_in_background)).
+    #[must_use]
+    pub fn bytes_in_memory(&self) -> u64 {
+        let pending = self
+            .pending
+            .as_ref()
+            .map_or(0, |p| p.len + p.overflow.len() as u64);
+        match &self.inner {
+            SpooledData::InMemory(cursor) => pending + cursor.get_ref().len() as u64,
+            SpooledData::OnDisk(_) => pending,
+        }
+    }
+
+    /// Returns the size of the file on disk, in bytes, or `0` if the data is still in memory.
+    ///
+    /// # Errors
+    ///
+    /// If the size of the file on disk can't be determined, `Err` is returned.
+    pub fn bytes_on_disk(&self) -> io::Result<u64> {
+        match &self.inner {
+            SpooledData::InMemory(_) => Ok(0),
+            SpooledData::OnDisk(file) => file.metadata().map(|m| m.len()),
+        }
+    }
+
+    /// Updates the global metrics with the current usage of this file.
+    #[cfg(feature = "metrics")]
+    fn account(&mut self) {
+        let memory = self.bytes_in_memory();
+        let disk = self.bytes_on_disk().unwrap_or(self.accounted.disk);
+        self.accounted.update(memory, disk);
+        // As long as we're writing at the end of the file, writes can be accounted for without
+        // querying the size of the file each time.
+        self.accounted.appending = match (&self.pending, &mut self.inner) {
+            (None, SpooledData::OnDisk(file)) => file.stream_position().ok() == Some(disk),
+            _ => false,
+        };
+    }
+
+    /// Forgets whether the file on disk is positioned at its end, e.g., because it's being read or
+    /// seeked. The next write queries the size of the file again.
+    #[cfg(feature = "metrics")]
+    fn forget_position(&mut self) {
+        self.accounted.appending = false;
+    }
+
+    /// Updates the global metrics after a write.
+    #[cfg(feature = "metrics")]
+    fn account_write(&mut self, result: &io::Result<usize>) {
+        match result {
+            Ok(n) if self.accounted.appending && matches!(self.inner, SpooledData::OnDisk(_)) => {
+                let disk = self.accounted.disk + *n as u64;
+                self.accounted.update(0, disk);
+            }
+            _ => self.account(),
+        }
+    }
+
+    /// Returns an independent, read-only view of the data written so far, starting at position 0.
+    ///
+    /// The snapshot can be read (and seeked) on its own while this file keeps being written to,
+    /// e.g., to stream what has been written so far to another component. It's limited to the
+    /// current length of the file, so data appended later isn't visible.
+    ///
+    /// While the data is in memory, the snapshot is a copy. Once the file has been rolled over to
+    /// disk, the snapshot shares the file instead, so data _overwritten_ within the snapshot's
+    /// range after it has been taken may be visible.
+    ///
+    /// # Errors
+    ///
+    /// If the file has been rolled over to disk and can't be shared, `Err` is returned. Sharing the
+    /// file isn't supported on platforms other than Unix and Windows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use tempfile::spooled_tempfile;
+    ///
+    /// let mut file = spooled_tempfile(15);
+    /// file.write_all(b"first")?;
+    ///
+    /// let mut snapshot = file.snapshot()?;
+    /// file.write_all(b", second")?;
+    ///
+    /// let mut contents = String::new();
+    /// snapshot.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "first");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn snapshot(&mut self) -> io::Result<SpooledSnapshot> {
+        self.settle()?;
+        let (data, len) = match &mut self.inner {
+            SpooledData::InMemory(cursor) => {
+                let data = cursor.get_ref().clone();
+                let len = data.len() as u64;
+                (SnapshotData::InMemory(data), len)
+            }
+            SpooledData::OnDisk(file) => (
+                SnapshotData::OnDisk(share_file(file)?),
+                file.metadata()?.len(),
+            ),
+        };
+        Ok(SpooledSnapshot { data, len, pos: 0 })
+    }
+
+    /// Returns the contents of the file if it's still in memory, or `None` if it has been rolled
+    /// over to disk. This makes it possible to inspect the data without copying it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::spooled_tempfile;
+    ///
+    /// let mut file = spooled_tempfile(15);
+    /// file.write_all(b"hello")?;
+    /// assert_eq!(file.as_slice(), Some(&b"hello"[..]));
+    ///
+    /// file.roll()?;
+    /// assert_eq!(file.as_slice(), None);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.inner {
+            SpooledData::InMemory(_) if self.pending.is_some() => None,
+            SpooledData::InMemory(cursor) => Some(cursor.get_ref()),
+            SpooledData::OnDisk(_) => None,
+        }
+    }
+
+    /// Returns the contents of the file for modification in place if it's still in memory, or
+    /// `None` if it has been rolled over to disk.
+    ///
+    /// The returned slice can't change the size of the file, use [`SpooledTempFile::set_len`] for
+    /// that.
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        match &mut self.inner {
+            SpooledData::InMemory(_) if self.pending.is_some() => None,
+            SpooledData::InMemory(cursor) => Some(cursor.get_mut()),
+            SpooledData::OnDisk(_) => None,
+        }
+    }
+
     /// Rolls over to a file on disk, regardless of current size. Does nothing
     /// if already rolled over.
     pub fn roll(&mut self) -> io::Result<()> {
+        self.settle()?;
         if let SpooledData::InMemory(cursor) = &mut self.inner {
-            self.inner = SpooledData::OnDisk(cursor_to_tempfile(cursor, &self.dir)?);
+            let len = cursor.get_ref().len() as u64;
+            let file = self.charge.resize(len, || {
+                cursor_to_tempfile(cursor, &self.dir, &self.settings)
+            })?;
+            self.charge.seeked(Some(cursor.position()));
+            self.inner = SpooledData::OnDisk(file);
+            crate::telemetry::spilled(len);
+            #[cfg(feature = "metrics")]
+            {
+                metrics::record_spill();
+                self.account();
+            }
         }
         Ok(())
     }
 
     /// Truncate the file to the specified size.
     pub fn set_len(&mut self, size: u64) -> Result<(), io::Error> {
+        if let Some(limit) = self.max_total.filter(|&limit| size > limit) {
+            return Err(SizeLimitExceeded { limit }.into());
+        }
+        self.settle()?;
         if size > self.max_size as u64 {
             self.roll()?; // does nothing if already rolled over
         }
         match &mut self.inner {
-            SpooledData::InMemory(cursor) => {
-                cursor.get_mut().resize(size as usize, 0);
-                Ok(())
-            }
-            SpooledData::OnDisk(file) => file.set_len(size),
+            SpooledData::InMemory(cursor) => cursor.get_mut().resize(size as usize, 0),
+            SpooledData::OnDisk(file) => self.charge.resize(size, || file.set_len(size))?,
         }
+        #[cfg(feature = "metrics")]
+        self.account();
+        Ok(())
     }
 
     /// Consumes and returns the inner `SpooledData` type.
-    #[must_use]
-    pub fn into_inner(self) -> SpooledData {
-        self.inner
+    ///
+    /// # Errors
+    ///
+    /// If a [background rollover](SpooledTempFile::spill_in_background) is in progress, it's
+    /// waited for first. If it failed, or the data written while it was in progress couldn't be
+    /// written to disk, `Err` is returned.
+    pub fn into_inner(mut self) -> io::Result<SpooledData> {
+        self.settle()?;
+        Ok(self.inner)
     }
 
     /// Convert into a regular unnamed temporary file, writing it to disk if necessary.
-    pub fn into_file(self) -> io::Result<File> {
+    pub fn into_file(mut self) -> io::Result<File> {
+        self.settle()?;
         match self.inner {
-            SpooledData::InMemory(cursor) => cursor_to_tempfile(&cursor, &self.dir),
+            SpooledData::InMemory(cursor) => cursor_to_tempfile(&cursor, &self.dir, &self.settings),
             SpooledData::OnDisk(file) => Ok(file),
         }
     }
+
+    /// Convert into a [`Stdio`] reading the contents from the start, writing them to disk if
+    /// necessary. This makes it possible to, e.g., pipe buffered data into a child process's stdin.
+    ///
+    /// # Errors
+    ///
+    /// If the data can't be written to disk or the file can't be rewound, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use std::process::Command;
+    /// use tempfile::spooled_tempfile;
+    ///
+    /// let mut body = spooled_tempfile(64 * 1024);
+    /// body.write_all(b"request body")?;
+    ///
+    /// let output = Command::new("wc").arg("-c").stdin(body.into_stdio()?).output()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn into_stdio(self) -> io::Result<Stdio> {
+        let mut file = self.into_file()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Stdio::from(file))
+    }
+
+    /// Convert into a named temporary file created with `builder`, writing the contents to disk.
+    ///
+    /// The file is created in the directory passed to [`SpooledTempFile::new_in`], if any, and in
+    /// [`env::temp_dir()`](crate::env::temp_dir) otherwise. The current position is preserved. If
+    /// the data has already been rolled over to an unnamed file, it's copied into the new file.
+    ///
+    /// This makes it possible to pass the spooled data to APIs that need a real file or path.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or written to, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::{spooled_tempfile, Builder};
+    ///
+    /// let mut spooled = spooled_tempfile(1024);
+    /// spooled.write_all(b"report")?;
+    ///
+    /// let named = spooled.into_named(Builder::new().suffix(".txt"))?;
+    /// assert_eq!(std::fs::read(named.path())?, b"report");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn into_named(mut self, builder: &Builder<'_, '_>) -> io::Result<NamedTempFile> {
+        self.settle()?;
+        let mut named = match &self.dir {
+            Some(dir) => builder.tempfile_in(dir)?,
+            None => builder.tempfile()?,
+        };
+        match self.inner {
+            SpooledData::InMemory(cursor) => {
+                named.write_all(cursor.get_ref())?;
+                named.seek(SeekFrom::Start(cursor.position()))?;
+            }
+            SpooledData::OnDisk(mut file) => {
+                let pos = file.stream_position()?;
+                file.seek(SeekFrom::Start(0))?;
+                io::copy(&mut file, &mut named)?;
+                named.seek(SeekFrom::Start(pos))?;
+            }
+        }
+        Ok(named)
+    }
+
+    /// Like [`SpooledTempFile::into_file`], but creates the file on disk with `builder` (e.g., to
+    /// apply custom [permissions](Builder::permissions)) if the data is still in memory.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or written to, `Err` is returned.
+    pub fn into_file_with(mut self, builder: &Builder<'_, '_>) -> io::Result<File> {
+        self.settle()?;
+        match self.inner {
+            SpooledData::InMemory(_) => self.into_named(builder).map(NamedTempFile::into_file),
+            SpooledData::OnDisk(file) => Ok(file),
+        }
+    }
+}
+
+/// A read-only view of the contents of a [`SpooledTempFile`], see [`SpooledTempFile::snapshot`].
+#[derive(Debug)]
+pub struct SpooledSnapshot {
+    data: SnapshotData,
+    len: u64,
+    pos: u64,
+}
+
+enum SnapshotData {
+    InMemory(Vec<u8>),
+    OnDisk(File),
+}
+
+impl fmt::Debug for SnapshotData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotData::InMemory(data) => f
+                .debug_struct("InMemory")
+                .field("len", &data.len())
+                .finish(),
+            SnapshotData::OnDisk(file) => f.debug_tuple("OnDisk").field(&Redact(file)).finish(),
+        }
+    }
+}
+
+impl SpooledSnapshot {
+    /// Returns the length of the snapshot, in bytes.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the snapshot is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Read for SpooledSnapshot {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        if remaining == 0 {
+            return Ok(0);
+        }
+        let buf_len = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+        let buf = &mut buf[..buf_len];
+        let n = match &self.data {
+            SnapshotData::InMemory(data) => (&data[self.pos as usize..]).read(buf)?,
+            SnapshotData::OnDisk(file) => read_at(file, buf, self.pos)?,
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SpooledSnapshot {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (self.len, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
+/// Shares the (unnamed) temporary file without sharing its position.
+#[cfg(unix)]
+fn share_file(file: &File) -> io::Result<File> {
+    // Snapshots only use positional reads.
+    file.try_clone()
+}
+
+#[cfg(windows)]
+fn share_file(file: &File) -> io::Result<File> {
+    // Positional reads move the file pointer on Windows, so use a separate handle.
+    file::imp::reopen(file, Path::new(""))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn share_file(_file: &File) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "snapshots of rolled over files are not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_at(_file: &File, _buf: &mut [u8], _offset: u64) -> io::Result<usize> {
+    unreachable!("snapshots of rolled over files are not supported on this platform")
 }
 
 impl Read for SpooledTempFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read(buf),
-            SpooledData::OnDisk(file) => file.read(buf),
+            SpooledData::OnDisk(file) => self.charge.read(|| file.read(buf), |&n| n),
         }
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_vectored(bufs),
-            SpooledData::OnDisk(file) => file.read_vectored(bufs),
+            SpooledData::OnDisk(file) => self.charge.read(|| file.read_vectored(bufs), |&n| n),
+        }
+    }
+
+    #[cfg(feature = "nightly")]
+    fn is_read_vectored(&self) -> bool {
+        match &self.inner {
+            SpooledData::InMemory(cursor) => cursor.is_read_vectored(),
+            SpooledData::OnDisk(file) => file.is_read_vectored(),
         }
     }
 
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_to_end(buf),
-            SpooledData::OnDisk(file) => file.read_to_end(buf),
+            SpooledData::OnDisk(file) => self.charge.read(|| file.read_to_end(buf), |&n| n),
         }
     }
 
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_to_string(buf),
-            SpooledData::OnDisk(file) => file.read_to_string(buf),
+            SpooledData::OnDisk(file) => self.charge.read(|| file.read_to_string(buf), |&n| n),
         }
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.read_exact(buf),
-            SpooledData::OnDisk(file) => file.read_exact(buf),
+            SpooledData::OnDisk(file) => {
+                let len = buf.len();
+                self.charge.read(|| file.read_exact(buf), |_| len)
+            }
         }
     }
 }
 
-impl Write for SpooledTempFile {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+impl SpooledTempFile {
+    fn write_unaccounted(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_limit(buf.len() as u64)?;
+        if let Some(n) = self.write_pending(&[io::IoSlice::new(buf)])? {
+            return Ok(n);
+        }
+
         // roll over to file if necessary
         if matches! {
             &self.inner, SpooledData::InMemory(cursor)
             if cursor.position().saturating_add(buf.len() as u64) > self.max_size as u64
         } {
-            self.roll()?;
+            self.spill()?;
+            if let Some(n) = self.write_pending(&[io::IoSlice::new(buf)])? {
+                return Ok(n);
+            }
         }
 
         // write the bytes
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.write(buf),
-            SpooledData::OnDisk(file) => file.write(buf),
+            SpooledData::OnDisk(file) => self.charge.write(buf.len(), || file.write(buf)),
         }
     }
 
-    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+    fn write_vectored_unaccounted(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.check_limit(
+            bufs.iter()
+                .fold(0u64, |a, b| a.saturating_add(b.len() as u64)),
+        )?;
+        if let Some(n) = self.write_pending(bufs)? {
+            return Ok(n);
+        }
         if matches! {
             &self.inner, SpooledData::InMemory(cursor)
             // Borrowed from the rust standard library.
@@ -222,16 +1032,47 @@ impl Write for SpooledTempFile {
                 .fold(cursor.position(), |a, b| a.saturating_add(b.len() as u64))
                 > self.max_size as u64
         } {
-            self.roll()?;
+            self.spill()?;
+            if let Some(n) = self.write_pending(bufs)? {
+                return Ok(n);
+            }
         }
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.write_vectored(bufs),
-            SpooledData::OnDisk(file) => file.write_vectored(bufs),
+            SpooledData::OnDisk(file) => {
+                let len = bufs.iter().map(|b| b.len()).fold(0, usize::saturating_add);
+                self.charge.write(len, || file.write_vectored(bufs))
+            }
+        }
+    }
+}
+
+impl Write for SpooledTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.write_unaccounted(buf);
+        #[cfg(feature = "metrics")]
+        self.account_write(&result);
+        result
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let result = self.write_vectored_unaccounted(bufs);
+        #[cfg(feature = "metrics")]
+        self.account_write(&result);
+        result
+    }
+
+    #[cfg(feature = "nightly")]
+    fn is_write_vectored(&self) -> bool {
+        match &self.inner {
+            SpooledData::InMemory(cursor) => cursor.is_write_vectored(),
+            SpooledData::OnDisk(file) => file.is_write_vectored(),
         }
     }
 
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
+        self.settle()?;
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.flush(),
             SpooledData::OnDisk(file) => file.flush(),
@@ -241,9 +1082,130 @@ impl Write for SpooledTempFile {
 
 impl Seek for SpooledTempFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.settle()?;
+        #[cfg(feature = "metrics")]
+        self.forget_position();
         match &mut self.inner {
             SpooledData::InMemory(cursor) => cursor.seek(pos),
-            SpooledData::OnDisk(file) => file.seek(pos),
+            SpooledData::OnDisk(file) => {
+                let result = file.seek(pos);
+                self.charge.seeked(result.as_ref().ok().copied());
+                result
+            }
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+mod metrics {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static BYTES_IN_MEMORY: AtomicU64 = AtomicU64::new(0);
+    static BYTES_ON_DISK: AtomicU64 = AtomicU64::new(0);
+    static SPILLS: AtomicU64 = AtomicU64::new(0);
+    static LARGEST_SPOOL: AtomicU64 = AtomicU64::new(0);
+
+    /// Process-wide metrics for all [`SpooledTempFile`](crate::SpooledTempFile)s (requires the
+    /// `metrics` feature), see [`spool_metrics`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SpoolMetrics {
+        bytes_in_memory: u64,
+        bytes_on_disk: u64,
+        spills: u64,
+        largest_spool: u64,
+    }
+
+    impl SpoolMetrics {
+        /// Returns the number of bytes currently held in memory by all spooled temporary files.
+        #[must_use]
+        pub fn bytes_in_memory(&self) -> u64 {
+            self.bytes_in_memory
+        }
+
+        /// Returns the number of bytes currently stored on disk by all spooled temporary files.
+        #[must_use]
+        pub fn bytes_on_disk(&self) -> u64 {
+            self.bytes_on_disk
+        }
+
+        /// Returns the number of times a spooled temporary file has been rolled over to disk.
+        #[must_use]
+        pub fn spills(&self) -> u64 {
+            self.spills
+        }
+
+        /// Returns the size of the largest spooled temporary file seen so far, in bytes.
+        #[must_use]
+        pub fn largest_spool(&self) -> u64 {
+            self.largest_spool
+        }
+    }
+
+    /// Returns the current process-wide metrics for spooled temporary files (requires the `metrics`
+    /// feature), e.g., for capacity planning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use tempfile::spooled_tempfile;
+    ///
+    /// let mut file = spooled_tempfile(4);
+    /// file.write_all(b"hello")?;
+    ///
+    /// let metrics = tempfile::spool_metrics();
+    /// assert!(metrics.spills() >= 1);
+    /// assert!(metrics.largest_spool() >= 5);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[must_use]
+    pub fn spool_metrics() -> SpoolMetrics {
+        SpoolMetrics {
+            bytes_in_memory: BYTES_IN_MEMORY.load(Ordering::Relaxed),
+            bytes_on_disk: BYTES_ON_DISK.load(Ordering::Relaxed),
+            spills: SPILLS.load(Ordering::Relaxed),
+            largest_spool: LARGEST_SPOOL.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(super) fn record_spill() {
+        SPILLS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The usage of a single spooled temporary file included in the global metrics, removed again
+    /// when it's dropped.
+    #[derive(Debug, Default)]
+    pub(super) struct Accounted {
+        memory: u64,
+        pub(super) disk: u64,
+        /// Whether the file on disk is positioned at its end (as of the last full update).
+        pub(super) appending: bool,
+    }
+
+    impl Accounted {
+        pub(super) fn update(&mut self, memory: u64, disk: u64) {
+            adjust(&BYTES_IN_MEMORY, self.memory, memory);
+            adjust(&BYTES_ON_DISK, self.disk, disk);
+            LARGEST_SPOOL.fetch_max(memory + disk, Ordering::Relaxed);
+            self.memory = memory;
+            self.disk = disk;
+        }
+    }
+
+    impl Drop for Accounted {
+        fn drop(&mut self) {
+            self.update(0, 0);
+        }
+    }
+
+    fn adjust(counter: &AtomicU64, old: u64, new: u64) {
+        if new > old {
+            counter.fetch_add(new - old, Ordering::Relaxed);
+        } else {
+            counter.fetch_sub(old - new, Ordering::Relaxed);
         }
     }
 }
+
+#[cfg(feature = "metrics")]
+pub use self::metrics::{spool_metrics, SpoolMetrics};
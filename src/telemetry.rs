@@ -0,0 +1,131 @@
+//! Lifecycle events of temporary files and directories, reported through the
+//! [`metrics`](https://docs.rs/metrics) facade (with the `metrics` feature) and the
+//! [`log`](https://docs.rs/log) facade (with the `log` feature). Without either feature, these are
+//! all no-ops.
+//!
+//! Metrics:
+//!
+//! - `tempfile_created_total` (counter, labeled with `kind`): files and directories created.
+//! - `tempfile_live` (gauge, labeled with `kind`): temporary files and directories that haven't
+//!   been deleted, persisted, or kept yet.
+//! - `tempfile_cleanup_failures_total` (counter, labeled with `kind`): temporary files and
+//!   directories that couldn't be deleted when dropped.
+//! - `tempfile_name_collisions_total` (counter): random names that were already taken.
+//! - `tempfile_spilled_bytes_total` (counter): bytes moved from memory to disk by spooled
+//!   temporary files.
+//!
+//! Log records (with the `tempfile` target): creation, persisting, and spilling at the debug
+//! level, and cleanup failures on drop (which are otherwise silent) at the warn level.
+
+use std::io;
+use std::path::Path;
+
+use crate::token::Kind;
+#[cfg(feature = "log")]
+use crate::util::Redact;
+
+#[cfg(any(feature = "metrics", feature = "log"))]
+fn label(kind: Kind) -> &'static str {
+    match kind {
+        Kind::File => "file",
+        Kind::Dir => "dir",
+    }
+}
+
+/// Records the creation of the temporary file or directory at `path`.
+#[inline]
+pub(crate) fn created(kind: Kind, path: &Path) {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("tempfile_created_total", "kind" => label(kind));
+    #[cfg(feature = "log")]
+    log::debug!(
+        target: "tempfile",
+        "created temporary {} {}",
+        label(kind),
+        Redact(&path.display())
+    );
+    let _ = (kind, path);
+}
+
+/// Records the temporary file at `from` being persisted to `to`.
+#[inline]
+pub(crate) fn persisted(from: &Path, to: &Path) {
+    #[cfg(feature = "log")]
+    log::debug!(
+        target: "tempfile",
+        "persisted temporary file {} to {}",
+        Redact(&from.display()),
+        Redact(&to.display())
+    );
+    let _ = (from, to);
+}
+
+/// Records a failure to delete the temporary file or directory at `path` when it's dropped.
+#[inline]
+pub(crate) fn cleanup_failed(kind: Kind, path: &Path, err: &io::Error) {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("tempfile_cleanup_failures_total", "kind" => label(kind));
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: "tempfile",
+        "failed to delete temporary {} {}: {}",
+        label(kind),
+        Redact(&path.display()),
+        err
+    );
+    let _ = (kind, path, err);
+}
+
+/// Records a random name that was already taken.
+#[inline]
+pub(crate) fn collision() {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("tempfile_name_collisions_total");
+}
+
+/// Records `bytes` being spilled from memory to disk by a spooled temporary file.
+#[inline]
+pub(crate) fn spilled(bytes: u64) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("tempfile_spilled_bytes_total", bytes);
+    #[cfg(feature = "log")]
+    log::debug!(target: "tempfile", "spilled {} bytes of a spooled temporary file to disk", bytes);
+    let _ = bytes;
+}
+
+/// Counts a temporary file or directory in the `tempfile_live` gauge until it's released or
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Live {
+    #[cfg(feature = "metrics")]
+    kind: Option<Kind>,
+}
+
+impl Live {
+    pub(crate) fn new(kind: Kind) -> Live {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::increment_gauge!("tempfile_live", 1.0, "kind" => label(kind));
+            Live { kind: Some(kind) }
+        }
+        #[cfg(not(feature = "metrics"))]
+        {
+            let _ = kind;
+            Live {}
+        }
+    }
+
+    /// Stops counting the temporary file or directory (e.g., because it has been persisted).
+    pub(crate) fn release(&mut self) {
+        #[cfg(feature = "metrics")]
+        if let Some(kind) = self.kind.take() {
+            metrics::decrement_gauge!("tempfile_live", 1.0, "kind" => label(kind));
+        }
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
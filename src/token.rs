@@ -0,0 +1,226 @@
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{TempDir, TempPath};
+
+const FILE_MARKER: &str = "tempfile-file:";
+const DIR_MARKER: &str = "tempfile-dir:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Kind {
+    File,
+    Dir,
+}
+
+impl Kind {
+    fn marker(self) -> &'static str {
+        match self {
+            Kind::File => FILE_MARKER,
+            Kind::Dir => DIR_MARKER,
+        }
+    }
+}
+
+/// A serializable claim on a temporary file or directory, used to transfer the responsibility of
+/// deleting it to another process.
+///
+/// Tokens are created with [`TempPath::into_token`](crate::TempPath::into_token) and
+/// [`TempDir::into_token`](crate::TempDir::into_token), which disarm the original guard. The token
+/// can then be [encoded](OwnershipToken::encode) (e.g., into an environment variable or command
+/// line argument), [decoded](OwnershipToken::decode) in another process, and re-adopted with
+/// [`TempPath::adopt`](crate::TempPath::adopt) or [`TempDir::adopt`](crate::TempDir::adopt). To hand
+/// a temporary file or directory to a child process, use [`OwnershipToken::delegate_to`] in the
+/// parent and [`OwnershipToken::from_env`] in the child.
+///
+/// Dropping a token does **not** delete anything: until it's adopted, nobody is responsible for
+/// cleaning up the temporary file or directory.
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::{NamedTempFile, OwnershipToken, TempPath};
+///
+/// let path = NamedTempFile::new()?.into_temp_path();
+///
+/// // In the parent process...
+/// let encoded = path.into_token().encode();
+///
+/// // ...and in the worker process.
+/// let token = OwnershipToken::decode(&encoded)?;
+/// let path = TempPath::adopt(token)?;
+/// assert!(path.exists());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(PartialEq, Eq)]
+pub struct OwnershipToken {
+    kind: Kind,
+    path: PathBuf,
+}
+
+impl fmt::Debug for OwnershipToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnershipToken")
+            .field("kind", &self.kind)
+            .field("path", &Redact(&self.path))
+            .finish()
+    }
+}
+
+impl OwnershipToken {
+    pub(crate) fn new(kind: Kind, path: PathBuf) -> Self {
+        OwnershipToken { kind, path }
+    }
+
+    /// Returns the path of the temporary file or directory this token refers to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if this token refers to a temporary directory.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.kind == Kind::Dir
+    }
+
+    /// Encode this token as a string that can be passed to another process.
+    ///
+    /// The encoding is lossless (even for paths that aren't valid UTF-8) and can be decoded with
+    /// [`OwnershipToken::decode`].
+    #[must_use]
+    pub fn encode(&self) -> OsString {
+        let mut encoded = OsString::from(self.kind.marker());
+        encoded.push(&self.path);
+        encoded
+    }
+
+    /// Decode a token previously encoded with [`OwnershipToken::encode`].
+    ///
+    /// # Errors
+    ///
+    /// If `encoded` isn't a valid token, an error with kind [`io::ErrorKind::InvalidInput`] is
+    /// returned.
+    pub fn decode<S: AsRef<OsStr> + ?Sized>(encoded: &S) -> io::Result<OwnershipToken> {
+        let encoded = encoded.as_ref();
+        [Kind::File, Kind::Dir]
+            .iter()
+            .find_map(|&kind| {
+                strip_prefix(encoded, kind.marker()).map(|path| OwnershipToken {
+                    kind,
+                    path: PathBuf::from(path),
+                })
+            })
+            .filter(|token| token.path.is_absolute())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid ownership token: {:?}", encoded),
+                )
+            })
+    }
+
+    /// Pass this token to a child process through the environment variable `var`, delegating the
+    /// responsibility of cleaning up the temporary file or directory to that child.
+    ///
+    /// The child can retrieve the token with [`OwnershipToken::from_env`] and then adopt it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::process::Command;
+    /// use tempfile::{OwnershipToken, TempDir};
+    ///
+    /// // In the supervisor...
+    /// let mut worker = Command::new("worker");
+    /// TempDir::new()?.into_token().delegate_to(&mut worker, "WORKER_SCRATCH");
+    /// worker.spawn()?;
+    ///
+    /// // ...and in the worker, which now deletes the directory when `scratch` is dropped.
+    /// let scratch = TempDir::adopt(OwnershipToken::from_env("WORKER_SCRATCH")?)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn delegate_to<K: AsRef<OsStr>>(self, command: &mut Command, var: K) {
+        command.env(var, self.encode());
+    }
+
+    /// Read a token passed to this process with [`OwnershipToken::delegate_to`].
+    ///
+    /// # Errors
+    ///
+    /// If the environment variable isn't set, an error with kind [`io::ErrorKind::NotFound`] is
+    /// returned. If it doesn't contain a valid token, an error with kind
+    /// [`io::ErrorKind::InvalidInput`] is returned.
+    pub fn from_env<K: AsRef<OsStr>>(var: K) -> io::Result<OwnershipToken> {
+        let var = var.as_ref();
+        match std::env::var_os(var) {
+            Some(encoded) => OwnershipToken::decode(&encoded),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("environment variable {:?} not set", var),
+            )),
+        }
+    }
+
+    /// Adopt and immediately delete the temporary file or directory this token refers to.
+    ///
+    /// # Errors
+    ///
+    /// If the file or directory no longer exists, or if it can't be deleted, `Err` is returned.
+    pub fn remove(self) -> io::Result<()> {
+        match self.kind {
+            Kind::File => TempPath::adopt(self)?.close(),
+            Kind::Dir => TempDir::adopt(self)?.close(),
+        }
+    }
+
+    /// Check that this token refers to an existing resource of the expected kind, returning its
+    /// path.
+    pub(crate) fn into_path(self, kind: Kind) -> io::Result<PathBuf> {
+        if self.kind != kind {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                match kind {
+                    Kind::File => "ownership token refers to a directory, not a file",
+                    Kind::Dir => "ownership token refers to a file, not a directory",
+                },
+            ))
+            .with_err_path(|| self.path);
+        }
+        let meta = self.path.symlink_metadata().with_err_path(|| &self.path)?;
+        if meta.is_dir() != (kind == Kind::Dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "temporary path has been replaced",
+            ))
+            .with_err_path(|| self.path);
+        }
+        Ok(self.path)
+    }
+}
+
+#[cfg(unix)]
+fn strip_prefix(s: &OsStr, prefix: &str) -> Option<OsString> {
+    use std::os::unix::ffi::OsStrExt;
+    s.as_bytes()
+        .strip_prefix(prefix.as_bytes())
+        .map(|rest| OsStr::from_bytes(rest).to_owned())
+}
+
+#[cfg(windows)]
+fn strip_prefix(s: &OsStr, prefix: &str) -> Option<OsString> {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    let wide: Vec<u16> = s.encode_wide().collect();
+    let prefix: Vec<u16> = prefix.encode_utf16().collect();
+    wide.strip_prefix(&prefix[..]).map(OsString::from_wide)
+}
+
+// Paths on the remaining platforms (e.g., WASI) are always valid UTF-8.
+#[cfg(not(any(unix, windows)))]
+fn strip_prefix(s: &OsStr, prefix: &str) -> Option<OsString> {
+    s.to_str()?.strip_prefix(prefix).map(OsString::from)
+}
@@ -0,0 +1,442 @@
+//! UTF-8 path variants of the temporary file types, backed by [`camino`].
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::util::Redact;
+use crate::{Builder, NamedTempFile, PathPersistError, PersistError, TempDir, TempPath};
+
+fn to_utf8(path: &Path) -> io::Result<&Utf8Path> {
+    crate::util::utf8_path(path).map(Utf8Path::new)
+}
+
+/// A [`TempDir`] whose path is known to be valid UTF-8.
+///
+/// The path is validated once, on construction, so [`Utf8TempDir::path`] can return a
+/// [`Utf8Path`] without any further checks. All other [`TempDir`] methods are available through
+/// [`Deref`].
+///
+/// # Examples
+///
+/// ```
+/// use tempfile::Utf8TempDir;
+///
+/// let tmp_dir = Utf8TempDir::new()?;
+/// let file_path = tmp_dir.path().join("my-temporary-note.txt");
+/// assert_eq!(file_path.file_name(), Some("my-temporary-note.txt"));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Utf8TempDir {
+    inner: TempDir,
+}
+
+impl Utf8TempDir {
+    /// Create a new temporary directory inside of [`env::temp_dir()`](crate::env::temp_dir). See
+    /// [`TempDir::new`].
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn new() -> io::Result<Utf8TempDir> {
+        Utf8TempDir::from_temp_dir(TempDir::new()?)
+    }
+
+    /// Create a new temporary directory inside of `dir`. See [`TempDir::new_in`].
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn new_in<P: AsRef<Utf8Path>>(dir: P) -> io::Result<Utf8TempDir> {
+        Utf8TempDir::from_temp_dir(TempDir::new_in(dir.as_ref())?)
+    }
+
+    /// Create a new temporary directory with the given [`Builder`], inside of `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn with_builder_in<P: AsRef<Utf8Path>>(
+        builder: &Builder<'_, '_>,
+        dir: P,
+    ) -> io::Result<Utf8TempDir> {
+        Utf8TempDir::from_temp_dir(builder.tempdir_in(dir.as_ref())?)
+    }
+
+    /// Convert a [`TempDir`] into a `Utf8TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the path of the temporary directory isn't valid UTF-8, `Err` is returned and the
+    /// temporary directory is deleted (unless cleanup has been disabled).
+    pub fn from_temp_dir(dir: TempDir) -> io::Result<Utf8TempDir> {
+        to_utf8(dir.path())?;
+        Ok(Utf8TempDir { inner: dir })
+    }
+
+    /// Accesses the [`Utf8Path`] to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Utf8Path {
+        Utf8Path::from_path(self.inner.path()).expect("path was validated on construction")
+    }
+
+    /// Persist the temporary directory to disk, returning the [`Utf8PathBuf`] where it is located.
+    /// See [`TempDir::keep`].
+    #[must_use]
+    pub fn keep(self) -> Utf8PathBuf {
+        Utf8PathBuf::from_path_buf(self.inner.keep()).expect("path was validated on construction")
+    }
+
+    /// Closes and removes the temporary directory. See [`TempDir::close`].
+    ///
+    /// # Errors
+    ///
+    /// See [`TempDir::close`].
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Unwraps the underlying [`TempDir`].
+    #[must_use]
+    pub fn into_inner(self) -> TempDir {
+        self.inner
+    }
+}
+
+impl Deref for Utf8TempDir {
+    type Target = TempDir;
+
+    fn deref(&self) -> &TempDir {
+        &self.inner
+    }
+}
+
+impl DerefMut for Utf8TempDir {
+    fn deref_mut(&mut self) -> &mut TempDir {
+        &mut self.inner
+    }
+}
+
+impl AsRef<Path> for Utf8TempDir {
+    fn as_ref(&self) -> &Path {
+        self.inner.path()
+    }
+}
+
+impl AsRef<Utf8Path> for Utf8TempDir {
+    fn as_ref(&self) -> &Utf8Path {
+        self.path()
+    }
+}
+
+impl fmt::Debug for Utf8TempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Utf8TempDir")
+            .field("path", &Redact(self.path()))
+            .finish()
+    }
+}
+
+/// A [`TempPath`] that is known to be valid UTF-8.
+///
+/// Dereferences to a [`Utf8Path`]. When dropped, the temporary file is deleted (see
+/// [`TempPath`]).
+pub struct Utf8TempPath {
+    inner: TempPath,
+}
+
+impl Utf8TempPath {
+    /// Convert a [`TempPath`] into a `Utf8TempPath`.
+    ///
+    /// # Errors
+    ///
+    /// If the path isn't valid UTF-8, `Err` is returned and the temporary file is deleted (unless
+    /// cleanup has been disabled).
+    pub fn from_temp_path(path: TempPath) -> io::Result<Utf8TempPath> {
+        to_utf8(&path)?;
+        Ok(Utf8TempPath { inner: path })
+    }
+
+    /// Create a new `Utf8TempPath` from an existing path. See [`TempPath::from_path`].
+    pub fn from_path(path: impl Into<Utf8PathBuf>) -> Utf8TempPath {
+        Utf8TempPath {
+            inner: TempPath::from_path(path.into().into_std_path_buf()),
+        }
+    }
+
+    /// Close and remove the temporary file. See [`TempPath::close`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Persist the temporary file at the target path. See [`TempPath::persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned.
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<(), PathPersistError> {
+        self.inner.persist(new_path)
+    }
+
+    /// Persist the temporary file at the target path if and only if no file exists there. See
+    /// [`TempPath::persist_noclobber`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location or a file already exists there, `Err` is
+    /// returned.
+    pub fn persist_noclobber<P: AsRef<Path>>(self, new_path: P) -> Result<(), PathPersistError> {
+        self.inner.persist_noclobber(new_path)
+    }
+
+    /// Keep the temporary file from being deleted. See [`TempPath::keep`].
+    ///
+    /// # Errors
+    ///
+    /// On some platforms (e.g., Windows), we need to mark the file as non-temporary. This
+    /// operation could fail.
+    pub fn keep(self) -> Result<Utf8PathBuf, PathPersistError> {
+        self.inner.keep().map(|path| {
+            Utf8PathBuf::from_path_buf(path).expect("path was validated on construction")
+        })
+    }
+
+    /// Disable cleanup of the temporary file. See [`TempPath::disable_cleanup`].
+    pub fn disable_cleanup(&mut self, disable_cleanup: bool) {
+        self.inner.disable_cleanup(disable_cleanup)
+    }
+
+    /// Unwraps the underlying [`TempPath`].
+    #[must_use]
+    pub fn into_inner(self) -> TempPath {
+        self.inner
+    }
+}
+
+impl Deref for Utf8TempPath {
+    type Target = Utf8Path;
+
+    fn deref(&self) -> &Utf8Path {
+        Utf8Path::from_path(&self.inner).expect("path was validated on construction")
+    }
+}
+
+impl AsRef<Path> for Utf8TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.inner
+    }
+}
+
+impl AsRef<Utf8Path> for Utf8TempPath {
+    fn as_ref(&self) -> &Utf8Path {
+        self
+    }
+}
+
+impl AsRef<OsStr> for Utf8TempPath {
+    fn as_ref(&self) -> &OsStr {
+        self.inner.as_os_str()
+    }
+}
+
+impl AsRef<str> for Utf8TempPath {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for Utf8TempPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// A [`NamedTempFile`] whose path is known to be valid UTF-8.
+///
+/// The path is validated once, on construction, so [`Utf8NamedTempFile::path`] can return a
+/// [`Utf8Path`] without any further checks. All other [`NamedTempFile`] methods are available
+/// through [`Deref`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use tempfile::Utf8NamedTempFile;
+///
+/// let mut file = Utf8NamedTempFile::new()?;
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// println!("wrote to {}", file.path());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Utf8NamedTempFile<F = File> {
+    inner: NamedTempFile<F>,
+}
+
+impl Utf8NamedTempFile<File> {
+    /// Create a new named temporary file. See [`NamedTempFile::new`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn new() -> io::Result<Utf8NamedTempFile> {
+        Utf8NamedTempFile::from_named_temp_file(NamedTempFile::new()?)
+    }
+
+    /// Create a new named temporary file in the specified directory. See
+    /// [`NamedTempFile::new_in`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn new_in<P: AsRef<Utf8Path>>(dir: P) -> io::Result<Utf8NamedTempFile> {
+        Utf8NamedTempFile::from_named_temp_file(NamedTempFile::new_in(dir.as_ref())?)
+    }
+
+    /// Create a new named temporary file with the given [`Builder`], inside of `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created or its path isn't valid UTF-8, `Err` is returned.
+    pub fn with_builder_in<P: AsRef<Utf8Path>>(
+        builder: &Builder<'_, '_>,
+        dir: P,
+    ) -> io::Result<Utf8NamedTempFile> {
+        Utf8NamedTempFile::from_named_temp_file(builder.tempfile_in(dir.as_ref())?)
+    }
+}
+
+impl<F> Utf8NamedTempFile<F> {
+    /// Convert a [`NamedTempFile`] into a `Utf8NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the path of the temporary file isn't valid UTF-8, `Err` is returned and the temporary
+    /// file is deleted (unless cleanup has been disabled).
+    pub fn from_named_temp_file(file: NamedTempFile<F>) -> io::Result<Utf8NamedTempFile<F>> {
+        to_utf8(file.path())?;
+        Ok(Utf8NamedTempFile { inner: file })
+    }
+
+    /// Get the temporary file's path. See [`NamedTempFile::path`].
+    #[must_use]
+    pub fn path(&self) -> &Utf8Path {
+        Utf8Path::from_path(self.inner.path()).expect("path was validated on construction")
+    }
+
+    /// Persist the temporary file at the target path. See [`NamedTempFile::persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned.
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<F, PersistError<F>> {
+        self.inner.persist(new_path)
+    }
+
+    /// Persist the temporary file at the target path if and only if no file exists there. See
+    /// [`NamedTempFile::persist_noclobber`].
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location or a file already exists there, `Err` is
+    /// returned.
+    pub fn persist_noclobber<P: AsRef<Path>>(self, new_path: P) -> Result<F, PersistError<F>> {
+        self.inner.persist_noclobber(new_path)
+    }
+
+    /// Keep the temporary file from being deleted. See [`NamedTempFile::keep`].
+    ///
+    /// # Errors
+    ///
+    /// On some platforms (e.g., Windows), we need to mark the file as non-temporary. This
+    /// operation could fail.
+    pub fn keep(self) -> Result<(F, Utf8PathBuf), PersistError<F>> {
+        self.inner.keep().map(|(file, path)| {
+            let path =
+                Utf8PathBuf::from_path_buf(path).expect("path was validated on construction");
+            (file, path)
+        })
+    }
+
+    /// Closes the file, leaving only the temporary file path. See
+    /// [`NamedTempFile::into_temp_path`].
+    pub fn into_temp_path(self) -> Utf8TempPath {
+        Utf8TempPath {
+            inner: self.inner.into_temp_path(),
+        }
+    }
+
+    /// Converts the named temporary file into its constituent parts. See
+    /// [`NamedTempFile::into_parts`].
+    pub fn into_parts(self) -> (F, Utf8TempPath) {
+        let (file, path) = self.inner.into_parts();
+        (file, Utf8TempPath { inner: path })
+    }
+
+    /// Unwraps the underlying [`NamedTempFile`].
+    pub fn into_inner(self) -> NamedTempFile<F> {
+        self.inner
+    }
+}
+
+impl<F> Deref for Utf8NamedTempFile<F> {
+    type Target = NamedTempFile<F>;
+
+    fn deref(&self) -> &NamedTempFile<F> {
+        &self.inner
+    }
+}
+
+impl<F> DerefMut for Utf8NamedTempFile<F> {
+    fn deref_mut(&mut self) -> &mut NamedTempFile<F> {
+        &mut self.inner
+    }
+}
+
+impl<F> AsRef<Path> for Utf8NamedTempFile<F> {
+    fn as_ref(&self) -> &Path {
+        self.inner.path()
+    }
+}
+
+impl<F> AsRef<Utf8Path> for Utf8NamedTempFile<F> {
+    fn as_ref(&self) -> &Utf8Path {
+        self.path()
+    }
+}
+
+impl<F> fmt::Debug for Utf8NamedTempFile<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Utf8NamedTempFile({:?})", Redact(self.path()))
+    }
+}
+
+impl<F: Read> Read for Utf8NamedTempFile<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<F: Write> Write for Utf8NamedTempFile<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: Seek> Seek for Utf8NamedTempFile<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
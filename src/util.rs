@@ -1,29 +1,411 @@
+use std::borrow::Cow;
 use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{io, iter::repeat_with};
 
 use crate::error::IoResultExt;
+#[cfg(windows)]
+use crate::error::{unsupported_dir, UnsupportedDirReason};
 
-fn tmpname(rng: &mut fastrand::Rng, prefix: &OsStr, suffix: &OsStr, rand_len: usize) -> OsString {
-    let capacity = prefix
-        .len()
-        .saturating_add(suffix.len())
-        .saturating_add(rand_len);
-    let mut buf = OsString::with_capacity(capacity);
+/// Describes how temporary file names are generated.
+#[derive(Debug, Clone, Copy)]
+pub struct NameSpec<'a> {
+    pub prefix: &'a OsStr,
+    pub suffix: &'a OsStr,
+    pub random_len: usize,
+    #[cfg(feature = "uuid")]
+    pub uuid: bool,
+    pub pid: bool,
+    pub timestamp: Option<TimestampFormat>,
+    pub sequence: bool,
+    pub adaptive: bool,
+    /// Shorten the prefix so the name fits in [`MAX_NAME_LEN`] bytes.
+    pub fit_name_length: bool,
+    /// Refuse to create paths that aren't ASCII.
+    pub ascii_only: bool,
+}
+
+/// The format of the creation timestamp embedded in temporary file names, see
+/// [`Builder::with_timestamp`](crate::Builder::with_timestamp).
+///
+/// All formats are in UTC and sort chronologically (for timestamps with the same number of digits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimestampFormat {
+    /// Seconds since the Unix epoch, e.g., `1700000000`.
+    UnixSeconds,
+    /// Milliseconds since the Unix epoch, e.g., `1700000000000`.
+    UnixMillis,
+    /// An ISO 8601 "basic format" date and time, e.g., `20231114T221320Z`.
+    Iso8601,
+}
+
+impl TimestampFormat {
+    /// Formats the current time, on platforms that have a clock.
+    fn now(self) -> Option<String> {
+        // `SystemTime::now` panics on wasm32-unknown-unknown.
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            return None;
+        }
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        Some(match self {
+            TimestampFormat::UnixSeconds => since_epoch.as_secs().to_string(),
+            TimestampFormat::UnixMillis => since_epoch.as_millis().to_string(),
+            TimestampFormat::Iso8601 => {
+                let secs = since_epoch.as_secs();
+                let (year, month, day) = civil_from_days(secs / 86400);
+                let secs_of_day = secs % 86400;
+                format!(
+                    "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+                    year,
+                    month,
+                    day,
+                    secs_of_day / 3600,
+                    secs_of_day / 60 % 60,
+                    secs_of_day % 60
+                )
+            }
+        })
+    }
+}
+
+/// Converts a number of days since the Unix epoch into a (year, month, day) date, using Howard
+/// Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+impl NameSpec<'static> {
+    /// The name spec used for unnamed temporary files (and other internal temporary names).
+    pub fn unnamed() -> Self {
+        NameSpec {
+            prefix: OsStr::new(".tmp"),
+            suffix: OsStr::new(""),
+            random_len: crate::NUM_RAND_CHARS,
+            #[cfg(feature = "uuid")]
+            uuid: false,
+            pid: false,
+            timestamp: None,
+            sequence: false,
+            adaptive: false,
+            fit_name_length: false,
+            ascii_only: false,
+        }
+    }
+}
+
+impl NameSpec<'_> {
+    fn is_random(&self) -> bool {
+        #[cfg(feature = "uuid")]
+        if self.uuid {
+            return true;
+        }
+        // Sequence numbers change on every attempt too.
+        self.random_len != 0 || self.sequence
+    }
+}
+
+/// A source of randomness for temporary file names.
+trait NameRng {
+    fn alphanumeric(&mut self) -> char;
+    #[cfg(feature = "uuid")]
+    fn fill(&mut self, buf: &mut [u8]);
+}
+
+impl NameRng for fastrand::Rng {
+    fn alphanumeric(&mut self) -> char {
+        fastrand::Rng::alphanumeric(self)
+    }
+
+    #[cfg(feature = "uuid")]
+    fn fill(&mut self, buf: &mut [u8]) {
+        fastrand::Rng::fill(self, buf)
+    }
+}
+
+#[cfg(feature = "rand")]
+impl NameRng for dyn rand::RngCore + '_ {
+    fn alphanumeric(&mut self) -> char {
+        use rand::distributions::{Alphanumeric, Distribution};
+        char::from(Alphanumeric.sample(self))
+    }
+
+    #[cfg(feature = "uuid")]
+    fn fill(&mut self, buf: &mut [u8]) {
+        self.fill_bytes(buf)
+    }
+}
+
+fn tmpname<Rng: NameRng + ?Sized>(rng: &mut Rng, spec: &NameSpec<'_>) -> OsString {
+    // Everything after the prefix, which is never shortened.
+    let mut rest = OsString::with_capacity(spec.suffix.len().saturating_add(spec.random_len));
+    if let Some(timestamp) = spec.timestamp.and_then(TimestampFormat::now) {
+        rest.push(timestamp);
+        rest.push("-");
+    }
+    if spec.pid {
+        if let Some(pid) = process_id() {
+            rest.push(format!("{}-", pid));
+        }
+    }
+    if spec.sequence {
+        rest.push(format!("{:04}-", next_sequence()));
+    }
+    push_random(rng, spec, &mut rest);
+    rest.push(spec.suffix);
+
+    let prefix = if spec.fit_name_length {
+        truncate(spec.prefix, MAX_NAME_LEN.saturating_sub(rest.len()))
+    } else {
+        Cow::Borrowed(spec.prefix)
+    };
+    let mut buf = OsString::with_capacity(prefix.len().saturating_add(rest.len()));
     buf.push(prefix);
+    buf.push(rest);
+    buf
+}
+
+fn push_random<Rng: NameRng + ?Sized>(rng: &mut Rng, spec: &NameSpec<'_>, buf: &mut OsString) {
+    #[cfg(feature = "uuid")]
+    if spec.uuid {
+        let mut bytes = [0u8; 16];
+        rng.fill(&mut bytes);
+        let uuid = uuid::Builder::from_random_bytes(bytes).into_uuid();
+        buf.push(
+            uuid.hyphenated()
+                .encode_lower(&mut uuid::Uuid::encode_buffer()),
+        );
+        return;
+    }
     let mut char_buf = [0u8; 4];
-    for c in repeat_with(|| rng.alphanumeric()).take(rand_len) {
+    for c in repeat_with(|| rng.alphanumeric()).take(spec.random_len) {
         buf.push(c.encode_utf8(&mut char_buf));
     }
-    buf.push(suffix);
-    buf
+}
+
+/// The file name length (in bytes) that [`NameSpec::fit_name_length`] shortens names to. This is
+/// the limit of virtually all current filesystems (in bytes or, on Windows, UTF-16 code units).
+const MAX_NAME_LEN: usize = 255;
+
+/// Shortens `s` to at most `max` bytes, without splitting any (valid) characters.
+#[cfg(unix)]
+fn truncate(s: &OsStr, max: usize) -> Cow<'_, OsStr> {
+    use std::os::unix::ffi::OsStrExt;
+    if s.len() <= max {
+        return Cow::Borrowed(s);
+    }
+    let bytes = s.as_bytes();
+    let end = match std::str::from_utf8(bytes) {
+        Ok(s) => floor_char_boundary(s, max),
+        Err(_) => max,
+    };
+    Cow::Borrowed(OsStr::from_bytes(&bytes[..end]))
+}
+
+#[cfg(not(unix))]
+fn truncate(s: &OsStr, max: usize) -> Cow<'_, OsStr> {
+    if s.len() <= max {
+        return Cow::Borrowed(s);
+    }
+    let s = s.to_string_lossy();
+    let end = floor_char_boundary(&s, max);
+    Cow::Owned(OsString::from(&s[..end]))
+}
+
+fn floor_char_boundary(s: &str, max: usize) -> usize {
+    (0..=max.min(s.len()))
+        .rev()
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// Returns the id of the current process, on platforms that have process ids.
+#[cfg(any(unix, windows))]
+pub(crate) fn process_id() -> Option<u32> {
+    Some(std::process::id())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn process_id() -> Option<u32> {
+    None
+}
+
+/// Formats the wrapped value (e.g., a path) for `Debug` output and log records, or `<redacted>` if
+/// requested with [`env::override_redact_debug`](crate::env::override_redact_debug).
+pub(crate) struct Redact<'a, T: ?Sized>(pub &'a T);
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for Redact<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if crate::env::redact_debug() {
+            f.write_str("<redacted>")
+        } else {
+            self.0.fmt(f)
+        }
+    }
+}
+
+impl<T: fmt::Display + ?Sized> fmt::Display for Redact<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if crate::env::redact_debug() {
+            f.write_str("<redacted>")
+        } else {
+            self.0.fmt(f)
+        }
+    }
+}
+
+/// Returns `path` as a string, failing if it isn't valid UTF-8.
+pub(crate) fn utf8_path(path: &Path) -> io::Result<&str> {
+    path.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("temporary path {:?} is not valid UTF-8", path),
+        )
+    })
+}
+
+/// Returns the next number in the per-process sequence, starting at 1.
+fn next_sequence() -> usize {
+    static SEQUENCE: AtomicUsize = AtomicUsize::new(1);
+    SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Fails if `path` is too long to be bound as a Unix domain socket.
+#[cfg(unix)]
+pub fn check_socket_path(path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    // The size of `sockaddr_un::sun_path`, which must also fit the terminating NUL.
+    const SUN_PATH_LEN: usize = if cfg!(any(target_os = "linux", target_os = "android")) {
+        108
+    } else {
+        104
+    };
+    let len = path.as_os_str().as_bytes().len();
+    if len >= SUN_PATH_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "socket path is {} bytes long, but must be shorter than {} bytes",
+                len, SUN_PATH_LEN
+            ),
+        ))
+        .with_err_path(|| path);
+    }
+    Ok(())
+}
+
+/// The number of consecutive collisions after which adaptive names get longer.
+const ADAPTIVE_COLLISIONS: u32 = 3;
+
+/// Returns the directory to create temporary files in so that they can later be renamed to
+/// `target` (see [`Builder::same_filesystem_as`](crate::Builder::same_filesystem_as)).
+pub fn same_filesystem_dir(target: &Path) -> io::Result<PathBuf> {
+    let dir = if target.is_dir() {
+        target
+    } else {
+        match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        }
+    };
+    fs::metadata(dir).with_err_path(|| dir)?;
+    let temp_dir = crate::env::temp_dir();
+    // If the temporary directory can't be examined (e.g., because it doesn't exist), don't use it.
+    match crate::file::imp::same_filesystem(&temp_dir, dir) {
+        Ok(true) => Ok(temp_dir),
+        _ => Ok(dir.to_owned()),
+    }
+}
+
+/// Adaptive names stop growing at this many random characters.
+const MAX_ADAPTIVE_RAND_LEN: usize = 64;
+
+/// Rejects directories that can't (safely) hold temporary files on Windows.
+#[cfg(windows)]
+fn check_dir(dir: &Path) -> io::Result<()> {
+    use std::path::{Component, Prefix};
+
+    let verbatim = matches!(
+        dir.components().next(),
+        Some(Component::Prefix(prefix)) if prefix.kind().is_verbatim()
+    );
+    let reason = dir.components().find_map(|component| match component {
+        Component::Prefix(prefix) => match prefix.kind() {
+            // Only volumes (e.g., `\\.\C:\`) are directories in the device namespace.
+            Prefix::DeviceNS(name) => {
+                let name = name.to_string_lossy();
+                let is_volume = (name.len() == 2 && name.ends_with(':'))
+                    || name.to_ascii_lowercase().starts_with("volume{");
+                (!is_volume).then_some(UnsupportedDirReason::DevicePath)
+            }
+            Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                if share.is_empty() {
+                    Some(UnsupportedDirReason::IncompleteUncPath)
+                } else if is_webdav(server, share) {
+                    Some(UnsupportedDirReason::UnreliableCreateNew)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        },
+        // Verbatim paths aren't subject to device name interpretation.
+        Component::Normal(name) if !verbatim => {
+            is_reserved_name(name).then_some(UnsupportedDirReason::ReservedName)
+        }
+        _ => None,
+    });
+    match reason {
+        Some(reason) => Err(unsupported_dir(dir, reason)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(windows))]
+fn check_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns true if the UNC share is served by the WebDAV redirector (e.g.,
+/// `\\server@SSL\DavWWWRoot\`), which doesn't reliably refuse to create files that already exist.
+#[cfg(windows)]
+fn is_webdav(server: &OsStr, share: &OsStr) -> bool {
+    server.to_string_lossy().contains('@') || share.eq_ignore_ascii_case("DavWWWRoot")
+}
+
+/// Returns true if `name` is a reserved DOS device name (ignoring any extension).
+#[cfg(windows)]
+fn is_reserved_name(name: &OsStr) -> bool {
+    let name = name.to_string_lossy().to_ascii_uppercase();
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    match stem {
+        "CON" | "PRN" | "AUX" | "NUL" | "CONIN$" | "CONOUT$" => true,
+        // Only strip the last character if it's an ASCII digit: the stem may end with a multi-byte
+        // character, which can't be split off by byte index.
+        _ => match stem.strip_suffix(|c| matches!(c, '1'..='9')) {
+            Some(base) => matches!(base, "COM" | "LPT"),
+            None => false,
+        },
+    }
 }
 
 pub fn create_helper<R>(
     base: &Path,
-    prefix: &OsStr,
-    suffix: &OsStr,
-    random_len: usize,
+    spec: &NameSpec<'_>,
     mut f: impl FnMut(PathBuf) -> io::Result<R>,
 ) -> io::Result<R> {
     // Make the path absolute. Otherwise, changing the current directory can invalidate a stored
@@ -35,14 +417,19 @@ pub fn create_helper<R>(
         base_path_storage = cur_dir.join(base);
         base = &base_path_storage;
     }
+    check_dir(base)?;
 
-    let num_retries = if random_len != 0 {
+    let mut spec = *spec;
+    let mut collisions = 0;
+
+    let num_retries = if spec.is_random() {
         crate::NUM_RETRIES
     } else {
         1
     };
 
     // We fork the fastrand rng.
+    #[cfg(not(feature = "rand"))]
     let mut rng = fastrand::Rng::new();
     for i in 0..num_retries {
         // If we fail to create the file the first three times, re-seed from system randomness in
@@ -53,25 +440,59 @@ pub fn create_helper<R>(
         //    Re-seeding the randomness won't help here.
         // 2. We're failing to create random files for some other reason. This shouldn't be the case
         //    given that we're checking error kinds, but it could happen.
-        #[cfg(all(
-            feature = "getrandom",
-            any(windows, unix, target_os = "redox", target_os = "wasi")
-        ))]
+        //
+        // When the `rand` feature is enabled, names come from the configured `rand` generator
+        // instead (see `env::override_rng`). It can't be re-seeded, so we switch to `thread_rng`
+        // (which is seeded from system randomness) after the first three failures instead.
+        #[cfg(not(feature = "rand"))]
         if i == 3 {
-            if let Ok(seed) = getrandom::u64() {
-                rng.seed(seed);
-            }
+            rng.seed(crate::entropy::seed().0);
         }
         let _ = i; // avoid unused variable warning for the above.
 
-        let path = base.join(tmpname(&mut rng, prefix, suffix, random_len));
-        return match f(path) {
-            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && num_retries > 1 => continue,
-            // AddrInUse can happen if we're creating a UNIX domain socket and
-            // the path already exists.
-            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse && num_retries > 1 => continue,
-            res => res,
+        #[cfg(not(feature = "rand"))]
+        let path = base.join(tmpname(&mut rng, &spec));
+        #[cfg(feature = "rand")]
+        let path = if i < 3 {
+            base.join(crate::env::with_rng(|rng| tmpname(rng, &spec)))
+        } else {
+            let rng: &mut dyn rand::RngCore = &mut rand::thread_rng();
+            base.join(tmpname(rng, &spec))
         };
+        if spec.ascii_only && !path.to_str().map_or(false, str::is_ascii) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "temporary path isn't ASCII",
+            ))
+            .with_err_path(|| path);
+        }
+        #[cfg(feature = "failpoints")]
+        let res = crate::failpoints::check(crate::failpoints::Failpoint::Collision)
+            .and_then(|()| f(path));
+        #[cfg(not(feature = "failpoints"))]
+        let res = f(path);
+        match res {
+            Err(ref e)
+                if matches!(
+                    e.kind(),
+                    // AddrInUse can happen if we're creating a UNIX domain socket and
+                    // the path already exists.
+                    io::ErrorKind::AlreadyExists | io::ErrorKind::AddrInUse
+                ) && num_retries > 1 =>
+            {
+                collisions += 1;
+                crate::telemetry::collision();
+                if spec.adaptive && collisions % ADAPTIVE_COLLISIONS == 0 {
+                    spec.random_len = spec
+                        .random_len
+                        .saturating_mul(2)
+                        .min(MAX_ADAPTIVE_RAND_LEN)
+                        .max(spec.random_len);
+                }
+                continue;
+            }
+            res => return res,
+        }
     }
 
     Err(io::Error::new(
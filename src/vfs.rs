@@ -0,0 +1,621 @@
+//! Filesystem abstraction for testing code that uses temporary files.
+//!
+//! The [`TempFs`] trait abstracts the handful of filesystem operations needed to create, persist,
+//! and clean up temporary files and directories. Code that creates its temporary files through
+//! [`Builder::tempfile_in_fs`] and [`Builder::tempdir_in_fs`] can be run against the real
+//! filesystem ([`OsFs`]) in production and against an in-memory filesystem (`MemFs`, enabled by
+//! the `testing` feature) in unit tests.
+//!
+//! At a higher level, the [`TempStorage`] trait abstracts creating temporary files, directories,
+//! and spooled buffers. It's implemented by [`Builder`] (creating them with the builder's
+//! settings) and by [`FsStorage`] (on top of any `TempFs`), and frameworks can accept
+//! `impl TempStorage` to let their users swap in mocks, quota-enforcing wrappers, or encrypted
+//! backends.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use std::path::Path;
+//! use tempfile::vfs::{OsFs, TempFs};
+//! use tempfile::Builder;
+//!
+//! fn save_report<Fs: TempFs>(fs: &Fs, dir: &Path, report: &str) -> std::io::Result<()> {
+//!     let mut file = Builder::new().tempfile_in_fs(fs, dir)?;
+//!     file.write_all(report.as_bytes())?;
+//!     file.persist(dir.join("report.txt"))
+//! }
+//!
+//! let dir = tempfile::tempdir()?;
+//! save_report(&OsFs, dir.path(), "all good")?;
+//! assert_eq!(dir.read_to_string("report.txt")?, "all good");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{util, Builder, NamedTempFile, SpooledTempFile, TempDir};
+
+#[cfg(feature = "testing")]
+pub use self::memfs::{MemFile, MemFs};
+
+/// The filesystem operations used to manage temporary files and directories.
+///
+/// Implementations must make [`TempFs::create_new`] and [`TempFs::create_dir`] fail with
+/// [`io::ErrorKind::AlreadyExists`] if something already exists at the given path so that a new
+/// random name can be tried.
+pub trait TempFs {
+    /// The type of file handles returned by [`TempFs::create_new`].
+    type File: Read + Write + Seek;
+
+    /// Create and open a new file for reading and writing, failing if it already exists.
+    fn create_new(&self, path: &Path) -> io::Result<Self::File>;
+
+    /// Create a new directory, failing if it already exists.
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Rename a file or directory, replacing the target if it exists.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Remove a file.
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Recursively remove a directory and everything inside it.
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The real, operating system backed, filesystem.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsFs;
+
+impl TempFs for OsFs {
+    type File = File;
+
+    fn create_new(&self, path: &Path) -> io::Result<File> {
+        let mut options = OpenOptions::new();
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            options.attributes(windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_TEMPORARY);
+        }
+        crate::file::imp::create_named(path, &mut options, None)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+}
+
+/// A named temporary file created on a [`TempFs`] with [`Builder::tempfile_in_fs`].
+///
+/// The file is removed (using the same `TempFs`) when dropped.
+pub struct FsTempFile<'fs, Fs: TempFs> {
+    fs: &'fs Fs,
+    path: PathBuf,
+    file: Fs::File,
+}
+
+impl<'fs, Fs: TempFs> FsTempFile<'fs, Fs> {
+    /// Get the temporary file's path.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &Fs::File {
+        &self.file
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut Fs::File {
+        &mut self.file
+    }
+
+    /// Persist the temporary file at the target path, replacing any existing file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned and the temporary file
+    /// is deleted.
+    pub fn persist<P: AsRef<Path>>(mut self, new_path: P) -> io::Result<()> {
+        let new_path = new_path.as_ref();
+        self.fs
+            .rename(&self.path, new_path)
+            .with_err_path(|| new_path)?;
+        self.path = PathBuf::new();
+        Ok(())
+    }
+
+    /// Close and remove the temporary file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned.
+    pub fn close(mut self) -> io::Result<()> {
+        let path = mem::take(&mut self.path);
+        self.fs.remove_file(&path).with_err_path(|| path)
+    }
+}
+
+impl<Fs: TempFs> fmt::Debug for FsTempFile<'_, Fs> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FsTempFile({:?})", Redact(&self.path))
+    }
+}
+
+impl<Fs: TempFs> Drop for FsTempFile<'_, Fs> {
+    fn drop(&mut self) {
+        // An empty path means the file has already been persisted or closed.
+        if !self.path.as_os_str().is_empty() {
+            let _ = self.fs.remove_file(&self.path);
+        }
+    }
+}
+
+impl<Fs: TempFs> Read for FsTempFile<'_, Fs> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).with_err_path(|| &self.path)
+    }
+}
+
+impl<Fs: TempFs> Write for FsTempFile<'_, Fs> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf).with_err_path(|| &self.path)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush().with_err_path(|| &self.path)
+    }
+}
+
+impl<Fs: TempFs> Seek for FsTempFile<'_, Fs> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).with_err_path(|| &self.path)
+    }
+}
+
+/// A temporary directory created on a [`TempFs`] with [`Builder::tempdir_in_fs`].
+///
+/// The directory is recursively removed (using the same `TempFs`) when dropped.
+pub struct FsTempDir<'fs, Fs: TempFs> {
+    fs: &'fs Fs,
+    path: PathBuf,
+}
+
+impl<'fs, Fs: TempFs> FsTempDir<'fs, Fs> {
+    /// Accesses the path to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Closes and removes the temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be deleted, `Err` is returned.
+    pub fn close(mut self) -> io::Result<()> {
+        let path = mem::take(&mut self.path);
+        self.fs.remove_dir_all(&path).with_err_path(|| path)
+    }
+}
+
+impl<Fs: TempFs> fmt::Debug for FsTempDir<'_, Fs> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsTempDir")
+            .field("path", &Redact(&self.path))
+            .finish()
+    }
+}
+
+impl<Fs: TempFs> Drop for FsTempDir<'_, Fs> {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = self.fs.remove_dir_all(&self.path);
+        }
+    }
+}
+
+impl<Fs: TempFs> AsRef<Path> for FsTempDir<'_, Fs> {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// A source of temporary files, directories, and spooled buffers.
+///
+/// Accept `impl TempStorage` (instead of calling [`NamedTempFile::new`](crate::NamedTempFile::new)
+/// and friends directly) to let callers decide where, and how, temporary data is stored. The
+/// [`Builder`] implementation creates real temporary files and directories with the builder's
+/// settings, while [`FsStorage`] creates them on any [`TempFs`] (e.g., an in-memory one in tests).
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Write};
+/// use tempfile::vfs::TempStorage;
+/// use tempfile::Builder;
+///
+/// fn render<S: TempStorage>(storage: &S) -> io::Result<()> {
+///     let mut frame = storage.tempfile()?;
+///     frame.write_all(b"...")?;
+///     let mut log = storage.spooled(4096);
+///     log.write_all(b"rendered one frame")?;
+///     Ok(())
+/// }
+///
+/// let mut builder = Builder::new();
+/// builder.prefix("render-");
+/// render(&builder)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait TempStorage {
+    /// The type of temporary files returned by [`TempStorage::tempfile`], deleted when dropped.
+    type File: Read + Write + Seek;
+
+    /// The type of temporary directories returned by [`TempStorage::tempdir`], deleted when
+    /// dropped.
+    type Dir: AsRef<Path>;
+
+    /// The type of spooled buffers returned by [`TempStorage::spooled`].
+    type Spooled: Read + Write + Seek;
+
+    /// Create a new temporary file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    fn tempfile(&self) -> io::Result<Self::File>;
+
+    /// Create a new temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be created, `Err` is returned.
+    fn tempdir(&self) -> io::Result<Self::Dir>;
+
+    /// Create a buffer that keeps its data in memory until it reaches `max_size` bytes (at which
+    /// point it may be moved to a temporary file).
+    fn spooled(&self, max_size: usize) -> Self::Spooled;
+}
+
+impl<S: TempStorage + ?Sized> TempStorage for &S {
+    type File = S::File;
+    type Dir = S::Dir;
+    type Spooled = S::Spooled;
+
+    fn tempfile(&self) -> io::Result<S::File> {
+        (**self).tempfile()
+    }
+
+    fn tempdir(&self) -> io::Result<S::Dir> {
+        (**self).tempdir()
+    }
+
+    fn spooled(&self, max_size: usize) -> S::Spooled {
+        (**self).spooled(max_size)
+    }
+}
+
+/// Creates temporary files and directories in [`env::temp_dir`](crate::env::temp_dir) with the
+/// builder's settings, see [`Builder::tempfile`], [`Builder::tempdir`], and [`Builder::spooled`].
+impl TempStorage for Builder<'_, '_> {
+    type File = NamedTempFile;
+    type Dir = TempDir;
+    type Spooled = SpooledTempFile;
+
+    fn tempfile(&self) -> io::Result<NamedTempFile> {
+        Builder::tempfile(self)
+    }
+
+    fn tempdir(&self) -> io::Result<TempDir> {
+        Builder::tempdir(self)
+    }
+
+    fn spooled(&self, max_size: usize) -> SpooledTempFile {
+        Builder::spooled(self, max_size)
+    }
+}
+
+/// A [`TempStorage`] that creates temporary files and directories in a directory of a [`TempFs`]
+/// (e.g., a `MemFs` in tests).
+///
+/// Spooled buffers are always kept in memory, since a `TempFs` can't provide unnamed files.
+pub struct FsStorage<'fs, Fs: TempFs> {
+    fs: &'fs Fs,
+    dir: PathBuf,
+}
+
+impl<'fs, Fs: TempFs> FsStorage<'fs, Fs> {
+    /// Create temporary files and directories in `dir` of `fs`.
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(fs: &'fs Fs, dir: P) -> FsStorage<'fs, Fs> {
+        FsStorage {
+            fs,
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    /// Returns the directory temporary files and directories are created in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl<Fs: TempFs> fmt::Debug for FsStorage<'_, Fs> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsStorage")
+            .field("dir", &Redact(&self.dir))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'fs, Fs: TempFs> TempStorage for FsStorage<'fs, Fs> {
+    type File = FsTempFile<'fs, Fs>;
+    type Dir = FsTempDir<'fs, Fs>;
+    type Spooled = Cursor<Vec<u8>>;
+
+    fn tempfile(&self) -> io::Result<FsTempFile<'fs, Fs>> {
+        Builder::new().tempfile_in_fs(self.fs, &self.dir)
+    }
+
+    fn tempdir(&self) -> io::Result<FsTempDir<'fs, Fs>> {
+        Builder::new().tempdir_in_fs(self.fs, &self.dir)
+    }
+
+    fn spooled(&self, _max_size: usize) -> Cursor<Vec<u8>> {
+        Cursor::new(Vec::new())
+    }
+}
+
+impl Builder<'_, '_> {
+    /// Create a named temporary file in the specified directory of the given [`TempFs`].
+    ///
+    /// The builder's prefix, suffix, and random length are honored. Other settings (permissions,
+    /// append mode, etc.) are up to the `TempFs` implementation.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    pub fn tempfile_in_fs<'fs, Fs: TempFs, P: AsRef<Path>>(
+        &self,
+        fs: &'fs Fs,
+        dir: P,
+    ) -> io::Result<FsTempFile<'fs, Fs>> {
+        util::create_helper(dir.as_ref(), &self.name_spec(), |path| {
+            let file = fs.create_new(&path).with_err_path(|| &path)?;
+            Ok(FsTempFile { fs, path, file })
+        })
+    }
+
+    /// Create a temporary directory in the specified directory of the given [`TempFs`].
+    ///
+    /// The builder's prefix, suffix, and random length are honored. Other settings are up to the
+    /// `TempFs` implementation.
+    ///
+    /// # Errors
+    ///
+    /// If the directory cannot be created, `Err` is returned.
+    pub fn tempdir_in_fs<'fs, Fs: TempFs, P: AsRef<Path>>(
+        &self,
+        fs: &'fs Fs,
+        dir: P,
+    ) -> io::Result<FsTempDir<'fs, Fs>> {
+        util::create_helper(dir.as_ref(), &self.name_spec(), |path| {
+            fs.create_dir(&path).with_err_path(|| &path)?;
+            Ok(FsTempDir { fs, path })
+        })
+    }
+}
+
+#[cfg(feature = "testing")]
+mod memfs {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex, MutexGuard};
+
+    use super::TempFs;
+
+    type Contents = Arc<Mutex<Vec<u8>>>;
+
+    #[derive(Debug, Default)]
+    struct State {
+        files: BTreeMap<PathBuf, Contents>,
+        dirs: BTreeSet<PathBuf>,
+    }
+
+    /// An in-memory [`TempFs`] for unit tests (requires the `testing` feature).
+    ///
+    /// Directories don't need to exist before files are created inside them; parent directories
+    /// are implied. File contents can be inspected with [`MemFs::read`].
+    #[derive(Debug, Default)]
+    pub struct MemFs {
+        state: Mutex<State>,
+    }
+
+    impl MemFs {
+        /// Create a new, empty, in-memory filesystem.
+        #[must_use]
+        pub fn new() -> MemFs {
+            MemFs::default()
+        }
+
+        /// Returns the contents of the file at `path`, if it exists.
+        pub fn read<P: AsRef<Path>>(&self, path: P) -> Option<Vec<u8>> {
+            let state = self.state();
+            let contents = state.files.get(path.as_ref())?;
+            let contents = lock(contents).clone();
+            Some(contents)
+        }
+
+        /// Returns `true` if a file or directory exists at `path`.
+        pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+            let state = self.state();
+            let path = path.as_ref();
+            state.files.contains_key(path) || state.dirs.contains(path)
+        }
+
+        /// Returns the paths of all files and directories, sorted.
+        pub fn entries(&self) -> Vec<PathBuf> {
+            let state = self.state();
+            let mut entries: Vec<PathBuf> = state
+                .files
+                .keys()
+                .chain(state.dirs.iter())
+                .cloned()
+                .collect();
+            entries.sort();
+            entries
+        }
+
+        fn state(&self) -> MutexGuard<'_, State> {
+            lock(&self.state)
+        }
+    }
+
+    fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+        m.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn not_found() -> io::Error {
+        io::ErrorKind::NotFound.into()
+    }
+
+    impl TempFs for MemFs {
+        type File = MemFile;
+
+        fn create_new(&self, path: &Path) -> io::Result<MemFile> {
+            let mut state = self.state();
+            if state.files.contains_key(path) || state.dirs.contains(path) {
+                return Err(io::ErrorKind::AlreadyExists.into());
+            }
+            let contents = Contents::default();
+            state.files.insert(path.to_owned(), contents.clone());
+            Ok(MemFile { contents, pos: 0 })
+        }
+
+        fn create_dir(&self, path: &Path) -> io::Result<()> {
+            let mut state = self.state();
+            if state.files.contains_key(path) || !state.dirs.insert(path.to_owned()) {
+                return Err(io::ErrorKind::AlreadyExists.into());
+            }
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut state = self.state();
+            if let Some(contents) = state.files.remove(from) {
+                state.files.insert(to.to_owned(), contents);
+                return Ok(());
+            }
+            if !state.dirs.remove(from) {
+                return Err(not_found());
+            }
+            state.dirs.insert(to.to_owned());
+            let moved: Vec<PathBuf> = state
+                .files
+                .keys()
+                .filter(|p| p.starts_with(from))
+                .cloned()
+                .collect();
+            for path in moved {
+                let contents = state.files.remove(&path).unwrap();
+                let new_path = to.join(path.strip_prefix(from).unwrap());
+                state.files.insert(new_path, contents);
+            }
+            let moved: Vec<PathBuf> = state
+                .dirs
+                .iter()
+                .filter(|p| p.starts_with(from))
+                .cloned()
+                .collect();
+            for path in moved {
+                state.dirs.remove(&path);
+                state.dirs.insert(to.join(path.strip_prefix(from).unwrap()));
+            }
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.state()
+                .files
+                .remove(path)
+                .map(drop)
+                .ok_or_else(not_found)
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+            let mut state = self.state();
+            if !state.dirs.contains(path) {
+                return Err(not_found());
+            }
+            state.files.retain(|p, _| !p.starts_with(path));
+            state.dirs.retain(|p| !p.starts_with(path));
+            Ok(())
+        }
+    }
+
+    /// An open file on a [`MemFs`].
+    ///
+    /// The file's contents remain accessible through the handle even after the file is removed
+    /// from the filesystem (like an unlinked file on Unix).
+    #[derive(Debug)]
+    pub struct MemFile {
+        contents: Contents,
+        pos: u64,
+    }
+
+    impl MemFile {
+        fn with_cursor<R>(&mut self, f: impl FnOnce(&mut Cursor<&mut Vec<u8>>) -> R) -> R {
+            let mut contents = lock(&self.contents);
+            let mut cursor = Cursor::new(&mut *contents);
+            cursor.set_position(self.pos);
+            let result = f(&mut cursor);
+            self.pos = cursor.position();
+            result
+        }
+    }
+
+    impl Read for MemFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.with_cursor(|c| c.read(buf))
+        }
+    }
+
+    impl Write for MemFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.with_cursor(|c| c.write(buf))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for MemFile {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.with_cursor(|c| c.seek(pos))
+        }
+    }
+}
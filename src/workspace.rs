@@ -0,0 +1,289 @@
+//! Named, reusable scratch directories (see [`Workspace`]).
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::error::IoResultExt;
+use crate::util::Redact;
+use crate::{dir, env};
+
+/// A stable, per-user scratch directory that's reused across runs (e.g., a render cache), as
+/// opposed to a [`TempDir`](crate::TempDir), which is unique and deleted when dropped.
+///
+/// Workspaces are identified by a relative name (e.g., `"myapp/render-cache"`), and live in a
+/// directory below [`env::temp_dir`] that only belongs to the current user. While a `Workspace` is
+/// open, it holds an exclusive lock (on a lock file next to the workspace), so no other process
+/// (or other `Workspace` in this process) can use the same workspace at the same time. Dropping
+/// the `Workspace` releases the lock, but leaves the directory and its contents in place.
+///
+/// Since workspaces live in the temporary directory, the system may still delete them at any time
+/// (e.g., on reboot), so only use them for data that can be recreated. Use
+/// [`Workspace::purge_older_than`] to keep them from growing forever.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tempfile::Workspace;
+///
+/// let cache = Workspace::open("myapp/render-cache")?;
+/// // Forget about anything that hasn't been touched in a week.
+/// cache.purge_older_than(Duration::from_secs(7 * 24 * 60 * 60))?;
+///
+/// let frame = cache.path().join("frame-0001.png");
+/// if !frame.exists() {
+///     std::fs::write(&frame, b"...")?;
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Workspace {
+    path: PathBuf,
+    // Held (and locked) for as long as the workspace is open.
+    _lock: File,
+}
+
+impl Workspace {
+    /// Opens (creating it if necessary) the workspace called `name`, waiting until no one else is
+    /// using it.
+    ///
+    /// # Errors
+    ///
+    /// If `name` isn't a relative path without `..` components, an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned. If the per-user directory holding the
+    /// workspaces exists but doesn't belong to the current user (or is accessible to others),
+    /// an error of kind [`io::ErrorKind::PermissionDenied`] is returned. If the workspace can't be
+    /// created or locked, `Err` is returned as well. On platforms without `flock` or `LockFileEx`
+    /// (e.g., Solaris), workspaces can't be locked, so this always fails.
+    pub fn open<N: AsRef<Path>>(name: N) -> io::Result<Workspace> {
+        Workspace::open_impl(name.as_ref(), true)
+    }
+
+    /// Like [`Workspace::open`], but fails with an error of kind [`io::ErrorKind::WouldBlock`]
+    /// instead of waiting if the workspace is already in use.
+    ///
+    /// # Errors
+    ///
+    /// See [`Workspace::open`].
+    pub fn try_open<N: AsRef<Path>>(name: N) -> io::Result<Workspace> {
+        Workspace::open_impl(name.as_ref(), false)
+    }
+
+    fn open_impl(name: &Path, wait: bool) -> io::Result<Workspace> {
+        let file_name = match name.file_name() {
+            Some(file_name)
+                if name
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) =>
+            {
+                file_name
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "workspace names must be relative paths without `..`",
+                ))
+                .with_err_path(|| name)
+            }
+        };
+
+        let root = user_root()?;
+        let path = root.join(name);
+        dir::create_all(&path, private_permissions().as_ref())?;
+
+        let mut lock_name = file_name.to_owned();
+        lock_name.push(".lock");
+        let lock_path = path.with_file_name(lock_name);
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(crate::file::imp::nofollow());
+        }
+        let lock = options.open(&lock_path).with_err_path(|| &lock_path)?;
+        imp::lock(&lock, wait).with_err_path(|| &lock_path)?;
+
+        Ok(Workspace { path, _lock: lock })
+    }
+
+    /// Returns the path of the workspace directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Deletes every file and directory directly inside the workspace that hasn't been modified
+    /// for at least `age`, and returns how many were deleted.
+    ///
+    /// Only the modification time of the entry itself is taken into account: a directory is deleted
+    /// (with all its contents) if no entries have been added to or removed from it for `age`, even
+    /// if files inside it have been modified since.
+    ///
+    /// Workspaces nested inside this one (e.g., `"myapp/render-cache"` inside `"myapp"`) are never
+    /// deleted, and neither are their lock files, because another process may be using them. Such
+    /// a workspace is recognized by the `<name>.lock` file next to its directory.
+    ///
+    /// # Errors
+    ///
+    /// If the workspace can't be read or an entry can't be deleted, `Err` is returned. Entries
+    /// deleted up to that point stay deleted.
+    pub fn purge_older_than(&self, age: Duration) -> io::Result<usize> {
+        let now = SystemTime::now();
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.path).with_err_path(|| &self.path)? {
+            let entry = entry.with_err_path(|| &self.path)?;
+            let path = entry.path();
+            // Don't follow symlinks, only remove the links themselves.
+            let meta = fs::symlink_metadata(&path).with_err_path(|| &path)?;
+            // Entries modified "in the future" (e.g., because the clock changed) are kept.
+            let stale = match now.duration_since(meta.modified().with_err_path(|| &path)?) {
+                Ok(elapsed) => elapsed >= age,
+                Err(_) => false,
+            };
+            if !stale || is_nested_workspace(&path, &meta) {
+                continue;
+            }
+            if meta.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            }
+            .with_err_path(|| &path)?;
+            purged += 1;
+        }
+        Ok(purged)
+    }
+}
+
+impl fmt::Debug for Workspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Workspace")
+            .field("path", &Redact(&self.path))
+            .finish()
+    }
+}
+
+impl AsRef<Path> for Workspace {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Returns true if `path` is the directory or the lock file of a workspace nested inside another
+/// one.
+fn is_nested_workspace(path: &Path, meta: &fs::Metadata) -> bool {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name,
+        None => return false,
+    };
+    if meta.is_dir() {
+        let mut lock_name = file_name.to_owned();
+        lock_name.push(".lock");
+        fs::symlink_metadata(path.with_file_name(lock_name)).map_or(false, |m| m.is_file())
+    } else {
+        match Path::new(file_name)
+            .extension()
+            .filter(|ext| *ext == "lock")
+            .and(Path::new(file_name).file_stem())
+        {
+            Some(stem) => {
+                fs::symlink_metadata(path.with_file_name(stem)).map_or(false, |m| m.is_dir())
+            }
+            None => false,
+        }
+    }
+}
+
+fn private_permissions() -> Option<fs::Permissions> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// Returns the directory holding the current user's workspaces, creating it if necessary.
+#[cfg(unix)]
+fn user_root() -> io::Result<PathBuf> {
+    // The temporary directory is usually shared between all users, so make sure no one else can
+    // get at (or swap out) our workspaces.
+    let uid = rustix::process::geteuid().as_raw();
+    let root = env::temp_dir().join(format!("tempfile-workspaces-{}", uid));
+    dir::create_private(&root, uid)?;
+    Ok(root)
+}
+
+/// Returns the directory holding the current user's workspaces, creating it if necessary.
+#[cfg(not(unix))]
+fn user_root() -> io::Result<PathBuf> {
+    // The temporary directory is per-user on Windows.
+    let root = env::temp_dir().join("tempfile-workspaces");
+    dir::create_all(&root, None)?;
+    Ok(root)
+}
+
+#[cfg(all(unix, not(target_os = "solaris")))]
+mod imp {
+    use std::fs::File;
+    use std::io;
+
+    use rustix::fs::{flock, FlockOperation};
+
+    pub fn lock(file: &File, wait: bool) -> io::Result<()> {
+        let operation = if wait {
+            FlockOperation::LockExclusive
+        } else {
+            FlockOperation::NonBlockingLockExclusive
+        };
+        Ok(flock(file, operation)?)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+
+    use windows_sys::Win32::Foundation::{ERROR_LOCK_VIOLATION, HANDLE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+    };
+    use windows_sys::Win32::System::IO::OVERLAPPED;
+
+    pub fn lock(file: &File, wait: bool) -> io::Result<()> {
+        let mut flags = LOCKFILE_EXCLUSIVE_LOCK;
+        if !wait {
+            flags |= LOCKFILE_FAIL_IMMEDIATELY;
+        }
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        let handle = file.as_raw_handle() as HANDLE;
+        if unsafe { LockFileEx(handle, flags, 0, u32::MAX, u32::MAX, &mut overlapped) } == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, err));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(all(unix, not(target_os = "solaris")), windows)))]
+mod imp {
+    use std::fs::File;
+    use std::io;
+
+    pub fn lock(_file: &File, _wait: bool) -> io::Result<()> {
+        // Without locking, workspaces can't be used exclusively, so don't pretend they can.
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "workspaces aren't supported on this platform (file locking is unavailable)",
+        ))
+    }
+}
@@ -0,0 +1,130 @@
+#![deny(rust_2018_idioms)]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tempfile::env::{self, DiskBudget};
+use tempfile::{spooled_tempfile, Builder, NamedTempFile};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+// The budget is process-wide, so everything is tested in one go.
+#[test]
+fn test_disk_budget() {
+    configure_wasi_temp_dir();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let budget = {
+        let calls = calls.clone();
+        DiskBudget::new(100).on_exceeded(move |exceeded| {
+            assert_eq!(exceeded.limit(), 100);
+            calls.fetch_add(1, Ordering::Relaxed);
+        })
+    };
+    env::override_disk_budget(budget).unwrap();
+    env::override_disk_budget(DiskBudget::new(1)).unwrap_err();
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&[0; 60]).unwrap();
+    assert_eq!(env::disk_usage(), 60);
+    (&file).write_all(&[0; 30]).unwrap();
+    assert_eq!(env::disk_usage(), 90);
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+    // The callback is called, but the write goes ahead.
+    let mut other = NamedTempFile::new().unwrap();
+    other.write_all(&[0; 21]).unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert_eq!(env::disk_usage(), 111);
+
+    // Deleting or persisting a file releases its bytes.
+    drop(file);
+    assert_eq!(env::disk_usage(), 21);
+    let dir = tempfile::tempdir().unwrap();
+    other.persist(dir.path().join("kept")).unwrap();
+    assert_eq!(env::disk_usage(), 0);
+
+    // Only the bytes that were written stay charged when a write fails part way.
+    let (_, path) = NamedTempFile::new().unwrap().into_parts();
+    let mut failing = NamedTempFile::from_parts(FailAfter(10), path);
+    failing.write_all(&[0; 30]).unwrap_err();
+    assert_eq!(env::disk_usage(), 10);
+    drop(failing);
+    assert_eq!(env::disk_usage(), 0);
+
+    // Overwriting data isn't counted again.
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&[0; 50]).unwrap();
+    file.seek(SeekFrom::Start(10)).unwrap();
+    file.write_all(&[1; 50]).unwrap();
+    assert_eq!(env::disk_usage(), 60);
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_exact(&mut [0; 10]).unwrap();
+    (&file).write_all(&[2; 10]).unwrap();
+    assert_eq!(env::disk_usage(), 60);
+
+    // Truncating the file gives the bytes back, extending it counts.
+    file.set_len(20).unwrap();
+    assert_eq!(env::disk_usage(), 20);
+    file.set_len(40).unwrap();
+    assert_eq!(env::disk_usage(), 40);
+    file.seek(SeekFrom::End(0)).unwrap();
+    file.write_all(&[3; 10]).unwrap();
+    assert_eq!(env::disk_usage(), 50);
+
+    // Copies count as well.
+    let copy = file.duplicate(&Builder::new()).unwrap();
+    assert_eq!(env::disk_usage(), 100);
+    drop(copy);
+    drop(file);
+    assert_eq!(env::disk_usage(), 0);
+
+    // Spooled files count once they've been rolled over, including the data written out.
+    let mut spooled = spooled_tempfile(10);
+    spooled.write_all(&[0; 8]).unwrap();
+    assert_eq!(env::disk_usage(), 0);
+    spooled.write_all(&[0; 8]).unwrap();
+    assert_eq!(env::disk_usage(), 16);
+    spooled.seek(SeekFrom::Start(0)).unwrap();
+    spooled.write_all(&[1; 8]).unwrap();
+    assert_eq!(env::disk_usage(), 16);
+    spooled.set_len(4).unwrap();
+    assert_eq!(env::disk_usage(), 4);
+    let named = spooled.into_named(&Builder::new()).unwrap();
+    assert_eq!(env::disk_usage(), 4);
+    drop(named);
+    assert_eq!(env::disk_usage(), 0);
+
+    let mut spooled = spooled_tempfile(10);
+    spooled.spill_in_background(true);
+    spooled.write_all(&[0; 8]).unwrap();
+    spooled.write_all(&[0; 8]).unwrap();
+    spooled.flush().unwrap();
+    assert_eq!(env::disk_usage(), 16);
+    drop(spooled);
+    assert_eq!(env::disk_usage(), 0);
+}
+
+/// Accepts the given number of bytes, then fails.
+struct FailAfter(usize);
+
+impl Write for FailAfter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.0 == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, "disk full"));
+        }
+        let n = buf.len().min(self.0);
+        self.0 -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+#![deny(rust_2018_idioms)]
+#![cfg(all(feature = "capi", unix))]
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+
+use tempfile::capi::{tempfile_mkdtemp, tempfile_mkstemp};
+
+fn template_in(dir: &Path, name: &str) -> CString {
+    CString::new(dir.join(name).into_os_string().into_string().unwrap()).unwrap()
+}
+
+#[test]
+fn test_mkstemp() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = template_in(dir.path(), "capi-XXXXXX").into_raw();
+    let fd = unsafe { tempfile_mkstemp(template) };
+    let template = unsafe { CString::from_raw(template) };
+    assert!(fd >= 0);
+
+    let path = template.to_str().unwrap();
+    assert!(!path.ends_with("XXXXXX"));
+    assert!(Path::new(path).is_file());
+    assert_eq!(path.len(), dir.path().join("capi-XXXXXX").as_os_str().len());
+
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(b"abcde").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+}
+
+#[test]
+fn test_mkdtemp() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = template_in(dir.path(), "capi-XXXXXX").into_raw();
+    let result = unsafe { tempfile_mkdtemp(template) };
+    assert_eq!(result, template);
+    let template = unsafe { CString::from_raw(template) };
+    assert!(Path::new(template.to_str().unwrap()).is_dir());
+}
+
+#[test]
+fn test_invalid_template() {
+    let template = CString::new("no-placeholder").unwrap().into_raw();
+    assert_eq!(unsafe { tempfile_mkstemp(template) }, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert!(unsafe { tempfile_mkdtemp(template) }.is_null());
+    let template = unsafe { CString::from_raw(template) };
+    assert_eq!(
+        template.as_c_str(),
+        CStr::from_bytes_with_nul(b"no-placeholder\0").unwrap()
+    );
+}
+
+#[test]
+fn test_short_placeholder() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = template_in(dir.path(), "capi-XXXXX").into_raw();
+    assert_eq!(unsafe { tempfile_mkstemp(template) }, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    drop(unsafe { CString::from_raw(template) });
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,99 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "cas")]
+
+use std::fs;
+use std::io::Write;
+
+use tempfile::{CasDir, HashAlgo, NamedTempFile};
+
+const HASH: &str = "c7c5c1d70c5dec4416ab6158afd0b223ef40c29b1dc1f97ed9428b94d4cadb1c";
+
+#[test]
+fn test_insert_dedup() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = CasDir::new(root.path().join("cache")).unwrap();
+
+    let mut first = cache.tempfile().unwrap();
+    first.write_all(b"artifact").unwrap();
+    let first_path = first.path().to_owned();
+    let first = cache.insert(first).unwrap();
+    assert!(!first.was_cached());
+    assert!(!first_path.exists());
+    assert_eq!(first.hash(), HASH);
+    assert_eq!(first.path(), cache.path().join(&HASH[..2]).join(&HASH[2..]));
+    assert_eq!(fs::read(first.path()).unwrap(), b"artifact");
+    assert!(fs::metadata(first.path()).unwrap().permissions().readonly());
+
+    let mut second = NamedTempFile::new_in(root.path()).unwrap();
+    second.write_all(b"artifact").unwrap();
+    let second_path = second.path().to_owned();
+    let second = cache.insert(second).unwrap();
+    assert!(second.was_cached());
+    assert!(!second_path.exists());
+    assert_eq!(second.path(), first.path());
+
+    assert_eq!(cache.get(HASH), Some(second));
+    assert_eq!(cache.get(&HASH.replace('c', "d")), None);
+    assert_eq!(cache.get("../etc"), None);
+}
+
+#[test]
+fn test_link_to() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = CasDir::new(root.path().join("cache")).unwrap();
+
+    let mut file = cache.tempfile().unwrap();
+    file.write_all(b"artifact").unwrap();
+    let cached = cache.insert(file).unwrap();
+
+    let dest = root.path().join("out.bin");
+    cached.link_to(&dest).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), b"artifact");
+    cached.link_to(&dest).unwrap_err();
+}
+
+#[test]
+fn test_persist_by_hash() {
+    let store = tempfile::tempdir().unwrap();
+
+    let mut file = tempfile::Builder::new()
+        .suffix(".txt")
+        .tempfile_in(store.path())
+        .unwrap();
+    file.write_all(b"artifact").unwrap();
+    let (_, path) = file
+        .persist_by_hash(store.path(), HashAlgo::Sha256)
+        .unwrap();
+    assert_eq!(path, store.path().join(format!("{}.txt", HASH)));
+    assert_eq!(fs::read(&path).unwrap(), b"artifact");
+
+    let mut file = NamedTempFile::new_in(store.path()).unwrap();
+    file.write_all(b"artifact").unwrap();
+    let (mut file, path) = file
+        .persist_by_hash(store.path(), HashAlgo::Sha512)
+        .unwrap();
+    assert_eq!(path.file_name().unwrap().len(), 128);
+    // The position is restored after hashing.
+    file.write_all(b"!").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"artifact!");
+}
+
+#[test]
+#[cfg(feature = "failpoints")]
+fn test_insert_cross_device() {
+    use tempfile::failpoints::{self, Failpoint};
+
+    let root = tempfile::tempdir().unwrap();
+    let cache = CasDir::new(root.path().join("cache")).unwrap();
+
+    // Files that can't be moved into the cache because they're on another filesystem are copied.
+    let mut file = NamedTempFile::new_in(root.path()).unwrap();
+    file.write_all(b"artifact").unwrap();
+    let file_path = file.path().to_owned();
+    failpoints::fail(Failpoint::PersistCrossDevice, 1);
+    let cached = cache.insert(file).unwrap();
+    failpoints::clear();
+    assert!(!cached.was_cached());
+    assert!(!file_path.exists());
+    assert_eq!(fs::read(cached.path()).unwrap(), b"artifact");
+}
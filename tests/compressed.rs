@@ -0,0 +1,78 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "zstd")]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use tempfile::CompressedSpooledTempFile;
+
+fn read_all(file: &mut CompressedSpooledTempFile) -> Vec<u8> {
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_in_memory() {
+    let mut file = CompressedSpooledTempFile::new(100, 0);
+    assert!(file.is_empty());
+    file.write_all(b"hello world").unwrap();
+    assert!(!file.is_rolled());
+    assert_eq!(file.len(), 11);
+
+    assert_eq!(read_all(&mut file), b"hello world");
+    file.seek(SeekFrom::Start(6)).unwrap();
+    assert_eq!(read_all(&mut file), b"world");
+
+    // Writes append, regardless of the read position.
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(b"!").unwrap();
+    assert_eq!(read_all(&mut file), b"hello world!");
+}
+
+#[test]
+fn test_spill() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut file = CompressedSpooledTempFile::new_in(64, 3, dir.path());
+    let line = b"{\"level\": \"info\", \"message\": \"compressible\"}\n";
+    for _ in 0..1000 {
+        file.write_all(line).unwrap();
+    }
+    assert!(file.is_rolled());
+    assert_eq!(file.len(), line.len() as u64 * 1000);
+
+    let expected = line.repeat(1000);
+    assert_eq!(read_all(&mut file), expected);
+
+    // Seek backwards and forwards.
+    file.seek(SeekFrom::Start(line.len() as u64 * 10)).unwrap();
+    let mut buf = vec![0; line.len()];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, line);
+    file.seek(SeekFrom::Current(line.len() as u64 as i64 * 5))
+        .unwrap();
+    assert_eq!(file.stream_position().unwrap(), line.len() as u64 * 16);
+    file.seek(SeekFrom::End(-(line.len() as i64))).unwrap();
+    assert_eq!(read_all(&mut file), line);
+    assert!(file.seek(SeekFrom::Current(-1_000_000)).is_err());
+
+    // Append after reading, then read everything again.
+    file.write_all(b"tail").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut expected = expected;
+    expected.extend_from_slice(b"tail");
+    assert_eq!(read_all(&mut file), expected);
+
+    // Past the end.
+    file.seek(SeekFrom::Start(file.len() + 10)).unwrap();
+    assert_eq!(read_all(&mut file), b"");
+}
+
+#[test]
+fn test_roll() {
+    let mut file = CompressedSpooledTempFile::new(100, 0);
+    file.write_all(b"abc").unwrap();
+    file.seek(SeekFrom::Start(1)).unwrap();
+    file.roll().unwrap();
+    assert!(file.is_rolled());
+    assert_eq!(read_all(&mut file), b"bc");
+}
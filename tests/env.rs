@@ -14,3 +14,76 @@ fn test_override_temp_dir() {
     let new_tmp2 = Path::new("/tmp/override2");
     tempfile::env::override_temp_dir(new_tmp2).expect_err("override should only be possible once");
 }
+
+#[test]
+fn test_override_default_builder() {
+    let mut defaults = tempfile::Builder::new();
+    defaults.prefix("default-prefix-").rand_bytes(10);
+    // Exact permissions stay exact when inherited, regardless of the umask.
+    #[cfg(unix)]
+    defaults.permissions_exact(std::os::unix::fs::PermissionsExt::from_mode(0o777));
+    tempfile::env::override_default_builder(defaults.clone()).unwrap();
+
+    let tmpdir = tempfile::Builder::new()
+        .tempdir_in(std::env::temp_dir())
+        .unwrap();
+    let name = tmpdir.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("default-prefix-"));
+    assert_eq!(name.len(), "default-prefix-".len() + 10);
+
+    // Explicit settings take precedence over the defaults.
+    let tmpfile = tempfile::Builder::new()
+        .prefix("explicit-")
+        .tempfile_in(&tmpdir)
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("explicit-"));
+    assert_eq!(name.len(), "explicit-".len() + 10);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |file: &tempfile::NamedTempFile| file.metadata().unwrap().permissions().mode();
+        assert_eq!(mode(&tmpfile) & 0o777, 0o777);
+        // Permission settings aren't mixed with the defaults.
+        let readable = tempfile::Builder::new()
+            .world_readable()
+            .tempfile_in(&tmpdir)
+            .unwrap();
+        assert_eq!(mode(&readable) & 0o777, 0o644);
+    }
+
+    let prev = tempfile::env::override_default_builder(tempfile::Builder::new())
+        .expect_err("override should only be possible once");
+    assert_eq!(prev, defaults);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_best_temp_dir() {
+    let tmp = std::env::temp_dir();
+    let missing = tmp.join("does-not-exist");
+
+    let best = tempfile::env::best_temp_dir_among([&missing, &tmp], 0).unwrap();
+    assert_eq!(best, tmp);
+
+    tempfile::env::best_temp_dir_among([&missing], 0).unwrap_err();
+    tempfile::env::best_temp_dir_among([&tmp], u64::MAX).unwrap_err();
+
+    let best = tempfile::env::best_temp_dir(1).unwrap();
+    assert!(best.is_dir());
+}
+
+#[test]
+fn test_entropy_source() {
+    use tempfile::env::EntropySource;
+
+    let source = tempfile::env::entropy_source();
+    if cfg!(feature = "getrandom") {
+        assert_eq!(source, EntropySource::Getrandom);
+    } else if cfg!(unix) {
+        assert_eq!(source, EntropySource::DevUrandom);
+    }
+    assert_eq!(source.is_secure(), source != EntropySource::Mixed);
+}
@@ -0,0 +1,110 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "failpoints")]
+
+use std::io;
+use std::thread;
+
+use tempfile::failpoints::{self, Failpoint};
+use tempfile::{env, tempdir, NamedTempFile, TempDir};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_collision() {
+    configure_wasi_temp_dir();
+    let dir = tempdir().unwrap();
+
+    // A few collisions are retried.
+    failpoints::fail(Failpoint::Collision, 2);
+    NamedTempFile::new_in(&dir).unwrap();
+    assert_eq!(failpoints::remaining(Failpoint::Collision), 0);
+
+    failpoints::fail(Failpoint::Collision, usize::MAX);
+    let err = TempDir::new_in(&dir).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    failpoints::clear();
+    TempDir::new_in(&dir).unwrap();
+}
+
+#[test]
+fn test_persist_cross_device() {
+    configure_wasi_temp_dir();
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("target");
+    let file = NamedTempFile::new_in(&dir).unwrap();
+
+    failpoints::fail(Failpoint::PersistCrossDevice, 1);
+    let err = file.persist(&target).unwrap_err();
+    #[cfg(unix)]
+    assert_eq!(err.error.raw_os_error(), Some(18)); // EXDEV
+    assert!(!target.exists());
+    assert!(err.file.path().exists());
+
+    err.file.persist(&target).unwrap();
+    assert!(target.exists());
+}
+
+#[test]
+fn test_persist_with_backup_fails() {
+    configure_wasi_temp_dir();
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("config");
+    let backup = dir.path().join("config~");
+    std::fs::write(&target, "current").unwrap();
+    std::fs::write(&backup, "previous").unwrap();
+
+    // The target is linked to the backup, so it stays in place.
+    failpoints::fail(Failpoint::PersistCrossDevice, 1);
+    let err = NamedTempFile::new_in(&dir)
+        .unwrap()
+        .persist_with_backup(&target, "~")
+        .unwrap_err();
+    assert!(err.file.path().exists());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "current");
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), "current");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+    // Directories can't be linked, so the target is moved to the backup and moved back.
+    #[cfg(unix)]
+    {
+        std::fs::remove_file(&target).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+        std::fs::create_dir(&target).unwrap();
+        failpoints::fail(Failpoint::PersistCrossDevice, 1);
+        err.file.persist_with_backup(&target, "~").unwrap_err();
+        assert!(target.is_dir());
+        assert!(!backup.exists());
+    }
+}
+
+#[test]
+fn test_cleanup_permission_denied() {
+    configure_wasi_temp_dir();
+    let dir = tempdir().unwrap();
+
+    let file = NamedTempFile::new_in(&dir).unwrap();
+    let path = file.path().to_owned();
+    failpoints::fail(Failpoint::CleanupPermissionDenied, 1);
+    let err = file.close().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    assert!(path.exists());
+
+    let subdir = TempDir::new_in(&dir).unwrap();
+    let path = subdir.path().to_owned();
+    failpoints::fail(Failpoint::CleanupPermissionDenied, 1);
+    drop(subdir);
+    assert!(path.exists());
+
+    // Failpoints only apply to the thread that armed them.
+    failpoints::fail(Failpoint::CleanupPermissionDenied, 1);
+    let file = NamedTempFile::new_in(&dir).unwrap();
+    let path = file.path().to_owned();
+    thread::spawn(move || drop(file)).join().unwrap();
+    assert!(!path.exists());
+    assert_eq!(failpoints::remaining(Failpoint::CleanupPermissionDenied), 1);
+    failpoints::clear();
+}
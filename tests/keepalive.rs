@@ -0,0 +1,46 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "keepalive")]
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use tempfile::keepalive::Keepalive;
+use tempfile::{NamedTempFile, TempDir};
+
+#[test]
+fn test_keepalive() {
+    let keepalive = Keepalive::new(Duration::from_millis(10)).unwrap();
+
+    let file = NamedTempFile::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let file_mtime = file.modified().unwrap();
+    let dir_mtime = dir.path().metadata().unwrap().modified().unwrap();
+
+    let file_guard = keepalive.register_file(&file).unwrap();
+    let _dir_guard = keepalive.register_dir(&dir);
+    sleep(Duration::from_millis(200));
+    assert!(file.modified().unwrap() > file_mtime);
+    assert!(dir.path().metadata().unwrap().modified().unwrap() > dir_mtime);
+
+    // Unregistered files are left alone.
+    drop(file_guard);
+    sleep(Duration::from_millis(50));
+    let file_mtime = file.modified().unwrap();
+    sleep(Duration::from_millis(100));
+    assert_eq!(file.modified().unwrap(), file_mtime);
+}
+
+#[test]
+fn test_touch_now() {
+    let keepalive = Keepalive::new(Duration::from_secs(60 * 60)).unwrap();
+    let file = NamedTempFile::new().unwrap();
+    let _guard = keepalive.register_file(&file).unwrap();
+
+    let mtime = file.modified().unwrap();
+    sleep(Duration::from_millis(50));
+    keepalive.touch_now();
+    assert!(file.modified().unwrap() > mtime);
+
+    // Stops the thread promptly, despite the long interval.
+    drop(keepalive);
+}
@@ -0,0 +1,112 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "metrics")]
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Recorder, SharedString, Unit};
+use tempfile::{env, spooled_tempfile, NamedTempFile, TempDir};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+/// Records counters and gauges by name and `kind` label.
+#[derive(Default)]
+struct TestRecorder(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+impl TestRecorder {
+    fn handle(&self, key: &Key) -> Arc<AtomicU64> {
+        let mut name = key.name().to_owned();
+        for label in key.labels() {
+            name = format!("{}/{}", name, label.value());
+        }
+        self.0.lock().unwrap().entry(name).or_default().clone()
+    }
+
+    fn counter(&self, name: &str) -> u64 {
+        self.0
+            .lock()
+            .unwrap()
+            .get(name)
+            .map_or(0, |v| v.load(Ordering::Relaxed))
+    }
+
+    fn gauge(&self, name: &str) -> f64 {
+        self.0
+            .lock()
+            .unwrap()
+            .get(name)
+            .map_or(0.0, |v| f64::from_bits(v.load(Ordering::Relaxed)))
+    }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key) -> Counter {
+        Counter::from_arc(self.handle(key))
+    }
+
+    fn register_gauge(&self, key: &Key) -> Gauge {
+        Gauge::from_arc(self.handle(key))
+    }
+
+    fn register_histogram(&self, _: &Key) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[test]
+fn test_lifecycle_metrics() {
+    configure_wasi_temp_dir();
+
+    let recorder: &'static TestRecorder = Box::leak(Box::default());
+    metrics::set_recorder(recorder).unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let a = NamedTempFile::new_in(dir.path()).unwrap();
+    let b = NamedTempFile::new_in(dir.path()).unwrap();
+    assert_eq!(recorder.counter("tempfile_created_total/dir"), 1);
+    assert_eq!(recorder.counter("tempfile_created_total/file"), 2);
+    assert_eq!(recorder.gauge("tempfile_live/dir"), 1.0);
+    assert_eq!(recorder.gauge("tempfile_live/file"), 2.0);
+
+    // Persisting, closing, and dropping all end the life of a temporary file.
+    a.persist(dir.path().join("persisted")).unwrap();
+    assert_eq!(recorder.gauge("tempfile_live/file"), 1.0);
+    b.close().unwrap();
+    assert_eq!(recorder.gauge("tempfile_live/file"), 0.0);
+    drop(NamedTempFile::new_in(dir.path()).unwrap());
+    assert_eq!(recorder.gauge("tempfile_live/file"), 0.0);
+    assert_eq!(recorder.counter("tempfile_created_total/file"), 3);
+
+    // The file is deleted behind our back, so dropping it fails to clean it up.
+    let c = NamedTempFile::new_in(dir.path()).unwrap();
+    std::fs::remove_file(c.path()).unwrap();
+    drop(c);
+    assert_eq!(recorder.counter("tempfile_cleanup_failures_total/file"), 1);
+
+    // A set that fails part way cleans up the members created so far, exactly once.
+    tempfile::Builder::new()
+        .tempfile_set_in(dir.path(), &[".a", "/missing"])
+        .unwrap_err();
+    assert_eq!(recorder.counter("tempfile_cleanup_failures_total/file"), 1);
+    assert_eq!(recorder.gauge("tempfile_live/file"), 0.0);
+
+    let mut spooled = spooled_tempfile(4);
+    spooled.write_all(b"abc").unwrap();
+    // Only the bytes already in memory are spilled, the rest is written to disk directly.
+    spooled.write_all(b"defgh").unwrap();
+    assert_eq!(recorder.counter("tempfile_spilled_bytes_total"), 3);
+
+    dir.close().unwrap();
+    assert_eq!(recorder.gauge("tempfile_live/dir"), 0.0);
+    assert_eq!(recorder.counter("tempfile_cleanup_failures_total/dir"), 0);
+}
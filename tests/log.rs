@@ -0,0 +1,148 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "log")]
+
+use std::io::Write;
+use std::sync::Mutex;
+
+use log::{Level, Log, Metadata, Record};
+use tempfile::{env, spooled_tempfile, NamedTempFile, TempDir};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+/// Collects the records of the `tempfile` target.
+struct TestLogger(Mutex<Vec<(Level, String)>>);
+
+impl TestLogger {
+    fn take(&self) -> Vec<(Level, String)> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Log for TestLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "tempfile"
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+
+#[test]
+fn test_log() {
+    configure_wasi_temp_dir();
+
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let dir = TempDir::new().unwrap();
+    let file = NamedTempFile::new_in(dir.path()).unwrap();
+    let records = LOGGER.take();
+    assert_eq!(
+        records,
+        [
+            (
+                Level::Debug,
+                format!("created temporary dir {}", dir.path().display())
+            ),
+            (
+                Level::Debug,
+                format!("created temporary file {}", file.path().display())
+            ),
+        ]
+    );
+
+    let from = file.path().to_owned();
+    let to = dir.path().join("persisted");
+    file.persist(&to).unwrap();
+    assert_eq!(
+        LOGGER.take(),
+        [(
+            Level::Debug,
+            format!(
+                "persisted temporary file {} to {}",
+                from.display(),
+                to.display()
+            )
+        )]
+    );
+
+    // So are the variants that keep the file open.
+    let durable = NamedTempFile::new_in(dir.path()).unwrap();
+    let from = durable.path().to_owned();
+    let by_handle = NamedTempFile::new_in(dir.path()).unwrap();
+    let by_handle_from = by_handle.path().to_owned();
+    LOGGER.take();
+    durable.persist_durable(&to).unwrap();
+    by_handle.persist_by_handle(&to).unwrap();
+    assert_eq!(
+        LOGGER.take(),
+        [
+            (
+                Level::Debug,
+                format!(
+                    "persisted temporary file {} to {}",
+                    from.display(),
+                    to.display()
+                )
+            ),
+            (
+                Level::Debug,
+                format!(
+                    "persisted temporary file {} to {}",
+                    by_handle_from.display(),
+                    to.display()
+                )
+            )
+        ]
+    );
+
+    let mut spooled = spooled_tempfile(4);
+    spooled.write_all(b"abc").unwrap();
+    spooled.write_all(b"defgh").unwrap();
+    let records = LOGGER.take();
+    assert_eq!(
+        records.last().unwrap(),
+        &(
+            Level::Debug,
+            "spilled 3 bytes of a spooled temporary file to disk".to_owned()
+        )
+    );
+
+    // The file is deleted behind our back, so dropping it fails to clean it up.
+    let file = NamedTempFile::new_in(dir.path()).unwrap();
+    let path = file.path().to_owned();
+    std::fs::remove_file(&path).unwrap();
+    LOGGER.take();
+    drop(file);
+    let records = LOGGER.take();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].0, Level::Warn);
+    assert!(records[0].1.starts_with(&format!(
+        "failed to delete temporary file {}: ",
+        path.display()
+    )));
+
+    // Paths are redacted on request.
+    env::override_redact_debug(true);
+    let file = NamedTempFile::new_in(dir.path()).unwrap();
+    drop(file);
+    env::override_redact_debug(false);
+    assert_eq!(
+        LOGGER.take(),
+        [(Level::Debug, "created temporary file <redacted>".to_owned())]
+    );
+}
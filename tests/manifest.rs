@@ -0,0 +1,134 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+
+use tempfile::{env, Builder, NamedTempFile, TempDir};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_manifest_recover() {
+    configure_wasi_temp_dir();
+
+    let root = TempDir::new().unwrap();
+    let manifest = root.path().join("app.manifest");
+
+    // Nothing to recover yet.
+    assert!(tempfile::recover(&manifest).unwrap().is_empty());
+
+    // Undecodable and stale entries are skipped.
+    let existing = root.path().join("existing");
+    fs::write(&existing, b"").unwrap();
+    fs::write(
+        &manifest,
+        format!(
+            "garbage\ntempfile-file:{}\ntempfile-dir:{}\ntempfile-fi",
+            existing.display(),
+            existing.display()
+        ),
+    )
+    .unwrap();
+    let leftovers = tempfile::recover(&manifest).unwrap();
+    assert_eq!(leftovers.len(), 1);
+    assert_eq!(leftovers[0].path(), existing);
+    assert!(!manifest.exists());
+
+    // Manifests in shared or redirected locations are rejected.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let shared = TempDir::new().unwrap();
+        fs::set_permissions(shared.path(), fs::Permissions::from_mode(0o777)).unwrap();
+        let err = env::override_manifest(shared.path().join("app.manifest")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        let err = tempfile::recover(shared.path().join("app.manifest")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+        let target = root.path().join("target");
+        fs::write(&target, b"").unwrap();
+        std::os::unix::fs::symlink(&target, &manifest).unwrap();
+        assert!(env::override_manifest(&manifest).is_err());
+        assert!(tempfile::recover(&manifest).is_err());
+        fs::remove_file(&manifest).unwrap();
+        fs::remove_file(&target).unwrap();
+    }
+
+    // This test binary must not create any other temporaries after this point.
+
+    env::override_manifest(&manifest).unwrap();
+    env::override_manifest(&manifest).expect_err("manifest should only be installed once");
+
+    // Simulate a crash by leaking some temporaries.
+    let leaked_dir = Builder::new().tempdir_in(root.path()).unwrap();
+    let leaked_dir_path = leaked_dir.path().to_owned();
+    std::mem::forget(leaked_dir);
+    let leaked_file = NamedTempFile::new_in(root.path()).unwrap().into_temp_path();
+    let leaked_file_path = leaked_file.to_path_buf();
+    std::mem::forget(leaked_file);
+    let cleaned_file = NamedTempFile::new_in(root.path())
+        .unwrap()
+        .path()
+        .to_owned();
+    assert!(!cleaned_file.exists());
+
+    // Kept and persisted temporaries aren't leftovers.
+    let kept_dir = Builder::new().tempdir_in(root.path()).unwrap().keep();
+    let kept_file = NamedTempFile::new_in(root.path())
+        .unwrap()
+        .into_temp_path()
+        .keep()
+        .unwrap();
+    let persisted = NamedTempFile::new_in(root.path()).unwrap();
+    let persisted_from = persisted.path().to_owned();
+    persisted.persist(root.path().join("persisted")).unwrap();
+    fs::rename(root.path().join("persisted"), &persisted_from).unwrap();
+    let mut disabled_file = NamedTempFile::new_in(root.path()).unwrap();
+    disabled_file.disable_cleanup(true);
+    let disabled = disabled_file.path().to_owned();
+    drop(disabled_file);
+
+    // A set that fails part way is cleaned up and released, even if cleanup is disabled. The first
+    // member is recreated afterwards: if it were still recorded, it would be recovered.
+    for (prefix, disable_cleanup) in [("partial", false), ("partial-kept", true)] {
+        Builder::new()
+            .prefix(prefix)
+            .rand_bytes(0)
+            .disable_cleanup(disable_cleanup)
+            .tempfile_set_in(root.path(), &[".a", "/missing"])
+            .unwrap_err();
+        let first = root.path().join(format!("{}.a", prefix));
+        assert!(!first.exists());
+        fs::write(&first, b"").unwrap();
+    }
+
+    // Lots of short-lived temporaries don't make the manifest grow without bound.
+    let size = fs::metadata(&manifest).unwrap().len();
+    for _ in 0..1000 {
+        NamedTempFile::new_in(root.path()).unwrap();
+    }
+    assert!(fs::metadata(&manifest).unwrap().len() < size + 64 * 1024);
+
+    let leftovers = tempfile::recover(&manifest).unwrap();
+    assert!(!manifest.exists());
+    assert_eq!(leftovers.len(), 2);
+    assert_eq!(leftovers[0].path(), leaked_dir_path);
+    assert!(leftovers[0].is_dir());
+    assert_eq!(leftovers[1].path(), leaked_file_path);
+    assert!(!leftovers[1].is_dir());
+    for path in [&kept_dir, &kept_file, &persisted_from, &disabled] {
+        assert!(path.exists());
+    }
+
+    let mut leftovers = leftovers.into_iter();
+    let dir = TempDir::adopt(leftovers.next().unwrap()).unwrap();
+    fs::write(dir.path().join("state"), b"kept").unwrap();
+    leftovers.next().unwrap().remove().unwrap();
+    assert!(!leaked_file_path.exists());
+    drop(dir);
+    assert!(!leaked_dir_path.exists());
+}
@@ -4,7 +4,7 @@ use std::ffi::{OsStr, OsString};
 use std::fs::File;
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use tempfile::{env, tempdir, Builder, NamedTempFile, TempPath};
+use tempfile::{env, tempdir, Builder, NamedTempFile, OwnershipToken, TempDir, TempPath};
 
 fn exists<P: AsRef<Path>>(path: P) -> bool {
     std::fs::metadata(path.as_ref()).is_ok()
@@ -132,6 +132,1055 @@ fn test_customnamed() {
     assert_eq!(name.len(), 18);
 }
 
+#[test]
+fn test_with_pid() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = Builder::new()
+        .prefix("tmp-")
+        .rand_bytes(4)
+        .with_pid(true)
+        .tempfile()
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    if cfg!(any(unix, windows)) {
+        let rest = name
+            .strip_prefix(&format!("tmp-{}-", std::process::id()))
+            .unwrap();
+        assert_eq!(rest.len(), 4);
+    } else {
+        assert_eq!(name.len(), "tmp-".len() + 4);
+    }
+}
+
+#[test]
+fn test_from_reader() {
+    configure_wasi_temp_dir();
+
+    let mut source = NamedTempFile::new().unwrap();
+    source.write_all(b"source data").unwrap();
+    source.seek(SeekFrom::Start(0)).unwrap();
+
+    let mut tmpfile =
+        NamedTempFile::from_reader(Builder::new().suffix(".copy"), source.as_file()).unwrap();
+    assert!(tmpfile.path().to_str().unwrap().ends_with(".copy"));
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "source data");
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"source data");
+}
+
+#[test]
+fn test_with_contents() {
+    configure_wasi_temp_dir();
+
+    let mut tmpfile = NamedTempFile::with_contents(Builder::new().prefix("data-"), b"abc").unwrap();
+    assert!(tmpfile
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("data-"));
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abc");
+
+    let mut tmpfile = NamedTempFile::with_contents_synced(&Builder::new(), "").unwrap();
+    assert_eq!(tmpfile.len().unwrap(), 0);
+    tmpfile.write_all(b"more").unwrap();
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"more");
+}
+
+#[test]
+fn test_persist_preserving_metadata() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("config");
+
+    // Nothing to preserve.
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "first").unwrap();
+    tmpfile.persist_preserving_metadata(&target).unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "first");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+    }
+
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "second").unwrap();
+    tmpfile
+        .into_temp_path()
+        .persist_preserving_metadata(&target)
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "second");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = target.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
+
+#[test]
+fn test_persist_by_handle() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("data");
+    std::fs::write(&target, "old").unwrap();
+
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "new").unwrap();
+    let tmp_path = tmpfile.path().to_owned();
+    let mut file = tmpfile.persist_by_handle(&target).unwrap();
+    assert!(!tmp_path.exists());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+    write!(file, "er").unwrap();
+    drop(file);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "newer");
+
+    let tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    let err = tmpfile
+        .persist_by_handle(dir.path().join("missing").join("data"))
+        .unwrap_err();
+    assert!(err.file.path().exists());
+}
+
+#[test]
+fn test_persist_durable() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("state");
+    std::fs::write(&target, "old").unwrap();
+
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "new").unwrap();
+    let tmp_path = tmpfile.path().to_owned();
+    let mut file = tmpfile.persist_durable(&target).unwrap();
+    assert!(!tmp_path.exists());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+    write!(file, "er").unwrap();
+    drop(file);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "newer");
+
+    // Failing to persist hands the temporary file back.
+    let tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    let err = tmpfile
+        .persist_durable(dir.path().join("missing").join("state"))
+        .unwrap_err();
+    assert!(err.file.path().exists());
+}
+
+#[test]
+fn test_atomic_write_file() {
+    use tempfile::{AtomicWriteFile, Durability};
+
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("state.json");
+    std::fs::write(&target, "old").unwrap();
+
+    // Dropping without committing leaves the target alone.
+    let mut file = AtomicWriteFile::open(&target).unwrap();
+    assert_eq!(file.target(), target);
+    write!(file, "abandoned").unwrap();
+    drop(file);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "old");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let mut file = AtomicWriteFile::open(&target).unwrap();
+    file.set_durability(Durability::File);
+    write!(file, "new").unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "old");
+    file.commit().unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // The target doesn't need to exist yet.
+    let other = dir.path().join("other");
+    let mut file = AtomicWriteFile::open(&other).unwrap();
+    write!(file, "created").unwrap();
+    file.commit().unwrap();
+    assert_eq!(std::fs::read_to_string(&other).unwrap(), "created");
+
+    AtomicWriteFile::open(&other).unwrap().discard().unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_executable() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let tmpfile = Builder::new()
+        .suffix(".bin")
+        .executable(true)
+        .tempfile_in(dir.path())
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.ends_with(&format!(".bin{}", std::env::consts::EXE_SUFFIX)));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = tmpfile.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // Explicit permissions win.
+        let tmpfile = Builder::new()
+            .executable(true)
+            .permissions(std::fs::Permissions::from_mode(0o600))
+            .tempfile_in(dir.path())
+            .unwrap();
+        let mode = tmpfile.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // The executable suffix isn't duplicated.
+    let tmpfile = Builder::new()
+        .suffix(".EXE")
+        .executable(true)
+        .tempfile_in(dir.path())
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    if cfg!(windows) {
+        assert!(name.ends_with(".EXE"));
+    }
+    assert!(!name.to_ascii_lowercase().ends_with(".exe.exe"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_unix_sockets() {
+    use std::os::unix::net::{UnixDatagram, UnixStream};
+
+    let dir = tempdir().unwrap();
+    let listener = Builder::new()
+        .suffix(".sock")
+        .unix_listener_in(dir.path())
+        .unwrap();
+    assert!(listener.path().to_str().unwrap().ends_with(".sock"));
+    let _client = UnixStream::connect(listener.path()).unwrap();
+    listener.as_file().accept().unwrap();
+    let path = listener.path().to_owned();
+    drop(listener);
+    assert!(!path.exists());
+
+    let socket = Builder::new().unix_datagram_in(dir.path()).unwrap();
+    UnixDatagram::unbound()
+        .unwrap()
+        .send_to(b"ping", socket.path())
+        .unwrap();
+    let mut buf = [0; 4];
+    socket.as_file().recv(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    let err = Builder::new()
+        .prefix(&"s".repeat(120))
+        .unix_listener_in(dir.path())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+#[cfg(all(unix, not(any(target_vendor = "apple", target_os = "redox"))))]
+fn test_fifo() {
+    use std::os::unix::fs::FileTypeExt;
+
+    let dir = tempdir().unwrap();
+    let fifo = Builder::new().prefix("pipe-").fifo_in(dir.path()).unwrap();
+    assert!(fifo.metadata().unwrap().file_type().is_fifo());
+
+    let path = fifo.path().to_owned();
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        std::fs::File::open(path)
+            .unwrap()
+            .read_to_string(&mut buf)
+            .unwrap();
+        buf
+    });
+    fifo.open_writer().unwrap().write_all(b"hello").unwrap();
+    assert_eq!(reader.join().unwrap(), "hello");
+
+    let path = fifo.path().to_owned();
+    drop(fifo);
+    assert!(!path.exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_symlink() {
+    use tempfile::TempSymlink;
+
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("target.txt");
+    std::fs::write(&target, "contents").unwrap();
+
+    let link = TempSymlink::new_in(&target, dir.path()).unwrap();
+    assert_eq!(link.target(), target);
+    assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read_link(&link).unwrap(), target);
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "contents");
+    let path = link.path().to_owned();
+    drop(link);
+    assert!(path.symlink_metadata().is_err());
+    assert!(target.exists());
+
+    // Dangling links are fine, and directories are left alone too.
+    let link = Builder::new()
+        .prefix("alias-")
+        .symlink_in(dir.path().join("missing"), dir.path())
+        .unwrap();
+    assert!(link
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("alias-"));
+    link.close().unwrap();
+
+    let link = Builder::new().symlink(dir.path()).unwrap();
+    assert!(link.is_dir());
+    drop(link);
+    assert!(dir.path().is_dir());
+}
+
+#[test]
+fn test_reserve_path() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let reserved = Builder::new()
+        .suffix(".out")
+        .reserve_path_in(dir.path())
+        .unwrap();
+    assert!(reserved.is_placeholder());
+    assert_eq!(reserved.parent().unwrap(), dir.path());
+
+    // The producer replaces the placeholder.
+    let staged = dir.path().join("staged");
+    std::fs::write(&staged, b"output").unwrap();
+    std::fs::rename(&staged, &reserved).unwrap();
+    assert!(!reserved.is_placeholder());
+
+    let path = reserved.path().to_owned();
+    drop(reserved);
+    assert!(!path.exists());
+
+    let reserved = Builder::new().reserve_path_in(dir.path()).unwrap();
+    std::fs::write(&reserved, b"output").unwrap();
+    let path = reserved.keep().unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"output");
+}
+
+#[test]
+fn test_temporary_attribute() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = Builder::new()
+        .windows_temporary_attribute(false)
+        .tempfile()
+        .unwrap();
+    tmpfile.clear_temporary_attribute().unwrap();
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_TEMPORARY: u32 = 0x100;
+        let attributes = |f: &NamedTempFile| f.path().metadata().unwrap().file_attributes();
+
+        assert_eq!(attributes(&tmpfile) & FILE_ATTRIBUTE_TEMPORARY, 0);
+
+        let tmpfile = Builder::new().hidden(true).tempfile().unwrap();
+        assert_ne!(attributes(&tmpfile) & FILE_ATTRIBUTE_TEMPORARY, 0);
+        tmpfile.clear_temporary_attribute().unwrap();
+        assert_eq!(attributes(&tmpfile) & FILE_ATTRIBUTE_TEMPORARY, 0);
+        // Other attributes are kept.
+        assert_ne!(attributes(&tmpfile) & 0x2, 0);
+    }
+}
+
+#[test]
+#[cfg(windows)]
+fn test_hidden() {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+    let tmpfile = Builder::new().hidden(true).tempfile().unwrap();
+    let attributes = tmpfile.path().metadata().unwrap().file_attributes();
+    assert_ne!(attributes & FILE_ATTRIBUTE_HIDDEN, 0);
+
+    let tmpfile = Builder::new().tempfile().unwrap();
+    let attributes = tmpfile.path().metadata().unwrap().file_attributes();
+    assert_eq!(attributes & FILE_ATTRIBUTE_HIDDEN, 0);
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("kept");
+    Builder::new()
+        .hidden(true)
+        .tempfile()
+        .unwrap()
+        .persist(&path)
+        .unwrap();
+    let attributes = path.metadata().unwrap().file_attributes();
+    assert_eq!(attributes & FILE_ATTRIBUTE_HIDDEN, 0);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_permissions_exact() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = |meta: std::fs::Metadata| meta.permissions().mode() & 0o777;
+
+    let tmpfile = Builder::new()
+        .permissions_exact(std::fs::Permissions::from_mode(0o666))
+        .tempfile()
+        .unwrap();
+    assert_eq!(mode(tmpfile.as_file().metadata().unwrap()), 0o666);
+
+    let tmpdir = Builder::new()
+        .permissions_exact(std::fs::Permissions::from_mode(0o777))
+        .tempdir()
+        .unwrap();
+    assert_eq!(mode(tmpdir.path().metadata().unwrap()), 0o777);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_world_and_group_readable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = |meta: std::fs::Metadata| meta.permissions().mode() & 0o777;
+
+    let tmpfile = Builder::new().world_readable().tempfile().unwrap();
+    assert_eq!(mode(tmpfile.as_file().metadata().unwrap()), 0o644);
+    let tmpfile = Builder::new()
+        .group_readable()
+        .executable(true)
+        .tempfile()
+        .unwrap();
+    assert_eq!(mode(tmpfile.as_file().metadata().unwrap()), 0o750);
+
+    let tmpdir = Builder::new().world_readable().tempdir().unwrap();
+    assert_eq!(mode(tmpdir.path().metadata().unwrap()), 0o755);
+    let tmpdir = Builder::new().group_readable().tempdir().unwrap();
+    assert_eq!(mode(tmpdir.path().metadata().unwrap()), 0o750);
+
+    // Explicit permissions set afterwards take precedence.
+    let tmpfile = Builder::new()
+        .world_readable()
+        .permissions(std::fs::Permissions::from_mode(0o600))
+        .tempfile()
+        .unwrap();
+    assert_eq!(mode(tmpfile.as_file().metadata().unwrap()), 0o600);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_custom_flags() {
+    use std::os::unix::io::AsRawFd;
+    const O_NONBLOCK: i32 = 0o4000;
+
+    let open_flags = |file: &NamedTempFile| {
+        let fdinfo =
+            std::fs::read_to_string(format!("/proc/self/fdinfo/{}", file.as_raw_fd())).unwrap();
+        let flags = fdinfo
+            .lines()
+            .find_map(|l| l.strip_prefix("flags:"))
+            .unwrap();
+        i32::from_str_radix(flags.trim(), 8).unwrap()
+    };
+
+    let tmpfile = Builder::new().custom_flags(O_NONBLOCK).tempfile().unwrap();
+    assert_ne!(open_flags(&tmpfile) & O_NONBLOCK, 0);
+    let tmpfile = Builder::new().tempfile().unwrap();
+    assert_eq!(open_flags(&tmpfile) & O_NONBLOCK, 0);
+}
+
+#[test]
+fn test_sparse() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = Builder::new().sparse(true).tempfile().unwrap();
+    tmpfile.as_file().set_len(1 << 30).unwrap();
+    let mut file = tmpfile.as_file();
+    file.seek(SeekFrom::Start(1 << 20)).unwrap();
+    file.write_all(b"data").unwrap();
+    assert_eq!(tmpfile.as_file().metadata().unwrap().len(), 1 << 30);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let allocated = tmpfile.as_file().metadata().unwrap().blocks() * 512;
+        assert!(allocated < 1 << 20, "{} bytes allocated", allocated);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_SPARSE_FILE: u32 = 0x200;
+        let attributes = tmpfile.as_file().metadata().unwrap().file_attributes();
+        assert_ne!(attributes & FILE_ATTRIBUTE_SPARSE_FILE, 0);
+    }
+}
+
+#[test]
+#[cfg(any(windows, target_os = "linux", target_os = "android"))]
+fn test_deallocate() {
+    const LEN: usize = 4 << 20;
+
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    tmpfile.write_all(&vec![0xaa; LEN]).unwrap();
+    tmpfile.as_file().sync_all().unwrap();
+
+    tmpfile.deallocate(0, LEN as u64 / 2).unwrap();
+    tmpfile.deallocate(0, 0).unwrap();
+    assert_eq!(
+        tmpfile.deallocate(u64::MAX, 1).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    let mut buf = Vec::new();
+    tmpfile.reopen().unwrap().read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), LEN);
+    assert!(buf[..LEN / 2].iter().all(|&b| b == 0));
+    assert!(buf[LEN / 2..].iter().all(|&b| b == 0xaa));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let allocated = tmpfile.as_file().metadata().unwrap().blocks() * 512;
+        assert!(allocated < LEN as u64, "{} bytes allocated", allocated);
+    }
+}
+
+#[test]
+fn test_same_filesystem_as() {
+    configure_wasi_temp_dir();
+
+    // The default temporary directory is used whenever it's on the right filesystem.
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("target");
+    let tmpfile = Builder::new()
+        .same_filesystem_as(&target)
+        .tempfile()
+        .unwrap();
+    let parent = tmpfile.path().parent().unwrap().to_owned();
+    assert!(parent == env::temp_dir() || parent == dir.path());
+    tmpfile.persist(&target).unwrap();
+
+    // Explicit directories take precedence.
+    let other = tempdir().unwrap();
+    let tmpfile = Builder::new()
+        .same_filesystem_as(&target)
+        .tempfile_in(&other)
+        .unwrap();
+    assert!(tmpfile.path().starts_with(other.path()));
+
+    let missing = dir.path().join("missing").join("target");
+    assert_eq!(
+        Builder::new()
+            .same_filesystem_as(&missing)
+            .tempfile()
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::NotFound
+    );
+
+    // Spooled files roll over to the same directory.
+    let mut spooled = Builder::new().same_filesystem_as(&missing).spooled(0);
+    assert_eq!(
+        spooled.write_all(b"data").unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+}
+
+#[test]
+fn test_verify() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    tmpfile.verify().unwrap();
+
+    // Replaced by another file.
+    let moved = dir.path().join("moved");
+    std::fs::rename(tmpfile.path(), &moved).unwrap();
+    std::fs::write(tmpfile.path(), "").unwrap();
+    if cfg!(not(target_os = "wasi")) {
+        assert_eq!(
+            tmpfile.verify().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    // Deleted.
+    std::fs::remove_file(tmpfile.path()).unwrap();
+    assert_eq!(
+        tmpfile.verify().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+
+    std::fs::rename(&moved, tmpfile.path()).unwrap();
+    tmpfile.verify().unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "notify", any(target_os = "linux", windows)))]
+fn test_watch() {
+    use std::time::Duration;
+
+    let dir = tempdir().unwrap();
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let watcher = tmpfile.watch().unwrap();
+
+    // Unrelated changes don't count.
+    std::fs::write(dir.path().join("other"), "").unwrap();
+    assert!(watcher.recv_timeout(Duration::from_millis(100)).is_none());
+
+    std::fs::rename(tmpfile.path(), dir.path().join("moved")).unwrap();
+    let error = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert!(watcher.try_recv().is_none());
+
+    // Watching a file that's already gone fails.
+    assert_eq!(tmpfile.watch().unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_private_subdir_for() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = tempdir().unwrap();
+    let uid = dir.path().metadata().unwrap().uid();
+    let private = dir.path().join(format!("tempfile-{}", uid));
+
+    let tmpfile = Builder::new()
+        .private_subdir_for(uid)
+        .tempfile_in(&dir)
+        .unwrap();
+    assert_eq!(tmpfile.path().parent().unwrap(), private);
+    let meta = private.symlink_metadata().unwrap();
+    assert_eq!(meta.uid(), uid);
+    assert_eq!(meta.permissions().mode() & 0o777, 0o700);
+
+    // The directory is reused, but only as long as it's private.
+    let tmpdir = Builder::new()
+        .private_subdir_for(uid)
+        .tempdir_in(&dir)
+        .unwrap();
+    assert!(tmpdir.path().starts_with(&private));
+    std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(
+        Builder::new()
+            .private_subdir_for(uid)
+            .tempfile_in(&dir)
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::PermissionDenied
+    );
+
+    // So are symbolic links to private directories.
+    std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o700)).unwrap();
+    let link_dir = tempdir().unwrap();
+    std::os::unix::fs::symlink(&private, link_dir.path().join(format!("tempfile-{}", uid)))
+        .unwrap();
+    assert_eq!(
+        Builder::new()
+            .private_subdir_for(uid)
+            .tempfile_in(&link_dir)
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::PermissionDenied
+    );
+
+    // Privileged processes keep the directory, but let the other user reach its contents.
+    if uid == 0 {
+        let tmpfile = Builder::new()
+            .private_subdir_for(12345)
+            .tempfile_in(&dir)
+            .unwrap();
+        let other = tmpfile.path().parent().unwrap();
+        let meta = other.symlink_metadata().unwrap();
+        assert_eq!(meta.uid(), 0);
+        assert_eq!(meta.permissions().mode() & 0o777, 0o711);
+
+        // A directory handed over to the other user is refused.
+        let status = std::process::Command::new("chown")
+            .arg("12345")
+            .arg(other)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            Builder::new()
+                .private_subdir_for(12345)
+                .tempfile_in(&dir)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+}
+
+#[test]
+#[cfg(any(windows, all(unix, not(target_os = "android"))))]
+fn test_shm_tempfile() {
+    use tempfile::ShmTempFile;
+
+    let shm = ShmTempFile::new(4096).unwrap();
+    assert_eq!(shm.len(), 4096);
+    assert_eq!(
+        ShmTempFile::new(0).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    #[cfg(unix)]
+    assert_eq!(shm.as_file().metadata().unwrap().len(), 4096);
+    #[cfg(target_os = "linux")]
+    {
+        let mut file = shm.into_file();
+        file.write_all(b"shared").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0; 8];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"shared\0\0");
+    }
+}
+
+#[test]
+fn test_reopen_read_only() {
+    configure_wasi_temp_dir();
+
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+
+    let mut reader = tmpfile.reopen_read_only().unwrap();
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+    reader.write_all(b"x").unwrap_err();
+
+    // With only read-only handles left, the file can be opened by path with a share mode that
+    // doesn't allow writing.
+    let tmpfile = tmpfile.map(|_| reader);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_SHARE_READ: u32 = 0x1;
+        let mut other = std::fs::OpenOptions::new()
+            .read(true)
+            .share_mode(FILE_SHARE_READ)
+            .open(tmpfile.path())
+            .unwrap();
+        let mut buf = String::new();
+        other.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "abcde");
+    }
+    tmpfile.close().unwrap();
+}
+
+#[test]
+#[cfg(all(unix, not(target_os = "wasi")))]
+fn test_inheritable() {
+    use std::os::unix::io::AsRawFd;
+    use std::process::Command;
+
+    let read_in_child = |file: &mut NamedTempFile| {
+        file.rewind().unwrap();
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("cat <&{} 2>/dev/null", file.as_raw_fd()))
+            .output()
+            .unwrap();
+        output.stdout
+    };
+
+    let mut tmpfile = Builder::new().inheritable(true).tempfile().unwrap();
+    write!(tmpfile, "inherited").unwrap();
+    assert_eq!(read_in_child(&mut tmpfile), b"inherited");
+
+    let mut tmpfile = Builder::new().tempfile().unwrap();
+    write!(tmpfile, "private").unwrap();
+    assert_eq!(read_in_child(&mut tmpfile), b"");
+}
+
+#[test]
+#[cfg(windows)]
+fn test_unsupported_dir() {
+    use tempfile::{UnsupportedDirError, UnsupportedDirReason};
+
+    let reason = |dir: &str| {
+        let err = NamedTempFile::new_in(dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = err.get_ref().unwrap();
+        err.downcast_ref::<UnsupportedDirError>().unwrap().reason()
+    };
+    assert_eq!(reason(r"\\.\pipe\"), UnsupportedDirReason::DevicePath);
+    assert_eq!(reason(r"\\.\COM1"), UnsupportedDirReason::DevicePath);
+    assert_eq!(reason(r"\\server"), UnsupportedDirReason::IncompleteUncPath);
+    assert_eq!(reason(r"C:\Temp\nul"), UnsupportedDirReason::ReservedName);
+    assert_eq!(
+        reason(r"C:\Temp\Com3.txt"),
+        UnsupportedDirReason::ReservedName
+    );
+    assert_eq!(
+        reason(r"\\example.com@SSL\DavWWWRoot\files"),
+        UnsupportedDirReason::UnreliableCreateNew
+    );
+    assert!(tempfile::tempdir_in(r"\\.\pipe\").is_err());
+
+    // Regular directories, including ones with names that merely resemble device names.
+    let dir = tempdir().unwrap();
+    for name in ["console", "com10", "lpt", "café", "COMé"] {
+        let sub = dir.path().join(name);
+        std::fs::create_dir(&sub).unwrap();
+        NamedTempFile::new_in(&sub).unwrap();
+    }
+}
+
+#[test]
+fn test_sanitize_affix() {
+    use tempfile::{sanitize_affix, Sanitize};
+
+    configure_wasi_temp_dir();
+
+    assert_eq!(sanitize_affix("report.pdf", Sanitize::Strip), "report.pdf");
+    assert_eq!(sanitize_affix("a/b\0c", Sanitize::Strip), "abc");
+    assert_eq!(
+        sanitize_affix("100%/x", Sanitize::PercentEncode),
+        "100%25%2Fx"
+    );
+    if cfg!(windows) {
+        assert_eq!(
+            sanitize_affix("a:b*c?\\d", Sanitize::PercentEncode),
+            "a%3Ab%2Ac%3F%5Cd"
+        );
+    } else {
+        assert_eq!(
+            sanitize_affix("a:b*c?\\d", Sanitize::PercentEncode),
+            "a:b*c?\\d"
+        );
+    }
+
+    let dir = tempdir().unwrap();
+    let prefix = sanitize_affix("../../escape", Sanitize::Strip);
+    let tmpfile = Builder::new()
+        .prefix(&prefix)
+        .tempfile_in(dir.path())
+        .unwrap();
+    assert_eq!(tmpfile.path().parent().unwrap(), dir.path());
+    assert!(tmpfile
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("....escape"));
+}
+
+#[test]
+fn test_tempfile_set() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let files = Builder::new()
+        .prefix("set-")
+        .suffix(".ignored")
+        .tempfile_set_in(dir.path(), &[".idx", ".dat", ".log"])
+        .unwrap();
+    assert_eq!(files.len(), 3);
+    let stem = files[0].path().file_stem().unwrap().to_owned();
+    assert!(stem.to_str().unwrap().starts_with("set-"));
+    for (file, ext) in files.iter().zip(["idx", "dat", "log"]) {
+        assert_eq!(file.path().parent().unwrap(), dir.path());
+        assert_eq!(file.path().file_stem().unwrap(), stem);
+        assert_eq!(file.path().extension().unwrap(), ext);
+        assert!(file.path().exists());
+    }
+    drop(files);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    // A collision on any member of the set leaves nothing behind.
+    File::create(dir.path().join("fixed.dat")).unwrap();
+    let err = Builder::new()
+        .prefix("fixed")
+        .rand_bytes(0)
+        .disable_cleanup(true)
+        .tempfile_set_in(dir.path(), &[".idx", ".dat"])
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert!(!dir.path().join("fixed.idx").exists());
+    assert!(dir.path().join("fixed.dat").exists());
+}
+
+#[test]
+fn test_adaptive_rand_bytes() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    // Pretend that every name with fewer than 4 random characters is taken.
+    let create = |path: &Path| {
+        if path.file_name().unwrap().len() < "tmp-".len() + 4 {
+            Err(io::Error::from(io::ErrorKind::AlreadyExists))
+        } else {
+            File::create(path)
+        }
+    };
+
+    let err = Builder::new()
+        .prefix("tmp-")
+        .rand_bytes(1)
+        .make_in(dir.path(), create)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    let tmpfile = Builder::new()
+        .prefix("tmp-")
+        .rand_bytes(1)
+        .adaptive_rand_bytes(true)
+        .make_in(dir.path(), create)
+        .unwrap();
+    // 1 -> 2 -> 4 random characters, after 3 collisions each.
+    let name = tmpfile.path().file_name().unwrap();
+    assert_eq!(name.len(), "tmp-".len() + 4);
+}
+
+#[test]
+fn test_fit_name_length() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    // Multi-byte characters must not be split.
+    let long_prefix = "\u{e9}".repeat(300);
+
+    let err = Builder::new()
+        .prefix(&long_prefix)
+        .tempfile_in(dir.path())
+        .unwrap_err();
+    assert_ne!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    let tmpfile = Builder::new()
+        .prefix(&long_prefix)
+        .suffix(".txt")
+        .rand_bytes(8)
+        .fit_name_length(true)
+        .tempfile_in(dir.path())
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.len() <= 255);
+    assert!(name.len() >= 254);
+    assert!(name.starts_with("\u{e9}\u{e9}"));
+    assert!(name.ends_with(".txt"));
+    assert_eq!(name.trim_start_matches('\u{e9}').len(), 8 + ".txt".len());
+
+    // Short prefixes are left alone.
+    let tmpfile = Builder::new()
+        .prefix("short-")
+        .fit_name_length(true)
+        .tempfile_in(dir.path())
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("short-"));
+}
+
+#[test]
+fn test_with_sequence() {
+    configure_wasi_temp_dir();
+
+    let mut builder = Builder::new();
+    builder.prefix("tmp-").rand_bytes(4).with_sequence(true);
+    let sequence = |tmpfile: &NamedTempFile| -> u64 {
+        let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+        let (seq, rest) = name.strip_prefix("tmp-").unwrap().split_once('-').unwrap();
+        assert!(seq.len() >= 4);
+        assert_eq!(rest.len(), 4);
+        seq.parse().unwrap()
+    };
+    let first = builder.tempfile().unwrap();
+    let second = builder.tempfile().unwrap();
+    assert!(sequence(&first) >= 1);
+    assert!(sequence(&second) > sequence(&first));
+
+    // Without any random characters, the sequence alone keeps names unique.
+    let mut builder = Builder::new();
+    builder.prefix("tmp-").rand_bytes(0).with_sequence(true);
+    let first = builder.tempfile().unwrap();
+    let second = builder.tempfile().unwrap();
+    assert_ne!(first.path(), second.path());
+}
+
+#[test]
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn test_with_timestamp() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::TimestampFormat;
+
+    configure_wasi_temp_dir();
+
+    let before = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let tmpfile = Builder::new()
+        .prefix("tmp-")
+        .rand_bytes(4)
+        .with_timestamp(TimestampFormat::UnixSeconds)
+        .tempfile()
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    let (secs, rest) = name.strip_prefix("tmp-").unwrap().split_once('-').unwrap();
+    let secs: u64 = secs.parse().unwrap();
+    assert!(secs >= before && secs <= before + 60);
+    assert_eq!(rest.len(), 4);
+
+    let tmpfile = Builder::new()
+        .prefix("tmp-")
+        .rand_bytes(4)
+        .with_timestamp(TimestampFormat::Iso8601)
+        .tempfile()
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    let (stamp, rest) = name.strip_prefix("tmp-").unwrap().split_once('-').unwrap();
+    assert_eq!(stamp.len(), "20231114T221320Z".len());
+    assert_eq!(stamp.as_bytes()[8], b'T');
+    assert!(stamp.ends_with('Z'));
+    assert_eq!(rest.len(), 4);
+}
+
+#[test]
+#[cfg(feature = "uuid")]
+fn test_uuid_names() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = Builder::new()
+        .prefix("tmp-")
+        .suffix(".rs")
+        .uuid_names(true)
+        .tempfile()
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    let uuid = name
+        .strip_prefix("tmp-")
+        .unwrap()
+        .strip_suffix(".rs")
+        .unwrap();
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(uuid.as_bytes()[14], b'4', "not a version 4 UUID: {uuid}");
+    assert!(uuid
+        .chars()
+        .all(|c| c == '-' || c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+}
+
 #[test]
 fn test_append() {
     configure_wasi_temp_dir();
@@ -162,6 +1211,25 @@ fn test_reopen() {
     assert_eq!("abcde", buf);
 }
 
+#[test]
+#[cfg(unix)]
+fn test_reopen_nofollow() {
+    let dir = tempdir().unwrap();
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let moved = dir.path().join("moved");
+    std::fs::rename(tmpfile.path(), &moved).unwrap();
+    std::os::unix::fs::symlink(&moved, tmpfile.path()).unwrap();
+
+    // The symlink points at the original file, but it's still refused.
+    tmpfile.reopen().unwrap_err();
+    tmpfile.reopen_read_only().unwrap_err();
+
+    env::override_follow_symlinks(true);
+    let result = tmpfile.reopen();
+    env::override_follow_symlinks(false);
+    result.unwrap();
+}
+
 #[test]
 fn test_into_file() {
     configure_wasi_temp_dir();
@@ -502,6 +1570,27 @@ fn test_make_uds() {
     assert!(temp_sock.path().exists());
 }
 
+/// `NamedTempFile<F>` exposes the file descriptor of any `F`, not just `File`.
+#[cfg(unix)]
+#[test]
+fn test_make_uds_as_fd() {
+    use std::os::unix::io::{AsFd, AsRawFd};
+    use std::os::unix::net::UnixListener;
+
+    let temp_sock = Builder::new()
+        .prefix("tmp")
+        .suffix(".sock")
+        .rand_bytes(12)
+        .make(|path| UnixListener::bind(path))
+        .unwrap();
+
+    assert_eq!(temp_sock.as_raw_fd(), temp_sock.as_file().as_raw_fd());
+    assert_eq!(
+        temp_sock.as_fd().as_raw_fd(),
+        temp_sock.as_file().as_fd().as_raw_fd()
+    );
+}
+
 #[cfg(unix)]
 #[test]
 fn test_make_uds_conflict() {
@@ -539,6 +1628,7 @@ fn test_make_uds_conflict() {
 
 /// Make sure we re-seed with system randomness if we run into a conflict.
 #[test]
+#[cfg_attr(feature = "rand", ignore = "names aren't generated by fastrand")]
 fn test_reseed() {
     configure_wasi_temp_dir();
 
@@ -605,3 +1695,546 @@ fn test_overly_generic_bounds() {
         };
     }
 }
+
+#[test]
+fn test_ownership_token() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = NamedTempFile::new().unwrap();
+    let path = tmpfile.path().to_owned();
+    let encoded = tmpfile.into_temp_path().into_token().encode();
+    // The token doesn't own the file.
+    assert!(exists(&path));
+
+    let token = OwnershipToken::decode(&encoded).unwrap();
+    assert_eq!(token.path(), path);
+    assert!(!token.is_dir());
+    let temp_path = TempPath::adopt(token).unwrap();
+    drop(temp_path);
+    assert!(!exists(&path));
+
+    // Tokens can't be adopted as the wrong kind of resource, or once the resource is gone.
+    let dir_encoded = TempDir::new().unwrap().into_token().encode();
+    let dir_token = OwnershipToken::decode(&dir_encoded).unwrap();
+    assert!(dir_token.is_dir());
+    TempPath::adopt(dir_token).unwrap_err();
+    let dir = TempDir::adopt(OwnershipToken::decode(&dir_encoded).unwrap()).unwrap();
+    dir.close().unwrap();
+    TempPath::adopt(OwnershipToken::decode(&encoded).unwrap()).unwrap_err();
+
+    OwnershipToken::decode("not-a-token").unwrap_err();
+    OwnershipToken::decode("tempfile-file:relative/path").unwrap_err();
+}
+
+#[test]
+fn test_temppath_with_cleanup() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    configure_wasi_temp_dir();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counting_cleanup = |calls: &Arc<AtomicUsize>| {
+        let calls = calls.clone();
+        move |path: &Path| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::fs::remove_file(path)
+        }
+    };
+
+    // Called on drop...
+    let path = NamedTempFile::new()
+        .unwrap()
+        .into_temp_path()
+        .keep()
+        .unwrap();
+    drop(TempPath::with_cleanup(&path, counting_cleanup(&calls)));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(!exists(&path));
+
+    // ...and on close, returning its result...
+    let temp_path = TempPath::with_cleanup(&path, counting_cleanup(&calls));
+    assert_eq!(
+        temp_path.close().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // ...but not after persisting.
+    let tmpdir = tempdir().unwrap();
+    let path = NamedTempFile::new_in(&tmpdir)
+        .unwrap()
+        .into_temp_path()
+        .keep()
+        .unwrap();
+    let persisted = tmpdir.path().join("persisted");
+    TempPath::with_cleanup(&path, counting_cleanup(&calls))
+        .persist(&persisted)
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert!(exists(&persisted));
+}
+
+#[test]
+fn test_map() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = NamedTempFile::new().unwrap();
+    let path = tmpfile.path().to_owned();
+    let mut tmpfile = tmpfile.map(std::io::BufWriter::new);
+    assert_eq!(tmpfile.path(), path);
+    write!(tmpfile, "buffered").unwrap();
+
+    let mut tmpfile = tmpfile
+        .try_map(|f| f.into_inner().map_err(|e| e.into_error()))
+        .unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "buffered");
+
+    // A failed transformation deletes the file.
+    let err = tmpfile.try_map(|_| Err::<File, _>("nope")).unwrap_err();
+    assert_eq!(err, "nope");
+    assert!(!exists(&path));
+}
+
+#[test]
+fn test_persist_in() {
+    configure_wasi_temp_dir();
+
+    let staging = tempdir().unwrap();
+    let artifacts = tempdir().unwrap();
+
+    let mut tmpfile = Builder::new().suffix(".out").tempfile_in(&staging).unwrap();
+    write!(tmpfile, "artifact").unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_owned();
+    let (_, path) = tmpfile.persist_in(&artifacts).unwrap();
+    assert_eq!(path, artifacts.path().join(&name));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "artifact");
+    assert!(!exists(staging.path().join(&name)));
+
+    // Doesn't replace existing files.
+    let tmpfile = NamedTempFile::new_in(&staging).unwrap();
+    std::fs::write(
+        artifacts.path().join(tmpfile.path().file_name().unwrap()),
+        "",
+    )
+    .unwrap();
+    let err = tmpfile.persist_in(&artifacts).unwrap_err();
+    assert_eq!(err.error.kind(), io::ErrorKind::AlreadyExists);
+    assert!(exists(err.file.path()));
+}
+
+#[test]
+fn test_persist_with_backup() {
+    configure_wasi_temp_dir();
+
+    let tmpdir = tempdir().unwrap();
+    let target = tmpdir.path().join("config");
+    let backup = tmpdir.path().join("config~");
+    let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+
+    for contents in ["one", "two", "three"] {
+        let mut tmpfile = NamedTempFile::new_in(&tmpdir).unwrap();
+        write!(tmpfile, "{}", contents).unwrap();
+        tmpfile.persist_with_backup(&target, "~").unwrap();
+        assert_eq!(read(&target), contents);
+    }
+    assert_eq!(read(&backup), "two");
+
+    // With nothing to back up, the previous backup is kept.
+    std::fs::remove_file(&target).unwrap();
+    let mut tmpfile = NamedTempFile::new_in(&tmpdir).unwrap();
+    write!(tmpfile, "four").unwrap();
+    tmpfile.persist_with_backup(&target, "~").unwrap();
+    assert_eq!(read(&target), "four");
+    assert_eq!(read(&backup), "two");
+
+    // Symlinks are backed up without following them.
+    #[cfg(unix)]
+    {
+        std::fs::remove_file(&target).unwrap();
+        std::os::unix::fs::symlink("missing", &target).unwrap();
+        NamedTempFile::new_in(&tmpdir)
+            .unwrap()
+            .persist_with_backup(&target, "~")
+            .unwrap();
+        assert_eq!(std::fs::read_link(&backup).unwrap(), Path::new("missing"));
+    }
+}
+
+#[test]
+fn test_persist_versioned() {
+    configure_wasi_temp_dir();
+
+    let tmpdir = tempdir().unwrap();
+    let target = tmpdir.path().join("report.txt");
+    for expected in ["report.txt", "report-1.txt", "report-2.txt"] {
+        let mut tmpfile = NamedTempFile::new_in(&tmpdir).unwrap();
+        write!(tmpfile, "{}", expected).unwrap();
+        let (_, path) = tmpfile.persist_versioned(&target).unwrap();
+        assert_eq!(path, tmpdir.path().join(expected));
+    }
+    for name in ["report.txt", "report-1.txt", "report-2.txt"] {
+        let mut buf = String::new();
+        File::open(tmpdir.path().join(name))
+            .unwrap()
+            .read_to_string(&mut buf)
+            .unwrap();
+        assert_eq!(buf, name);
+    }
+
+    let path = NamedTempFile::new_in(&tmpdir)
+        .unwrap()
+        .into_temp_path()
+        .persist_versioned_with(&target, |target, version| {
+            target.with_extension(format!("txt.{}", version))
+        })
+        .unwrap();
+    assert_eq!(path, tmpdir.path().join("report.txt.1"));
+}
+
+#[test]
+fn test_persist_versioned_slots() {
+    configure_wasi_temp_dir();
+
+    let tmpdir = tempdir().unwrap();
+    let target = tmpdir.path().join("report.txt");
+    std::fs::write(&target, "").unwrap();
+    let slot = |version: u32| tmpdir.path().join(format!("slot-{}", version));
+
+    // The first version tried is 1.
+    let mut versions = Vec::new();
+    let path = NamedTempFile::new_in(&tmpdir)
+        .unwrap()
+        .into_temp_path()
+        .persist_versioned_with(&target, |_, version| {
+            versions.push(version);
+            slot(version)
+        })
+        .unwrap();
+    assert_eq!(versions, [1]);
+    assert_eq!(path, slot(1));
+
+    // The last version tried is 9999. Every other version maps to an existing file.
+    let taken = |version: u32| {
+        if version < 9999 {
+            target.clone()
+        } else {
+            slot(version)
+        }
+    };
+    let path = NamedTempFile::new_in(&tmpdir)
+        .unwrap()
+        .into_temp_path()
+        .persist_versioned_with(&target, |_, version| taken(version))
+        .unwrap();
+    assert_eq!(path, slot(9999));
+
+    // Once every version is taken, persisting fails without trying more versions.
+    let err = NamedTempFile::new_in(&tmpdir)
+        .unwrap()
+        .into_temp_path()
+        .persist_versioned_with(&target, |_, version| {
+            assert!(version <= 9999);
+            taken(version)
+        })
+        .unwrap_err();
+    assert_eq!(err.error.kind(), io::ErrorKind::AlreadyExists);
+    assert!(err.path.exists());
+}
+
+#[test]
+fn test_set_permissions_through_handle() {
+    configure_wasi_temp_dir();
+
+    let tmpfile = NamedTempFile::new().unwrap();
+    let mut permissions = tmpfile.metadata().unwrap().permissions();
+    permissions.set_readonly(true);
+    tmpfile.set_permissions(permissions).unwrap();
+    assert!(tmpfile.metadata().unwrap().permissions().readonly());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tmpfile
+            .set_permissions(std::fs::Permissions::from_mode(0o640))
+            .unwrap();
+        assert_eq!(
+            tmpfile.metadata().unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
+
+#[test]
+fn test_metadata_through_handle() {
+    configure_wasi_temp_dir();
+
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    assert_eq!(tmpfile.len().unwrap(), 0);
+    tmpfile.write_all(b"abcdef").unwrap();
+    assert_eq!(tmpfile.len().unwrap(), 6);
+    assert!(tmpfile.metadata().unwrap().is_file());
+    tmpfile.modified().unwrap();
+
+    tmpfile.set_len(3).unwrap();
+    assert_eq!(tmpfile.len().unwrap(), 3);
+
+    // Still works after the path has been removed.
+    std::fs::remove_file(tmpfile.path()).unwrap();
+    assert_eq!(tmpfile.len().unwrap(), 3);
+}
+
+#[test]
+fn test_shared_temp_path() {
+    configure_wasi_temp_dir();
+
+    let shared = NamedTempFile::new().unwrap().into_temp_path().into_shared();
+    let path = shared.to_path_buf();
+    let clone = shared.clone();
+    assert_eq!(shared.ref_count(), 2);
+
+    // Can't unwrap while another reference exists.
+    let shared = shared.try_unwrap().unwrap_err();
+    drop(clone);
+    assert!(exists(&path));
+
+    let temp_path = shared.try_unwrap().unwrap();
+    let shared = temp_path.into_shared();
+    let clone = shared.clone();
+    drop(shared);
+    assert!(exists(&path));
+    drop(clone);
+    assert!(!exists(&path));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_capture_output() {
+    use std::process::Command;
+
+    let mut output =
+        tempfile::capture_output(Command::new("sh").args(["-c", "echo hello"])).unwrap();
+    let mut buf = String::new();
+    output.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello\n");
+
+    tempfile::capture_output(Command::new("sh").args(["-c", "exit 3"])).unwrap_err();
+
+    // Redirect output into a named temporary file while keeping its path around.
+    let (file, path) = NamedTempFile::new().unwrap().into_parts();
+    let status = Command::new("sh")
+        .args(["-c", "echo world"])
+        .stdout(file)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "world\n");
+
+    // Converting the `NamedTempFile` keeps the path around until the `TempPath` is dropped.
+    let (stdout, path) = NamedTempFile::new().unwrap().into_stdio();
+    let mut child = Command::new("sh")
+        .args(["-c", "echo again"])
+        .stdout(stdout)
+        .spawn()
+        .unwrap();
+    assert!(exists(&path));
+    assert!(child.wait().unwrap().success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "again\n");
+    let kept = path.to_path_buf();
+    drop(path);
+    assert!(!exists(kept));
+}
+
+#[test]
+fn test_utf8_path() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let file = Builder::new()
+        .ascii_only(true)
+        .tempfile_in(dir.path())
+        .unwrap();
+    assert_eq!(file.utf8_path().unwrap(), file.path().to_str().unwrap());
+
+    let err = Builder::new()
+        .prefix("caf\u{e9}-")
+        .ascii_only(true)
+        .tempfile_in(dir.path())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    // Nothing was created.
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // Without the option, any UTF-8 path is fine.
+    let file = Builder::new()
+        .prefix("caf\u{e9}-")
+        .tempfile_in(dir.path())
+        .unwrap();
+    assert!(file.utf8_path().unwrap().contains("caf\u{e9}-"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_utf8_path_invalid() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempdir().unwrap();
+    let file = Builder::new()
+        .prefix(OsStr::from_bytes(b"invalid-\xff-"))
+        .tempfile_in(dir.path())
+        .unwrap();
+    assert_eq!(
+        file.utf8_path().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    let dir = Builder::new()
+        .prefix(OsStr::from_bytes(b"invalid-\xff-"))
+        .tempdir_in(dir.path())
+        .unwrap();
+    assert_eq!(
+        dir.utf8_path().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+#[test]
+fn test_clone_for_thread() {
+    configure_wasi_temp_dir();
+
+    static BUILDER: Builder<'static, 'static> = Builder::new();
+    assert_eq!(BUILDER, Builder::default());
+
+    let dir = tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("pinned-").same_filesystem_as(dir.path());
+    let pinned = builder.clone_for_thread().unwrap();
+    let file = pinned.tempfile().unwrap();
+    assert_eq!(
+        file.path().parent().unwrap(),
+        builder.tempfile().unwrap().path().parent().unwrap()
+    );
+    assert!(file
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("pinned-"));
+
+    let file = BUILDER.clone_for_thread().unwrap().tempfile().unwrap();
+    assert_eq!(file.path().parent().unwrap(), env::temp_dir());
+}
+
+#[test]
+#[cfg_attr(target_os = "wasi", ignore = "reopen is not supported")]
+fn test_duplicate() {
+    let dir = tempdir().unwrap();
+    let mut original = NamedTempFile::new_in(dir.path()).unwrap();
+    original.write_all(b"abcdef").unwrap();
+    original.seek(SeekFrom::Start(2)).unwrap();
+
+    let mut builder = Builder::new();
+    builder.prefix("copy-");
+    let mut copy = original.duplicate(&builder).unwrap();
+    assert_ne!(copy.path(), original.path());
+    assert!(copy
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("copy-"));
+
+    // The copy starts at its beginning, and the original's position is untouched.
+    let mut buf = String::new();
+    copy.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcdef");
+    assert_eq!(original.stream_position().unwrap(), 2);
+
+    // The copies are independent.
+    copy.write_all(b"ghi").unwrap();
+    let mut buf = String::new();
+    original.reopen().unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcdef");
+
+    let path = copy.path().to_owned();
+    drop(copy);
+    assert!(!exists(path));
+}
+
+#[test]
+#[cfg_attr(target_os = "wasi", ignore = "canonicalize is not supported on WASI")]
+fn test_temp_path_try_from() {
+    configure_wasi_temp_dir();
+
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("adopted");
+    std::fs::write(&file_path, b"abc").unwrap();
+
+    let temp_path = TempPath::try_from(file_path.clone()).unwrap();
+    assert!(exists(&temp_path));
+    drop(temp_path);
+    assert!(!exists(&file_path));
+
+    // The path doesn't have to exist yet.
+    TempPath::try_from(dir.path().join("missing")).unwrap();
+
+    // Neither the root itself nor paths outside of it are accepted.
+    for path in [
+        env::temp_dir(),
+        dir.path().join("..").join(".."),
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"),
+    ] {
+        let err = TempPath::try_from(path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    assert!(exists(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")
+    ));
+
+    // Symbolic links can't be used to escape.
+    #[cfg(unix)]
+    {
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(env!("CARGO_MANIFEST_DIR"), &link).unwrap();
+        let err = TempPath::try_from(link.join("Cargo.toml")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_named_tempfile_macro() {
+    configure_wasi_temp_dir();
+
+    mod nested {
+        pub fn create(dir: &std::path::Path) -> (u32, tempfile::NamedTempFile) {
+            (line!(), tempfile::named_tempfile!(dir).unwrap())
+        }
+    }
+
+    let dir = tempdir().unwrap();
+    let (line, file) = nested::create(dir.path());
+    assert_eq!(file.path().parent(), Some(dir.path()));
+    let name = file.path().file_name().unwrap().to_str().unwrap();
+    assert!(
+        name.starts_with(&format!("namedtempfile-nested-{}-", line)),
+        "{}",
+        name
+    );
+
+    let file = tempfile::named_tempfile!().unwrap();
+    assert!(exists(file.path()));
+
+    // Non-ASCII module names are replaced, so the names stay ASCII.
+    mod café {
+        pub fn create(dir: &std::path::Path) -> tempfile::NamedTempFile {
+            tempfile::named_tempfile!(dir).unwrap()
+        }
+    }
+    let file = café::create(dir.path());
+    let name = file.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("namedtempfile-caf_-"), "{}", name);
+}
@@ -0,0 +1,54 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::{Builder, NamedTempFile};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_purge_older_than() {
+    configure_wasi_temp_dir();
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let (_, file) = NamedTempFile::new_in(dir.path()).unwrap().keep().unwrap();
+    let subdir = tempfile::tempdir_in(dir.path()).unwrap().keep();
+    fs::write(subdir.join("data"), b"data").unwrap();
+    let named = Builder::new()
+        .prefix("mine-")
+        .tempfile_in(dir.path())
+        .unwrap()
+        .into_temp_path();
+    let suffixed = Builder::new()
+        .suffix(".txt")
+        .tempfile_in(dir.path())
+        .unwrap()
+        .into_temp_path();
+    let short = dir.path().join(".tmpab");
+    fs::write(&short, b"").unwrap();
+    let other = dir.path().join("other");
+    fs::write(&other, b"").unwrap();
+
+    // Nothing is old enough yet.
+    let day = Duration::from_secs(24 * 60 * 60);
+    assert_eq!(tempfile::purge_older_than(dir.path(), day).unwrap(), 0);
+    assert!(file.exists());
+
+    assert_eq!(
+        tempfile::purge_older_than(dir.path(), Duration::from_secs(0)).unwrap(),
+        2
+    );
+    assert!(!file.exists());
+    assert!(!subdir.exists());
+    assert!(named.exists());
+    assert!(suffixed.exists());
+    assert!(short.exists());
+    assert!(other.exists());
+}
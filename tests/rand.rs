@@ -0,0 +1,85 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "rand")]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Once;
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use tempfile::Builder;
+
+/// For the wasi platforms, `std::env::temp_dir` will panic. For those targets, configure the /tmp
+/// directory instead as the base directory for temp files.
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+static STUCK: AtomicBool = AtomicBool::new(false);
+
+/// Counts how often the crate draws randomness, and can be made to always return the same value.
+struct CountingRng(StdRng);
+
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        if STUCK.load(Ordering::SeqCst) {
+            return 0;
+        }
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+fn install_rng() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        assert!(tempfile::env::override_rng(CountingRng(StdRng::seed_from_u64(1))).is_ok());
+    });
+}
+
+#[test]
+fn test_override_rng() {
+    configure_wasi_temp_dir();
+
+    install_rng();
+    assert!(tempfile::env::override_rng(StdRng::seed_from_u64(2)).is_err());
+
+    let before = CALLS.load(Ordering::SeqCst);
+    let file = Builder::new().rand_bytes(8).tempfile().unwrap();
+    assert!(CALLS.load(Ordering::SeqCst) >= before + 8);
+
+    let name = file.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(name.len(), ".tmp".len() + 8);
+    assert!(name[4..].chars().all(|c| c.is_ascii_alphanumeric()));
+}
+
+#[test]
+fn test_stuck_rng() {
+    configure_wasi_temp_dir();
+    install_rng();
+
+    let dir = tempfile::tempdir().unwrap();
+    STUCK.store(true, Ordering::SeqCst);
+    let first = Builder::new().rand_bytes(4).tempfile_in(&dir).unwrap();
+    // Every name drawn from the stuck generator collides, but creation still succeeds.
+    let second = Builder::new().rand_bytes(4).tempfile_in(&dir).unwrap();
+    STUCK.store(false, Ordering::SeqCst);
+    assert_ne!(first.path(), second.path());
+}
@@ -0,0 +1,48 @@
+#![deny(rust_2018_idioms)]
+
+use std::io::Write;
+
+use tempfile::{env, spooled_tempfile, Builder, NamedTempFile};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+// Redaction is process-wide, so this file holds a single test.
+#[test]
+fn test_redact_debug() {
+    configure_wasi_temp_dir();
+
+    let dir = Builder::new().prefix("secret-id").tempdir().unwrap();
+    let file = NamedTempFile::with_prefix_in("secret-id", &dir).unwrap();
+    let mut spooled = spooled_tempfile(1024);
+    spooled.write_all(b"secret-data").unwrap();
+    let err = NamedTempFile::new_in(dir.path().join("missing")).unwrap_err();
+    let mut builder = Builder::new();
+    builder.prefix("secret-id").same_filesystem_as(dir.path());
+    let subdir = dir.subdir_builder();
+
+    assert!(format!("{:?}", dir).contains("secret-id"));
+    assert!(format!("{:?}", err).contains("missing"));
+    assert!(format!("{:?}", builder).contains("secret-id"));
+    // In-memory data is never printed.
+    assert!(!format!("{:?}", spooled).contains("secret"));
+
+    env::override_redact_debug(true);
+    assert_eq!(format!("{:?}", file), "NamedTempFile(<redacted>)");
+    assert!(!format!("{:?}", dir).contains("secret-id"));
+    assert!(!format!("{:?}", spooled).contains("secret"));
+    assert!(!format!("{:?}", err).contains("missing"));
+    assert!(!format!("{:?}", builder).contains("secret-id"));
+    assert!(!format!("{:?}", subdir).contains("secret-id"));
+    // Display output isn't affected.
+    assert!(err.to_string().contains("missing"));
+
+    spooled.roll().unwrap();
+    assert!(!format!("{:?}", spooled).contains("secret"));
+
+    env::override_redact_debug(false);
+    assert!(format!("{:?}", file).contains("secret-id"));
+}
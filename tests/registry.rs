@@ -0,0 +1,83 @@
+#![deny(rust_2018_idioms)]
+
+use std::path::Path;
+
+use tempfile::{NamedTempFile, TempDir};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_register() {
+    configure_wasi_temp_dir();
+
+    let dir = TempDir::new().unwrap();
+    dir.register("tempfile-test-register-dir").unwrap();
+    let file = NamedTempFile::new().unwrap();
+    file.register("tempfile-test-register-file").unwrap();
+    // Registering again replaces our own registration.
+    file.register("tempfile-test-register-file").unwrap();
+
+    // This process is still running, so nothing of ours is reclaimed.
+    let reclaimed = tempfile::reclaim().unwrap();
+    assert!(!reclaimed
+        .iter()
+        .any(|r| r.name().starts_with("tempfile-test-register-")));
+
+    assert!(tempfile::unregister("tempfile-test-register-dir").unwrap());
+    assert!(!tempfile::unregister("tempfile-test-register-dir").unwrap());
+    assert!(tempfile::unregister("tempfile-test-register-file").unwrap());
+}
+
+#[test]
+fn test_register_invalid_name() {
+    configure_wasi_temp_dir();
+
+    let dir = TempDir::new().unwrap();
+    for name in ["", ".hidden", "a/b", "a b"] {
+        let err = dir.register(name).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reclaim_after_exit() {
+    use std::process::Command;
+
+    // Register a directory from a process that exits right away, without cleaning up.
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "register_from_child", "--ignored"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let reclaimed = tempfile::reclaim().unwrap();
+    let leftover = reclaimed
+        .into_iter()
+        .find(|r| r.name() == "tempfile-test-reclaim")
+        .expect("registration of the exited process should be reclaimed");
+    assert!(leftover.is_dir());
+    assert!(leftover.pid().is_some());
+    assert_ne!(leftover.pid(), Some(std::process::id()));
+    let dir = TempDir::adopt(leftover.into_token()).unwrap();
+    dir.close().unwrap();
+
+    // Reclaimed registrations are gone.
+    assert!(!tempfile::reclaim()
+        .unwrap()
+        .iter()
+        .any(|r| r.name() == "tempfile-test-reclaim"));
+}
+
+#[cfg(unix)]
+#[test]
+#[ignore = "run by test_reclaim_after_exit"]
+fn register_from_child() {
+    let dir = TempDir::new().unwrap();
+    dir.register("tempfile-test-reclaim").unwrap();
+    let _ = dir.keep();
+}
@@ -0,0 +1,67 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use tempfile::ScratchCache;
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+fn insert(cache: &mut ScratchCache, key: &str, contents: &[u8]) -> io::Result<()> {
+    let mut file = cache.tempfile()?;
+    file.write_all(contents)?;
+    cache.insert(key, file).map(|_| ())
+}
+
+#[test]
+fn test_scratch_cache_evicts_lru() {
+    configure_wasi_temp_dir();
+
+    let mut cache = ScratchCache::new(10).unwrap();
+    insert(&mut cache, "a", b"aaaa").unwrap();
+    insert(&mut cache, "b", b"bbbb").unwrap();
+    assert_eq!(cache.size(), 8);
+
+    // Using "a" makes "b" the least recently used entry.
+    let a = cache.get("a").unwrap();
+    assert_eq!(fs::read(&a).unwrap(), b"aaaa");
+    assert!(cache.last_accessed("a").is_some());
+    insert(&mut cache, "c", b"cccc").unwrap();
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.size(), 8);
+    assert!(cache.get("b").is_none());
+    assert!(!cache.path().join("b").exists());
+    assert!(cache.get("c").is_some());
+
+    // Replacing an entry only counts its new size.
+    insert(&mut cache, "a", b"aa").unwrap();
+    assert_eq!(cache.size(), 6);
+
+    assert!(cache.remove("a").unwrap());
+    assert!(!cache.remove("a").unwrap());
+    assert_eq!(cache.size(), 4);
+
+    let path = cache.path().to_owned();
+    drop(cache);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_scratch_cache_rejects() {
+    configure_wasi_temp_dir();
+
+    let mut cache = ScratchCache::new(4).unwrap();
+    for key in ["", ".hidden", "a/b", ".."] {
+        let err = insert(&mut cache, key, b"x").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    let err = insert(&mut cache, "big", b"too big").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(cache.is_empty());
+    assert_eq!(fs::read_dir(cache.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,61 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "metrics")]
+
+use std::io::{Seek, SeekFrom, Write};
+
+use tempfile::{env, spool_metrics, spooled_tempfile};
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_spool_metrics() {
+    configure_wasi_temp_dir();
+
+    let before = spool_metrics();
+    assert_eq!(before.bytes_in_memory(), 0);
+    assert_eq!(before.bytes_on_disk(), 0);
+    assert_eq!(before.spills(), 0);
+
+    let mut a = spooled_tempfile(10);
+    a.write_all(b"abcde").unwrap();
+    let mut b = spooled_tempfile(10);
+    b.write_all(b"abc").unwrap();
+    let metrics = spool_metrics();
+    assert_eq!(metrics.bytes_in_memory(), 8);
+    assert_eq!(metrics.bytes_on_disk(), 0);
+
+    a.write_all(b"fghijklmnop").unwrap();
+    let metrics = spool_metrics();
+    assert_eq!(metrics.bytes_in_memory(), 3);
+    assert_eq!(metrics.bytes_on_disk(), 16);
+    assert_eq!(metrics.spills(), 1);
+    assert_eq!(metrics.largest_spool(), 16);
+
+    a.set_len(4).unwrap();
+    assert_eq!(spool_metrics().bytes_on_disk(), 4);
+    assert_eq!(spool_metrics().largest_spool(), 16);
+
+    // Appending and overwriting on disk.
+    a.seek(SeekFrom::End(0)).unwrap();
+    a.write_all(b"qr").unwrap();
+    a.write_all(b"st").unwrap();
+    assert_eq!(spool_metrics().bytes_on_disk(), 8);
+    a.seek(SeekFrom::Start(0)).unwrap();
+    a.write_all(b"AB").unwrap();
+    assert_eq!(spool_metrics().bytes_on_disk(), 8);
+    a.seek(SeekFrom::End(0)).unwrap();
+    a.write_all(b"u").unwrap();
+    assert_eq!(spool_metrics().bytes_on_disk(), 9);
+
+    drop(a);
+    let data = b.into_inner().unwrap();
+    let metrics = spool_metrics();
+    assert_eq!(metrics.bytes_in_memory(), 0);
+    assert_eq!(metrics.bytes_on_disk(), 0);
+    assert_eq!(metrics.spills(), 1);
+    drop(data);
+}
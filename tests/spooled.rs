@@ -1,4 +1,5 @@
 #![deny(rust_2018_idioms)]
+#![cfg_attr(feature = "nightly", feature(can_vector))]
 
 use std::io::{Read, Seek, SeekFrom, Write};
 
@@ -375,3 +376,342 @@ fn test_set_len_truncation() {
     assert!(t.set_len(usize::MAX as u64 + 5).is_ok());
     assert!(t.is_rolled());
 }
+
+#[test]
+fn test_into_named() {
+    configure_wasi_temp_dir();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut t = spooled_tempfile_in(10, dir.path());
+    t.write_all(b"abcde").unwrap();
+    t.seek(SeekFrom::Start(2)).unwrap();
+    assert!(!t.is_rolled());
+
+    let mut named = t
+        .into_named(tempfile::Builder::new().prefix("spooled-"))
+        .unwrap();
+    assert_eq!(named.path().parent().unwrap(), dir.path());
+    let name = named.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("spooled-"));
+    assert_eq!(std::fs::read(named.path()).unwrap(), b"abcde");
+    let mut buf = String::new();
+    named.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "cde");
+
+    // Data that has already been rolled over is copied.
+    let mut t = spooled_tempfile(3);
+    t.write_all(b"abcde").unwrap();
+    assert!(t.is_rolled());
+    let mut named = t.into_named(&tempfile::Builder::new()).unwrap();
+    assert_eq!(std::fs::read(named.path()).unwrap(), b"abcde");
+    assert_eq!(named.stream_position().unwrap(), 5);
+    named.write_all(b"f").unwrap();
+    assert_eq!(std::fs::read(named.path()).unwrap(), b"abcdef");
+}
+
+#[test]
+fn test_into_file_with() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcde").unwrap();
+    let mut file = t.into_file_with(&tempfile::Builder::new()).unwrap();
+    assert_eq!(file.stream_position().unwrap(), 5);
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+}
+
+#[test]
+fn test_as_slice() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    assert_eq!(t.as_slice(), Some(&[][..]));
+    t.write_all(b"abcde").unwrap();
+    assert_eq!(t.as_slice(), Some(&b"abcde"[..]));
+
+    t.as_mut_slice().unwrap()[0] = b'x';
+    let mut buf = String::new();
+    t.seek(SeekFrom::Start(0)).unwrap();
+    t.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "xbcde");
+
+    t.write_all(b"fghijk").unwrap();
+    assert!(t.is_rolled());
+    assert_eq!(t.as_slice(), None);
+    assert_eq!(t.as_mut_slice(), None);
+}
+
+#[test]
+fn test_vectored() {
+    use std::io::{IoSlice, IoSliceMut};
+
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    let n = t
+        .write_vectored(&[IoSlice::new(b"ab"), IoSlice::new(b"cd")])
+        .unwrap();
+    assert_eq!(n, 4);
+    assert!(!t.is_rolled());
+
+    // A burst that doesn't fit in memory is written to disk in one go.
+    let n = t
+        .write_vectored(&[IoSlice::new(b"efgh"), IoSlice::new(b"ijkl")])
+        .unwrap();
+    assert!(t.is_rolled());
+    assert!(n > 0);
+    t.write_all(&b"efghijkl"[n..]).unwrap();
+
+    for _ in 0..2 {
+        t.seek(SeekFrom::Start(0)).unwrap();
+        let (mut a, mut b) = ([0u8; 5], [0u8; 7]);
+        let mut read = 0;
+        while read < 12 {
+            let (first, second) = if read < 5 {
+                (&mut a[read..], &mut b[..])
+            } else {
+                (&mut a[5..], &mut b[read - 5..])
+            };
+            let n = t
+                .read_vectored(&mut [IoSliceMut::new(first), IoSliceMut::new(second)])
+                .unwrap();
+            assert!(n > 0);
+            read += n;
+        }
+        assert_eq!(&a, b"abcde");
+        assert_eq!(&b, b"fghijkl");
+
+        // Repeat in memory.
+        t = spooled_tempfile(20);
+        t.write_all(b"abcdefghijkl").unwrap();
+        assert!(!t.is_rolled());
+    }
+}
+
+#[cfg(feature = "nightly")]
+#[test]
+fn test_is_vectored() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    assert!(t.is_write_vectored());
+    assert!(t.is_read_vectored());
+    t.roll().unwrap();
+    let file = tempfile::tempfile().unwrap();
+    assert_eq!(t.is_write_vectored(), file.is_write_vectored());
+    assert_eq!(t.is_read_vectored(), file.is_read_vectored());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_into_stdio() {
+    use std::process::Command;
+
+    let mut t = spooled_tempfile(100);
+    t.write_all(b"hello world").unwrap();
+    // The position doesn't matter, the child reads everything.
+    t.seek(SeekFrom::Start(6)).unwrap();
+    let output = Command::new("cat")
+        .stdin(t.into_stdio().unwrap())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello world");
+}
+
+#[test]
+fn test_builder_spooled() {
+    configure_wasi_temp_dir();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("spool-").suffix(".bin");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o640));
+    }
+    let mut t = builder.spooled_in(5, dir.path());
+    t.write_all(b"abc").unwrap();
+    assert!(!t.is_rolled());
+    t.write_all(b"defgh").unwrap();
+    assert!(t.is_rolled());
+
+    // The file is unlinked right away.
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let file = t.into_file().unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = file.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let target = std::fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+        let name = target.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("spool-"), "{}", name);
+        assert!(name.contains(".bin"), "{}", name);
+    }
+    let _ = file;
+}
+
+#[test]
+fn test_with_limits() {
+    use tempfile::SizeLimitExceeded;
+
+    configure_wasi_temp_dir();
+
+    fn limit(err: &std::io::Error) -> Option<u64> {
+        err.get_ref()
+            .and_then(|e| e.downcast_ref::<SizeLimitExceeded>())
+            .map(|e| e.limit())
+    }
+
+    let mut t = SpooledTempFile::with_limits(4, 10);
+    t.write_all(b"abc").unwrap();
+    assert!(!t.is_rolled());
+    t.write_all(b"defgh").unwrap();
+    assert!(t.is_rolled());
+
+    // Rejected writes don't write anything.
+    let err = t.write(b"ijk").unwrap_err();
+    assert_eq!(limit(&err), Some(10));
+    assert_eq!(t.stream_position().unwrap(), 8);
+    t.write_all(b"ij").unwrap();
+    assert_eq!(limit(&t.write(b"k").unwrap_err()), Some(10));
+
+    // Overwriting is fine.
+    t.seek(SeekFrom::Start(0)).unwrap();
+    t.write_all(b"ABCDEFGHIJ").unwrap();
+    let mut buf = String::new();
+    t.seek(SeekFrom::Start(0)).unwrap();
+    t.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "ABCDEFGHIJ");
+
+    assert_eq!(limit(&t.set_len(11).unwrap_err()), Some(10));
+    t.set_len(5).unwrap();
+
+    // In memory too.
+    let mut t = SpooledTempFile::with_limits(100, 3);
+    assert_eq!(limit(&t.write(b"abcd").unwrap_err()), Some(3));
+    assert!(!t.is_rolled());
+}
+
+#[test]
+fn test_snapshot() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcde").unwrap();
+    let mut snapshot = t.snapshot().unwrap();
+    assert_eq!(snapshot.len(), 5);
+    t.write_all(b"fghijklmno").unwrap();
+    assert!(t.is_rolled());
+
+    let mut buf = String::new();
+    snapshot.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+    snapshot.seek(SeekFrom::Start(100)).unwrap();
+    assert_eq!(snapshot.read(&mut [0; 4]).unwrap(), 0);
+
+    // Snapshots of rolled over files don't disturb the writer.
+    #[cfg(any(unix, windows))]
+    {
+        let mut snapshot = t.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 15);
+        t.write_all(b"p").unwrap();
+
+        snapshot.seek(SeekFrom::End(-5)).unwrap();
+        let mut buf = String::new();
+        snapshot.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "klmno");
+        t.write_all(b"qr").unwrap();
+
+        t.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = String::new();
+        t.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "abcdefghijklmnopqr");
+    }
+}
+
+#[test]
+fn test_spill_in_background() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(1024);
+    t.spill_in_background(true);
+    t.write_all(&[b'a'; 1000]).unwrap();
+    assert!(!t.is_rolled());
+    t.write_all(&[b'b'; 100]).unwrap();
+    assert!(t.is_rolled());
+    assert_eq!(t.as_slice(), None);
+    for _ in 0..100 {
+        t.write_all(b"c").unwrap();
+    }
+
+    t.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    let mut expected = vec![b'a'; 1000];
+    expected.extend_from_slice(&[b'b'; 100]);
+    expected.extend_from_slice(&[b'c'; 100]);
+    assert_eq!(buf, expected);
+    assert!(matches!(
+        t.into_inner().unwrap(),
+        tempfile::SpooledData::OnDisk(_)
+    ));
+}
+
+#[test]
+fn test_spill_in_background_failure() {
+    configure_wasi_temp_dir();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut t = spooled_tempfile_in(4, dir.path().join("missing"));
+    t.spill_in_background(true);
+    t.write_all(b"abc").unwrap();
+    // Either this write or the next operation reports the failure.
+    let write = t.write_all(b"de");
+    let flush = t.flush();
+    assert!(write.is_err() != flush.is_err());
+
+    // The data is still there.
+    t.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    t.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, if write.is_ok() { "abcde" } else { "abc" });
+    assert!(!t.is_rolled());
+}
+
+#[test]
+fn test_into_inner_reports_background_failure() {
+    configure_wasi_temp_dir();
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut t = spooled_tempfile_in(4, dir.path().join("missing"));
+    t.spill_in_background(true);
+    // Either this write or `into_inner` reports the failure.
+    let write = t.write_all(b"abcde");
+    assert!(write.is_err() != t.into_inner().is_err());
+}
+
+#[test]
+fn test_bytes_in_memory_and_on_disk() {
+    configure_wasi_temp_dir();
+
+    let mut t = spooled_tempfile(10);
+    assert_eq!(t.bytes_in_memory(), 0);
+    assert_eq!(t.bytes_on_disk().unwrap(), 0);
+    t.write_all(b"abcde").unwrap();
+    assert_eq!(t.bytes_in_memory(), 5);
+    assert_eq!(t.bytes_on_disk().unwrap(), 0);
+    t.write_all(b"fghijk").unwrap();
+    assert_eq!(t.bytes_in_memory(), 0);
+    assert_eq!(t.bytes_on_disk().unwrap(), 11);
+}
@@ -11,7 +11,8 @@
 #![deny(rust_2018_idioms)]
 
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::thread;
 
@@ -197,8 +198,449 @@ fn test_disable_cleanup() {
     }
 }
 
+fn test_read_back_helpers() {
+    let tmpdir = TempDir::new().unwrap();
+    fs::create_dir(tmpdir.path().join("sub")).unwrap();
+    fs::write(tmpdir.path().join("sub").join("b.txt"), "bbb").unwrap();
+    fs::write(tmpdir.path().join("a.txt"), "aaa").unwrap();
+
+    assert_eq!(tmpdir.read_to_string("a.txt").unwrap(), "aaa");
+    assert_eq!(tmpdir.read_to_string("./sub/b.txt").unwrap(), "bbb");
+    assert!(tmpdir.exists("sub/b.txt"));
+    assert!(!tmpdir.exists("sub/c.txt"));
+    assert_eq!(
+        tmpdir.entries_sorted().unwrap(),
+        [PathBuf::from("a.txt"), PathBuf::from("sub")]
+    );
+
+    // Paths escaping the temporary directory are rejected.
+    let outside = tmpdir.path().join("a.txt");
+    assert_eq!(
+        tmpdir.read_to_string(&outside).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        tmpdir.read_to_string("sub/../a.txt").unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    assert!(!tmpdir.exists(&outside));
+    assert!(!tmpdir.exists(".."));
+}
+
+fn test_entries() {
+    let tmpdir = TempDir::new().unwrap();
+    fs::create_dir_all(tmpdir.path().join("b").join("c")).unwrap();
+    fs::write(tmpdir.path().join("b").join("c").join("d.txt"), "").unwrap();
+    fs::write(tmpdir.path().join("b").join("a.txt"), "").unwrap();
+    fs::write(tmpdir.path().join("z.txt"), "").unwrap();
+
+    let mut top: Vec<PathBuf> = tmpdir.entries().map(Result::unwrap).collect();
+    top.sort();
+    assert_eq!(top, [PathBuf::from("b"), PathBuf::from("z.txt")]);
+
+    let all: Vec<PathBuf> = tmpdir
+        .entries()
+        .recursive(true)
+        .sorted(true)
+        .map(Result::unwrap)
+        .collect();
+    let b = Path::new("b");
+    assert_eq!(
+        all,
+        [
+            b.to_owned(),
+            b.join("a.txt"),
+            b.join("c"),
+            b.join("c").join("d.txt"),
+            PathBuf::from("z.txt"),
+        ]
+    );
+
+    let missing = TempDir::new().unwrap();
+    fs::remove_dir(missing.path()).unwrap();
+    let mut entries = missing.entries();
+    assert_eq!(
+        entries.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert!(entries.next().is_none());
+}
+
+fn test_open_handle() {
+    let tmpdir = TempDir::new().unwrap();
+    let handle = tmpdir.open_handle().unwrap();
+    assert!(handle.metadata().unwrap().is_dir());
+
+    // The handle keeps referring to the same directory after a rename.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let renamed = tmpdir.path().with_extension("renamed");
+        fs::rename(tmpdir.path(), &renamed).unwrap();
+        assert_eq!(
+            handle.metadata().unwrap().ino(),
+            fs::metadata(&renamed).unwrap().ino()
+        );
+        fs::rename(&renamed, tmpdir.path()).unwrap();
+    }
+}
+
+fn test_set_permissions() {
+    let tmpdir = TempDir::new().unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tmpdir
+            .set_permissions(fs::Permissions::from_mode(0o750))
+            .unwrap();
+        let mode = tmpdir.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+    #[cfg(windows)]
+    {
+        let original = tmpdir.path().metadata().unwrap().permissions();
+        let mut permissions = original.clone();
+        permissions.set_readonly(true);
+        tmpdir.set_permissions(permissions).unwrap();
+        assert!(tmpdir.path().metadata().unwrap().permissions().readonly());
+        tmpdir.set_permissions(original).unwrap();
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = tmpdir;
+}
+
+fn test_create_parents() {
+    let deep = Path::new("a").join("b").join("c");
+    let err = Builder::new().tempdir_in(&deep).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let tmpdir = Builder::new()
+        .create_parents(true)
+        .tempdir_in(&deep)
+        .unwrap();
+    assert!(tmpdir.path().parent().unwrap().ends_with(&deep));
+    let tmpfile = Builder::new()
+        .create_parents(true)
+        .tempfile_in(&deep)
+        .unwrap();
+    assert!(tmpfile.path().parent().unwrap().ends_with(&deep));
+
+    // The parents aren't temporary.
+    drop(tmpdir);
+    drop(tmpfile);
+    assert!(deep.is_dir());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let private = Path::new("private").join("nested");
+        let _tmpdir = Builder::new()
+            .create_parents(true)
+            .parent_permissions(fs::Permissions::from_mode(0o700))
+            .tempdir_in(&private)
+            .unwrap();
+        for dir in [Path::new("private"), &private] {
+            let mode = dir.metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+    }
+}
+
+fn test_remove_only_if_empty() {
+    let tmpdir = Builder::new().remove_only_if_empty(true).tempdir().unwrap();
+    let path = tmpdir.path().to_owned();
+    drop(tmpdir);
+    assert!(!path.exists());
+
+    let tmpdir = Builder::new().remove_only_if_empty(true).tempdir().unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::write(path.join("output"), "data").unwrap();
+    drop(tmpdir);
+    assert!(path.join("output").exists());
+    fs::remove_dir_all(&path).unwrap();
+
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.remove_only_if_empty(true);
+    fs::write(tmpdir.path().join("output"), "data").unwrap();
+    let path = tmpdir.path().to_owned();
+    tmpdir.close().unwrap_err();
+    assert!(path.join("output").exists());
+    fs::remove_dir_all(&path).unwrap();
+
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.remove_only_if_empty(true);
+    fs::write(tmpdir.path().join("output"), "data").unwrap();
+    let (path, guard) = tmpdir.into_parts();
+    drop(guard);
+    assert!(path.join("output").exists());
+    fs::remove_dir_all(&path).unwrap();
+}
+
+fn test_preserve_on_drop() {
+    let artifacts = TempDir::new().unwrap();
+
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.preserve_on_drop(
+        |path| path.extension().map_or(false, |ext| ext == "log"),
+        artifacts.path(),
+    );
+    let path = tmpdir.path().to_owned();
+    fs::create_dir(path.join("sub")).unwrap();
+    fs::write(path.join("a.log"), "a").unwrap();
+    fs::write(path.join("sub").join("b.log"), "b").unwrap();
+    fs::write(path.join("sub").join("c.txt"), "c").unwrap();
+    tmpdir.close().unwrap();
+
+    assert!(!path.exists());
+    assert_eq!(
+        fs::read_to_string(artifacts.path().join("a.log")).unwrap(),
+        "a"
+    );
+    assert_eq!(
+        fs::read_to_string(artifacts.path().join("sub").join("b.log")).unwrap(),
+        "b"
+    );
+    assert!(!artifacts.path().join("sub").join("c.txt").exists());
+
+    // Also applies through a detached cleanup guard.
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.preserve_on_drop(|_| true, artifacts.path().join("all"));
+    fs::write(tmpdir.path().join("core"), "dump").unwrap();
+    let (path, guard) = tmpdir.into_parts();
+    drop(guard);
+    assert!(!path.exists());
+    assert!(artifacts.path().join("all").join("core").exists());
+}
+
+fn test_close_with_progress() {
+    let tmpdir = TempDir::new().unwrap();
+    let root = tmpdir.path().to_owned();
+    fs::create_dir_all(root.join("a").join("b")).unwrap();
+    fs::write(root.join("a").join("b").join("one"), [0u8; 100]).unwrap();
+    fs::write(root.join("a").join("two"), [0u8; 20]).unwrap();
+    fs::write(root.join("three"), [0u8; 3]).unwrap();
+
+    let mut reports = Vec::new();
+    tmpdir
+        .close_with_progress(|p| {
+            reports.push((p.entries_removed(), p.bytes_freed(), p.path().to_owned()))
+        })
+        .unwrap();
+    assert!(!root.exists());
+
+    // 3 files + 2 subdirectories + the temporary directory itself.
+    assert_eq!(reports.len(), 6);
+    for (i, (entries, _, _)) in reports.iter().enumerate() {
+        assert_eq!(*entries, i as u64 + 1);
+    }
+    let (_, bytes, last) = reports.last().unwrap();
+    assert_eq!(*bytes, 123);
+    assert_eq!(*last, root);
+}
+
+fn test_share() {
+    let shared = TempDir::new().unwrap().share();
+    let path = shared.path().to_owned();
+    let clone = shared.clone();
+    assert_eq!(shared.ref_count(), 2);
+
+    // Closing a non-last reference only releases it.
+    shared.close().unwrap();
+    assert!(path.exists());
+    assert_eq!(clone.ref_count(), 1);
+
+    let other = clone.clone();
+    thread::spawn(move || drop(other)).join().unwrap();
+    clone.close().unwrap();
+    assert!(!path.exists());
+
+    let shared = TempDir::new().unwrap().share();
+    let path = shared.path().to_owned();
+    let tmpdir = shared.try_unwrap().unwrap();
+    assert_eq!(tmpdir.path(), path);
+}
+
+fn test_subdir_builder() {
+    let root = TempDir::new().unwrap();
+
+    let sub = root
+        .subdir_builder()
+        .prefix("sub-")
+        .suffix(".d")
+        .tempdir()
+        .unwrap();
+    let name = sub.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("sub-") && name.ends_with(".d"));
+    assert_eq!(sub.path().parent().unwrap(), root.path());
+
+    let kept = root
+        .subdir_builder()
+        .disable_cleanup(true)
+        .tempdir()
+        .unwrap();
+    let kept_path = kept.path().to_owned();
+    drop(kept);
+    assert!(kept_path.exists());
+
+    let file = root.subdir_builder().prefix("f-").tempfile().unwrap();
+    assert_eq!(file.path().parent().unwrap(), root.path());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let private = root
+            .subdir_builder()
+            .permissions(fs::Permissions::from_mode(0o700))
+            .tempdir()
+            .unwrap();
+        let mode = private.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    // The guards are independent of each other.
+    let sub_path = sub.path().to_owned();
+    drop(sub);
+    assert!(!sub_path.exists());
+    assert!(root.path().exists());
+}
+
+fn test_into_parts() {
+    let (path, guard) = TempDir::new().unwrap().into_parts();
+    assert_eq!(guard.path(), path);
+    assert!(path.exists());
+    thread::spawn(move || drop(guard)).join().unwrap();
+    assert!(!path.exists());
+
+    let (path, mut guard) = TempDir::new().unwrap().into_parts();
+    guard.disable_cleanup(true);
+    drop(guard);
+    assert!(path.exists());
+    fs::remove_dir(&path).unwrap();
+
+    let mut tmpdir = TempDir::new().unwrap();
+    tmpdir.disable_cleanup(true);
+    let (path, guard) = tmpdir.into_parts();
+    drop(guard);
+    assert!(path.exists());
+    fs::remove_dir(&path).unwrap();
+
+    let (path, guard) = TempDir::new().unwrap().into_parts();
+    guard.close().unwrap();
+    assert!(!path.exists());
+}
+
 #[test]
 #[cfg_attr(target_os = "wasi", ignore = "thread::spawn is not supported")]
+fn test_write_executable() {
+    let tmp_dir = TempDir::new().unwrap();
+    let helper = tmp_dir
+        .write_executable("helper", b"#!/bin/sh\necho hello\n")
+        .unwrap();
+    assert_eq!(helper.parent().unwrap(), tmp_dir.path());
+    assert_eq!(
+        helper.file_name().unwrap(),
+        format!("helper{}", std::env::consts::EXE_SUFFIX).as_str()
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = helper.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let output = std::process::Command::new(&helper).output().unwrap();
+        assert_eq!(output.stdout, b"hello\n");
+    }
+
+    // Existing files aren't replaced, and the name can't escape the directory.
+    assert!(tmp_dir.write_executable("helper", b"").is_err());
+    let err = tmp_dir.write_executable("../helper", b"").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let helper = tempfile::executable_tempfile(b"#!/bin/sh\n").unwrap();
+    assert!(helper
+        .to_str()
+        .unwrap()
+        .ends_with(std::env::consts::EXE_SUFFIX));
+    assert_eq!(fs::read(&helper).unwrap(), b"#!/bin/sh\n");
+    let path = helper.to_path_buf();
+    drop(helper);
+    assert!(!path.exists());
+}
+
+fn test_long_paths() {
+    use std::io::{Read, Write};
+    use tempfile::NamedTempFile;
+
+    // Deeper than `MAX_PATH` (260 characters) allows on Windows without extended-length paths.
+    let root = TempDir::new().unwrap();
+    let mut deep = root.path().to_owned();
+    while deep.as_os_str().len() <= 300 {
+        deep.push("a-rather-long-directory-name");
+    }
+    fs::create_dir_all(&deep).unwrap();
+
+    let mut tmp = NamedTempFile::new_in(&deep).unwrap();
+    tmp.write_all(b"data").unwrap();
+    let mut buf = String::new();
+    tmp.reopen().unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "data");
+    tmp.clear_temporary_attribute().unwrap();
+    tmp.persist(deep.join("persisted")).unwrap();
+    assert_eq!(fs::read(deep.join("persisted")).unwrap(), b"data");
+
+    let tmp = NamedTempFile::new_in(&deep).unwrap();
+    tmp.persist_durable(deep.join("durable")).unwrap();
+    let (_, kept) = NamedTempFile::new_in(&deep).unwrap().keep().unwrap();
+    assert!(kept.exists());
+    tempfile::tempfile_in(&deep).unwrap();
+    let sub = TempDir::new_in(&deep).unwrap();
+    fs::write(sub.path().join("file"), "data").unwrap();
+    sub.close().unwrap();
+
+    let root_path = root.path().to_owned();
+    root.close().unwrap();
+    assert!(!root_path.exists());
+}
+
+#[test]
+fn test_join_checked() {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir(tmp.path().join("sub")).unwrap();
+
+    assert_eq!(tmp.join_checked("").unwrap(), tmp.path());
+    assert_eq!(
+        tmp.join_checked("sub/../a/./b").unwrap(),
+        tmp.path().join("a").join("b")
+    );
+    // Missing components are fine.
+    assert_eq!(
+        tmp.join_checked("missing/file").unwrap(),
+        tmp.path().join("missing").join("file")
+    );
+
+    for escaping in ["..", "sub/../..", "a/../../b"] {
+        let err = tmp.join_checked(escaping).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", escaping);
+    }
+    let absolute = tmp.path().join("sub");
+    assert_eq!(
+        tmp.join_checked(absolute).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("/", tmp.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("/", tmp.path().join("sub").join("link")).unwrap();
+        for through_link in ["link", "link/etc", "sub/link/etc"] {
+            let err = tmp.join_checked(through_link).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", through_link);
+        }
+        // The link is never traversed.
+        tmp.join_checked("link/../sub").unwrap();
+    }
+}
+
 fn main() {
     in_tmpdir(test_tempdir);
     in_tmpdir(test_prefix);
@@ -209,4 +651,208 @@ fn main() {
     in_tmpdir(dont_double_panic);
     in_tmpdir(pass_as_asref_path);
     in_tmpdir(test_disable_cleanup);
+    in_tmpdir(test_read_back_helpers);
+    in_tmpdir(test_open_handle);
+    in_tmpdir(test_entries);
+    in_tmpdir(test_into_parts);
+    in_tmpdir(test_set_permissions);
+    in_tmpdir(test_create_parents);
+    in_tmpdir(test_remove_only_if_empty);
+    in_tmpdir(test_preserve_on_drop);
+    in_tmpdir(test_close_with_progress);
+    in_tmpdir(test_share);
+    in_tmpdir(test_subdir_builder);
+    in_tmpdir(test_write_executable);
+    in_tmpdir(test_long_paths);
+    in_tmpdir(test_join_checked);
+}
+
+#[test]
+#[cfg(windows)]
+fn test_cleanup_on_kill() {
+    // When re-executed as a child process, create the directory and exit without running
+    // destructors, as if the process had been killed.
+    if std::env::var_os("TEMPFILE_KILLED_CHILD").is_some() {
+        let dir = Builder::new()
+            .prefix("killed-")
+            .windows_cleanup_on_kill(true)
+            .tempdir()
+            .unwrap();
+        let kept = Builder::new()
+            .prefix("kept-")
+            .windows_cleanup_on_kill(true)
+            .tempdir()
+            .unwrap();
+        fs::write(dir.path().join("file"), "data").unwrap();
+        println!("killed-path:{}", dir.path().display());
+        println!("kept-path:{}", kept.keep().display());
+        std::process::exit(0);
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["test_cleanup_on_kill", "--exact", "--nocapture"])
+        .env("TEMPFILE_KILLED_CHILD", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let reported = |key: &str| {
+        PathBuf::from(
+            stdout
+                .lines()
+                .find_map(|l| l.split(key).nth(1))
+                .expect("child didn't report a path"),
+        )
+    };
+    let killed = reported("killed-path:");
+    let kept = reported("kept-path:");
+
+    // The watchdog notices the exit asynchronously.
+    for _ in 0..600 {
+        if !killed.exists() {
+            break;
+        }
+        thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert!(!killed.exists(), "tempdir wasn't deleted by the watchdog");
+    assert!(kept.exists());
+    fs::remove_dir(kept).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_global_tempdir() {
+    static GLOBAL_DIR: tempfile::GlobalTempDir = tempfile::GlobalTempDir::with_prefix("global-");
+
+    // When re-executed as a child process, create the directory and exit normally.
+    if std::env::var_os("TEMPFILE_GLOBAL_CHILD").is_some() {
+        let path = GLOBAL_DIR.path().unwrap();
+        assert_eq!(GLOBAL_DIR.path().unwrap(), path);
+        fs::write(path.join("file"), "data").unwrap();
+        println!("global-path:{}", path.display());
+        return;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["test_global_tempdir", "--exact", "--nocapture"])
+        .env("TEMPFILE_GLOBAL_CHILD", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let path = stdout
+        .lines()
+        .find_map(|l| l.split("global-path:").nth(1))
+        .expect("child didn't report a path");
+    let name = Path::new(path).file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("global-"));
+    assert!(!Path::new(path).exists(), "global tempdir wasn't deleted");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_leak() {
+    // When re-executed as a child process, leak two directories and exit normally.
+    if std::env::var_os("TEMPFILE_LEAK_CHILD").is_some() {
+        let leaked = Builder::new().prefix("leaked-").tempdir().unwrap().leak();
+        fs::write(leaked.join("file"), "data").unwrap();
+        println!("leaked-path:{}", leaked.display());
+
+        let mut kept = Builder::new().prefix("kept-").tempdir().unwrap();
+        kept.disable_cleanup(true);
+        println!("kept-path:{}", kept.leak().display());
+        return;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["test_leak", "--exact", "--nocapture"])
+        .env("TEMPFILE_LEAK_CHILD", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let path = |tag: &str| {
+        PathBuf::from(
+            stdout
+                .lines()
+                .find_map(|l| l.split(tag).nth(1))
+                .expect("child didn't report a path"),
+        )
+    };
+    assert!(
+        !path("leaked-path:").exists(),
+        "leaked tempdir wasn't deleted"
+    );
+    let kept = path("kept-path:");
+    assert!(kept.exists(), "kept tempdir was deleted");
+    fs::remove_dir(kept).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_delegate_to_child() {
+    use tempfile::OwnershipToken;
+
+    // When re-executed as a child process, adopt the directory and delete it on exit.
+    if std::env::var_os("TEMPFILE_DELEGATED_DIR").is_some() {
+        let tmpdir =
+            TempDir::adopt(OwnershipToken::from_env("TEMPFILE_DELEGATED_DIR").unwrap()).unwrap();
+        assert!(tmpdir.path().join("file").exists());
+        return;
+    }
+
+    assert_eq!(
+        OwnershipToken::from_env("TEMPFILE_DELEGATED_DIR")
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::NotFound
+    );
+
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().to_owned();
+    fs::write(path.join("file"), "data").unwrap();
+
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap());
+    child.args(["test_delegate_to_child", "--exact"]);
+    tmpdir
+        .into_token()
+        .delegate_to(&mut child, "TEMPFILE_DELEGATED_DIR");
+    // The parent no longer owns the directory.
+    assert!(path.exists());
+
+    assert!(child.status().unwrap().success());
+    assert!(
+        !path.exists(),
+        "child didn't delete the delegated directory"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "notify", any(target_os = "linux", windows)))]
+fn test_watch() {
+    use std::time::Duration;
+
+    let tmpdir = TempDir::new().unwrap();
+    let events = tmpdir.watch().unwrap();
+    fs::write(tmpdir.path().join("watched.txt"), "data").unwrap();
+
+    let mut seen = false;
+    while let Some(event) = events.recv_timeout(Duration::from_secs(5)).unwrap() {
+        if event.paths.iter().any(|p| p.ends_with("watched.txt")) {
+            seen = true;
+            break;
+        }
+    }
+    assert!(seen, "no event for the created file");
+}
+
+#[test]
+fn test_tempdir_macro() {
+    let line = line!() + 1;
+    let tmp_dir = tempfile::tempdir!().unwrap();
+    let name = tmp_dir.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with(&format!("tempdir-{}-", line)), "{}", name);
+
+    let nested = tempfile::tempdir!(tmp_dir.path()).unwrap();
+    assert_eq!(nested.path().parent(), Some(tmp_dir.path()));
 }
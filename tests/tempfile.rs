@@ -23,6 +23,21 @@ fn test_basic() {
     assert_eq!("abcde", buf);
 }
 
+#[test]
+fn test_from_reader() {
+    // For the wasi platforms, `std::env::temp_dir` will panic. For those targets, configure the /tmp
+    // directory instead as the base directory for temp files.
+    #[cfg(target_os = "wasi")]
+    let _ = tempfile::env::override_temp_dir(std::path::Path::new("/tmp"));
+
+    let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+    let mut tmpfile = tempfile::tempfile_from_reader(&data[..]).unwrap();
+    assert_eq!(tmpfile.stream_position().unwrap(), 0);
+    let mut buf = Vec::new();
+    tmpfile.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, data);
+}
+
 #[test]
 fn test_cleanup() {
     // For the wasi platforms, `std::env::temp_dir` will panic. For those targets, configure the /tmp
@@ -0,0 +1,27 @@
+#![deny(rust_2018_idioms)]
+#![cfg(all(feature = "tmpfs", target_os = "linux"))]
+
+use std::fs;
+use std::io::{self, Write};
+
+use tempfile::Builder;
+
+#[test]
+fn test_tmpfs() {
+    let dir = match Builder::new().tmpfs(64 * 1024).tempdir() {
+        Ok(dir) => dir,
+        // Mounting requires privileges we might not have.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("failed to mount tmpfs: {}", e),
+    };
+    let path = dir.path().to_owned();
+
+    // The tmpfs is size-limited.
+    let mut file = fs::File::create(path.join("big")).unwrap();
+    let err = file.write_all(&[0u8; 256 * 1024]).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(28)); // ENOSPC
+    drop(file);
+
+    dir.close().unwrap();
+    assert!(!path.exists());
+}
@@ -0,0 +1,52 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "camino")]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use camino::Utf8PathBuf;
+use tempfile::{Builder, Utf8NamedTempFile, Utf8TempDir};
+
+/// For the wasi platforms, `std::env::temp_dir` will panic. For those targets, configure the /tmp
+/// directory instead as the base directory for temp files.
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(std::path::Path::new("/tmp"));
+    }
+}
+
+#[test]
+fn test_utf8_tempdir() {
+    configure_wasi_temp_dir();
+
+    let dir = Utf8TempDir::new().unwrap();
+    assert!(dir.path().is_dir());
+    assert_eq!(dir.path().as_std_path(), dir.as_ref() as &std::path::Path);
+
+    let sub = Utf8TempDir::with_builder_in(Builder::new().prefix("sub-"), dir.path()).unwrap();
+    assert!(sub.path().file_name().unwrap().starts_with("sub-"));
+    assert!(sub.path().starts_with(dir.path()));
+
+    let path: Utf8PathBuf = sub.keep();
+    assert!(path.is_dir());
+    dir.close().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_utf8_named_tempfile() {
+    configure_wasi_temp_dir();
+
+    let dir = Utf8TempDir::new().unwrap();
+    let mut file = Utf8NamedTempFile::new_in(dir.path()).unwrap();
+    write!(file, "abcde").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    let path = file.into_temp_path();
+    assert!(path.is_file());
+    let target = dir.path().join("persisted");
+    path.persist(&target).unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "abcde");
+}
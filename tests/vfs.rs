@@ -0,0 +1,99 @@
+#![deny(rust_2018_idioms)]
+#![cfg(feature = "testing")]
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use tempfile::vfs::{FsStorage, MemFs, OsFs, TempFs, TempStorage};
+use tempfile::Builder;
+
+/// For the wasi platforms, `std::env::temp_dir` will panic. For those targets, configure the /tmp
+/// directory instead as the base directory for temp files.
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+fn write_atomically<Fs: TempFs>(fs: &Fs, dir: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = Builder::new().prefix("atomic").tempfile_in_fs(fs, dir)?;
+    file.write_all(data)?;
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    assert_eq!(buf, data);
+    file.persist(dir.join("out"))
+}
+
+#[test]
+fn test_memfs_file() {
+    let fs = MemFs::new();
+    let dir = Path::new("/virtual");
+    write_atomically(&fs, dir, b"hello").unwrap();
+    assert_eq!(fs.read(dir.join("out")).unwrap(), b"hello");
+    assert_eq!(fs.entries(), [dir.join("out")]);
+
+    let file = Builder::new()
+        .suffix(".x")
+        .tempfile_in_fs(&fs, dir)
+        .unwrap();
+    let path = file.path().to_owned();
+    assert!(path.to_str().unwrap().ends_with(".x"));
+    assert!(fs.exists(&path));
+    drop(file);
+    assert!(!fs.exists(&path));
+
+    let file = Builder::new().tempfile_in_fs(&fs, dir).unwrap();
+    let path = file.path().to_owned();
+    file.close().unwrap();
+    assert!(!fs.exists(&path));
+}
+
+#[test]
+fn test_memfs_dir() {
+    let fs = MemFs::new();
+    let tmpdir = Builder::new()
+        .prefix("dir")
+        .tempdir_in_fs(&fs, "/virtual")
+        .unwrap();
+    let path = tmpdir.path().to_owned();
+    write_atomically(&fs, &path, b"data").unwrap();
+    assert!(fs.exists(path.join("out")));
+    drop(tmpdir);
+    assert!(fs.entries().is_empty());
+}
+
+#[test]
+fn test_osfs() {
+    configure_wasi_temp_dir();
+    let dir = tempfile::tempdir().unwrap();
+    write_atomically(&OsFs, dir.path(), b"real").unwrap();
+    assert_eq!(std::fs::read(dir.path().join("out")).unwrap(), b"real");
+    assert_eq!(dir.entries_sorted().unwrap().len(), 1);
+}
+
+fn store_report<S: TempStorage>(storage: &S) -> std::io::Result<Vec<u8>> {
+    let dir = storage.tempdir()?;
+    assert!(!dir.as_ref().as_os_str().is_empty());
+    let mut file = storage.tempfile()?;
+    file.write_all(b"report")?;
+    let mut spooled = storage.spooled(2);
+    spooled.write_all(b"spooled")?;
+    spooled.seek(SeekFrom::Start(0))?;
+    let mut buf = Vec::new();
+    spooled.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn test_temp_storage() {
+    configure_wasi_temp_dir();
+
+    assert_eq!(store_report(&Builder::new()).unwrap(), b"spooled");
+
+    let fs = MemFs::new();
+    let storage = FsStorage::new(&fs, "/tmp");
+    assert_eq!(store_report(&storage).unwrap(), b"spooled");
+    // Everything has been cleaned up again.
+    assert!(fs.entries().is_empty());
+}
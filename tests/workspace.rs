@@ -0,0 +1,84 @@
+#![deny(rust_2018_idioms)]
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::Workspace;
+
+fn configure_wasi_temp_dir() {
+    if cfg!(target_os = "wasi") {
+        let _ = tempfile::env::override_temp_dir(Path::new("/tmp"));
+    }
+}
+
+#[test]
+#[cfg_attr(target_os = "wasi", ignore = "file locking is not supported")]
+fn test_workspace() {
+    configure_wasi_temp_dir();
+
+    let name = format!("tempfile-tests/workspace-{}", std::process::id());
+    let workspace = Workspace::open(&name).unwrap();
+    assert!(workspace.path().ends_with(&name));
+    fs::write(workspace.path().join("data"), "cached").unwrap();
+
+    // Only one `Workspace` may use it at a time.
+    assert_eq!(
+        Workspace::try_open(&name).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    // The contents survive reopening.
+    let path = workspace.path().to_owned();
+    drop(workspace);
+    let workspace = Workspace::try_open(&name).unwrap();
+    assert_eq!(workspace.path(), path);
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("data")).unwrap(),
+        "cached"
+    );
+
+    assert_eq!(
+        workspace
+            .purge_older_than(Duration::from_secs(3600))
+            .unwrap(),
+        0
+    );
+    fs::create_dir(workspace.path().join("dir")).unwrap();
+    fs::write(workspace.path().join("dir").join("file"), "").unwrap();
+    assert_eq!(workspace.purge_older_than(Duration::ZERO).unwrap(), 2);
+    assert_eq!(fs::read_dir(workspace.path()).unwrap().count(), 0);
+
+    fs::remove_dir(workspace.path()).unwrap();
+}
+
+#[test]
+fn test_workspace_names() {
+    configure_wasi_temp_dir();
+
+    for name in ["", "../escape", "/absolute", "a/../b"] {
+        let err = Workspace::open(name).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", name);
+    }
+}
+
+#[test]
+#[cfg_attr(target_os = "wasi", ignore = "file locking is not supported")]
+fn test_workspace_purge_keeps_nested() {
+    configure_wasi_temp_dir();
+
+    let name = format!("tempfile-tests/outer-{}", std::process::id());
+    let outer = Workspace::open(&name).unwrap();
+    let inner = Workspace::open(Path::new(&name).join("inner")).unwrap();
+    fs::write(inner.path().join("data"), "cached").unwrap();
+    fs::write(outer.path().join("stale"), "").unwrap();
+
+    // Only the plain file is purged, not the nested workspace or its lock file.
+    assert_eq!(outer.purge_older_than(Duration::ZERO).unwrap(), 1);
+    assert!(inner.path().join("data").exists());
+    assert!(outer.path().join("inner.lock").exists());
+
+    drop(inner);
+    fs::remove_dir_all(outer.path()).unwrap();
+}